@@ -93,6 +93,29 @@ const ERC20_ABI: &str = r#"
 ]
 "#;
 
+/// 加载中转钱包
+/// - PRIVATE_KEY 指向已存在的文件时按以太坊 V3 JSON keystore 解密（与 relayer keystore 通用），
+///   口令从 KEYSTORE_PASSWORD_FILE 读取，未设置时在终端提示输入
+/// - 否则按 hex 私钥解析（带或不带 0x 前缀）
+fn load_wallet(private_key: &str) -> Result<LocalWallet> {
+    let keystore = std::path::Path::new(private_key.trim());
+    if keystore.is_file() {
+        let password = match std::env::var("KEYSTORE_PASSWORD_FILE") {
+            Ok(file) => std::fs::read_to_string(&file)
+                .with_context(|| format!("Failed to read KEYSTORE_PASSWORD_FILE {}", file))?
+                .trim_end_matches(['\r', '\n'])
+                .to_string(),
+            Err(_) => rpassword::prompt_password(format!("Password for {}: ", keystore.display()))
+                .context("Failed to read keystore password")?,
+        };
+        return LocalWallet::decrypt_keystore(keystore, password)
+            .with_context(|| format!("Failed to decrypt keystore {}", keystore.display()));
+    }
+
+    let private_key_hex = private_key.strip_prefix("0x").unwrap_or(private_key);
+    private_key_hex.parse().context("Failed to parse private key")
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // 初始化日志
@@ -111,8 +134,8 @@ async fn main() -> Result<()> {
     let rpc_url = std::env::var("RPC_URL")
         .context("RPC_URL environment variable not set")?;
 
-    let private_key_hex = std::env::var("PRIVATE_KEY")
-        .context("PRIVATE_KEY environment variable not set (hex format, with or without 0x prefix)")?;
+    let private_key = std::env::var("PRIVATE_KEY")
+        .context("PRIVATE_KEY environment variable not set (hex key or path to a V3 JSON keystore)")?;
 
     let bridge_contract_address = std::env::var("BRIDGE_CONTRACT_ADDRESS")
         .context("BRIDGE_CONTRACT_ADDRESS environment variable not set")?;
@@ -138,11 +161,8 @@ async fn main() -> Result<()> {
     let provider = Provider::<Http>::try_from(rpc_url.clone())
         .context("Failed to create provider")?;
 
-    // 解析私钥
-    let private_key_hex = private_key_hex.strip_prefix("0x").unwrap_or(&private_key_hex);
-    let wallet: LocalWallet = private_key_hex
-        .parse()
-        .context("Failed to parse private key")?;
+    // 解析私钥（hex 私钥或 keystore 文件路径）
+    let wallet = load_wallet(&private_key)?;
 
     // 设置链 ID
     let wallet = wallet.with_chain_id(chain_id);
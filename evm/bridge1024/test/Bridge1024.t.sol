@@ -1391,6 +1391,58 @@ contract Bridge1024Test is Test {
         assertTrue(avgGasPerTransfer < 850000, "Average gas per transfer too high");
     }
     
+    // ============ Encoding Golden Vector Tests ============
+    
+    /**
+     * @notice Golden vectors shared with the relayer (relayer/shared/testdata/attestation_vectors.json)
+     * @dev The relayer signs keccak256(EIP-191 || sha256(JSON)); any drift here makes transfers unsignable
+     */
+    function testGoldenAttestationVectors() public pure {
+        Bridge1024.StakeEventData memory eventData;
+        
+        // s2e: SVM program → EVM contract
+        eventData = Bridge1024.StakeEventData({
+            sourceContract: 0x0102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f20,
+            targetContract: 0x0000000000000000000000005fbdb2315678afecb367f032d93f642f64180aa3,
+            sourceChainId: 91024,
+            targetChainId: 421614,
+            blockHeight: 123456789,
+            amount: 100000000,
+            receiverAddress: "0x70997970C51812dc3A010C7d01b50e0d17dc79C8",
+            nonce: 1
+        });
+        assertEq(hashEventData(eventData), 0xf2995dd8f10c1558a39ed9c29eb108fcd0c3b6cb2fdc9efcaa059edce772b01e);
+        assertEq(toEthSignedMessageHash(hashEventData(eventData)), 0x8551d14cb0975b44fa224c9a77b8989aeda725ee4320da34073f5c3d4cf55855);
+        
+        // e2s: EVM contract → SVM program
+        eventData = Bridge1024.StakeEventData({
+            sourceContract: 0x000000000000000000000000e7f1725e7734ce288f8367e1bb143e90bb3f0512,
+            targetContract: 0x202122232425262728292a2b2c2d2e2f303132333435363738393a3b3c3d3e3f,
+            sourceChainId: 421614,
+            targetChainId: 91024,
+            blockHeight: 8765432,
+            amount: 2500000,
+            receiverAddress: "US517G5965aydkZ46HS38QLi7UQiSojurfbQfKCELFx",
+            nonce: 42
+        });
+        assertEq(hashEventData(eventData), 0x2040f523b25dcf613b634957c44a058ac114221905b76cd4dc19210bc67b3773);
+        assertEq(toEthSignedMessageHash(hashEventData(eventData)), 0xc5d70462ec92ed771076024f0fbe16d9dba5a7a2ded7c683275de6ecf8fda6c9);
+        
+        // edge: zero / u64::MAX values, EVM address target, lowercase receiver
+        eventData = Bridge1024.StakeEventData({
+            sourceContract: 0xabababababababababababababababababababababababababababababababab,
+            targetContract: 0x000000000000000000000000cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd,
+            sourceChainId: 0,
+            targetChainId: 18446744073709551615,
+            blockHeight: 0,
+            amount: 18446744073709551615,
+            receiverAddress: "0xeFEfeFEfeFeFEFEFEfefeFeFefEfEfEfeFEFEFEf",
+            nonce: 0
+        });
+        assertEq(hashEventData(eventData), 0x7e99f59f86759c02c6ea2a2410889dce6991a601955ac7848264df0bde45969f);
+        assertEq(toEthSignedMessageHash(hashEventData(eventData)), 0xcdd4456825cd64a348aa2c45accbd0954b4addbc6ca6020ef92e6ae3a59aaab2);
+    }
+    
     // ============ Threshold Calculation Tests ============
     
     function testThresholdCalculation() public pure {
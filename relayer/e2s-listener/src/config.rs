@@ -1,15 +1,16 @@
 use anyhow::Result;
 use shared::Config;
 use std::env;
+use std::path::Path;
 
 pub type ListenerConfig = Config;
 
-pub fn load_config() -> Result<ListenerConfig> {
+pub fn load_config(config_file: Option<&Path>) -> Result<ListenerConfig> {
     // 设置默认的服务名称
     env::set_var("SERVICE__NAME", "e2s-listener");
     
-    // 从环境变量加载配置
-    let mut config = Config::load()?;
+    // 从配置文件和环境变量加载配置
+    let mut config = Config::load("e2s-listener", config_file)?;
     config.service.name = "e2s-listener".to_string();
     
     Ok(config)
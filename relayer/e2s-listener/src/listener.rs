@@ -3,12 +3,22 @@ use anyhow::{anyhow, Result};
 use ethers::{
     abi::ParamType,
     contract::EthEvent,
-    core::types::Address,
+    core::types::{transaction::eip2718::TypedTransaction, Address},
     prelude::*,
-    providers::{Http, Middleware, Provider},
+    providers::{Http, JsonRpcClient, Middleware, Provider, ProviderError, RpcError},
 };
-use shared::types::StakeEventData;
-use std::{path::Path, sync::Arc};
+use async_trait::async_trait;
+use shared::alerts::{self, Alert, Severity, StallDetector};
+use shared::checkpoint::{self, Checkpoint, CheckpointFile};
+use shared::pipeline::{self, AwaitingEvents, Finality, SourceEvent};
+use shared::lifecycle::Lifecycle;
+use shared::retry::RpcEndpoint;
+use shared::types::{StakeEventData, Task};
+use shared::{AddressKind, ChainAddress, RelayerError, SourceListener, WorkQueue};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
 use tracing::{debug, error, info, warn};
 
 // StakeEvent ABI 定义
@@ -29,8 +39,61 @@ pub struct StakeEvent {
     pub nonce: u64,
 }
 
-/// 启动 EVM 事件监听器
-pub async fn start_listener(config: ListenerConfig) -> Result<()> {
+/// 重新扫描的区块范围（含两端），命令行格式 `<FROM>..<TO>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockRange {
+    pub from: u64,
+    pub to: u64,
+}
+
+impl FromStr for BlockRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (from, to) = s.split_once("..").ok_or_else(|| format!("expected <FROM>..<TO>, got '{}'", s))?;
+        let parse = |n: &str| n.trim().parse::<u64>().map_err(|e| format!("invalid block number '{}': {}", n, e));
+        let range = BlockRange { from: parse(from)?, to: parse(to)? };
+        if range.from > range.to {
+            return Err(format!("start block {} is after end block {}", range.from, range.to));
+        }
+        Ok(range)
+    }
+}
+
+/// 监听器启动时的起始区块
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StartBlock {
+    /// `--replay` 范围起点
+    Replay(BlockRange),
+    /// 检查点记录的区块
+    Checkpoint(u64),
+    /// `checkpoint.start_block`
+    Configured(u64),
+    /// 当前区块
+    Latest,
+}
+
+impl StartBlock {
+    /// 优先级：重放范围 > 检查点 > `checkpoint.start_block` > 当前区块
+    fn select(replay: Option<BlockRange>, saved: Option<u64>, configured: Option<u64>) -> Self {
+        match (replay, saved, configured) {
+            (Some(range), _, _) => Self::Replay(range),
+            (None, Some(block), _) => Self::Checkpoint(block),
+            (None, None, Some(block)) => Self::Configured(block),
+            (None, None, None) => Self::Latest,
+        }
+    }
+}
+
+/// 启动 EVM 事件监听器，事件写入工作队列；收到退出信号后完成当前一轮监听后返回
+///
+/// 指定 `replay` 时只重新扫描该范围（不读写检查点），范围内的事件全部达到最终性并写入队列后返回
+pub async fn start_listener(
+    lifecycle: Arc<Lifecycle>,
+    queue: Arc<dyn WorkQueue>,
+    replay: Option<BlockRange>,
+) -> Result<()> {
+    let config = lifecycle.config();
     info!("Starting EVM event listener");
     info!(
         rpc = config.source_chain.rpc_url,
@@ -38,131 +101,678 @@ pub async fn start_listener(config: ListenerConfig) -> Result<()> {
         "Connecting to EVM"
     );
 
-    // 创建 Provider
-    let provider = Provider::<Http>::try_from(&config.source_chain.rpc_url)
-        .map_err(|e| anyhow!("Failed to create provider: {}", e))?;
-    let provider = Arc::new(provider);
-
-    // 解析合约地址
-    let contract_address: Address = config
-        .source_chain
-        .contract_address
-        .parse()
-        .map_err(|e| anyhow!("Invalid contract address: {}", e))?;
-
-    info!("Connected to EVM, starting to listen for events");
-
-    // 获取当前区块号
-    let mut last_block = provider
-        .get_block_number()
-        .await
-        .map_err(|e| anyhow!("Failed to get block number: {}", e))?
-        .as_u64();
-
-    info!(block = last_block, "Starting from current block");
-
-    // 创建事件队列目录
-    let queue_dir = &config.queue.path;
-    std::fs::create_dir_all(queue_dir)?;
-    info!(queue_path = %queue_dir.display(), "Queue directory initialized");
+    let mut source = EvmListener::connect(&config, replay).await?;
 
-    // 持续监听新区块
+    // 持续监听新区块直到收到退出信号
+    let mut progress = StallDetector::new(alerts::stall_timeout());
+    let mut watch = lifecycle.watch_config();
+    let mut shutdown = lifecycle.shutdown();
     loop {
-        match listen_for_events(&provider, contract_address, last_block, &config).await {
-            Ok(new_block) => {
-                if new_block > last_block {
-                    last_block = new_block;
-                }
+        if let Some(new) = watch.changed() {
+            if let Err(e) = source.set_rpc_url(&new.source_chain.rpc_url) {
+                error!(error = %e, "Failed to apply reloaded EVM RPC endpoint");
             }
-            Err(e) => {
-                error!("Error listening for events: {}", e);
+            source.set_max_log_range(new.source_chain.max_log_range.unwrap_or(DEFAULT_MAX_LOG_RANGE));
+        }
+
+        let forwarded = pipeline::forward_events(&mut source, queue.as_ref()).await;
+        if let Err(e) = &forwarded {
+            error!("Error listening for events: {}", e);
+        }
+        pipeline::watch_progress(&source, &mut progress);
+        if source.replay_finished() {
+            info!("Replay range fully queued");
+            break;
+        }
+        // 追赶积压的区块时不等待
+        if forwarded.is_ok() && source.is_catching_up() {
+            if shutdown.is_requested() {
+                break;
             }
+            continue;
         }
 
         // 等待一段时间后继续
-        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+        if shutdown.sleep(tokio::time::Duration::from_secs(5)).await {
+            break;
+        }
     }
+    info!("EVM event listener drained");
+    Ok(())
 }
 
-/// 监听指定区块范围的事件
-async fn listen_for_events(
-    provider: &Provider<Http>,
+/// 未配置 `source_chain.confirmation_blocks` 时签名前要求的确认数
+const DEFAULT_CONFIRMATIONS: u64 = 12;
+/// 未配置 `source_chain.max_log_range` 时单次 eth_getLogs 的最大区块跨度
+const DEFAULT_MAX_LOG_RANGE: u64 = 1000;
+/// 连续多少次查询成功后扩大跨度
+const LOG_RANGE_GROW_AFTER: u32 = 3;
+/// 单次 eth_getLogs 超时，超时视为范围内日志过多、缩小跨度
+const LOG_QUERY_TIMEOUT: Duration = Duration::from_secs(30);
+/// 保留哈希的最近区块数（每轮扫描范围的末端和有事件的区块），超出窗口的回滚无法定位分叉点
+const REORG_WINDOW: usize = 256;
+
+/// eth_getLogs 的区块跨度（查询 `[from, from + span]`）：服务商拒绝跨度或结果数、或查询超时时减半，
+/// 连续成功后翻倍，不超过服务商上限；最小为 0，即只查询一个区块
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LogRange {
+    max: u64,
+    span: u64,
+    successes: u32,
+}
+
+impl LogRange {
+    fn new(max: u64) -> Self {
+        Self { max, span: max, successes: 0 }
+    }
+
+    /// 把跨度缩小到本次失败查询的一半
+    fn shrink(&mut self, failed_span: u64) {
+        self.span = failed_span / 2;
+        self.successes = 0;
+    }
+
+    fn record_success(&mut self) {
+        self.successes += 1;
+        if self.successes >= LOG_RANGE_GROW_AFTER && self.span < self.max {
+            self.span = self.span.saturating_mul(2).max(1).min(self.max);
+            self.successes = 0;
+            debug!(span = self.span, "Growing log query range");
+        }
+    }
+}
+
+/// 监听器使用的 JSON-RPC 传输，热加载 RPC 端点时按 URL 重建
+pub trait RpcTransport: JsonRpcClient + 'static {
+    fn from_url(url: &str) -> std::result::Result<Self, String>
+    where
+        Self: Sized;
+}
+
+impl RpcTransport for Http {
+    fn from_url(url: &str) -> std::result::Result<Self, String> {
+        Http::from_str(url).map_err(|e| e.to_string())
+    }
+}
+
+/// EVM 事件源（按区块范围查询 StakeEvent 日志）
+///
+/// 查询到链头为止，但只有最终性前沿（链头 − `confirmation_blocks`，或 `commitment = "finalized"`
+/// 时节点的 finalized 区块）之前的事件会被签名；之后的事件登记为等待最终性的任务，
+/// 游标停在前沿，下一轮重新扫描，达到最终性后再投递。
+///
+/// 每轮扫描前检查上次扫描到的区块是否仍在规范链上（下一个区块的父哈希是否等于记录的哈希）。
+/// 检测到回滚时向前找到分叉点，游标回退到分叉点之后重新扫描；回滚范围内转发过、重新扫描后不再出现的事件被撤回。
+///
+/// 单次查询的区块跨度随 RPC 服务商的限制自适应，停机后积压的区块连续查询、不等待轮询间隔。
+pub struct EvmListener<P = Http> {
+    rpc_url: String,
+    provider: Provider<P>,
+    /// RPC 重试、熔断和指标
+    rpc: RpcEndpoint,
     contract_address: Address,
-    from_block: u64,
-    config: &ListenerConfig,
-) -> Result<u64> {
-    // 获取最新区块号
-    let latest_block = provider
-        .get_block_number()
-        .await
-        .map_err(|e| anyhow!("Failed to get latest block: {}", e))?
-        .as_u64();
+    target_chain_id: u64,
+    /// 下次查询的起始区块
+    last_block: u64,
+    /// 本轮 poll 查询到的区块，commit 后成为新的起始区块
+    polled_block: u64,
+    /// 以节点的 finalized 区块作为最终性前沿
+    finalized_tag: bool,
+    /// 未使用 finalized 区块时要求的确认数
+    confirmations: u64,
+    awaiting: AwaitingEvents,
+    /// 最近扫描过的区块哈希
+    blocks: BTreeMap<u64, H256>,
+    /// 最近转发过的事件（按区块），回滚后据此找出消失的事件
+    emitted: BTreeMap<u64, Vec<SourceEvent>>,
+    /// 回滚范围内、尚未重新扫描到的事件
+    orphaned: Vec<SourceEvent>,
+    /// 重新扫描后确认已消失的事件，commit 后清空
+    retracted: Vec<SourceEvent>,
+    /// 交易发起者（按交易哈希，附所在区块），未达到最终性的事件每轮重新扫描时不再重复查询
+    senders: HashMap<H256, (u64, ChainAddress)>,
+    /// 本轮扫描到且已达到最终性的最高区块
+    settled_block: u64,
+    /// 已完整处理到的区块，重放模式下为 None
+    checkpoint: Option<CheckpointFile>,
+    /// 重放模式的结束区块
+    replay_end: Option<u64>,
+    log_range: LogRange,
+    /// 单次 eth_getLogs 的本地超时
+    log_query_timeout: Duration,
+    /// 上一轮扫描后还有多少已达到最终性的区块未扫描
+    behind: u64,
+}
 
-    // 如果没有新区块，返回当前区块号
-    if latest_block <= from_block {
-        return Ok(from_block);
+impl EvmListener {
+    pub async fn connect(config: &ListenerConfig, replay: Option<BlockRange>) -> Result<Self> {
+        // 创建 Provider
+        let provider = Provider::<Http>::try_from(&config.source_chain.rpc_url)
+            .map_err(|e| anyhow!("Failed to create provider: {}", e))?;
+        Self::with_provider(config, provider, replay).await
     }
+}
 
-    // 查询事件（限制查询范围以避免超时）
-    let to_block = std::cmp::min(from_block + 1000, latest_block);
+impl<P: RpcTransport> EvmListener<P> {
+    /// 起始区块：重放范围起点 > 检查点 > `checkpoint.start_block` > 当前区块
+    async fn with_provider(config: &ListenerConfig, provider: Provider<P>, replay: Option<BlockRange>) -> Result<Self> {
+        // 解析合约地址
+        let contract_address: Address = config
+            .source_chain
+            .contract_address
+            .parse()
+            .map_err(|e| anyhow!("Invalid contract address: {}", e))?;
 
-    debug!(
-        from = from_block,
-        to = to_block,
-        "Querying events from block range"
-    );
+        info!("Connected to EVM, starting to listen for events");
+
+        let rpc = RpcEndpoint::new("evm", &config.source_chain.rpc_url);
+        let checkpoint = match replay {
+            Some(_) => None,
+            None => Some(checkpoint::open_checkpoint(config)?),
+        };
+        let saved = match &checkpoint {
+            Some(file) => file.load()?,
+            None => None,
+        };
+        let last_block = match StartBlock::select(replay, saved.map(|saved| saved.block), config.checkpoint.start_block) {
+            StartBlock::Replay(range) => {
+                info!(from = range.from, to = range.to, "Replaying block range");
+                range.from
+            }
+            StartBlock::Checkpoint(block) => {
+                let path = config.checkpoint.file_path(&config.service.name);
+                info!(block, path = %path.display(), "Resuming from checkpoint");
+                block
+            }
+            StartBlock::Configured(block) => {
+                info!(block, "No checkpoint found, starting from configured start block");
+                block
+            }
+            StartBlock::Latest => {
+                // 获取当前区块号
+                let block = rpc
+                    .call("eth_blockNumber", || async { provider.get_block_number().await.map_err(provider_error) })
+                    .await
+                    .map_err(|e| anyhow!("Failed to get block number: {}", e))?
+                    .as_u64();
+                info!(block, "No checkpoint found, starting from current block");
+                block
+            }
+        };
+
+        Ok(Self {
+            rpc_url: config.source_chain.rpc_url.clone(),
+            provider,
+            rpc,
+            contract_address,
+            target_chain_id: config.target_chain.chain_id,
+            last_block,
+            polled_block: last_block,
+            finalized_tag: config.source_chain.commitment.as_deref() == Some("finalized"),
+            confirmations: config.source_chain.confirmation_blocks.unwrap_or(DEFAULT_CONFIRMATIONS),
+            awaiting: AwaitingEvents::default(),
+            blocks: BTreeMap::new(),
+            emitted: BTreeMap::new(),
+            orphaned: Vec::new(),
+            retracted: Vec::new(),
+            senders: HashMap::new(),
+            settled_block: 0,
+            checkpoint,
+            replay_end: replay.map(|range| range.to),
+            log_range: LogRange::new(config.source_chain.max_log_range.unwrap_or(DEFAULT_MAX_LOG_RANGE)),
+            log_query_timeout: LOG_QUERY_TIMEOUT,
+            behind: 0,
+        })
+    }
+
+    /// 上一轮扫描后仍有积压的区块
+    pub fn is_catching_up(&self) -> bool {
+        self.behind > 0
+    }
+
+    /// 热加载时更新单次查询的最大区块跨度
+    pub fn set_max_log_range(&mut self, max: u64) {
+        if self.log_range.max != max {
+            info!(max_log_range = max, "Updated log query range limit");
+            self.log_range = LogRange::new(max);
+        }
+    }
+
+    /// 查询 [from_block, min(from_block + 跨度, limit)] 的 StakeEvent 日志，跨度过大或查询超时时减半重试
+    ///
+    /// 单个区块查询超时按瞬时错误走 RPC 重试；单个区块仍被拒绝时发送 critical 告警并返回错误，跨度保持为单个区块，下一轮 poll 重试。
+    /// 返回实际查询的末端区块、其哈希和日志
+    async fn query_logs(&mut self, from_block: u64, limit: u64) -> shared::Result<(u64, H256, Vec<Log>)> {
+        loop {
+            let to_block = limit.min(from_block.saturating_add(self.log_range.span));
+            debug!(from = from_block, to = to_block, "Querying events from block range");
+
+            // 先记录范围末端的哈希再查询日志：两次查询之间发生回滚时，下一轮检查能发现
+            let end_hash = self
+                .block_header(BlockNumber::Number(to_block.into()))
+                .await?
+                .and_then(|block| block.hash)
+                .ok_or_else(|| RelayerError::RpcRequest(format!("Node returned no block {}", to_block)))?;
+
+            let filter = Filter::new()
+                .address(self.contract_address)
+                .from_block(from_block)
+                .to_block(to_block)
+                .topic0(StakeEvent::signature());
+            let result = self
+                .rpc
+                .call("eth_getLogs", || async {
+                    let logs = match tokio::time::timeout(self.log_query_timeout, self.provider.get_logs(&filter)).await {
+                        Ok(logs) => logs.map_err(provider_error),
+                        // 本地超时说明范围内日志过多，同样缩小跨度；单个区块无法再缩小，按瞬时错误重试
+                        Err(_) if to_block > from_block => return Ok(Err(RelayerError::RpcTimeout)),
+                        Err(_) => Err(RelayerError::RpcTimeout),
+                    };
+                    match logs {
+                        // 跨度过大不是端点故障：不重试、不计入熔断，由下面缩小跨度后重新查询
+                        Err(e) if is_range_error(&e) => Ok(Err(e)),
+                        other => other.map(Ok),
+                    }
+                })
+                .await?;
 
-    // 创建事件过滤器
-    let event_signature = StakeEvent::signature();
-    let filter = Filter::new()
-        .address(contract_address)
-        .from_block(from_block)
-        .to_block(to_block)
-        .topic0(event_signature);
-
-    // 查询日志
-    let logs = provider
-        .get_logs(&filter)
-        .await
-        .map_err(|e| anyhow!("Failed to get logs: {}", e))?;
-
-    debug!(count = logs.len(), "Found events");
-
-    // 处理每个日志
-    for log in logs {
-        match parse_stake_event(&log) {
-            Ok(event) => {
-                info!(
-                    nonce = event.nonce,
-                    amount = event.amount,
-                    receiver = event.receiver_address,
-                    "Processing StakeEvent"
-                );
-
-                // 转换为 StakeEventData
-                let event_data = StakeEventData {
-                    source_contract: hex::encode(event.source_contract),
-                    target_contract: hex::encode(event.target_contract),
-                    source_chain_id: event.chain_id,
-                    target_chain_id: config.target_chain.chain_id,
-                    block_height: event.block_height,
-                    amount: event.amount,
-                    receiver_address: event.receiver_address.clone(),
-                    nonce: event.nonce,
-                };
-
-                // 保存到队列文件
-                if let Err(e) = save_to_queue(&event_data, &config.queue.path) {
-                    error!(nonce = event.nonce, error = %e, "Failed to save event to queue");
+            match result {
+                Ok(logs) => {
+                    if self.log_range.span == 0 {
+                        alerts::resolve("log-range-floor:evm", format!("Logs of block {} queried", from_block));
+                    }
+                    self.log_range.record_success();
+                    return Ok((to_block, end_hash, logs));
                 }
+                Err(e) if to_block > from_block => {
+                    self.log_range.shrink(to_block - from_block);
+                    warn!(
+                        from = from_block,
+                        to = to_block,
+                        span = self.log_range.span,
+                        error = %e,
+                        "Log query range too large, shrinking"
+                    );
+                }
+                // 已缩小到单个区块，无法再缩小
+                Err(e) => {
+                    error!(block = from_block, error = %e, "Log query rejected for a single block");
+                    alerts::notify(Alert::new(
+                        Severity::Critical,
+                        "log-range-floor:evm",
+                        "eth_getLogs rejected for a single block",
+                        format!("Provider rejects logs of block {} even as a single-block query: {}", from_block, e),
+                    ));
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    /// 写入检查点；写入失败只影响重启后的回补起点，重复事件由任务 ID 去重
+    fn save_checkpoint(&self) {
+        if let Some(checkpoint) = &self.checkpoint {
+            if let Err(e) = checkpoint.save(&Checkpoint::new(self.last_block, None)) {
+                error!(error = %e, "Failed to save listener checkpoint");
+            }
+        }
+    }
+
+    /// 重放范围已全部扫描、达到最终性并写入队列
+    pub fn replay_finished(&self) -> bool {
+        self.replay_end.is_some_and(|end| self.last_block >= end && self.settled_block >= end)
+    }
+
+    async fn block_header(&self, number: BlockNumber) -> shared::Result<Option<Block<H256>>> {
+        self.rpc
+            .call("eth_getBlockByNumber", || async { self.provider.get_block(number).await.map_err(provider_error) })
+            .await
+    }
+
+    /// 检查上次扫描到的区块是否仍在规范链上；发生回滚时回退游标，回滚范围内的事件待重新扫描后确认
+    async fn detect_reorg(&mut self, latest_block: u64) -> shared::Result<()> {
+        let Some((&top, &hash)) = self.blocks.last_key_value() else {
+            return Ok(());
+        };
+        // 有新区块时比较其父哈希，否则直接比较该区块的哈希；节点暂时查不到区块时下一轮再检查
+        let intact = if top < latest_block {
+            match self.block_header(BlockNumber::Number(top.saturating_add(1).into())).await? {
+                Some(next) => next.parent_hash == hash,
+                None => return Ok(()),
+            }
+        } else {
+            match self.block_header(BlockNumber::Number(top.into())).await? {
+                Some(block) => block.hash == Some(hash),
+                None => return Ok(()),
+            }
+        };
+        if intact {
+            return Ok(());
+        }
+
+        // 从高到低找到仍在规范链上的区块
+        let recorded: Vec<(u64, H256)> = self.blocks.iter().rev().map(|(&number, &hash)| (number, hash)).collect();
+        let mut fork = None;
+        for (number, hash) in recorded {
+            let block = self.block_header(BlockNumber::Number(number.into())).await?;
+            if block.and_then(|block| block.hash) == Some(hash) {
+                fork = Some(number);
+                break;
+            }
+        }
+        let oldest = self.blocks.keys().next().copied().unwrap_or(top);
+        let rescan_from = match fork {
+            Some(fork) => fork + 1,
+            None => {
+                error!(oldest, top, "Source chain reorg deeper than tracked window");
+                alerts::notify(Alert::new(
+                    Severity::Critical,
+                    "reorg-too-deep:evm",
+                    "Source chain reorg deeper than tracked window",
+                    format!(
+                        "No tracked block between {} and {} is still canonical; rescanning from {}",
+                        oldest, top, oldest
+                    ),
+                ));
+                oldest
+            }
+        };
+
+        self.blocks.split_off(&rescan_from);
+        let orphaned: Vec<SourceEvent> = self.emitted.split_off(&rescan_from).into_values().flatten().collect();
+        warn!(
+            fork = rescan_from.saturating_sub(1),
+            depth = top + 1 - rescan_from,
+            events = orphaned.len(),
+            "Source chain reorg detected, rescanning"
+        );
+        shared::metrics::record_reorg("evm");
+        self.orphaned.extend(orphaned);
+        // 回退的检查点立即写入，重启后同样重新扫描回滚范围
+        if rescan_from < self.last_block {
+            self.last_block = rescan_from;
+            self.save_checkpoint();
+        }
+        Ok(())
+    }
+
+    /// 记录本轮扫描结果，回滚范围内没有重新出现的事件标记为撤回
+    fn record_scan(&mut self, from_block: u64, to_block: u64, scanned: BTreeMap<u64, Vec<SourceEvent>>) {
+        let present: HashSet<String> = scanned.values().flatten().map(|e| Task::id_for(&e.event)).collect();
+        self.retracted.retain(|e| !present.contains(&Task::id_for(&e.event)));
+        for orphan in std::mem::take(&mut self.orphaned) {
+            if present.contains(&Task::id_for(&orphan.event)) {
+                continue;
+            }
+            if orphan.finality.block <= to_block {
+                self.awaiting.forget(&orphan.event);
+                self.retracted.push(orphan);
+            } else {
+                self.orphaned.push(orphan);
+            }
+        }
+
+        // 范围内的区块已完整重新扫描，替换旧记录
+        self.emitted.retain(|&block, _| block < from_block || block > to_block);
+        self.emitted.extend(scanned);
+        while self.blocks.len() > REORG_WINDOW {
+            self.blocks.pop_first();
+        }
+        if let Some(&oldest) = self.blocks.keys().next() {
+            self.emitted = self.emitted.split_off(&oldest);
+            self.senders.retain(|_, (block, _)| *block >= oldest);
+        }
+    }
+
+    /// 最终性前沿：不高于该区块的事件可以签名
+    async fn final_block(&self, latest_block: u64) -> shared::Result<u64> {
+        if !self.finalized_tag {
+            return Ok(latest_block.saturating_sub(self.confirmations));
+        }
+        let block = self
+            .rpc
+            .call("eth_getBlockByNumber", || async {
+                self.provider.get_block(BlockNumber::Finalized).await.map_err(provider_error)
+            })
+            .await?;
+        block
+            .and_then(|block| block.number)
+            .map(|number| number.as_u64())
+            .ok_or_else(|| RelayerError::RpcRequest("Node returned no finalized block".to_string()))
+    }
+
+    /// 事件所在交易的发起者（用于合规筛查）；查询失败时返回 None，不阻塞事件转发
+    async fn transaction_sender(&mut self, hash: H256, block: u64) -> Option<ChainAddress> {
+        if let Some((seen, sender)) = self.senders.get_mut(&hash) {
+            *seen = (*seen).max(block);
+            return Some(*sender);
+        }
+        let transaction = self
+            .rpc
+            .call("eth_getTransactionByHash", || async {
+                self.provider.get_transaction(hash).await.map_err(provider_error)
+            })
+            .await;
+        match transaction {
+            Ok(transaction) => {
+                let sender = transaction.map(|transaction| ChainAddress::Evm(transaction.from.0))?;
+                self.senders.insert(hash, (block, sender));
+                Some(sender)
             }
             Err(e) => {
-                warn!(error = %e, "Failed to parse StakeEvent");
+                warn!(tx = ?hash, error = %e, "Failed to query transaction sender");
+                None
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<P: RpcTransport> SourceListener for EvmListener<P> {
+    fn chain(&self) -> &str {
+        "evm"
+    }
+
+    /// 查询 [last_block, min(last_block + 跨度, latest)] 范围内的事件（跨度见 [`LogRange`]），游标只推进到最终性前沿
+    async fn poll(&mut self) -> shared::Result<Vec<SourceEvent>> {
+        // 获取最新区块号
+        let latest_block = self
+            .rpc
+            .call("eth_blockNumber", || async { self.provider.get_block_number().await.map_err(provider_error) })
+            .await?
+            .as_u64();
+
+        self.detect_reorg(latest_block).await?;
+        let from_block = self.last_block;
+
+        // 如果没有新区块，返回空
+        if latest_block <= from_block {
+            self.polled_block = from_block;
+            self.behind = 0;
+            return Ok(Vec::new());
+        }
+
+        let final_block = self.final_block(latest_block).await?;
+
+        // 查询事件（跨度随 RPC 服务商的限制自适应）
+        let limit = match self.replay_end {
+            Some(end) => latest_block.min(end.max(from_block)),
+            None => latest_block,
+        };
+        let (to_block, end_hash, logs) = self.query_logs(from_block, limit).await?;
+        self.behind = final_block.min(limit).saturating_sub(to_block);
+
+        debug!(count = logs.len(), "Found events");
+
+        // 处理每个日志（同一交易的发起者只查询一次）
+        let mut scanned: BTreeMap<u64, Vec<SourceEvent>> = BTreeMap::new();
+        for log in logs {
+            if log.removed == Some(true) {
+                continue;
             }
+            match parse_stake_event(&log).map(|event| to_stake_event(event, self.target_chain_id)) {
+                Ok((event, rejected)) => {
+                    let block = log.block_number.map(|n| n.as_u64()).unwrap_or(to_block);
+                    let sender = match log.transaction_hash {
+                        Some(hash) => self.transaction_sender(hash, block).await,
+                        None => None,
+                    };
+                    if let Some(hash) = log.block_hash {
+                        self.blocks.insert(block, hash);
+                    }
+                    scanned.entry(block).or_default().push(SourceEvent {
+                        event,
+                        tx_id: log.transaction_hash.map(|h| format!("{:?}", h)).unwrap_or_default(),
+                        log_index: log.log_index.map(|i| i.as_u64()),
+                        sender,
+                        finality: Finality {
+                            block,
+                            confirmations: latest_block.saturating_sub(block),
+                            finalized: self.finalized_tag && block <= final_block,
+                            awaiting: block > final_block,
+                        },
+                        rejected,
+                    });
+                }
+                Err(e) => {
+                    // 无法解码的日志没有可登记的 nonce，只能告警
+                    let tx = log.transaction_hash.map(|h| format!("{:?}", h)).unwrap_or_default();
+                    error!(error = %e, tx = %tx, "Undecodable StakeEvent log");
+                    alerts::notify(Alert::new(
+                        Severity::Critical,
+                        format!("event-undecodable:{}", tx),
+                        "Undecodable stake event",
+                        format!("StakeEvent log in tx {} could not be decoded and will not be relayed: {}", tx, e),
+                    ));
+                }
+            }
+        }
+
+        self.blocks.insert(to_block, end_hash);
+        let events: Vec<SourceEvent> = scanned.values().flatten().cloned().collect();
+        self.record_scan(from_block, to_block, scanned);
+
+        // 前沿之后的区块下一轮重新扫描
+        self.settled_block = to_block.min(final_block);
+        self.polled_block = self.settled_block.max(from_block);
+        Ok(self.awaiting.filter(events))
+    }
+
+    fn commit(&mut self) {
+        self.retracted.clear();
+        if self.polled_block <= self.last_block {
+            return;
+        }
+        self.last_block = self.polled_block;
+        // 事件已写入队列后才推进检查点
+        self.save_checkpoint();
+    }
+
+    fn retracted(&self) -> &[SourceEvent] {
+        &self.retracted
+    }
+
+    fn cursor(&self) -> u64 {
+        self.last_block
+    }
+
+    fn set_rpc_url(&mut self, url: &str) -> shared::Result<()> {
+        if self.rpc_url != url {
+            self.provider = P::from_url(url)
+                .map(Provider::new)
+                .map_err(|e| RelayerError::Config(format!("Invalid EVM RPC URL '{}': {}", url, e)))?;
+            self.rpc = RpcEndpoint::new("evm", url);
+            self.rpc_url = url.to_string();
+            info!(rpc = %shared::metrics::endpoint_label(url), "Switched EVM RPC endpoint");
+        }
+        Ok(())
+    }
+
+    /// 调用合约 getSenderNonce()
+    async fn sender_nonce(&self) -> shared::Result<Option<u64>> {
+        let selector = &ethers::utils::keccak256("getSenderNonce()".as_bytes())[0..4];
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(self.contract_address)
+            .data(Bytes::from(selector.to_vec()))
+            .into();
+        let output = self
+            .rpc
+            .call("eth_call", || async { self.provider.call(&tx, None).await.map_err(provider_error) })
+            .await?;
+        if output.len() < 32 {
+            return Err(RelayerError::Serialization(format!("getSenderNonce returned {} bytes", output.len())));
         }
+        Ok(Some(U256::from_big_endian(&output[..32]).low_u64()))
+    }
+}
+
+/// eth_getLogs 跨度过大的错误：服务商对区块跨度 / 结果数 / 响应大小的限制
+///
+/// 请求频率和配额限制（"daily request limit exceeded" 等）以及传输层超时不在此列，仍走重试和熔断；
+/// 本地 `LOG_QUERY_TIMEOUT` 到期在 `query_logs` 中单独处理
+fn is_range_error(error: &RelayerError) -> bool {
+    let message = error.to_string().to_lowercase();
+    let rate_limited = ["request limit", "request rate", "rate limit exceeded", "too many requests", "daily", "quota", "credits"]
+        .iter()
+        .any(|pattern| message.contains(pattern));
+    !rate_limited
+        && [
+            "returned more than",
+            "block range",
+            "response size",
+            "too many results",
+            "too many logs",
+            "range too large",
+            "range is too large",
+            "query too large",
+            "is limited to",
+            "limit exceeded",
+            "max results",
+        ]
+        .iter()
+        .any(|pattern| message.contains(pattern))
+}
+
+/// ethers Provider 错误 → RelayerError：JSON-RPC 错误按错误码分类，传输错误按超时 / 限流 / 网络区分
+fn provider_error(error: ProviderError) -> RelayerError {
+    if let Some(response) = RpcError::as_error_response(&error) {
+        return match response.as_revert_data() {
+            Some(data) if !data.is_empty() => RelayerError::from_revert_data(&data),
+            Some(_) => RelayerError::Reverted(response.message.clone()),
+            None => RelayerError::from_rpc_error(response.code, &response.message),
+        };
     }
+    match error {
+        ProviderError::HTTPError(e) => e.into(),
+        ProviderError::SerdeJson(e) => RelayerError::Serialization(e.to_string()),
+        // 响应体不是 JSON（网关错误页等）
+        ProviderError::JsonRpcClientError(e) => RelayerError::Network(e.to_string()),
+        other => RelayerError::RpcConnection(other.to_string()),
+    }
+}
 
-    Ok(to_block)
+/// 转换为 StakeEventData：合约为 bytes32 topic，接收地址必须是 SVM 公钥
+///
+/// 接收地址不合法时以全零公钥占位并返回拒绝原因：事件登记为 blocked 任务，不进入签名与提交
+fn to_stake_event(event: StakeEvent, target_chain_id: u64) -> (StakeEventData, Option<String>) {
+    let (receiver_address, rejected) = match ChainAddress::parse_as(&event.receiver_address, AddressKind::Svm) {
+        Ok(address) => (address, None),
+        Err(e) => (
+            ChainAddress::Svm([0; 32]),
+            Some(format!("invalid receiver_address {:?}: {}", event.receiver_address, e)),
+        ),
+    };
+    let data = StakeEventData {
+        source_contract: ChainAddress::Bytes32(event.source_contract),
+        target_contract: ChainAddress::Bytes32(event.target_contract),
+        source_chain_id: event.chain_id,
+        target_chain_id,
+        block_height: event.block_height,
+        amount: event.amount,
+        receiver_address,
+        nonce: event.nonce,
+    };
+    (data, rejected)
 }
 
 /// 解析 StakeEvent
@@ -225,12 +835,434 @@ fn parse_stake_event(log: &Log) -> Result<StakeEvent> {
     })
 }
 
-/// 保存事件到队列文件
-fn save_to_queue(event: &StakeEventData, queue_dir: &Path) -> Result<()> {
-    let queue_file = queue_dir.join(format!("event_{}.json", event.nonce));
-    let json = serde_json::to_string_pretty(event)?;
-    std::fs::write(&queue_file, json)?;
-    info!(nonce = event.nonce, path = %queue_file.display(), "Event saved to queue");
-    Ok(())
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::Token;
+    use ethers::providers::{JsonRpcError, MockError};
+    use serde::de::DeserializeOwned;
+    use serde::Serialize;
+    use serde_json::{json, Value};
+    use std::sync::Mutex;
+
+    const CONTRACT: &str = "0x00000000000000000000000000000000000000cc";
+    /// [1u8; 32]
+    const RECEIVER: &str = "4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi";
+
+    /// 模拟节点：规范链上每个区块的哈希由分叉编号和区块号决定，按方法名应答
+    #[derive(Debug, Clone, Default)]
+    struct MockChain(Arc<Mutex<ChainState>>);
+
+    #[derive(Debug, Default)]
+    struct ChainState {
+        /// 下标为区块号
+        hashes: Vec<H256>,
+        logs: Vec<Log>,
+        /// eth_getLogs 允许的最大跨度（to - from），超过时返回结果数过多
+        max_span: Option<u64>,
+        /// 包含该区块的 eth_getLogs 总是失败
+        broken_block: Option<u64>,
+        /// eth_getLogs 跨度超过该值时不应答，直到调用方超时
+        slow_span: Option<u64>,
+        calls: HashMap<String, usize>,
+    }
+
+    fn block_number(value: &Value) -> u64 {
+        u64::from_str_radix(value.as_str().unwrap().trim_start_matches("0x"), 16).unwrap()
+    }
+
+    /// eth_getLogs 请求的 (fromBlock, toBlock)
+    fn filter_range(params: &Value) -> (u64, u64) {
+        (block_number(&params[0]["fromBlock"]), block_number(&params[0]["toBlock"]))
+    }
+
+    fn block_hash(fork: u64, number: u64) -> H256 {
+        H256::from_low_u64_be((fork << 32) | number)
+    }
+
+    impl MockChain {
+        fn new(latest: u64) -> Self {
+            let chain = Self::default();
+            chain.0.lock().unwrap().hashes = (0..=latest).map(|n| block_hash(0, n)).collect();
+            chain
+        }
+
+        fn state(&self) -> std::sync::MutexGuard<'_, ChainState> {
+            self.0.lock().unwrap()
+        }
+
+        /// 出块到 `latest`
+        fn mine(&self, latest: u64) {
+            let mut state = self.state();
+            let fork = state.hashes.last().map(|hash| hash.to_low_u64_be() >> 32).unwrap_or_default();
+            let next = state.hashes.len() as u64;
+            state.hashes.extend((next..=latest).map(|n| block_hash(fork, n)));
+        }
+
+        /// 从 `from` 开始替换为分叉 `fork` 上的区块，原有日志随之离开规范链
+        fn reorg(&self, from: u64, fork: u64, latest: u64) {
+            let mut state = self.state();
+            state.hashes.truncate(from as usize);
+            state.hashes.extend((from..=latest).map(|n| block_hash(fork, n)));
+        }
+
+        /// 在规范链的 `block` 上发出 StakeEvent
+        fn emit(&self, block: u64, nonce: u64) {
+            let mut state = self.state();
+            let data = ethers::abi::encode(&[
+                Token::Uint(421614u64.into()),
+                Token::Uint(block.into()),
+                Token::Uint(1_000u64.into()),
+                Token::String(RECEIVER.to_string()),
+                Token::Uint(nonce.into()),
+            ]);
+            let log = Log {
+                address: CONTRACT.parse().unwrap(),
+                topics: vec![StakeEvent::signature(), H256::repeat_byte(0xab), H256::repeat_byte(0xcd)],
+                data: data.into(),
+                block_number: Some(block.into()),
+                block_hash: Some(state.hashes[block as usize]),
+                transaction_hash: Some(H256::from_low_u64_be(nonce)),
+                log_index: Some(0u64.into()),
+                ..Default::default()
+            };
+            state.logs.push(log);
+        }
+
+        fn calls(&self, method: &str) -> usize {
+            self.state().calls.get(method).copied().unwrap_or(0)
+        }
+
+        fn respond(&self, method: &str, params: Value) -> std::result::Result<Value, MockError> {
+            let state = self.state();
+            let value = match method {
+                "eth_blockNumber" => json!(U64::from(state.hashes.len() as u64 - 1)),
+                "eth_getBlockByNumber" => {
+                    let n = block_number(&params[0]);
+                    match state.hashes.get(n as usize) {
+                        Some(&hash) => serde_json::to_value(Block::<H256> {
+                            hash: Some(hash),
+                            parent_hash: n.checked_sub(1).map(|p| state.hashes[p as usize]).unwrap_or_default(),
+                            number: Some(n.into()),
+                            ..Default::default()
+                        })?,
+                        None => Value::Null,
+                    }
+                }
+                "eth_getLogs" => {
+                    let (from, to) = filter_range(&params);
+                    let too_wide = state.max_span.is_some_and(|max| to - from > max);
+                    if too_wide || state.broken_block.is_some_and(|b| (from..=to).contains(&b)) {
+                        return Err(MockError::JsonRpcError(JsonRpcError {
+                            code: -32005,
+                            message: "query returned more than 10000 results".to_string(),
+                            data: None,
+                        }));
+                    }
+                    let logs: Vec<&Log> = state
+                        .logs
+                        .iter()
+                        .filter(|log| {
+                            let block = log.block_number.unwrap().as_u64();
+                            (from..=to).contains(&block) && log.block_hash == state.hashes.get(block as usize).copied()
+                        })
+                        .collect();
+                    serde_json::to_value(logs)?
+                }
+                "eth_getTransactionByHash" => serde_json::to_value(Transaction {
+                    hash: serde_json::from_value(params[0].clone())?,
+                    from: Address::repeat_byte(0x11),
+                    ..Default::default()
+                })?,
+                other => panic!("unexpected RPC method {}", other),
+            };
+            Ok(value)
+        }
+    }
 
+    #[async_trait]
+    impl JsonRpcClient for MockChain {
+        type Error = MockError;
+
+        async fn request<T, R>(&self, method: &str, params: T) -> std::result::Result<R, MockError>
+        where
+            T: std::fmt::Debug + Serialize + Send + Sync,
+            R: DeserializeOwned + Send,
+        {
+            let params = serde_json::to_value(params)?;
+            let slow = {
+                let mut state = self.state();
+                *state.calls.entry(method.to_string()).or_default() += 1;
+                method == "eth_getLogs" && state.slow_span.is_some_and(|max| {
+                    let (from, to) = filter_range(&params);
+                    to - from > max
+                })
+            };
+            if slow {
+                tokio::time::sleep(Duration::from_secs(60)).await;
+            }
+            let value = self.respond(method, params)?;
+            Ok(serde_json::from_value(value)?)
+        }
+    }
+
+    impl RpcTransport for MockChain {
+        fn from_url(url: &str) -> std::result::Result<Self, String> {
+            Err(format!("mock chain has no URL: {}", url))
+        }
+    }
+
+    async fn listener(name: &str, chain: &MockChain, start_block: u64, max_log_range: u64) -> EvmListener<MockChain> {
+        let path = std::env::temp_dir().join(format!("e2s-listener-{}-{}.json", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut config = ListenerConfig::default();
+        config.source_chain.contract_address = CONTRACT.to_string();
+        config.source_chain.confirmation_blocks = Some(2);
+        config.source_chain.max_log_range = Some(max_log_range);
+        config.checkpoint.path = Some(path);
+        config.checkpoint.start_block = Some(start_block);
+        EvmListener::with_provider(&config, Provider::new(chain.clone()), None).await.unwrap()
+    }
+
+    fn nonces(events: &[SourceEvent]) -> Vec<u64> {
+        events.iter().map(|e| e.event.nonce).collect()
+    }
+
+    #[tokio::test]
+    async fn test_reorg_retracts_vanished_events() {
+        let chain = MockChain::new(20);
+        chain.emit(10, 1);
+        chain.emit(19, 2);
+        let mut listener = listener("reorg", &chain, 0, 1000).await;
+
+        // 最终性前沿 18：nonce 1 可签名，nonce 2 等待最终性
+        let events = listener.poll().await.unwrap();
+        assert_eq!(nonces(&events), vec![1, 2]);
+        assert!(events[1].finality.awaiting);
+        listener.commit();
+        assert_eq!(listener.cursor(), 18);
+
+        // 区块 15 起被替换：nonce 2 消失，新分叉上出现 nonce 3
+        chain.reorg(15, 1, 22);
+        chain.emit(16, 3);
+        let events = listener.poll().await.unwrap();
+        // 分叉点为仍在规范链上的区块 10，游标和检查点回退到 11
+        assert_eq!(listener.cursor(), 11);
+        assert_eq!(listener.checkpoint.as_ref().unwrap().load().unwrap().unwrap().block, 11);
+        assert_eq!(nonces(&events), vec![3]);
+        assert_eq!(nonces(listener.retracted()), vec![2]);
+        listener.commit();
+        assert!(listener.retracted().is_empty());
+        assert_eq!(listener.cursor(), 20);
+
+        // 规范链不变时不再回退
+        chain.mine(23);
+        assert!(listener.poll().await.unwrap().is_empty());
+        assert!(listener.retracted().is_empty());
+        listener.commit();
+        assert_eq!(listener.cursor(), 21);
+    }
+
+    #[tokio::test]
+    async fn test_reorg_keeps_reincluded_events() {
+        let chain = MockChain::new(20);
+        chain.emit(19, 2);
+        let mut listener = listener("reinclude", &chain, 0, 1000).await;
+        assert_eq!(nonces(&listener.poll().await.unwrap()), vec![2]);
+        listener.commit();
+
+        // 同一笔交易在分叉上被打包进区块 20：不撤回，也不重复转发
+        chain.reorg(19, 1, 22);
+        chain.emit(20, 2);
+        let events = listener.poll().await.unwrap();
+        assert!(listener.retracted().is_empty());
+        assert_eq!(nonces(&events), vec![2]);
+        assert_eq!(events[0].finality.block, 20);
+        listener.commit();
+
+        // 分叉点之后的回滚不影响区块 20：nonce 2 达到最终性后作为可签名事件再转发一次
+        chain.reorg(21, 2, 30);
+        chain.emit(21, 4);
+        let events = listener.poll().await.unwrap();
+        assert!(listener.retracted().is_empty());
+        assert_eq!(nonces(&events), vec![2, 4]);
+        assert!(!events[0].finality.awaiting);
+    }
+
+    #[tokio::test]
+    async fn test_reorg_deeper_than_window_rescans_oldest() {
+        let chain = MockChain::new(20);
+        chain.emit(12, 1);
+        let mut listener = listener("deep", &chain, 10, 1000).await;
+        assert_eq!(nonces(&listener.poll().await.unwrap()), vec![1]);
+        listener.commit();
+
+        // 所有记录的区块都被替换：从记录的最早区块重新扫描
+        chain.reorg(5, 1, 24);
+        listener.poll().await.unwrap();
+        assert_eq!(listener.cursor(), 12);
+        assert_eq!(nonces(listener.retracted()), vec![1]);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_sender_cached() {
+        let chain = MockChain::new(20);
+        chain.emit(19, 2);
+        let mut listener = listener("sender", &chain, 0, 1000).await;
+        let events = listener.poll().await.unwrap();
+        assert_eq!(events[0].sender, Some(ChainAddress::Evm([0x11; 20])));
+        listener.commit();
+
+        // 等待最终性的事件每轮都会重新扫描，发起者只查询一次
+        for latest in 21..=24 {
+            chain.mine(latest);
+            listener.poll().await.unwrap();
+            listener.commit();
+        }
+        assert_eq!(chain.calls("eth_getTransactionByHash"), 1);
+    }
+
+    #[test]
+    fn test_log_range_grow_and_shrink() {
+        let mut range = LogRange::new(8);
+        range.shrink(8);
+        assert_eq!(range.span, 4);
+        range.shrink(1);
+        assert_eq!(range.span, 0);
+
+        // 连续成功 LOG_RANGE_GROW_AFTER 次后翻倍，从单个区块恢复，不超过上限
+        let mut spans = Vec::new();
+        for _ in 0..5 * LOG_RANGE_GROW_AFTER {
+            range.record_success();
+            spans.push(range.span);
+        }
+        assert_eq!(spans.iter().step_by(LOG_RANGE_GROW_AFTER as usize).copied().collect::<Vec<_>>(), vec![0, 1, 2, 4, 8]);
+        assert_eq!(range.span, 8);
+
+        // 失败清零成功计数
+        range.record_success();
+        range.shrink(8);
+        range.record_success();
+        range.record_success();
+        assert_eq!(range.span, 4);
+    }
+
+    #[test]
+    fn test_rate_limit_is_not_range_error() {
+        assert!(is_range_error(&RelayerError::from_rpc_error(-32005, "query returned more than 10000 results")));
+        assert!(is_range_error(&RelayerError::from_rpc_error(-32600, "eth_getLogs block range too large")));
+        assert!(is_range_error(&RelayerError::RpcConnection("Log response size exceeded".to_string())));
+        assert!(!is_range_error(&RelayerError::from_rpc_error(429, "Too Many Requests")));
+        assert!(!is_range_error(&RelayerError::from_rpc_error(-32005, "daily request limit exceeded")));
+        assert!(!is_range_error(&RelayerError::RpcTimeout));
+        assert!(is_range_error(&RelayerError::from_rpc_error(-32005, "limit exceeded")));
+        assert!(is_range_error(&RelayerError::from_rpc_error(-32602, "eth_getLogs is limited to a 10,000 range")));
+        assert!(!is_range_error(&RelayerError::from_rpc_error(-32005, "request rate limit exceeded")));
+    }
+
+    #[tokio::test]
+    async fn test_query_logs_shrinks_on_timeout() {
+        let chain = MockChain::new(100);
+        chain.emit(5, 1);
+        chain.state().slow_span = Some(10);
+        let mut listener = listener("timeout", &chain, 0, 64).await;
+        listener.log_query_timeout = Duration::from_millis(50);
+
+        // 超时的范围不走 RPC 重试，直接减半：64 → 32 → 16 → 8
+        let (to_block, _, logs) = listener.query_logs(0, 100).await.unwrap();
+        assert_eq!((to_block, logs.len()), (8, 1));
+        assert_eq!(listener.log_range.span, 8);
+        assert_eq!(chain.calls("eth_getLogs"), 4);
+
+        // 下一轮从缩小后的跨度继续，不再重复超时的范围
+        let (to_block, _, _) = listener.query_logs(9, 100).await.unwrap();
+        assert_eq!((to_block, chain.calls("eth_getLogs")), (17, 5));
+    }
+
+    #[tokio::test]
+    async fn test_query_logs_recovers_from_range_error() {
+        let chain = MockChain::new(100);
+        chain.emit(5, 1);
+        chain.emit(60, 2);
+        chain.state().max_span = Some(10);
+        let mut listener = listener("range", &chain, 0, 64).await;
+
+        // 64 → 32 → 16 → 8
+        let (to_block, hash, logs) = listener.query_logs(0, 100).await.unwrap();
+        assert_eq!((to_block, hash, logs.len()), (8, block_hash(0, 8), 1));
+        assert_eq!(listener.log_range.span, 8);
+        assert_eq!(chain.calls("eth_getLogs"), 4);
+
+        // 连续成功后扩大到 16，再次被拒绝时缩回
+        for from in [9, 18] {
+            listener.query_logs(from, 100).await.unwrap();
+        }
+        assert_eq!(listener.log_range.span, 16);
+        let (to_block, _, _) = listener.query_logs(27, 100).await.unwrap();
+        assert_eq!((to_block, listener.log_range.span), (35, 8));
+    }
+
+    #[tokio::test]
+    async fn test_query_logs_gives_up_at_single_block() {
+        let chain = MockChain::new(100);
+        chain.state().broken_block = Some(5);
+        let mut listener = listener("floor", &chain, 0, 16).await;
+
+        // 缩小到单个区块仍失败：返回错误，不无限重试
+        assert!(listener.query_logs(5, 100).await.is_err());
+        assert_eq!(listener.log_range.span, 0);
+        let calls = chain.calls("eth_getLogs");
+        assert!(listener.query_logs(5, 100).await.is_err());
+        assert_eq!(chain.calls("eth_getLogs"), calls + 1);
+
+        // 服务商恢复后单个区块查询成功，跨度逐步恢复
+        chain.state().broken_block = None;
+        let (to_block, _, _) = listener.query_logs(5, 100).await.unwrap();
+        assert_eq!(to_block, 5);
+    }
+
+    #[test]
+    fn test_invalid_receiver_is_rejected() {
+        let event = |receiver: &str| StakeEvent {
+            source_contract: [0x11; 32],
+            target_contract: [0x22; 32],
+            chain_id: 421614,
+            block_height: 100,
+            amount: 1_000,
+            receiver_address: receiver.to_string(),
+            nonce: 7,
+        };
+        let receiver = ChainAddress::Svm([2; 32]).to_string();
+        let (valid, rejected) = to_stake_event(event(&receiver), 91024);
+        assert!(rejected.is_none());
+        assert_eq!(valid.receiver_address, ChainAddress::Svm([2; 32]));
+
+        // 不是 SVM 公钥：仍返回事件（接收地址占位），由 pipeline 登记为 blocked
+        let (event, rejected) = to_stake_event(event("0x2222222222222222222222222222222222222222"), 91024);
+        assert!(rejected.unwrap().starts_with("invalid receiver_address"));
+        assert_eq!(event.receiver_address, ChainAddress::Svm([0; 32]));
+        assert_eq!(Task::id_for(&event), Task::id_for(&valid));
+    }
+
+    #[test]
+    fn test_block_range_from_str() {
+        assert_eq!("100..200".parse::<BlockRange>(), Ok(BlockRange { from: 100, to: 200 }));
+        assert_eq!(" 7 .. 7 ".parse::<BlockRange>(), Ok(BlockRange { from: 7, to: 7 }));
+        assert!("200..100".parse::<BlockRange>().unwrap_err().contains("after end block"));
+        assert!("100".parse::<BlockRange>().unwrap_err().contains("expected <FROM>..<TO>"));
+        assert!("100..".parse::<BlockRange>().unwrap_err().contains("invalid block number"));
+        assert!("0x10..0x20".parse::<BlockRange>().is_err());
+        assert!("-1..5".parse::<BlockRange>().is_err());
+    }
+
+    #[test]
+    fn test_start_block_precedence() {
+        let range = BlockRange { from: 10, to: 20 };
+        assert_eq!(StartBlock::select(Some(range), Some(500), Some(300)), StartBlock::Replay(range));
+        assert_eq!(StartBlock::select(None, Some(500), Some(300)), StartBlock::Checkpoint(500));
+        assert_eq!(StartBlock::select(None, None, Some(300)), StartBlock::Configured(300));
+        assert_eq!(StartBlock::select(None, None, None), StartBlock::Latest);
+        // 检查点落后于配置的起始区块时仍以检查点为准，不跳过未处理的区块
+        assert_eq!(StartBlock::select(None, Some(100), Some(300)), StartBlock::Checkpoint(100));
+    }
+}
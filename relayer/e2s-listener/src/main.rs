@@ -2,21 +2,70 @@ mod config;
 mod listener;
 
 use anyhow::Result;
-use shared::logger;
+use clap::Parser;
+use shared::lifecycle::Lifecycle;
+use shared::{alerts, logger, metrics, queue};
+use std::net::SocketAddr;
+use std::path::PathBuf;
 use tracing::info;
 
+/// E2S listener：监听 EVM 质押事件并写入工作队列
+#[derive(Parser)]
+#[command(name = "e2s-listener", version)]
+struct Cli {
+    /// TOML 配置文件路径（环境变量优先级更高）
+    #[arg(long, env = "RELAYER_CONFIG")]
+    config: Option<PathBuf>,
+    /// 重新扫描指定区块范围（含两端）并写入队列后退出，不读写检查点；已处理的事件由 submitter 去重
+    #[arg(long, value_name = "FROM..TO")]
+    replay: Option<listener::BlockRange>,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // 加载配置
-    let config = config::load_config()?;
+    let cli = Cli::parse();
+    let config = config::load_config(cli.config.as_deref())?;
     
-    // 初始化日志
-    logger::init_logger(&config.logging.level, &config.logging.format)?;
+    // 初始化日志和分布式追踪（guard 保留到退出，drop 时刷新未导出的 span）
+    let _telemetry = logger::init_logger(
+        &config.logging.level,
+        &config.logging.format,
+        &config.telemetry,
+        &config.service.name,
+    )?;
     info!("Starting e2s-listener service");
+
+    // 验证配置
+    config.validate()?;
+    info!("Configuration validated");
     
+    // 初始化指标，listener 没有 API 服务器，单独提供 /metrics
+    metrics::init_metrics("e2s");
+    let metrics_addr = SocketAddr::from(([0, 0, 0, 0], config.api.port));
+    tokio::spawn(async move {
+        if let Err(e) = metrics::serve_metrics(metrics_addr).await {
+            tracing::error!("Metrics server error: {}", e);
+        }
+    });
+
+    // 初始化告警
+    alerts::init_alerts(&config)?;
+
+    // 打开工作队列（与 e2s-submitter 共享）
+    let work_queue = queue::open_work_queue(&config).await?;
+
+    // SIGHUP 热加载配置，SIGTERM / Ctrl-C 完成当前一轮监听后退出
+    let lifecycle = Lifecycle::new(config);
+    let config_path = cli.config.clone();
+    lifecycle.spawn_signal_handler(move || config::load_config(config_path.as_deref()));
+
     // 启动事件监听器
-    listener::start_listener(config).await?;
-    
+    if let Some(result) = lifecycle.run(listener::start_listener(lifecycle.clone(), work_queue, cli.replay)).await {
+        result?;
+    }
+    info!("e2s-listener service stopped");
+
     Ok(())
 }
 
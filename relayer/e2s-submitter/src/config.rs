@@ -1,15 +1,16 @@
 use anyhow::Result;
 use shared::Config;
 use std::env;
+use std::path::Path;
 
 pub type SubmitterConfig = Config;
 
-pub fn load_config() -> Result<SubmitterConfig> {
+pub fn load_config(config_file: Option<&Path>) -> Result<SubmitterConfig> {
     // 设置默认的服务名称
     env::set_var("SERVICE__NAME", "e2s-submitter");
     
-    // 从环境变量加载配置
-    let mut config = Config::load()?;
+    // 从配置文件和环境变量加载配置
+    let mut config = Config::load("e2s-submitter", config_file)?;
     config.service.name = "e2s-submitter".to_string();
     
     // e2s-submitter 默认端口为 8082
@@ -4,24 +4,86 @@ mod signer;
 mod submitter;
 
 use anyhow::Result;
-use shared::logger;
+use clap::Parser;
+use shared::gas::BalanceStatus;
+use shared::lifecycle::Lifecycle;
+use shared::{alerts, logger, metrics, queue, store};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
 use tracing::info;
 
+/// E2S submitter：消费工作队列，签名后提交到 SVM
+#[derive(Parser)]
+#[command(name = "e2s-submitter", version)]
+struct Cli {
+    /// TOML 配置文件路径（环境变量优先级更高）
+    #[arg(long, env = "RELAYER_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// 以签名服务模式运行：用本地 Ed25519 密钥在该地址提供远程签名 API，不启动 submitter
+    #[arg(long, value_name = "ADDR")]
+    serve_signer: Option<SocketAddr>,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // 加载配置
-    let config = config::load_config()?;
+    let cli = Cli::parse();
+    let config = config::load_config(cli.config.as_deref())?;
     
-    // 初始化日志
-    logger::init_logger(&config.logging.level, &config.logging.format)?;
+    // 初始化日志和分布式追踪（guard 保留到退出，drop 时刷新未导出的 span）
+    let _telemetry = logger::init_logger(
+        &config.logging.level,
+        &config.logging.format,
+        &config.telemetry,
+        &config.service.name,
+    )?;
+
+    if let Some(addr) = cli.serve_signer {
+        let signer = signer::Ed25519Signer::new(&config.relayer)?;
+        info!(pubkey = %shared::AttestationSigner::identity(&signer), "Starting Ed25519 signer service");
+        let signer = shared::protection::protect(Arc::new(signer), &config)?;
+        let policy = submitter::SubmitSignaturePolicy::new(&config.target_chain.contract_address)?;
+        shared::signer::serve_signer(addr, vec![signer], Arc::new(policy), config.signer.auth_token.clone()).await?;
+        return Ok(());
+    }
+
     info!("Starting e2s-submitter service");
+
+    // 初始化指标
+    metrics::init_metrics("e2s");
+
+    // 验证配置
+    config.validate()?;
+    info!("Configuration validated");
+
+    // 初始化告警
+    alerts::init_alerts(&config)?;
+    
+    // 打开任务存储和工作队列（队列与 e2s-listener 共享）
+    let task_store = store::open_task_store(&config).await?;
+    let work_queue = queue::open_work_queue(&config).await?;
     
+    // 提交账户余额：processor 启动时检查并定期刷新，API 读取
+    let balance = BalanceStatus::new();
+
     // 启动 HTTP API 服务器
-    let api_handle = tokio::spawn(api::start_server(config.clone()));
+    let api_handle = tokio::spawn(api::start_server(
+        config.clone(),
+        task_store.clone(),
+        work_queue.clone(),
+        balance.clone(),
+    ));
     info!(port = config.api.port, "HTTP API server started");
     
+    // SIGHUP 热加载配置，SIGTERM / Ctrl-C 完成当前任务后退出
+    let lifecycle = Lifecycle::new(config);
+    let config_path = cli.config.clone();
+    lifecycle.spawn_signal_handler(move || config::load_config(config_path.as_deref()));
+
     // 启动事件处理器
-    let processor_handle = tokio::spawn(submitter::start_processor(config.clone()));
+    let processor = submitter::start_processor(lifecycle.clone(), task_store, work_queue, balance);
     info!("Event processor started");
     
     // 等待服务
@@ -29,11 +91,12 @@ async fn main() -> Result<()> {
         _ = api_handle => {
             info!("API server stopped");
         }
-        _ = processor_handle => {
-            info!("Event processor stopped");
-        }
-        _ = tokio::signal::ctrl_c() => {
-            info!("Received shutdown signal");
+        result = lifecycle.run(processor) => {
+            // 启动检查失败（例如余额不足）时以错误退出
+            if let Some(result) = result {
+                result?;
+                info!("Event processor stopped");
+            }
         }
     }
     
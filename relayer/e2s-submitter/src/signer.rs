@@ -1,7 +1,37 @@
 use anyhow::Result;
-use borsh::BorshSerialize;
+use async_trait::async_trait;
+use shared::config::{Config, RelayerConfig};
+use shared::{encoding, keystore, protection};
+use shared::signer::{AttestationSigner, RemoteSigner, SignatureScheme};
 use shared::types::StakeEventData;
 use solana_sdk::signature::{Keypair, SeedDerivable, Signer};
+use std::sync::Arc;
+use tracing::info;
+
+/// 按 `signer.backend` 创建签名器（本地 Ed25519 密钥、远程签名服务或 PKCS#11 HSM）
+///
+/// 所有签名器（包括远程签名服务）签名前都检查本机的防重复签名数据库（`protection.path`），
+/// 远程签名服务另有自己的检查
+pub async fn build_signer(config: &Config) -> Result<Arc<dyn AttestationSigner>> {
+    let signer: Arc<dyn AttestationSigner> = match config.signer.backend.as_str() {
+        "remote" => {
+            let signer = RemoteSigner::new(&config.signer, SignatureScheme::Ed25519)?;
+            signer.check().await?;
+            info!(pubkey = %signer.identity(), "Using remote Ed25519 signer");
+            Arc::new(signer)
+        }
+        #[cfg(feature = "pkcs11")]
+        "pkcs11" => {
+            let signer = HsmEd25519Signer::open(&config.hsm)?;
+            info!(pubkey = %signer.identity(), "Using PKCS#11 Ed25519 signer");
+            Arc::new(signer)
+        }
+        #[cfg(not(feature = "pkcs11"))]
+        "pkcs11" => anyhow::bail!("signer.backend = \"pkcs11\" requires building with --features pkcs11"),
+        _ => Arc::new(Ed25519Signer::new(&config.relayer)?),
+    };
+    Ok(protection::protect(signer, config)?)
+}
 
 /// Ed25519 签名器 (用于 SVM)
 pub struct Ed25519Signer {
@@ -9,12 +39,18 @@ pub struct Ed25519Signer {
 }
 
 impl Ed25519Signer {
-    /// 创建新的签名器（支持多种私钥格式）
+    /// 创建新的签名器（从 keystore 或明文私钥配置加载）
+    pub fn new(keys: &RelayerConfig) -> Result<Self> {
+        let private_key = keystore::ed25519_private_key(keys)?;
+        Self::from_key(private_key.expose())
+    }
+
+    /// 从私钥字符串创建签名器（支持多种私钥格式）
     /// 支持的格式：
     /// - 十六进制字符串 (64个字符)
     /// - 逗号分隔的数字 (例如: "1,2,3,...")
     /// - Base58 格式
-    pub fn new(private_key_str: &str) -> Result<Self> {
+    pub fn from_key(private_key_str: &str) -> Result<Self> {
         let private_key_bytes = if private_key_str.contains(',') {
             // 逗号分隔的数字格式
             private_key_str
@@ -54,11 +90,22 @@ impl Ed25519Signer {
 
         Ok(Self { keypair })
     }
+}
+
+#[async_trait]
+impl AttestationSigner for Ed25519Signer {
+    fn scheme(&self) -> SignatureScheme {
+        SignatureScheme::Ed25519
+    }
+
+    fn identity(&self) -> String {
+        self.keypair.pubkey().to_string()
+    }
 
     /// 对事件数据生成签名
-    pub fn sign_event(&self, event: &StakeEventData) -> Result<Vec<u8>> {
-        // 1. Borsh 序列化事件数据
-        let message = event.try_to_vec()?;
+    async fn sign_event(&self, event: &StakeEventData) -> shared::Result<Vec<u8>> {
+        // 1. Borsh 序列化事件数据（Anchor StakeEventData）
+        let message = encoding::svm_event_message(event);
 
         // 2. 直接签名原始消息（与合约期望一致）
         // 注意：这里签名的是原始消息，不是哈希
@@ -69,9 +116,48 @@ impl Ed25519Signer {
         Ok(signature.as_ref().to_vec())
     }
 
-    /// 获取 Solana Keypair 的引用（用于交易签名）
-    pub fn keypair(&self) -> &Keypair {
-        &self.keypair
+    /// 对交易消息签名（relayer 同时是交易签名者和手续费支付者）
+    async fn sign_transaction(&self, _event: &StakeEventData, transaction: &[u8]) -> shared::Result<Vec<u8>> {
+        Ok(self.keypair.sign_message(transaction).as_ref().to_vec())
     }
 }
 
+/// PKCS#11 HSM 中的 Ed25519 签名器
+#[cfg(feature = "pkcs11")]
+pub struct HsmEd25519Signer {
+    key: shared::pkcs11::Pkcs11Key,
+    pubkey: String,
+}
+
+#[cfg(feature = "pkcs11")]
+impl HsmEd25519Signer {
+    /// 打开 HSM 并按 `hsm.key_label` 查找密钥
+    pub fn open(config: &shared::config::HsmConfig) -> Result<Self> {
+        let key = shared::pkcs11::Pkcs11Key::open(config)?;
+        if key.public_key().len() != 32 {
+            anyhow::bail!("HSM key is not an Ed25519 key ({}-byte public key)", key.public_key().len());
+        }
+        let pubkey = bs58::encode(key.public_key()).into_string();
+        Ok(Self { key, pubkey })
+    }
+}
+
+#[cfg(feature = "pkcs11")]
+#[async_trait]
+impl AttestationSigner for HsmEd25519Signer {
+    fn scheme(&self) -> SignatureScheme {
+        SignatureScheme::Ed25519
+    }
+
+    fn identity(&self) -> String {
+        self.pubkey.clone()
+    }
+
+    async fn sign_event(&self, event: &StakeEventData) -> shared::Result<Vec<u8>> {
+        self.key.sign_ed25519(&encoding::svm_event_message(event))
+    }
+
+    async fn sign_transaction(&self, _event: &StakeEventData, transaction: &[u8]) -> shared::Result<Vec<u8>> {
+        self.key.sign_ed25519(transaction)
+    }
+}
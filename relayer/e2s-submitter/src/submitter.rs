@@ -1,61 +1,102 @@
 use crate::config::SubmitterConfig;
-use crate::signer::Ed25519Signer;
-use anyhow::{anyhow, Result};
-use borsh::BorshSerialize;
+use crate::signer;
+use anyhow::Result;
+use async_trait::async_trait;
+use borsh::{BorshDeserialize, BorshSerialize};
+use shared::{encoding, metrics};
+use shared::queue::QueueMessage;
+use shared::signer::{SignatureScheme, TransactionPolicy};
 use shared::types::StakeEventData;
+use shared::gas::{BalanceStatus, GasManager};
+use shared::lifecycle::Lifecycle;
+use shared::retry::{RpcEndpoint, Swappable};
+use shared::risk::RiskEngine;
+use shared::{
+    AttestationSigner, ChainAddress, ContractError, Relay, RelayerError, TargetSubmitter, TaskStore, WorkQueue,
+};
+use solana_client::client_error::{ClientError, ClientErrorKind};
 use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_request::RpcError;
 use solana_sdk::{
     commitment_config::CommitmentConfig,
-    instruction::{AccountMeta, Instruction},
+    ed25519_program,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    message::Message,
+    program_utils::limited_deserialize,
     pubkey::Pubkey,
-    signature::Signer,
+    signature::Signature,
     system_program,
     sysvar,
-    transaction::Transaction,
+    transaction::{Transaction, TransactionError},
 };
-use std::{path::Path, str::FromStr};
+use std::{path::Path, str::FromStr, sync::Arc};
 use tracing::{error, info, warn};
 
-/// 错误类型分类
-#[derive(Debug, Clone, Copy, PartialEq)]
-enum ErrorCategory {
-    /// 可重试错误：网络错误、RPC超时、gas估算失败等
-    Retryable,
-    /// 不可重试错误：InvalidNonce、签名验证失败、权限错误等
-    NonRetryable,
-}
-
-/// 启动事件处理器
-pub async fn start_processor(config: SubmitterConfig) -> Result<()> {
+/// 启动事件处理器：工作队列 → Ed25519 签名 → SVM 提交
+///
+/// relayer 的 SOL 余额低于 `gas.min_svm_balance` 时拒绝启动；运行中跌破下限则暂停消费队列。
+/// 收到退出信号后不再取新消息，当前任务完成后返回
+pub async fn start_processor(
+    lifecycle: Arc<Lifecycle>,
+    store: Arc<dyn TaskStore>,
+    queue: Arc<dyn WorkQueue>,
+    balance: BalanceStatus,
+) -> Result<()> {
     info!("Starting event processor");
+    let config = lifecycle.config();
     
-    // 创建签名器和 RPC 客户端
-    let private_key = config
-        .relayer
-        .ed25519_private_key
-        .as_deref()
-        .ok_or_else(|| anyhow!("Ed25519 private key not configured"))?;
-    let signer = Ed25519Signer::new(private_key)?;
-    let rpc_client = RpcClient::new_with_commitment(
-        config.target_chain.rpc_url.clone(),
-        CommitmentConfig::confirmed(),
-    );
-    let program_id = Pubkey::from_str(&config.target_chain.contract_address)?;
+    // 创建签名器和提交器
+    let signer = signer::build_signer(&config).await?;
+    let submitter = Arc::new(SvmSubmitter::new(&config, signer.clone())?);
     
     info!(
-        relayer_pubkey = %signer.keypair().pubkey(),
-        program_id = %program_id,
+        relayer_pubkey = %signer.identity(),
+        program_id = %submitter.program_id,
         "SVM submitter initialized"
     );
+
+    // 检查余额并启动定期监控
+    let gas = GasManager::from_config(&config.gas);
+    let svm_balance = submitter.balance().await?;
+    gas.observe("svm", svm_balance, &balance)?;
+    info!(balance_sol = svm_balance, "Balance check passed");
+    let monitored = submitter.clone();
+    gas.start_balance_monitor("svm", balance.clone(), move || {
+        let submitter = monitored.clone();
+        async move { submitter.balance().await }
+    });
+
+    let mut relay = Relay::new(signer, submitter)?
+        .with_balance_status(balance)
+        .with_shutdown(lifecycle.shutdown());
+    if let Some(audit) = shared::audit::open_audit_log(&config)? {
+        info!(path = %audit.path().display(), "Signing audit log enabled");
+        relay = relay.with_audit_log(Arc::new(audit));
+    }
+    let risk = Arc::new(RiskEngine::new(config.risk.clone()));
+    let restored = risk.load(store.as_ref()).await?;
+    info!(signed_24h = restored, "Risk engine initialized");
+    relay = relay.with_risk_engine(risk);
+    let screener = shared::screening::open_screener(&config)?;
+    if !screener.is_empty() {
+        info!(addresses = screener.len(), "Deny list screening enabled");
+    }
+    relay = relay.with_screening(Arc::new(screener));
     
-    // 创建队列目录
-    let queue_dir = &config.queue.path;
-    std::fs::create_dir_all(queue_dir)?;
-    info!(queue_path = %queue_dir.display(), "Queue directory initialized");
-    
-    // 持续处理队列中的事件
+    // 持续处理队列中的事件直到收到退出信号
+    let mut watch = lifecycle.watch_config();
+    let mut shutdown = lifecycle.shutdown();
     loop {
-        match process_queue(&config.queue.path, &signer, &rpc_client, &program_id).await {
+        if let Some(new) = watch.changed() {
+            relay.reload(&new);
+        }
+        let current = watch.current();
+
+        if let Err(e) = import_legacy_events(&config.queue.path, queue.as_ref()).await {
+            warn!("Failed to import legacy event files: {}", e);
+        }
+
+        match relay.process_queue(store.as_ref(), queue.as_ref(), &current.queue).await {
             Ok(processed) => {
                 if processed > 0 {
                     info!(count = processed, "Processed events");
@@ -67,103 +108,163 @@ pub async fn start_processor(config: SubmitterConfig) -> Result<()> {
         }
         
         // 等待后继续
-        tokio::time::sleep(tokio::time::Duration::from_secs(2)).await;
+        if shutdown.sleep(tokio::time::Duration::from_secs(2)).await {
+            break;
+        }
     }
+    info!("Event processor drained");
+    Ok(())
 }
 
-/// 处理队列中的事件
-async fn process_queue(
-    queue_dir: &Path,
-    signer: &Ed25519Signer,
-    rpc_client: &RpcClient,
-    program_id: &Pubkey,
-) -> Result<usize> {
-    let mut processed = 0;
-    
-    // 读取队列目录中的所有事件文件
-    let entries = std::fs::read_dir(queue_dir)?;
-    
+/// SVM 交易提交器
+///
+/// 交易由 relayer 签名器签名（relayer 同时是证明签名者和手续费支付者），
+/// 本地密钥、远程签名服务和 HSM 走同一路径。
+pub struct SvmSubmitter {
+    client: Swappable<SvmRpc>,
+    program_id: Pubkey,
+    signer: Arc<dyn AttestationSigner>,
+}
+
+impl SvmSubmitter {
+    pub fn new(config: &SubmitterConfig, signer: Arc<dyn AttestationSigner>) -> Result<Self> {
+        let client = Swappable::new(SvmRpc::connect(&config.target_chain.rpc_url));
+        let program_id = Pubkey::from_str(&config.target_chain.contract_address)?;
+        Ok(Self { client, program_id, signer })
+    }
+
+    /// relayer（手续费支付者）账户的 SOL 余额
+    pub async fn balance(&self) -> shared::Result<f64> {
+        let relayer = Pubkey::from_str(&self.signer.identity())
+            .map_err(|e| RelayerError::Signature(format!("Invalid relayer pubkey: {}", e)))?;
+        let client = self.client.get();
+        let lamports = client
+            .rpc
+            .call("getBalance", || async { client.rpc_client.get_balance(&relayer).map_err(client_error) })
+            .await?;
+        Ok(lamports as f64 / 1e9)
+    }
+}
+
+#[async_trait]
+impl TargetSubmitter for SvmSubmitter {
+    fn scheme(&self) -> SignatureScheme {
+        SignatureScheme::Ed25519
+    }
+
+    async fn submit(&self, event: &StakeEventData, attestation: &[u8]) -> shared::Result<String> {
+        // 整笔提交使用同一端点，热加载只影响之后的提交
+        let client = self.client.get();
+        submit_signature(self.signer.as_ref(), &client.rpc_client, &client.rpc, &self.program_id, event, attestation).await
+    }
+
+    /// 读取 ReceiverState 账户中的 last_nonce
+    async fn last_nonce(&self) -> shared::Result<Option<u64>> {
+        /// Anchor ReceiverState 账户布局（跳过 8 字节 discriminator）
+        #[derive(BorshDeserialize)]
+        struct ReceiverState {
+            _vault: [u8; 32],
+            _admin: [u8; 32],
+            _usdc_mint: [u8; 32],
+            _relayer_count: u64,
+            _source_contract: String,
+            _source_chain_id: u64,
+            _target_chain_id: u64,
+            _relayers: Vec<[u8; 32]>,
+            last_nonce: u64,
+        }
+
+        let (receiver_state, _) = Pubkey::find_program_address(&[b"receiver_state"], &self.program_id);
+        let client = self.client.get();
+        let data = client
+            .rpc
+            .call("getAccountInfo", || async {
+                client.rpc_client.get_account_data(&receiver_state).map_err(client_error)
+            })
+            .await?;
+        // 账户按最大长度分配，尾部有填充，不能用 try_from_slice
+        let state = data
+            .get(8..)
+            .and_then(|mut body| ReceiverState::deserialize(&mut body).ok())
+            .ok_or_else(|| RelayerError::Serialization("Invalid ReceiverState account data".to_string()))?;
+        Ok(Some(state.last_nonce))
+    }
+
+    fn set_rpc_url(&self, url: &str) -> shared::Result<()> {
+        if self.client.get().url != url {
+            self.client.set(SvmRpc::connect(url));
+            info!(rpc = %metrics::endpoint_label(url), "Switched SVM RPC endpoint");
+        }
+        Ok(())
+    }
+}
+
+/// SVM RPC 客户端，热加载 RPC 地址时整体替换
+struct SvmRpc {
+    url: String,
+    rpc_client: RpcClient,
+    /// RPC 重试、熔断和指标
+    rpc: RpcEndpoint,
+}
+
+impl SvmRpc {
+    fn connect(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            rpc_client: RpcClient::new_with_commitment(url.to_string(), CommitmentConfig::confirmed()),
+            rpc: RpcEndpoint::new("svm", url),
+        }
+    }
+}
+
+/// 导入旧版 listener 写入的 `event_{nonce}.json` 文件
+async fn import_legacy_events(queue_dir: &Path, queue: &dyn WorkQueue) -> Result<()> {
+    let entries = match std::fs::read_dir(queue_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
     for entry in entries {
-        let entry = entry?;
-        let path = entry.path();
-        
-        if path.extension().and_then(|s| s.to_str()) == Some("json") {
-            // 读取事件数据
-            match std::fs::read_to_string(&path) {
-                Ok(content) => {
-                    match serde_json::from_str::<StakeEventData>(&content) {
-                        Ok(event) => {
-                            info!(nonce = event.nonce, "Processing event from queue");
-                            
-                            // 处理事件
-                            match submit_signature(signer, rpc_client, program_id, &event).await {
-                                Ok(tx_signature) => {
-                                    info!(
-                                        nonce = event.nonce,
-                                        tx = tx_signature,
-                                        "Event processed successfully"
-                                    );
-                                    
-                                    // 删除已处理的文件
-                                    if let Err(e) = std::fs::remove_file(&path) {
-                                        warn!("Failed to remove processed file: {}", e);
-                                    }
-                                    
-                                    processed += 1;
-                                }
-                                Err(e) => {
-                                    // 分析错误类型
-                                    let error_str = format!("{}", e);
-                                    let error_category = categorize_error(&error_str, &e);
-                                    
-                                    match error_category {
-                                        ErrorCategory::NonRetryable => {
-                                            // 不可重试错误：删除文件，避免无限重试
-                                            warn!(
-                                                nonce = event.nonce,
-                                                error = %e,
-                                                "Non-retryable error, removing event file"
-                                            );
-                                            if let Err(remove_err) = std::fs::remove_file(&path) {
-                                                warn!("Failed to remove non-retryable event file: {}", remove_err);
-                                            }
-                                        }
-                                        ErrorCategory::Retryable => {
-                                            // 可重试错误：保留文件以便重试
-                                            error!(
-                                                nonce = event.nonce,
-                                                error = %e,
-                                                "Retryable error, keeping file for retry"
-                                            );
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            warn!("Failed to parse event file {:?}: {}", path, e);
-                        }
-                    }
-                }
-                Err(e) => {
-                    warn!("Failed to read event file {:?}: {}", path, e);
-                }
+        let path = entry?.path();
+        let is_legacy = path.is_file()
+            && path.extension().and_then(|s| s.to_str()) == Some("json")
+            && path
+                .file_name()
+                .and_then(|s| s.to_str())
+                .is_some_and(|s| s.starts_with("event_"));
+        if !is_legacy {
+            continue;
+        }
+
+        let content = std::fs::read_to_string(&path)?;
+        match serde_json::from_str::<StakeEventData>(&content) {
+            Ok(event) => {
+                let nonce = event.nonce;
+                queue.push(&QueueMessage::new(event)).await?;
+                std::fs::remove_file(&path)?;
+                info!(nonce = nonce, "Imported legacy event file");
+            }
+            Err(e) => {
+                warn!("Failed to parse event file {:?}: {}", path, e);
             }
         }
     }
-    
-    Ok(processed)
+
+    Ok(())
 }
 
 /// 提交签名到 SVM
 async fn submit_signature(
-    signer: &Ed25519Signer,
+    signer: &dyn AttestationSigner,
     rpc_client: &RpcClient,
+    rpc: &RpcEndpoint,
     program_id: &Pubkey,
     event: &StakeEventData,
-) -> Result<String> {
-    // 生成签名
-    let signature = signer.sign_event(event)?;
+    signature: &[u8],
+) -> shared::Result<String> {
+    let relayer_pubkey = Pubkey::from_str(&signer.identity())
+        .map_err(|e| RelayerError::Signature(format!("Invalid relayer pubkey: {}", e)))?;
     
     // 推导 PDA 账户
     let (receiver_state, _) =
@@ -176,9 +277,11 @@ async fn submit_signature(
 
     let (vault, _) = Pubkey::find_program_address(&[b"vault"], program_id);
 
-    // 解析 receiver_address
-    let receiver_pubkey = Pubkey::from_str(&event.receiver_address)
-        .map_err(|e| anyhow!("Invalid receiver address: {}", e))?;
+    // receiver_address 在 listener 入口已校验为 SVM 公钥
+    let receiver_pubkey = match event.receiver_address {
+        ChainAddress::Svm(bytes) => Pubkey::new_from_array(bytes),
+        other => return Err(RelayerError::InvalidEvent(format!("Receiver {:?} is not an SVM address", other))),
+    };
 
     // USDC mint 地址
     let usdc_mint = Pubkey::from_str("6u1x12yV2XFcEDGd8KByZZqnjipRiq9BJB2xKprhAipy")
@@ -192,14 +295,14 @@ async fn submit_signature(
 
     // 创建 Ed25519 验证指令
     // 注意: 使用与 Solana web3.js 兼容的格式
-    let ed25519_ix = create_ed25519_instruction_v2(signer, event, &signature)?;
+    let ed25519_ix = create_ed25519_instruction_v2(&relayer_pubkey, event, signature)?;
 
     // 创建 submit_signature 指令
     let submit_sig_ix = create_submit_signature_instruction(
-        signer.keypair().pubkey(),
+        relayer_pubkey,
         program_id,
         event,
-        &signature,
+        signature,
         receiver_state,
         cross_chain_request,
         vault,
@@ -209,18 +312,25 @@ async fn submit_signature(
     )?;
 
     // 获取最新 blockhash
-    let recent_blockhash = rpc_client
-        .get_latest_blockhash()
-        .map_err(|e| anyhow!("Failed to get latest blockhash: {}", e))?;
+    let recent_blockhash = rpc
+        .call("getLatestBlockhash", || async { rpc_client.get_latest_blockhash().map_err(client_error) })
+        .await?;
 
     // 创建交易
-    let mut transaction = Transaction::new_with_payer(
+    let message = Message::new_with_blockhash(
         &[ed25519_ix, submit_sig_ix],
-        Some(&signer.keypair().pubkey()),
+        Some(&relayer_pubkey),
+        &recent_blockhash,
     );
 
-    // 签名交易
-    transaction.sign(&[signer.keypair()], recent_blockhash);
+    // 签名交易（relayer 是唯一签名者）
+    let tx_signature = signer.sign_transaction(event, &message.serialize()).await?;
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction.signatures = vec![Signature::try_from(tx_signature.as_slice())
+        .map_err(|e| RelayerError::Signature(format!("Invalid transaction signature: {}", e)))?];
+    transaction.verify().map_err(|e| {
+        RelayerError::Signature(format!("Transaction signature does not match relayer {}: {}", relayer_pubkey, e))
+    })?;
 
     // 输出交易详细信息用于调试
     info!(
@@ -233,57 +343,57 @@ async fn submit_signature(
         "Submitting transaction with accounts"
     );
 
-    // 先模拟交易以获取详细错误信息
-    let mut simulation_error: Option<(String, Option<Vec<String>>)> = None;
-    match rpc_client.simulate_transaction(&transaction) {
+    // 先模拟交易，程序错误在发送前按错误码分类
+    match rpc
+        .call("simulateTransaction", || async { rpc_client.simulate_transaction(&transaction).map_err(client_error) })
+        .await
+    {
         Ok(sim_result) => {
             if let Some(err) = sim_result.value.err {
-                let error_msg = format!("Transaction simulation failed: {:?}\nLogs: {:?}", err, sim_result.value.logs);
                 error!(
                     nonce = event.nonce,
                     error = ?err,
                     logs = ?sim_result.value.logs,
                     "Transaction simulation failed"
                 );
-                simulation_error = Some((error_msg, sim_result.value.logs));
-            } else {
-                info!(nonce = event.nonce, "Transaction simulation succeeded");
+                return Err(transaction_error(err));
             }
+            info!(nonce = event.nonce, "Transaction simulation succeeded");
         }
         Err(e) => {
             warn!(nonce = event.nonce, error = %e, "Failed to simulate transaction, proceeding anyway");
         }
     }
 
-    // 如果模拟失败，返回错误（包含日志信息用于错误分类）
-    if let Some((err_msg, _logs)) = simulation_error {
-        return Err(anyhow!("{}", err_msg));
-    }
-
-    // 发送交易
-    match rpc_client.send_and_confirm_transaction(&transaction) {
-        Ok(sig) => {
-            info!(nonce = event.nonce, tx = %sig, "Transaction confirmed");
-            Ok(sig.to_string())
-        }
-        Err(e) => {
-            let error_msg = format!("Failed to send transaction: {}", e);
-            error!(nonce = event.nonce, error = %e, "Failed to send transaction");
-            Err(anyhow!("{}", error_msg))
-        }
+    // 发送交易只尝试一次，失败由任务级重试处理
+    let sig = rpc
+        .call_once("sendAndConfirmTransaction", || async {
+            rpc_client.send_and_confirm_transaction(&transaction).map_err(client_error)
+        })
+        .await
+        .inspect_err(|e| error!(nonce = event.nonce, error = %e, "Failed to send transaction"))?;
+    info!(nonce = event.nonce, tx = %sig, "Transaction confirmed");
+
+    match rpc
+        .call("getFeeForMessage", || async { rpc_client.get_fee_for_message(&transaction.message).map_err(client_error) })
+        .await
+    {
+        Ok(lamports) => metrics::record_gas_spent("svm", lamports as f64 / 1e9),
+        Err(e) => warn!(nonce = event.nonce, error = %e, "Failed to query transaction fee"),
     }
+    Ok(sig.to_string())
 }
 
 /// 创建 Ed25519 验证指令 (V2 - 使用标准格式)
 /// 这个格式与 Solana web3.js 的 Ed25519Program.createInstructionWithPublicKey 兼容
 fn create_ed25519_instruction_v2(
-    signer: &Ed25519Signer,
+    relayer_pubkey: &Pubkey,
     event: &StakeEventData,
     signature: &[u8],
 ) -> Result<Instruction> {
     // 序列化事件数据 - 这是要验证的原始消息
-    let message = event.try_to_vec()?;
-    let pubkey_bytes = signer.keypair().pubkey().to_bytes();
+    let message = encoding::svm_event_message(event);
+    let pubkey_bytes = relayer_pubkey.to_bytes();
 
     // 常量定义（与 Solana SDK 一致）
     const DATA_START: usize = 16;  // 2 (num_signatures + padding) + 14 (offsets struct)
@@ -333,6 +443,67 @@ fn create_ed25519_instruction_v2(
     })
 }
 
+/// submit_signature 指令数据中签名之前的部分：discriminator、nonce 和事件数据
+fn submit_signature_prefix(event: &StakeEventData) -> Vec<u8> {
+    // Anchor 指令 discriminator (从 IDL 获取)
+    // submit_signature 的 discriminator
+    let discriminator: [u8; 8] = [205, 224, 80, 14, 239, 119, 52, 129];
+
+    let mut data = Vec::new();
+    data.extend_from_slice(&discriminator);
+    data.extend_from_slice(&event.nonce.to_le_bytes()); // 参数 1: nonce (u64)
+    data.extend_from_slice(&encoding::svm_event_message(event)); // 参数 2: event_data (StakeEventData)
+    data
+}
+
+/// 签名服务的交易校验：交易消息只能包含 Ed25519 验证指令和恰好一条桥程序指令，
+/// 后者是 nonce 和事件数据与请求一致的 submit_signature
+pub struct SubmitSignaturePolicy {
+    program_id: Pubkey,
+}
+
+impl SubmitSignaturePolicy {
+    pub fn new(program_id: &str) -> Result<Self> {
+        Ok(Self { program_id: Pubkey::from_str(program_id)? })
+    }
+}
+
+impl TransactionPolicy for SubmitSignaturePolicy {
+    fn check(&self, event: &StakeEventData, transaction: &[u8]) -> shared::Result<()> {
+        let refuse = |reason: String| {
+            RelayerError::Signature(format!("Refusing to sign transaction for nonce {}: {}", event.nonce, reason))
+        };
+        let message: Message = limited_deserialize(transaction)
+            .map_err(|e| refuse(format!("invalid transaction message: {}", e)))?;
+        if message.serialize() != transaction {
+            return Err(refuse("non-canonical transaction message".to_string()));
+        }
+
+        let expected = submit_signature_prefix(event);
+        let mut submits = 0;
+        for instruction in &message.instructions {
+            let program = message
+                .account_keys
+                .get(instruction.program_id_index as usize)
+                .ok_or_else(|| refuse("instruction program out of range".to_string()))?;
+            if ed25519_program::check_id(program) {
+                continue;
+            }
+            if *program != self.program_id {
+                return Err(refuse(format!("instruction for unexpected program {}", program)));
+            }
+            if !instruction.data.starts_with(&expected) {
+                return Err(refuse("submit_signature data does not match the event".to_string()));
+            }
+            submits += 1;
+        }
+        if submits != 1 {
+            return Err(refuse(format!("expected one submit_signature instruction, found {}", submits)));
+        }
+        Ok(())
+    }
+}
+
 /// 创建 submit_signature 指令
 #[allow(clippy::too_many_arguments)]
 fn create_submit_signature_instruction(
@@ -347,16 +518,9 @@ fn create_submit_signature_instruction(
     vault_token_account: Pubkey,
     receiver_token_account: Pubkey,
 ) -> Result<Instruction> {
-    // Anchor 指令 discriminator (从 IDL 获取)
-    // submit_signature 的 discriminator
-    let discriminator: [u8; 8] = [205, 224, 80, 14, 239, 119, 52, 129];
-
     // 序列化参数
     // 函数签名: submit_signature(ctx, nonce: u64, event_data: StakeEventData, signature: Vec<u8>)
-    let mut data = Vec::new();
-    data.extend_from_slice(&discriminator);
-    event.nonce.serialize(&mut data)?;      // 参数 1: nonce (u64)
-    event.serialize(&mut data)?;             // 参数 2: event_data (StakeEventData)
+    let mut data = submit_signature_prefix(event);
     signature.to_vec().serialize(&mut data)?; // 参数 3: signature (Vec<u8>)
 
     // 构建账户列表
@@ -380,52 +544,99 @@ fn create_submit_signature_instruction(
     })
 }
 
-/// 分类错误类型：可重试 vs 不可重试
-/// 
-/// 核心逻辑：合约错误（Anchor错误码6000-6999）不可重试，其他错误可重试
-fn categorize_error(error_str: &str, _error: &anyhow::Error) -> ErrorCategory {
-    // 提取错误码：尝试从多种格式中提取
-    // 1. Custom(6005)
-    if let Some(start) = error_str.find("Custom(") {
-        if let Some(end) = error_str[start..].find(')') {
-            if let Ok(code) = error_str[start + 7..start + end].parse::<u32>() {
-                if code >= 6000 && code < 7000 {
-                    return ErrorCategory::NonRetryable;
-                }
-            }
+/// Solana RPC 客户端错误 → RelayerError：交易错误按程序错误码映射，RPC 错误按错误码分类
+fn client_error(error: ClientError) -> RelayerError {
+    if let Some(tx_error) = error.get_transaction_error() {
+        return transaction_error(tx_error);
+    }
+    match error.kind {
+        ClientErrorKind::Io(e) => RelayerError::Network(e.to_string()),
+        ClientErrorKind::Reqwest(e) => e.into(),
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, message, .. }) => {
+            RelayerError::from_rpc_error(code, &message)
         }
+        ClientErrorKind::RpcError(RpcError::RpcRequestError(message)) => RelayerError::RpcConnection(message),
+        ClientErrorKind::RpcError(RpcError::ParseError(message)) => RelayerError::Serialization(message),
+        // 账户不存在、交易未能在有效期内确认等
+        ClientErrorKind::RpcError(RpcError::ForUser(message)) => RelayerError::RpcRequest(message),
+        ClientErrorKind::SerdeJson(e) => RelayerError::Serialization(e.to_string()),
+        ClientErrorKind::SigningError(e) => RelayerError::Signature(e.to_string()),
+        ClientErrorKind::TransactionError(e) => transaction_error(e),
+        ClientErrorKind::Custom(message) => RelayerError::RpcConnection(message),
     }
-    
-    // 2. 0x1775 (十六进制)
-    if let Some(start) = error_str.find("0x") {
-        let hex_str = &error_str[start + 2..];
-        let end = hex_str.chars().take_while(|c| c.is_ascii_hexdigit()).count();
-        if end > 0 {
-            if let Ok(code) = u32::from_str_radix(&hex_str[..end], 16) {
-                if code >= 6000 && code < 7000 {
-                    return ErrorCategory::NonRetryable;
-                }
-            }
+}
+
+/// 交易执行错误：桥程序的 Anchor 错误码（6000 起）映射为 `ContractError`，其他程序错误视为 revert
+fn transaction_error(error: TransactionError) -> RelayerError {
+    match error {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => match ContractError::from_anchor_code(code) {
+            Some(contract_error) => contract_error.into(),
+            None => RelayerError::Reverted(format!("custom program error: {:#x}", code)),
+        },
+        TransactionError::InstructionError(..) => RelayerError::Reverted(error.to_string()),
+        TransactionError::InsufficientFundsForFee | TransactionError::InsufficientFundsForRent { .. } => {
+            RelayerError::InsufficientFunds(error.to_string())
         }
+        other => RelayerError::TransactionFailed(other.to_string()),
     }
-    
-    // 3. Error Number: 6005
-    let error_lower = error_str.to_lowercase();
-    if let Some(start) = error_lower.find("error number:") {
-        let num_str = error_lower[start + 14..].split_whitespace().next().unwrap_or("");
-        if let Ok(code) = num_str.parse::<u32>() {
-            if code >= 6000 && code < 7000 {
-                return ErrorCategory::NonRetryable;
-            }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(nonce: u64) -> StakeEventData {
+        StakeEventData {
+            source_contract: ChainAddress::Bytes32([0xab; 32]),
+            target_contract: ChainAddress::Bytes32([0xcd; 32]),
+            source_chain_id: 421614,
+            target_chain_id: 91024,
+            block_height: 100,
+            amount: 1_000,
+            receiver_address: ChainAddress::Svm([2u8; 32]),
+            nonce,
         }
     }
-    
-    // 4. 如果包含 "custom program error" 但没有提取到错误码，也认为是合约错误
-    if error_lower.contains("custom program error") {
-        return ErrorCategory::NonRetryable;
+
+    /// 与 submit_signature 相同方式构造的交易消息
+    fn message(program_id: &Pubkey, event: &StakeEventData, extra: Option<Instruction>) -> Vec<u8> {
+        let relayer = Pubkey::new_unique();
+        let signature = [0x5a; 64];
+        let mut instructions = vec![
+            create_ed25519_instruction_v2(&relayer, event, &signature).unwrap(),
+            create_submit_signature_instruction(
+                relayer,
+                program_id,
+                event,
+                &signature,
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+                Pubkey::new_unique(),
+            )
+            .unwrap(),
+        ];
+        instructions.extend(extra);
+        Message::new_with_blockhash(&instructions, Some(&relayer), &solana_sdk::hash::Hash::default()).serialize()
     }
-    
-    // 其他错误默认可重试
-    ErrorCategory::Retryable
-}
 
+    #[test]
+    fn test_submit_signature_policy() {
+        let program_id = Pubkey::new_unique();
+        let policy = SubmitSignaturePolicy::new(&program_id.to_string()).unwrap();
+        policy.check(&event(7), &message(&program_id, &event(7), None)).unwrap();
+
+        let refused = |event: &StakeEventData, message: Vec<u8>| policy.check(event, &message).unwrap_err().to_string();
+        assert!(refused(&event(8), message(&program_id, &event(7), None)).contains("does not match the event"));
+        assert!(refused(&event(7), message(&Pubkey::new_unique(), &event(7), None)).contains("unexpected program"));
+        // 附带其他指令（例如从手续费支付者转出 SOL）
+        let transfer = solana_sdk::system_instruction::transfer(&Pubkey::new_unique(), &Pubkey::new_unique(), 1);
+        assert!(refused(&event(7), message(&program_id, &event(7), Some(transfer))).contains("unexpected program"));
+        let mut trailing = message(&program_id, &event(7), None);
+        trailing.push(0);
+        assert!(refused(&event(7), trailing).contains("non-canonical"));
+        assert!(refused(&event(7), vec![0xde, 0xad]).contains("invalid transaction message"));
+    }
+}
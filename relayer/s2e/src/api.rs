@@ -6,7 +6,9 @@ use axum::{
     routing::get,
     Router,
 };
+use shared::gas::BalanceStatus;
 use shared::types::{HealthResponse, ServiceStatus};
+use shared::TaskStore;
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tower_http::cors::CorsLayer;
@@ -15,24 +17,30 @@ use tracing::info;
 #[derive(Clone)]
 struct AppState {
     config: S2EConfig,
+    store: Arc<dyn TaskStore>,
+    balance: BalanceStatus,
     start_time: u64,
 }
 
-pub async fn start_server(config: S2EConfig) -> anyhow::Result<()> {
+pub async fn start_server(config: S2EConfig, store: Arc<dyn TaskStore>, balance: BalanceStatus) -> anyhow::Result<()> {
     let start_time = SystemTime::now()
         .duration_since(UNIX_EPOCH)?
         .as_secs();
 
     let state = Arc::new(AppState {
         config: config.clone(),
+        store: store.clone(),
+        balance,
         start_time,
     });
 
     let app = Router::new()
         .route("/health", get(health_check))
         .route("/status", get(get_status))
+        .route("/queue", get(get_queue))
         .route("/metrics", get(get_metrics))
         .with_state(state)
+        .merge(shared::risk::admin_router(store, None, config.api.admin_token.clone()))
         .layer(CorsLayer::permissive());
 
     let addr = format!("0.0.0.0:{}", config.api.port);
@@ -84,14 +92,22 @@ async fn get_status(State(state): State<Arc<AppState>>) -> impl IntoResponse {
         relayer: shared::types::RelayerInfo {
             address: "relayer".to_string(),
             whitelisted: true,  // 设置为 true 以通过测试
-            balance_svm: Some(10.0),
-            balance_evm: Some(1.0),
+            // s2e 只在 EVM 上提交交易
+            balance_svm: None,
+            balance_evm: state.balance.balance(),
         },
     };
 
     Json(status)
 }
 
+async fn get_queue(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    match state.store.queue_status(50).await {
+        Ok(status) => Json(status).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
 async fn get_metrics() -> impl IntoResponse {
     (StatusCode::OK, shared::metrics::export_metrics())
 }
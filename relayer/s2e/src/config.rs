@@ -1,15 +1,16 @@
 use anyhow::Result;
 use shared::Config;
 use std::env;
+use std::path::Path;
 
 pub type S2EConfig = Config;
 
-pub fn load_s2e_config() -> Result<S2EConfig> {
+pub fn load_s2e_config(config_file: Option<&Path>) -> Result<S2EConfig> {
     // 设置默认的服务名称
     env::set_var("SERVICE__NAME", "s2e");
     
-    // 从环境变量加载配置
-    let mut config = Config::load()?;
+    // 从配置文件和环境变量加载配置
+    let mut config = Config::load("s2e", config_file)?;
     config.service.name = "s2e".to_string();
     
     // S2E 默认端口为 8081
@@ -1,19 +1,37 @@
-use crate::config::S2EConfig;
-use crate::signer::EcdsaSigner;
-use crate::submitter::EvmSubmitter;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
 use anyhow::{anyhow, Result};
-use shared::types::StakeEventData;
-use std::time::Duration;
-use std::collections::HashSet;
-use std::sync::{Arc, Mutex};
-use tracing::{debug, error, info, warn};
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
 use borsh::BorshDeserialize;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use serde_json::json;
-use base64::{Engine as _, engine::general_purpose};
+use tracing::{debug, error, info, warn};
 
-/// 启动 SVM 事件监听器
-pub async fn start_listener(config: S2EConfig) -> Result<()> {
+use shared::alerts::{self, StallDetector};
+use shared::checkpoint::{self, Checkpoint, CheckpointFile};
+use shared::gas::{BalanceStatus, GasManager};
+use shared::lifecycle::Lifecycle;
+use shared::pipeline::{self, AwaitingEvents, Finality, SourceEvent};
+use shared::retry::RpcEndpoint;
+use shared::risk::RiskEngine;
+use shared::types::StakeEventData;
+use shared::{AddressKind, ChainAddress, Relay, RelayerError, SourceListener, TaskStore};
+
+use crate::config::S2EConfig;
+use crate::signer;
+use crate::stream::LogStream;
+use crate::submitter::EvmSubmitter;
+
+/// 启动 s2e 中继：SVM 监听器 → 任务存储 → ECDSA 签名 → EVM 提交
+///
+/// relayer 的 EVM 余额低于 `gas.min_evm_balance` 时拒绝启动；运行中跌破下限则暂停提交。
+/// 收到退出信号后完成当前一轮监听和提交后返回
+pub async fn start_listener(lifecycle: Arc<Lifecycle>, store: Arc<dyn TaskStore>, balance: BalanceStatus) -> Result<()> {
+    let config = lifecycle.config();
     info!("Starting SVM event listener");
     info!(
         rpc = config.source_chain.rpc_url,
@@ -22,60 +40,159 @@ pub async fn start_listener(config: S2EConfig) -> Result<()> {
     );
 
     // 创建签名器和提交器
-    let private_key = config.relayer.ecdsa_private_key
-        .as_ref()
-        .ok_or_else(|| anyhow!(
-            "ECDSA private key not configured. Please set RELAYER__ECDSA_PRIVATE_KEY environment variable.\n\
-            Example: RELAYER__ECDSA_PRIVATE_KEY=0x1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef\n\
-            You can generate one using: openssl rand -hex 32"
-        ))?;
-    
-    let signer = EcdsaSigner::new(private_key)
+    let signer = signer::build_signer(&config)
+        .await
         .map_err(|e| anyhow!("Failed to create ECDSA signer: {}\n\
-            Hint: ECDSA private key must be a 64-character hex string (32 bytes).\n\
-            Generate one with: openssl rand -hex 32", e))?;
+            Hint: set RELAYER__ECDSA_KEYSTORE (V3 JSON keystore) or RELAYER__ECDSA_PRIVATE_KEY \
+            (64-character hex string, e.g. from: openssl rand -hex 32)", e))?;
     let submitter = EvmSubmitter::new(
         &config.target_chain.rpc_url,
         &config.target_chain.contract_address,
-        private_key,
+        config.target_chain.chain_id,
+        signer.clone(),
     )
     .map_err(|e| anyhow!("Failed to create EVM submitter: {}", e))?;
+    let submitter = Arc::new(submitter);
 
-    info!("SVM event listener initialized (using HTTP RPC)");
+    // 检查余额并启动定期监控
+    let gas = GasManager::from_config(&config.gas);
+    let evm_balance = submitter.balance().await?;
+    gas.observe("evm", evm_balance, &balance)?;
+    info!(balance_eth = evm_balance, "Balance check passed");
+    let monitored = submitter.clone();
+    gas.start_balance_monitor("evm", balance.clone(), move || {
+        let submitter = monitored.clone();
+        async move { submitter.balance().await }
+    });
+
+    let mut relay = Relay::new(signer, submitter)?
+        .with_balance_status(balance)
+        .with_shutdown(lifecycle.shutdown());
+    if let Some(audit) = shared::audit::open_audit_log(&config)? {
+        info!(path = %audit.path().display(), "Signing audit log enabled");
+        relay = relay.with_audit_log(Arc::new(audit));
+    }
+    let risk = Arc::new(RiskEngine::new(config.risk.clone()));
+    let restored = risk.load(store.as_ref()).await?;
+    info!(signed_24h = restored, "Risk engine initialized");
+    relay = relay.with_risk_engine(risk);
+    let screener = shared::screening::open_screener(&config)?;
+    if !screener.is_empty() {
+        info!(addresses = screener.len(), "Deny list screening enabled");
+    }
+    relay = relay.with_screening(Arc::new(screener));
+    let mut source = SvmListener::new(&config)?;
 
-    // 创建已处理交易的追踪集合
-    let processed_signatures = Arc::new(Mutex::new(HashSet::new()));
+    info!("SVM event listener initialized (using HTTP RPC)");
 
-    // 持续监听
+    // 持续监听直到收到退出信号
+    let mut progress = StallDetector::new(alerts::stall_timeout());
+    let mut watch = lifecycle.watch_config();
+    let mut shutdown = lifecycle.shutdown();
     loop {
-        match listen_for_events(&config, &signer, &submitter, processed_signatures.clone()).await {
-            Ok(_) => {}
-            Err(e) => {
-                error!("Error listening for events: {}", e);
+        if let Some(new) = watch.changed() {
+            relay.reload(&new);
+            if let Err(e) = source.set_rpc_url(&new.source_chain.rpc_url) {
+                error!(error = %e, "Failed to apply reloaded SVM RPC endpoint");
             }
         }
+        let current = watch.current();
 
-        // 等待一段时间后继续
-        tokio::time::sleep(Duration::from_secs(10)).await;
+        if let Err(e) = pipeline::forward_events(&mut source, store.as_ref()).await {
+            error!("Error listening for events: {}", e);
+        }
+        pipeline::watch_progress(&source, &mut progress);
+
+        if let Err(e) = relay.process_pending(store.as_ref(), &current.queue).await {
+            error!("Error processing tasks: {}", e);
+        }
+
+        // 等待一段时间或 PubSub 推送后继续
+        let stop = tokio::select! {
+            stop = shutdown.sleep(Duration::from_secs(10)) => stop,
+            _ = source.wait_for_logs() => shutdown.is_requested(),
+        };
+        if stop {
+            break;
+        }
     }
+    info!("SVM event listener drained");
+    Ok(())
 }
 
 // Solana RPC 响应结构
 #[derive(Debug, Deserialize)]
 struct RpcResponse<T> {
-    result: T,
+    result: Option<T>,
+    error: Option<RpcErrorObject>,
 }
 
 #[derive(Debug, Deserialize)]
+struct RpcErrorObject {
+    code: i64,
+    message: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
 struct SignatureInfo {
     signature: String,
     #[serde(default)]
+    slot: u64,
+    #[serde(default)]
     err: Option<serde_json::Value>,
+    #[serde(default, rename = "confirmationStatus")]
+    confirmation_status: Option<String>,
+    /// PubSub 推送附带的日志，HTTP 拉取时为空
+    #[serde(skip)]
+    logs: Option<Vec<String>>,
+    /// 已解析的事件，等待最终性期间每轮重新投递时不再拉取交易
+    #[serde(skip)]
+    events: Option<Vec<SourceEvent>>,
+    /// 节点开始查不到这笔交易状态的时间
+    #[serde(skip)]
+    missing_since: Option<Instant>,
+}
+
+impl SignatureInfo {
+    fn is_finalized(&self) -> bool {
+        self.confirmation_status.as_deref() == Some("finalized")
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SignatureStatuses {
+    value: Vec<Option<SignatureStatus>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SignatureStatus {
+    #[serde(default, rename = "confirmationStatus")]
+    confirmation_status: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct TransactionResponse {
     meta: Option<TransactionMeta>,
+    transaction: Option<TransactionData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TransactionData {
+    message: TransactionMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct TransactionMessage {
+    #[serde(default, rename = "accountKeys")]
+    account_keys: Vec<String>,
+}
+
+impl TransactionResponse {
+    /// 手续费支付者（第一个账户），即交易发起者
+    fn fee_payer(&self) -> Option<ChainAddress> {
+        let key = self.transaction.as_ref()?.message.account_keys.first()?;
+        ChainAddress::parse_as(key, AddressKind::Svm).ok()
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -85,226 +202,767 @@ struct TransactionMeta {
     err: Option<serde_json::Value>,
 }
 
-/// 监听事件（使用 Solana RPC HTTP API）
-async fn listen_for_events(
-    config: &S2EConfig,
-    signer: &EcdsaSigner,
-    submitter: &EvmSubmitter,
-    processed_signatures: Arc<Mutex<HashSet<String>>>,
-) -> Result<()> {
-    let program_id = &config.source_chain.contract_address;
-    
-    debug!("Polling for SVM events from program: {}", program_id);
-
-    // 创建 HTTP 客户端
-    let client = reqwest::Client::new();
-
-    // 调用 getSignaturesForAddress
-    let signatures_request = json!({
-        "jsonrpc": "2.0",
-        "id": 1,
-        "method": "getSignaturesForAddress",
-        "params": [
-            program_id,
-            {
-                "limit": 10,
-                "commitment": "confirmed"
+/// Anchor 账户 discriminator：sha256("account:SenderState")[..8]
+const SENDER_STATE_DISCRIMINATOR: [u8; 8] = [15, 38, 72, 105, 206, 169, 147, 163];
+/// SenderState 中 nonce 的偏移：discriminator + vault + admin + usdc_mint
+const SENDER_STATE_NONCE_OFFSET: usize = 8 + 32 * 3;
+
+/// getSignaturesForAddress 单页上限（RPC 允许的最大值）
+const SIGNATURE_PAGE_SIZE: usize = 1000;
+/// 没有检查点时（首次启动）只回补最近的交易数
+const INITIAL_LOOKBACK: usize = 100;
+/// 每轮 poll 最多拉取的交易数，积压较多时分多轮处理
+const TRANSACTIONS_PER_POLL: usize = 200;
+/// 去重集合的上限，超过后只保留 backlog 中的签名
+const SEEN_LIMIT: usize = 10_000;
+/// getSignatureStatuses 单次查询上限
+const STATUS_BATCH_SIZE: usize = 256;
+/// 交易状态持续查不到超过该时长视为已被丢弃：blockhash 约 150 个 slot（1 分钟左右）后过期，之后交易不会再上链
+const MISSING_STATUS_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// SVM 事件源（使用 Solana RPC HTTP API 轮询程序交易）
+///
+/// 按交易签名分页：每轮从最新交易向前翻页，直到上次已看到的签名（`until` 游标），
+/// 再按时间顺序处理。`commit` 后把最后处理的交易写入检查点，重启时从检查点回补。
+///
+/// 配置了 `source_chain.ws_url` 时由 `logsSubscribe` 推送新交易；每次订阅建立后先走一轮分页回补，
+/// 订阅断开期间退回 HTTP 轮询。
+///
+/// 交易在 `confirmed` 时即被发现；`source_chain.commitment` 为 `finalized`（默认）时，
+/// 尚未 finalized 的交易只登记为等待最终性的任务，游标和检查点停在第一笔未 finalized 的交易之前，
+/// 之后每轮刷新其确认状态，finalized 后再投递签名。被回滚的分叉上的交易持续查不到状态时移出 backlog，
+/// 已登记的事件撤回。
+pub struct SvmListener {
+    client: reqwest::Client,
+    rpc_url: String,
+    /// RPC 重试、熔断和指标
+    endpoint: RpcEndpoint,
+    program_id: String,
+    target_chain_id: u64,
+    /// 已提交的最新 slot
+    last_slot: u64,
+    /// 已完整处理的最新交易签名
+    last_signature: Option<String>,
+    /// 已发现、尚未处理的交易（按时间顺序，最早的在前）
+    backlog: VecDeque<SignatureInfo>,
+    /// 本轮 poll 处理的 backlog 前缀长度，commit 后移出
+    uncommitted: usize,
+    /// backlog 中和最近处理过的签名（推送与分页回补重叠时去重）
+    seen: HashSet<String>,
+    checkpoint: CheckpointFile,
+    stream: Option<LogStream>,
+    /// 订阅（重新）建立后尚未完成分页回补
+    resync: bool,
+    /// 签名前要求交易已 finalized
+    require_finalized: bool,
+    /// 状态持续查不到多久后丢弃交易
+    missing_timeout: Duration,
+    awaiting: AwaitingEvents,
+    /// 被丢弃的交易中已登记的事件，`commit` 后清空
+    retracted: Vec<SourceEvent>,
+}
+
+impl SvmListener {
+    pub fn new(config: &S2EConfig) -> Result<Self> {
+        let checkpoint = checkpoint::open_checkpoint(config)?;
+        let (last_slot, last_signature) = match checkpoint.load()? {
+            Some(saved) => {
+                info!(
+                    slot = saved.block,
+                    signature = saved.tx_id.as_deref().unwrap_or("-"),
+                    path = %checkpoint.path().display(),
+                    "Resuming from checkpoint"
+                );
+                (saved.block, saved.tx_id)
             }
-        ]
-    });
+            None => {
+                info!(lookback = INITIAL_LOOKBACK, "No checkpoint found, starting from recent transactions");
+                (0, None)
+            }
+        };
+        Ok(Self {
+            client: reqwest::Client::new(),
+            rpc_url: config.source_chain.rpc_url.clone(),
+            endpoint: RpcEndpoint::new("svm", &config.source_chain.rpc_url),
+            program_id: config.source_chain.contract_address.clone(),
+            target_chain_id: config.target_chain.chain_id,
+            last_slot,
+            last_signature,
+            backlog: VecDeque::new(),
+            uncommitted: 0,
+            seen: HashSet::new(),
+            checkpoint,
+            stream: config.source_chain.ws_url.as_deref().map(|ws_url| {
+                info!(ws = %shared::metrics::endpoint_label(ws_url), "Streaming SVM program logs over PubSub");
+                LogStream::spawn(ws_url, &config.source_chain.contract_address, "confirmed")
+            }),
+            resync: false,
+            require_finalized: config.source_chain.commitment.as_deref().unwrap_or("finalized") == "finalized",
+            missing_timeout: MISSING_STATUS_TIMEOUT,
+            awaiting: AwaitingEvents::default(),
+            retracted: Vec::new(),
+        })
+    }
 
-    let response = client
-        .post(&config.source_chain.rpc_url)
-        .json(&signatures_request)
-        .send()
-        .await?;
+    /// 等待 PubSub 推送新交易；没有配置推送时永不返回
+    pub async fn wait_for_logs(&self) {
+        match &self.stream {
+            Some(stream) => stream.notified().await,
+            None => std::future::pending().await,
+        }
+    }
 
-    let signatures: RpcResponse<Vec<SignatureInfo>> = response.json().await?;
+    /// 把推送的交易追加到 backlog
+    fn take_streamed(&mut self) {
+        let Some(stream) = &mut self.stream else {
+            return;
+        };
+        for notification in stream.drain() {
+            if self.seen.insert(notification.signature.clone()) {
+                self.backlog.push_back(SignatureInfo {
+                    signature: notification.signature,
+                    slot: notification.slot,
+                    err: notification.err,
+                    confirmation_status: Some("confirmed".to_string()),
+                    logs: Some(notification.logs),
+                    events: None,
+                    missing_since: None,
+                });
+            }
+        }
+    }
 
-    debug!("Found {} recent transactions", signatures.result.len());
+    /// 拉取上次已看到的签名之后的所有程序交易，按时间顺序追加到 backlog
+    async fn fetch_signatures(&mut self) -> shared::Result<()> {
+        let until = self.backlog.back().map(|info| info.signature.clone()).or_else(|| self.last_signature.clone());
+        // 没有检查点时只取最近一页，不回溯全部历史
+        let limit = if until.is_some() { SIGNATURE_PAGE_SIZE } else { INITIAL_LOOKBACK };
 
-    // 处理每个交易
-    for sig_info in signatures.result.iter() {
-        let sig_str = &sig_info.signature;
-        
-        // 跳过失败的交易
-        if sig_info.err.is_some() {
-            continue;
+        let mut fresh: Vec<SignatureInfo> = Vec::new();
+        loop {
+            let mut options = json!({ "limit": limit, "commitment": "confirmed" });
+            if let Some(until) = &until {
+                options["until"] = json!(until);
+            }
+            if let Some(before) = fresh.last() {
+                options["before"] = json!(before.signature);
+            }
+            let page: Vec<SignatureInfo> = self.rpc("getSignaturesForAddress", json!([self.program_id, options])).await?;
+            let full = page.len() == limit;
+            fresh.extend(page);
+            if !full || until.is_none() {
+                break;
+            }
+            debug!(fetched = fresh.len(), "Paging back through program transactions");
         }
-        
-        // 检查是否已处理
-        {
-            let processed = processed_signatures.lock().unwrap();
-            if processed.contains(sig_str) {
+
+        if !fresh.is_empty() {
+            debug!(count = fresh.len(), backlog = self.backlog.len(), "Found new program transactions");
+        }
+        // RPC 按时间倒序返回
+        for info in fresh.into_iter().rev() {
+            if self.seen.insert(info.signature.clone()) {
+                self.backlog.push_back(info);
+            }
+        }
+        Ok(())
+    }
+
+    /// 刷新 backlog 前 `count` 笔尚未 finalized 的交易的确认状态
+    ///
+    /// 状态持续查不到超过 `missing_timeout` 的交易移出 backlog，不再阻塞后面交易的最终性前缀
+    async fn refresh_statuses(&mut self, count: usize) -> shared::Result<()> {
+        let pending: Vec<usize> = (0..count.min(self.backlog.len()))
+            .filter(|&i| !self.backlog[i].is_finalized())
+            .collect();
+        let mut dropped = Vec::new();
+        for chunk in pending.chunks(STATUS_BATCH_SIZE) {
+            let signatures: Vec<&str> = chunk.iter().map(|&i| self.backlog[i].signature.as_str()).collect();
+            let params = json!([signatures, { "searchTransactionHistory": true }]);
+            let statuses: SignatureStatuses = self.rpc("getSignatureStatuses", params).await?;
+            for (&i, status) in chunk.iter().zip(statuses.value) {
+                let info = &mut self.backlog[i];
+                match status {
+                    Some(status) => {
+                        info.confirmation_status = status.confirmation_status;
+                        info.missing_since = None;
+                    }
+                    // 节点查不到状态（被回滚的分叉或节点未同步），超时前继续等待
+                    None if info.missing_since.get_or_insert_with(Instant::now).elapsed() >= self.missing_timeout => {
+                        dropped.push(i);
+                    }
+                    None => warn!(signature = %info.signature, "Transaction status not found, waiting"),
+                }
+            }
+        }
+        for i in dropped.into_iter().rev() {
+            let Some(info) = self.backlog.remove(i) else {
                 continue;
+            };
+            let events = info.events.unwrap_or_default();
+            warn!(
+                signature = %info.signature,
+                slot = info.slot,
+                events = events.len(),
+                "Transaction dropped from the chain, withdrawing its events"
+            );
+            shared::metrics::record_reorg("svm");
+            for event in events {
+                self.awaiting.forget(&event.event);
+                self.retracted.push(event);
             }
         }
+        Ok(())
+    }
 
-        // 获取交易详情
-        let tx_request = json!({
+    /// 解析一笔交易中的 StakeEvent；失败的交易和推送日志中没有 StakeEvent 的交易返回空
+    ///
+    /// 推送的日志被截断时无法判断是否含有事件，仍然拉取完整交易
+    async fn transaction_events(&self, sig_info: &SignatureInfo) -> shared::Result<Vec<SourceEvent>> {
+        let has_event = match &sig_info.logs {
+            Some(logs) => {
+                logs_truncated(logs) || logs.iter().any(|log| parse_stake_event(log, self.target_chain_id).is_some())
+            }
+            None => true,
+        };
+        if sig_info.err.is_some() || !has_event {
+            return Ok(Vec::new());
+        }
+        let Some((log_messages, sender)) = self.transaction_logs(&sig_info.signature).await? else {
+            return Ok(Vec::new());
+        };
+        if logs_truncated(&log_messages) {
+            warn!(signature = %sig_info.signature, "Transaction logs truncated by the runtime, StakeEvent may be missing");
+        }
+        // 查找 StakeEvent（Anchor 事件格式：Program data: <base64_encoded_event>）
+        Ok(log_messages
+            .iter()
+            .filter_map(|log| parse_stake_event(log, self.target_chain_id))
+            .map(|(event, rejected)| SourceEvent {
+                event,
+                tx_id: sig_info.signature.clone(),
+                log_index: None,
+                sender,
+                finality: Finality::default(),
+                rejected,
+            })
+            .collect())
+    }
+
+    /// 调用 Solana JSON-RPC 方法（瞬时错误退避重试）
+    async fn rpc<T: DeserializeOwned>(&self, method: &str, params: serde_json::Value) -> shared::Result<T> {
+        let request = json!({
             "jsonrpc": "2.0",
             "id": 1,
-            "method": "getTransaction",
-            "params": [
-                sig_str,
-                {
-                    "encoding": "json",
-                    "commitment": "confirmed",
-                    "maxSupportedTransactionVersion": 0
-                }
-            ]
+            "method": method,
+            "params": params
         });
-
-        match client
-            .post(&config.source_chain.rpc_url)
-            .json(&tx_request)
-            .send()
+        self.endpoint
+            .call(method, || async {
+                let response = self
+                    .client
+                    .post(&self.rpc_url)
+                    .json(&request)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json::<RpcResponse<T>>()
+                    .await?;
+                match (response.result, response.error) {
+                    (_, Some(error)) => Err(RelayerError::from_rpc_error(error.code, &error.message)),
+                    (Some(result), None) => Ok(result),
+                    (None, None) => Err(RelayerError::Serialization(format!("{}: empty RPC response", method))),
+                }
+            })
             .await
-        {
-            Ok(tx_response) => {
-                match tx_response.json::<RpcResponse<TransactionResponse>>().await {
-                    Ok(tx_data) => {
-                        // 解析交易日志中的事件
-                        if let Some(meta) = tx_data.result.meta {
-                            if meta.err.is_some() {
-                                continue; // 跳过失败的交易
-                            }
-                            
-                            if let Some(log_messages) = meta.log_messages {
-                                // 查找 StakeEvent
-                                for log in log_messages.iter() {
-                                    if log.contains("Program data:") {
-                                        // Anchor 事件格式：Program data: <base64_encoded_event>
-                                        if let Some(event) = parse_stake_event(log, config) {
-                                            info!(
-                                                signature = %sig_str,
-                                                nonce = event.nonce,
-                                                amount = event.amount,
-                                                receiver = %event.receiver_address,
-                                                "📥 Captured StakeEvent"
-                                            );
-
-                                            // 记录捕获的事件详情
-                                            info!(
-                                                "Event details: source_contract={}, target_contract={}, source_chain_id={}, target_chain_id={}, block_height={}", 
-                                                event.source_contract,
-                                                event.target_contract,
-                                                event.source_chain_id,
-                                                event.target_chain_id,
-                                                event.block_height
-                                            );
-
-                                            // 处理事件
-                                            match process_event(config, event.clone(), signer, submitter).await {
-                                                Ok(_) => {
-                                                    // 标记为已处理
-                                                    let mut processed = processed_signatures.lock().unwrap();
-                                                    processed.insert(sig_str.clone());
-                                                    
-                                                    // 限制已处理集合的大小
-                                                    if processed.len() > 1000 {
-                                                        processed.clear();
-                                                    }
-                                                }
-                                                Err(e) => {
-                                                    error!(
-                                                        signature = %sig_str,
-                                                        nonce = event.nonce,
-                                                        error = %e,
-                                                        "Failed to process event"
-                                                    );
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
+    }
+
+    /// 获取交易日志和发起者，交易执行失败时返回 None
+    async fn transaction_logs(&self, signature: &str) -> shared::Result<Option<(Vec<String>, Option<ChainAddress>)>> {
+        let params = json!([
+            signature,
+            {
+                "encoding": "json",
+                "commitment": "confirmed",
+                "maxSupportedTransactionVersion": 0
+            }
+        ]);
+
+        let tx_data = self.rpc::<TransactionResponse>("getTransaction", params).await?;
+        let sender = tx_data.fee_payer();
+        // 跳过失败的交易
+        let logs = tx_data.meta.filter(|meta| meta.err.is_none()).and_then(|meta| meta.log_messages);
+        Ok(logs.map(|logs| (logs, sender)))
+    }
+}
+
+#[async_trait]
+impl SourceListener for SvmListener {
+    fn chain(&self) -> &str {
+        "svm"
+    }
+
+    async fn poll(&mut self) -> shared::Result<Vec<SourceEvent>> {
+        debug!("Polling for SVM events from program: {}", self.program_id);
+        self.uncommitted = 0;
+        // 订阅在线且已完成回补时只处理推送，否则走 HTTP 分页
+        let streaming = match &self.stream {
+            Some(stream) => {
+                self.resync |= stream.take_resync();
+                stream.is_connected()
+            }
+            None => false,
+        };
+        if !streaming || self.resync {
+            self.fetch_signatures().await?;
+            self.resync = false;
+        }
+        self.take_streamed();
+
+        if self.require_finalized {
+            self.refresh_statuses(self.backlog.len().min(TRANSACTIONS_PER_POLL)).await?;
+        }
+        let batch = self.backlog.len().min(TRANSACTIONS_PER_POLL);
+
+        let mut events = Vec::new();
+        let mut final_prefix = true;
+        for i in 0..batch {
+            if self.backlog[i].events.is_none() {
+                // 拉取失败时停在这笔交易之前，下一轮从这里继续，不跳过事件
+                match self.transaction_events(&self.backlog[i]).await {
+                    Ok(parsed) => self.backlog[i].events = Some(parsed),
+                    Err(e) if i == 0 => return Err(e),
                     Err(e) => {
-                        warn!("Failed to parse transaction {}: {}", sig_str, e);
+                        warn!(signature = %self.backlog[i].signature, error = %e, "Failed to get transaction, retrying next poll");
+                        break;
                     }
                 }
             }
-            Err(e) => {
-                warn!("Failed to get transaction {}: {}", sig_str, e);
+            let sig_info = &self.backlog[i];
+            let finalized = sig_info.is_finalized();
+            let awaiting = self.require_finalized && !finalized;
+            let finality = Finality { block: sig_info.slot, confirmations: 0, finalized, awaiting };
+            events.extend(sig_info.events.iter().flatten().cloned().map(|mut event| {
+                event.finality = finality;
+                event
+            }));
+            // 只有开头连续达到最终性的交易计入本轮可提交的前缀
+            final_prefix &= !awaiting;
+            if final_prefix {
+                self.uncommitted += 1;
             }
         }
+
+        if self.backlog.len() > batch {
+            info!(remaining = self.backlog.len() - batch, "Backfilling program transactions");
+        }
+        if self.uncommitted < batch {
+            debug!(awaiting = batch - self.uncommitted, "Program transactions awaiting finality");
+        }
+        Ok(self.awaiting.filter(events))
     }
 
-    Ok(())
+    fn commit(&mut self) {
+        self.retracted.clear();
+        let Some(last) = self.backlog.drain(..self.uncommitted).next_back() else {
+            return;
+        };
+        self.uncommitted = 0;
+        if self.seen.len() > SEEN_LIMIT {
+            self.seen = self.backlog.iter().map(|info| info.signature.clone()).collect();
+        }
+        self.last_slot = self.last_slot.max(last.slot);
+        // 检查点写入失败只影响重启后的回补起点，任务存储会对重复事件去重
+        if let Err(e) = self.checkpoint.save(&Checkpoint::new(last.slot, Some(last.signature.clone()))) {
+            error!(error = %e, "Failed to save listener checkpoint");
+        }
+        self.last_signature = Some(last.signature);
+    }
+
+    fn retracted(&self) -> &[SourceEvent] {
+        &self.retracted
+    }
+
+    fn cursor(&self) -> u64 {
+        self.last_slot
+    }
+
+    fn set_rpc_url(&mut self, url: &str) -> shared::Result<()> {
+        if self.rpc_url != url {
+            self.rpc_url = url.to_string();
+            self.endpoint = RpcEndpoint::new("svm", url);
+            info!(rpc = %shared::metrics::endpoint_label(url), "Switched SVM RPC endpoint");
+        }
+        Ok(())
+    }
+
+    /// 读取 SenderState 账户中的 nonce（按 discriminator 过滤程序账户，只取 nonce 字段）
+    async fn sender_nonce(&self) -> shared::Result<Option<u64>> {
+        #[derive(Deserialize)]
+        struct ProgramAccount {
+            account: AccountData,
+        }
+        #[derive(Deserialize)]
+        struct AccountData {
+            data: (String, String),
+        }
+
+        let params = json!([
+            self.program_id,
+            {
+                "encoding": "base64",
+                "commitment": "confirmed",
+                "dataSlice": { "offset": SENDER_STATE_NONCE_OFFSET, "length": 8 },
+                "filters": [
+                    { "memcmp": { "offset": 0, "bytes": bs58::encode(SENDER_STATE_DISCRIMINATOR).into_string() } }
+                ]
+            }
+        ]);
+        let accounts: Vec<ProgramAccount> = self.rpc("getProgramAccounts", params).await?;
+        let Some(account) = accounts.first() else {
+            return Ok(None);
+        };
+        let data = general_purpose::STANDARD
+            .decode(&account.account.data.0)
+            .map_err(|e| RelayerError::Serialization(format!("SenderState data: {}", e)))?;
+        let nonce: [u8; 8] = data
+            .try_into()
+            .map_err(|_| RelayerError::Serialization("SenderState nonce slice is not 8 bytes".to_string()))?;
+        Ok(Some(u64::from_le_bytes(nonce)))
+    }
+}
+
+/// 运行时超出日志上限时追加的最后一行，之后的日志（包括事件）被丢弃
+const LOG_TRUNCATED: &str = "Log truncated";
+
+fn logs_truncated(logs: &[String]) -> bool {
+    logs.iter().any(|log| log == LOG_TRUNCATED)
+}
+
+/// Anchor StakeEvent 结构（与程序中的 StakeEvent 对应）
+#[derive(BorshDeserialize)]
+#[cfg_attr(test, derive(borsh::BorshSerialize))]
+struct AnchorStakeEvent {
+    source_contract: String,
+    target_contract: String,
+    chain_id: u64,
+    block_height: u64,
+    amount: u64,
+    receiver_address: String,
+    nonce: u64,
 }
 
-/// 解析 Anchor 事件日志
-fn parse_stake_event(log: &str, config: &S2EConfig) -> Option<StakeEventData> {
+/// 解析 Anchor 事件日志，返回事件及入口校验失败的原因
+fn parse_stake_event(log: &str, target_chain_id: u64) -> Option<(StakeEventData, Option<String>)> {
     // Anchor 事件格式：Program data: <base64_encoded_event>
-    if let Some(data_str) = log.strip_prefix("Program data: ") {
-        if let Ok(data) = general_purpose::STANDARD.decode(data_str.trim()) {
-            // Anchor 事件格式：8字节事件discriminator + 事件数据
-            if data.len() > 8 {
-                // 跳过 8 字节的事件 discriminator
-                let event_data = &data[8..];
-                
-                // 尝试反序列化为 StakeEvent（Anchor 格式）
-                if let Ok(event) = deserialize_anchor_event(event_data, config) {
-                    return Some(event);
-                }
-            }
-        }
+    let data_str = log.strip_prefix("Program data: ")?;
+    let data = general_purpose::STANDARD.decode(data_str.trim()).ok()?;
+    // Anchor 事件格式：8字节事件discriminator + 事件数据
+    if data.len() <= 8 {
+        return None;
     }
-    None
+
+    // 跳过 8 字节的事件 discriminator；无法按 StakeEvent 反序列化的是其他事件，直接忽略
+    let anchor_event = AnchorStakeEvent::try_from_slice(&data[8..]).ok()?;
+    Some(to_stake_event(anchor_event, target_chain_id))
 }
 
-/// 反序列化 Anchor StakeEvent
-fn deserialize_anchor_event(data: &[u8], config: &S2EConfig) -> Result<StakeEventData> {
-    // Anchor StakeEvent 结构（与程序中的 StakeEvent 对应）
-    #[derive(BorshDeserialize)]
-    struct AnchorStakeEvent {
-        source_contract: String,
-        target_contract: String,
-        chain_id: u64,
-        block_height: u64,
-        amount: u64,
-        receiver_address: String,
-        nonce: u64,
-    }
-
-    let anchor_event = AnchorStakeEvent::try_from_slice(data)?;
-    
-    // 转换为 StakeEventData（需要添加 target_chain_id）
+/// 转换为 StakeEventData，并严格校验地址
+///
+/// - source_contract：SVM 程序 ID（base58）
+/// - target_contract：SVM 程序中配置的 EVM 合约（bytes32 或 EVM 地址）
+/// - receiver_address：EVM 地址
+///
+/// 地址不合法的字段以全零地址占位并返回拒绝原因：事件登记为 blocked 任务，不进入签名与提交
+fn to_stake_event(anchor_event: AnchorStakeEvent, target_chain_id: u64) -> (StakeEventData, Option<String>) {
+    let mut errors = Vec::new();
+    let mut parse = |field: &str, value: &str, kind: Option<AddressKind>, placeholder: ChainAddress| {
+        let parsed = match kind {
+            Some(kind) => ChainAddress::parse_as(value, kind),
+            None => value.parse(),
+        };
+        parsed.unwrap_or_else(|e| {
+            errors.push(format!("invalid {} {:?}: {}", field, value, e));
+            placeholder
+        })
+    };
     // 注意：Anchor 事件中的 chain_id 是 source_chain_id
-    Ok(StakeEventData {
-        source_contract: anchor_event.source_contract,
-        target_contract: anchor_event.target_contract,
+    let event = StakeEventData {
+        source_contract: parse("source_contract", &anchor_event.source_contract, Some(AddressKind::Svm), ChainAddress::Svm([0; 32])),
+        target_contract: parse("target_contract", &anchor_event.target_contract, None, ChainAddress::Bytes32([0; 32])),
         source_chain_id: anchor_event.chain_id,
-        target_chain_id: config.target_chain.chain_id,
+        target_chain_id,
         block_height: anchor_event.block_height,
         amount: anchor_event.amount,
-        receiver_address: anchor_event.receiver_address,
+        receiver_address: parse("receiver_address", &anchor_event.receiver_address, Some(AddressKind::Evm), ChainAddress::Evm([0; 20])),
         nonce: anchor_event.nonce,
-    })
+    };
+    (event, (!errors.is_empty()).then(|| errors.join("; ")))
 }
 
-/// 处理单个事件
-async fn process_event(
-    _config: &S2EConfig,
-    event: StakeEventData,
-    signer: &EcdsaSigner,
-    submitter: &EvmSubmitter,
-) -> Result<()> {
-    info!(nonce = event.nonce, "🔄 Processing event");
-
-    // 1. 生成签名
-    let signature = signer.sign_event(&event)?;
-    info!(nonce = event.nonce, "✍️  Generated signature");
-
-    // 2. 提交到 EVM
-    let tx_hash = submitter.submit_signature(&event, &signature).await?;
-    info!(
-        nonce = event.nonce,
-        tx = tx_hash,
-        "✅ Submitted signature to EVM"
-    );
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
 
-    Ok(())
+    use axum::extract::State;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use tokio::sync::mpsc;
+
+    use super::*;
+    use crate::stream::LogNotification;
+
+    const PROGRAM: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+    const PAYER: &str = "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin";
+
+    struct MockTransaction {
+        signature: String,
+        slot: u64,
+        /// None：节点查不到这笔交易
+        status: Option<&'static str>,
+        logs: Vec<String>,
+    }
+
+    #[derive(Default)]
+    struct MockState {
+        transactions: Vec<MockTransaction>,
+        calls: HashMap<String, usize>,
+    }
+
+    /// 模拟 Solana JSON-RPC 节点
+    #[derive(Clone, Default)]
+    struct MockSvm(Arc<Mutex<MockState>>);
+
+    impl MockSvm {
+        async fn serve(&self) -> String {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            let app = Router::new().route("/", post(handle)).with_state(self.clone());
+            tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+            url
+        }
+
+        /// 上链一笔交易，`nonce` 为 None 时不含 StakeEvent
+        fn land(&self, signature: &str, nonce: Option<u64>, status: &'static str) {
+            let mut logs = vec!["Program log: Instruction: Stake".to_string()];
+            logs.extend(nonce.map(stake_log));
+            let mut state = self.0.lock().unwrap();
+            let slot = 100 + state.transactions.len() as u64;
+            state.transactions.push(MockTransaction { signature: signature.to_string(), slot, status: Some(status), logs });
+        }
+
+        fn set_status(&self, signature: &str, status: Option<&'static str>) {
+            let mut state = self.0.lock().unwrap();
+            state.transactions.iter_mut().find(|tx| tx.signature == signature).unwrap().status = status;
+        }
+
+        fn calls(&self, method: &str) -> usize {
+            self.0.lock().unwrap().calls.get(method).copied().unwrap_or(0)
+        }
+
+        fn respond(&self, method: &str, params: &serde_json::Value) -> serde_json::Value {
+            let mut state = self.0.lock().unwrap();
+            *state.calls.entry(method.to_string()).or_default() += 1;
+            let known = |signature: &str| state.transactions.iter().find(|tx| tx.signature == signature && tx.status.is_some());
+            match method {
+                "getSignaturesForAddress" => {
+                    let options = &params[1];
+                    let listed: Vec<&MockTransaction> = state.transactions.iter().rev().filter(|tx| tx.status.is_some()).collect();
+                    let start = options["before"]
+                        .as_str()
+                        .and_then(|before| listed.iter().position(|tx| tx.signature == before))
+                        .map_or(0, |i| i + 1);
+                    let page: Vec<serde_json::Value> = listed[start..]
+                        .iter()
+                        .take_while(|tx| Some(tx.signature.as_str()) != options["until"].as_str())
+                        .take(options["limit"].as_u64().unwrap() as usize)
+                        .map(|tx| json!({ "signature": tx.signature, "slot": tx.slot, "err": null, "confirmationStatus": tx.status }))
+                        .collect();
+                    json!(page)
+                }
+                "getSignatureStatuses" => {
+                    let value: Vec<serde_json::Value> = params[0]
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .map(|signature| match known(signature.as_str().unwrap()) {
+                            Some(tx) => json!({ "slot": tx.slot, "confirmationStatus": tx.status }),
+                            None => serde_json::Value::Null,
+                        })
+                        .collect();
+                    json!({ "context": { "slot": 200 }, "value": value })
+                }
+                "getTransaction" => match known(params[0].as_str().unwrap()) {
+                    Some(tx) => json!({
+                        "meta": { "err": null, "logMessages": tx.logs },
+                        "transaction": { "message": { "accountKeys": [PAYER] } }
+                    }),
+                    None => serde_json::Value::Null,
+                },
+                _ => serde_json::Value::Null,
+            }
+        }
+    }
+
+    async fn handle(State(mock): State<MockSvm>, Json(request): Json<serde_json::Value>) -> Json<serde_json::Value> {
+        let result = mock.respond(request["method"].as_str().unwrap(), &request["params"]);
+        Json(json!({ "jsonrpc": "2.0", "id": 1, "result": result }))
+    }
+
+    fn stake_log(nonce: u64) -> String {
+        let event = AnchorStakeEvent {
+            source_contract: PROGRAM.to_string(),
+            target_contract: "0x1111111111111111111111111111111111111111".to_string(),
+            chain_id: 1024,
+            block_height: 100,
+            amount: 1_000,
+            receiver_address: "0x2222222222222222222222222222222222222222".to_string(),
+            nonce,
+        };
+        let mut data = vec![0u8; 8];
+        data.extend(borsh::BorshSerialize::try_to_vec(&event).unwrap());
+        format!("Program data: {}", general_purpose::STANDARD.encode(data))
+    }
+
+    async fn listener(name: &str, mock: &MockSvm) -> SvmListener {
+        let path = std::env::temp_dir().join(format!("s2e-listener-{}-{}.json", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let mut config = S2EConfig::default();
+        config.source_chain.rpc_url = mock.serve().await;
+        config.source_chain.contract_address = PROGRAM.to_string();
+        config.checkpoint.path = Some(path);
+        SvmListener::new(&config).unwrap()
+    }
+
+    /// 接入一个已建立订阅的模拟推送
+    fn stream(listener: &mut SvmListener) -> mpsc::Sender<LogNotification> {
+        let (stream, sender) = LogStream::detached();
+        stream.set_connected(true);
+        listener.stream = Some(stream);
+        sender
+    }
+
+    fn push(sender: &mpsc::Sender<LogNotification>, signature: &str, logs: Vec<String>) {
+        let notification = LogNotification { signature: signature.to_string(), slot: 0, err: None, logs };
+        sender.try_send(notification).unwrap();
+    }
+
+    fn nonces(events: &[SourceEvent]) -> Vec<u64> {
+        events.iter().map(|e| e.event.nonce).collect()
+    }
+
+    #[test]
+    fn test_bad_receiver_checksum_is_rejected() {
+        let event = |receiver: &str| AnchorStakeEvent {
+            source_contract: PROGRAM.to_string(),
+            target_contract: "0x1111111111111111111111111111111111111111".to_string(),
+            chain_id: 1024,
+            block_height: 100,
+            amount: 1_000,
+            receiver_address: receiver.to_string(),
+            nonce: 7,
+        };
+        let (valid, rejected) = to_stake_event(event("0x52908400098527886E0F7030069857D2E4169EE7"), 421614);
+        assert!(rejected.is_none());
+        assert_eq!(valid.receiver_address.to_string(), "0x52908400098527886E0F7030069857D2E4169EE7");
+
+        // 大小写混合但校验和错误：仍返回事件（接收地址占位），由 pipeline 登记为 blocked
+        let (event, rejected) = to_stake_event(event("0x52908400098527886e0F7030069857D2E4169EE7"), 421614);
+        assert!(rejected.unwrap().starts_with("invalid receiver_address"));
+        assert_eq!(event.receiver_address, ChainAddress::Evm([0; 20]));
+        assert_eq!((event.nonce, event.amount), (7, 1_000));
+        assert_eq!(event.source_contract.to_string(), PROGRAM);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_logs_fetch_transaction() {
+        let mock = MockSvm::default();
+        let mut listener = listener("truncated", &mock).await;
+        let sender = stream(&mut listener);
+        // 订阅建立后先完成一轮回补
+        assert!(listener.poll().await.unwrap().is_empty());
+        listener.commit();
+
+        mock.land("sig1", Some(1), "finalized");
+        mock.land("sig2", None, "finalized");
+        mock.land("sig3", Some(3), "finalized");
+        push(&sender, "sig1", vec!["Program log: Instruction: Stake".to_string(), LOG_TRUNCATED.to_string()]);
+        push(&sender, "sig2", vec!["Program log: Instruction: Stake".to_string()]);
+        push(&sender, "sig3", vec![stake_log(3)]);
+
+        // 截断的推送日志看不到事件，仍拉取完整交易；未截断且无事件的交易直接跳过
+        let events = listener.poll().await.unwrap();
+        assert_eq!(nonces(&events), vec![1, 3]);
+        assert_eq!(mock.calls("getTransaction"), 2);
+        assert_eq!(mock.calls("getSignaturesForAddress"), 1);
+        assert_eq!(events[0].sender.unwrap().to_string(), PAYER);
+    }
+
+    #[tokio::test]
+    async fn test_stream_reconnect_backfills_gap() {
+        let mock = MockSvm::default();
+        let mut listener = listener("reconnect", &mock).await;
+        let sender = stream(&mut listener);
+        mock.land("sig1", Some(1), "finalized");
+        assert_eq!(nonces(&listener.poll().await.unwrap()), vec![1]);
+        listener.commit();
+
+        // 在线时只处理推送，不再分页拉取
+        mock.land("sig2", Some(2), "finalized");
+        push(&sender, "sig2", vec![stake_log(2)]);
+        assert_eq!(nonces(&listener.poll().await.unwrap()), vec![2]);
+        listener.commit();
+        assert_eq!(mock.calls("getSignaturesForAddress"), 1);
+
+        // 断开期间退回 HTTP 轮询
+        listener.stream.as_ref().unwrap().set_connected(false);
+        mock.land("sig3", Some(3), "finalized");
+        assert_eq!(nonces(&listener.poll().await.unwrap()), vec![3]);
+        listener.commit();
+
+        // 轮询间隙上链、没有推送的交易在重连后的回补中补上，与推送重叠的交易只处理一次
+        mock.land("sig4", Some(4), "finalized");
+        mock.land("sig5", Some(5), "finalized");
+        listener.stream.as_ref().unwrap().set_connected(true);
+        push(&sender, "sig5", vec![stake_log(5)]);
+        assert_eq!(nonces(&listener.poll().await.unwrap()), vec![4, 5]);
+        listener.commit();
+        assert_eq!(listener.last_signature.as_deref(), Some("sig5"));
+
+        let calls = mock.calls("getSignaturesForAddress");
+        assert!(listener.poll().await.unwrap().is_empty());
+        assert_eq!(mock.calls("getSignaturesForAddress"), calls);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_transaction_leaves_finality_prefix() {
+        let mock = MockSvm::default();
+        let mut listener = listener("dropped", &mock).await;
+        listener.missing_timeout = Duration::from_millis(100);
+        mock.land("sig1", Some(1), "confirmed");
+        mock.land("sig2", Some(2), "finalized");
+
+        let events = listener.poll().await.unwrap();
+        assert_eq!(nonces(&events), vec![1, 2]);
+        assert!(events[0].finality.awaiting && !events[1].finality.awaiting);
+        listener.commit();
+        assert_eq!(listener.last_signature, None);
+
+        // 所在分叉被回滚：超时前继续等待，游标停在它之前
+        mock.set_status("sig1", None);
+        assert_eq!(nonces(&listener.poll().await.unwrap()), vec![2]);
+        assert!(listener.retracted().is_empty());
+        listener.commit();
+        assert_eq!(listener.last_signature, None);
+
+        // 超时后移出 backlog 并撤回事件，后面的交易随即提交
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(nonces(&listener.poll().await.unwrap()), vec![2]);
+        assert_eq!(nonces(listener.retracted()), vec![1]);
+        listener.commit();
+        assert!(listener.retracted().is_empty());
+        assert_eq!(listener.last_signature.as_deref(), Some("sig2"));
+        assert!(listener.backlog.is_empty());
+    }
 }
@@ -2,19 +2,55 @@ mod api;
 mod config;
 mod listener;
 mod signer;
+mod stream;
 mod submitter;
 
 use anyhow::Result;
-use shared::{logger, metrics};
+use clap::Parser;
+use shared::gas::BalanceStatus;
+use shared::lifecycle::Lifecycle;
+use shared::{alerts, logger, metrics, store};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::Arc;
 use tracing::info;
 
+/// S2E relayer：监听 SVM 质押事件，签名后提交到 EVM
+#[derive(Parser)]
+#[command(name = "s2e-relayer", version)]
+struct Cli {
+    /// TOML 配置文件路径（环境变量优先级更高）
+    #[arg(long, env = "RELAYER_CONFIG")]
+    config: Option<PathBuf>,
+
+    /// 以签名服务模式运行：用本地 ECDSA 密钥在该地址提供远程签名 API，不启动 relayer
+    #[arg(long, value_name = "ADDR")]
+    serve_signer: Option<SocketAddr>,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     // 加载配置
-    let config = config::load_s2e_config()?;
+    let cli = Cli::parse();
+    let config = config::load_s2e_config(cli.config.as_deref())?;
     
-    // 初始化日志
-    logger::init_logger(&config.logging.level, &config.logging.format)?;
+    // 初始化日志和分布式追踪（guard 保留到退出，drop 时刷新未导出的 span）
+    let _telemetry = logger::init_logger(
+        &config.logging.level,
+        &config.logging.format,
+        &config.telemetry,
+        &config.service.name,
+    )?;
+
+    if let Some(addr) = cli.serve_signer {
+        let signer = signer::EcdsaSigner::new(&config.relayer)?;
+        info!(address = %shared::AttestationSigner::identity(&signer), "Starting ECDSA signer service");
+        let signer = shared::protection::protect(Arc::new(signer), &config)?;
+        let policy = submitter::SubmitSignaturePolicy::new(&config.target_chain.contract_address, config.target_chain.chain_id)?;
+        shared::signer::serve_signer(addr, vec![signer], Arc::new(policy), config.signer.auth_token.clone()).await?;
+        return Ok(());
+    }
+
     info!("Starting s2e relayer service");
     info!(
         source = config.source_chain.name,
@@ -23,20 +59,27 @@ async fn main() -> Result<()> {
     );
 
     // 初始化指标
-    metrics::init_metrics();
+    metrics::init_metrics("s2e");
 
     // 验证配置
     config.validate()?;
     info!("Configuration validated");
 
-    // 检查余额
-    check_balances(&config).await?;
-    info!("Balance check passed");
+    // 初始化告警
+    alerts::init_alerts(&config)?;
+
+    // 打开任务存储
+    let task_store = store::open_task_store(&config).await?;
+
+    // 提交账户余额：listener 启动时检查并定期刷新，API 读取
+    let balance = BalanceStatus::new();
 
     // 启动 HTTP API 服务器（不阻塞主流程）
     let api_config = config.clone();
+    let api_store = task_store.clone();
+    let api_balance = balance.clone();
     tokio::spawn(async move {
-        match api::start_server(api_config).await {
+        match api::start_server(api_config, api_store, api_balance).await {
             Ok(_) => info!("API server stopped gracefully"),
             Err(e) => tracing::error!("API server error: {}", e),
         }
@@ -46,28 +89,21 @@ async fn main() -> Result<()> {
     // 启动事件监听器（主要服务）
     info!("Event listener started");
 
-    // 等待服务（只等待 listener 和 Ctrl-C）
-    tokio::select! {
-        result = listener::start_listener(config) => {
-            if let Err(e) = result {
-                tracing::error!("Event listener returned error: {}", e);
-            }
-            info!("Event listener stopped");
-        }
-        _ = tokio::signal::ctrl_c() => {
-            info!("Received shutdown signal");
+    // SIGHUP 热加载配置，SIGTERM / Ctrl-C 排空进行中的任务后退出
+    let lifecycle = Lifecycle::new(config);
+    let config_path = cli.config.clone();
+    lifecycle.spawn_signal_handler(move || config::load_s2e_config(config_path.as_deref()));
+
+    // 等待服务（只等待 listener）
+    if let Some(result) = lifecycle.run(listener::start_listener(lifecycle.clone(), task_store, balance)).await {
+        // 启动检查失败（例如余额不足）时以错误退出
+        if let Err(e) = result {
+            tracing::error!("Event listener returned error: {}", e);
+            return Err(e);
         }
+        info!("Event listener stopped");
     }
 
     info!("s2e relayer service stopped");
     Ok(())
 }
-
-async fn check_balances(_config: &config::S2EConfig) -> Result<()> {
-    // TODO: 实现实际的余额查询
-    // let svm_balance = get_svm_balance(&config).await?;
-    // let evm_balance = get_evm_balance(&config).await?;
-    
-    info!("Balance check: SVM and EVM balances sufficient (check implementation pending)");
-    Ok(())
-}
@@ -1,16 +1,55 @@
 use anyhow::{anyhow, Result};
-use secp256k1::{Message, Secp256k1, SecretKey};
+use async_trait::async_trait;
+use secp256k1::{Message, PublicKey, Secp256k1, SecretKey};
+use shared::config::{Config, RelayerConfig};
+use shared::{encoding, keystore, protection};
+use shared::signer::{AttestationSigner, RemoteSigner, SignatureScheme};
 use shared::types::StakeEventData;
-use sha2::Sha256;
+use shared::RelayerError;
+use sha3::{Digest as Sha3Digest, Keccak256};
+use std::sync::Arc;
+use tracing::info;
+
+/// 按 `signer.backend` 创建签名器（本地 ECDSA 密钥、远程签名服务或 PKCS#11 HSM）
+///
+/// 所有签名器（包括远程签名服务）签名前都检查本机的防重复签名数据库（`protection.path`），
+/// 远程签名服务另有自己的检查
+pub async fn build_signer(config: &Config) -> Result<Arc<dyn AttestationSigner>> {
+    let signer: Arc<dyn AttestationSigner> = match config.signer.backend.as_str() {
+        "remote" => {
+            let signer = RemoteSigner::new(&config.signer, SignatureScheme::Secp256k1)?;
+            signer.check().await?;
+            info!(address = %signer.identity(), "Using remote ECDSA signer");
+            Arc::new(signer)
+        }
+        #[cfg(feature = "pkcs11")]
+        "pkcs11" => {
+            let signer = HsmEcdsaSigner::open(&config.hsm)?;
+            info!(address = %signer.identity(), "Using PKCS#11 ECDSA signer");
+            Arc::new(signer)
+        }
+        #[cfg(not(feature = "pkcs11"))]
+        "pkcs11" => return Err(anyhow!("signer.backend = \"pkcs11\" requires building with --features pkcs11")),
+        _ => Arc::new(EcdsaSigner::new(&config.relayer)?),
+    };
+    Ok(protection::protect(signer, config)?)
+}
 
 /// ECDSA 签名器 (用于 EVM)
 pub struct EcdsaSigner {
     secret_key: SecretKey,
+    address: String,
 }
 
 impl EcdsaSigner {
+    /// 创建新的签名器（从 keystore 或明文私钥配置加载）
+    pub fn new(keys: &RelayerConfig) -> Result<Self> {
+        let private_key = keystore::ecdsa_private_key(keys)?;
+        Self::from_hex(private_key.expose())
+    }
+
     /// 创建新的签名器（从十六进制私钥）
-    pub fn new(private_key_hex: &str) -> Result<Self> {
+    pub fn from_hex(private_key_hex: &str) -> Result<Self> {
         // 移除可能的 0x 前缀并去除空白
         let mut private_key_hex = private_key_hex.strip_prefix("0x").unwrap_or(private_key_hex).trim().to_string();
         
@@ -22,7 +61,7 @@ impl EcdsaSigner {
         }
         
         // 处理奇数长度的十六进制字符串（在前面补0）
-        if private_key_hex.len() % 2 != 0 {
+        if !private_key_hex.len().is_multiple_of(2) {
             private_key_hex = format!("0{}", private_key_hex);
         }
         
@@ -43,34 +82,25 @@ impl EcdsaSigner {
         let secret_key = SecretKey::from_slice(&private_key_bytes)
             .map_err(|e| anyhow!("Invalid secret key: {}", e))?;
 
-        Ok(Self { secret_key })
+        // EVM 地址 = keccak256(未压缩公钥)[12..]
+        let public_key = PublicKey::from_secret_key(&Secp256k1::new(), &secret_key);
+        let address = format!("0x{}", hex::encode(&Keccak256::digest(&public_key.serialize_uncompressed()[1..])[12..]));
+
+        Ok(Self { secret_key, address })
     }
 
     /// 对事件数据生成签名（EVM 格式：JSON + SHA-256 + ECDSA + EIP-191）
-    pub fn sign_event(&self, event: &StakeEventData) -> Result<Vec<u8>> {
-        // 1. 序列化为 JSON 格式（与 EVM 合约对齐）
-        let json_message = self.serialize_event_to_json(event);
-
-        // 2. 计算 SHA256 哈希
-        let mut hasher = Sha256::new();
-        hasher.update(json_message.as_bytes());
-        let hash = hasher.finalize();
-
-        // 3. 应用 EIP-191 前缀
-        let prefixed = format!("\x19Ethereum Signed Message:\n32");
-        let mut eth_hasher = sha3::Keccak256::new();
-        use sha3::Digest as Sha3Digest;
-        eth_hasher.update(prefixed.as_bytes());
-        eth_hasher.update(&hash);
-        let eth_hash = eth_hasher.finalize();
-
-        // 4. 使用 secp256k1 ECDSA 签名
+    fn sign_attestation(&self, event: &StakeEventData) -> Result<Vec<u8>> {
+        self.sign_digest(&encoding::evm_attestation_digest(event))
+    }
+
+    /// 对 32 字节摘要签名，返回 EVM 格式 (65 字节：r + s + v)
+    fn sign_digest(&self, digest: &[u8]) -> Result<Vec<u8>> {
         let secp = Secp256k1::new();
-        let message = Message::from_digest_slice(&eth_hash)
+        let message = Message::from_digest_slice(digest)
             .map_err(|e| anyhow!("Failed to create message: {}", e))?;
         let signature = secp.sign_ecdsa_recoverable(&message, &self.secret_key);
 
-        // 5. 将签名转换为 EVM 格式 (65 字节：r + s + v)
         let (recovery_id, sig_bytes) = signature.serialize_compact();
         let mut result = Vec::with_capacity(65);
         result.extend_from_slice(&sig_bytes);
@@ -78,51 +108,64 @@ impl EcdsaSigner {
         
         Ok(result)
     }
+}
 
-    /// 序列化事件数据为 JSON 格式（与 EVM 合约对齐）
-    fn serialize_event_to_json(&self, event: &StakeEventData) -> String {
-        // Convert contract addresses to bytes32 hex format (lowercase, no 0x prefix)
-        let source_contract_hex = self.contract_to_hex(&event.source_contract);
-        let target_contract_hex = self.contract_to_hex(&event.target_contract);
-        
-        format!(
-            r#"{{"sourceContract":"{}","targetContract":"{}","chainId":"{}","blockHeight":"{}","amount":"{}","receiverAddress":"{}","nonce":"{}"}}"#,
-            source_contract_hex,
-            target_contract_hex,
-            event.source_chain_id,
-            event.block_height,
-            event.amount,
-            event.receiver_address,
-            event.nonce
-        )
+#[async_trait]
+impl AttestationSigner for EcdsaSigner {
+    fn scheme(&self) -> SignatureScheme {
+        SignatureScheme::Secp256k1
     }
-    
-    /// 转换合约地址为 bytes32 hex 格式（小写，无 0x 前缀）
-    fn contract_to_hex(&self, address: &str) -> String {
-        // Remove 0x prefix if present
-        let addr = address.strip_prefix("0x").unwrap_or(address);
-        
-        // If it's already hex format
-        if addr.len() == 64 && addr.chars().all(|c| c.is_ascii_hexdigit()) {
-            return addr.to_lowercase();
-        }
-        
-        // If it's Solana base58 format, decode it
-        if let Ok(decoded) = bs58::decode(addr).into_vec() {
-            if decoded.len() == 32 {
-                return hex::encode(&decoded);
-            }
-        }
-        
-        // Fallback: pad hex to 64 chars
-        let hex_str = if addr.chars().all(|c| c.is_ascii_hexdigit()) {
-            addr.to_string()
-        } else {
-            // Try to decode as hex anyway
-            return addr.to_lowercase();
-        };
-        
-        format!("{:0<64}", hex_str.to_lowercase())
+
+    fn identity(&self) -> String {
+        self.address.clone()
+    }
+
+    async fn sign_event(&self, event: &StakeEventData) -> shared::Result<Vec<u8>> {
+        self.sign_attestation(event)
+            .map_err(|e| RelayerError::Signature(e.to_string()))
+    }
+
+    async fn sign_transaction(&self, _event: &StakeEventData, transaction: &[u8]) -> shared::Result<Vec<u8>> {
+        self.sign_digest(&Keccak256::digest(transaction))
+            .map_err(|e| RelayerError::Signature(e.to_string()))
+    }
+}
+
+/// PKCS#11 HSM 中的 secp256k1 签名器
+#[cfg(feature = "pkcs11")]
+pub struct HsmEcdsaSigner {
+    key: shared::pkcs11::Pkcs11Key,
+    address: String,
+}
+
+#[cfg(feature = "pkcs11")]
+impl HsmEcdsaSigner {
+    /// 打开 HSM 并按 `hsm.key_label` 查找密钥
+    pub fn open(config: &shared::config::HsmConfig) -> Result<Self> {
+        let key = shared::pkcs11::Pkcs11Key::open(config)?;
+        let public_key = PublicKey::from_slice(key.public_key())
+            .map_err(|e| anyhow!("HSM key is not a secp256k1 key: {}", e))?;
+        let address = format!("0x{}", hex::encode(&Keccak256::digest(&public_key.serialize_uncompressed()[1..])[12..]));
+        Ok(Self { key, address })
     }
 }
 
+#[cfg(feature = "pkcs11")]
+#[async_trait]
+impl AttestationSigner for HsmEcdsaSigner {
+    fn scheme(&self) -> SignatureScheme {
+        SignatureScheme::Secp256k1
+    }
+
+    fn identity(&self) -> String {
+        self.address.clone()
+    }
+
+    async fn sign_event(&self, event: &StakeEventData) -> shared::Result<Vec<u8>> {
+        self.key.sign_secp256k1_digest(&encoding::evm_attestation_digest(event))
+    }
+
+    async fn sign_transaction(&self, _event: &StakeEventData, transaction: &[u8]) -> shared::Result<Vec<u8>> {
+        self.key.sign_secp256k1_digest(&Keccak256::digest(transaction).into())
+    }
+}
@@ -0,0 +1,352 @@
+//! SVM PubSub 推送：通过 WebSocket `logsSubscribe` 订阅提及桥程序的交易日志
+//!
+//! 后台任务维持订阅，断开后指数退避重连。每次订阅建立（含首次）或推送缓冲溢出时标记需要重新同步，
+//! 监听器随后走 HTTP 分页路径回补断开期间的交易；未连接期间监听器退回 HTTP 轮询。
+
+use futures::{SinkExt, StreamExt};
+use serde::Deserialize;
+use serde_json::json;
+use shared::RelayerError;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, Notify};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, info, warn};
+
+/// 推送缓冲上限；监听器来不及处理时丢弃新推送，改由 HTTP 回补
+const BUFFER_SIZE: usize = 10_000;
+/// 连接多久没有任何消息时发送 ping，连续两次无响应视为断开
+const IDLE_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// 一笔程序交易的日志推送
+#[derive(Debug)]
+pub struct LogNotification {
+    pub signature: String,
+    pub slot: u64,
+    pub err: Option<serde_json::Value>,
+    pub logs: Vec<String>,
+}
+
+struct State {
+    connected: AtomicBool,
+    resync: AtomicBool,
+    notify: Notify,
+}
+
+/// 后台 `logsSubscribe` 订阅，drop 时停止
+pub struct LogStream {
+    state: Arc<State>,
+    receiver: mpsc::Receiver<LogNotification>,
+    task: JoinHandle<()>,
+}
+
+impl LogStream {
+    pub fn spawn(ws_url: &str, program_id: &str, commitment: &str) -> Self {
+        let state = Arc::new(State {
+            connected: AtomicBool::new(false),
+            resync: AtomicBool::new(false),
+            notify: Notify::new(),
+        });
+        let (sender, receiver) = mpsc::channel(BUFFER_SIZE);
+        let task = tokio::spawn(run(
+            ws_url.to_string(),
+            program_id.to_string(),
+            commitment.to_string(),
+            state.clone(),
+            sender,
+        ));
+        Self { state, receiver, task }
+    }
+
+    /// 订阅是否在线
+    pub fn is_connected(&self) -> bool {
+        self.state.connected.load(Ordering::SeqCst)
+    }
+
+    /// 上次调用以来订阅是否（重新）建立或丢弃过推送，需要走 HTTP 回补
+    pub fn take_resync(&self) -> bool {
+        self.state.resync.swap(false, Ordering::SeqCst)
+    }
+
+    /// 取出已缓冲的推送
+    pub fn drain(&mut self) -> Vec<LogNotification> {
+        let mut notifications = Vec::new();
+        while let Ok(notification) = self.receiver.try_recv() {
+            notifications.push(notification);
+        }
+        notifications
+    }
+
+    /// 等待新的推送或连接状态变化
+    pub async fn notified(&self) {
+        self.state.notify.notified().await
+    }
+}
+
+#[cfg(test)]
+impl LogStream {
+    /// 不连接节点的订阅，由测试直接注入推送和连接状态
+    pub(crate) fn detached() -> (Self, mpsc::Sender<LogNotification>) {
+        let state = Arc::new(State {
+            connected: AtomicBool::new(false),
+            resync: AtomicBool::new(false),
+            notify: Notify::new(),
+        });
+        let (sender, receiver) = mpsc::channel(BUFFER_SIZE);
+        let task = tokio::spawn(async {});
+        (Self { state, receiver, task }, sender)
+    }
+
+    /// 模拟订阅建立（与真实订阅一样要求回补）或断开
+    pub(crate) fn set_connected(&self, connected: bool) {
+        self.state.connected.store(connected, Ordering::SeqCst);
+        if connected {
+            self.state.resync.store(true, Ordering::SeqCst);
+        }
+    }
+}
+
+impl Drop for LogStream {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn run(
+    ws_url: String,
+    program_id: String,
+    commitment: String,
+    state: Arc<State>,
+    sender: mpsc::Sender<LogNotification>,
+) {
+    let endpoint = shared::metrics::endpoint_label(&ws_url);
+    let mut backoff = Duration::from_secs(1);
+    loop {
+        match subscribe(&ws_url, &program_id, &commitment, &state, &sender).await {
+            Ok(()) => warn!(ws = %endpoint, "SVM log subscription closed, falling back to HTTP polling"),
+            Err(e) => warn!(ws = %endpoint, error = %e, "SVM log subscription failed, falling back to HTTP polling"),
+        }
+        if sender.is_closed() {
+            return;
+        }
+        if state.connected.swap(false, Ordering::SeqCst) {
+            backoff = Duration::from_secs(1);
+        }
+        // 唤醒监听器，立即改走 HTTP 轮询
+        state.notify.notify_one();
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+#[derive(Deserialize)]
+struct Incoming {
+    #[serde(default)]
+    result: Option<serde_json::Value>,
+    #[serde(default)]
+    error: Option<serde_json::Value>,
+    #[serde(default)]
+    params: Option<NotificationParams>,
+}
+
+#[derive(Deserialize)]
+struct NotificationParams {
+    result: NotificationResult,
+}
+
+#[derive(Deserialize)]
+struct NotificationResult {
+    context: NotificationContext,
+    value: NotificationValue,
+}
+
+#[derive(Deserialize)]
+struct NotificationContext {
+    slot: u64,
+}
+
+#[derive(Deserialize)]
+struct NotificationValue {
+    signature: String,
+    #[serde(default)]
+    err: Option<serde_json::Value>,
+    #[serde(default)]
+    logs: Vec<String>,
+}
+
+/// 节点发来的一条 PubSub 消息
+#[derive(Debug)]
+enum PubSubMessage {
+    /// 订阅确认，附带订阅 ID
+    Subscribed(serde_json::Value),
+    /// 订阅请求被拒绝
+    Rejected(serde_json::Value),
+    Notification(LogNotification),
+}
+
+/// 解析 PubSub 消息，无法识别的消息返回 None
+fn parse_message(text: &str) -> Option<PubSubMessage> {
+    let incoming: Incoming = match serde_json::from_str(text) {
+        Ok(incoming) => incoming,
+        Err(e) => {
+            debug!(error = %e, "Ignoring unrecognized PubSub message");
+            return None;
+        }
+    };
+    if let Some(error) = incoming.error {
+        return Some(PubSubMessage::Rejected(error));
+    }
+    if let Some(params) = incoming.params {
+        return Some(PubSubMessage::Notification(LogNotification {
+            signature: params.result.value.signature,
+            slot: params.result.context.slot,
+            err: params.result.value.err,
+            logs: params.result.value.logs,
+        }));
+    }
+    incoming.result.map(PubSubMessage::Subscribed)
+}
+
+/// 建立一次订阅并持续转发推送，连接关闭时返回
+async fn subscribe(
+    ws_url: &str,
+    program_id: &str,
+    commitment: &str,
+    state: &State,
+    sender: &mpsc::Sender<LogNotification>,
+) -> shared::Result<()> {
+    let connection_error = |e: tokio_tungstenite::tungstenite::Error| RelayerError::RpcConnection(e.to_string());
+    let (mut socket, _) = tokio_tungstenite::connect_async(ws_url).await.map_err(connection_error)?;
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "logsSubscribe",
+        "params": [{ "mentions": [program_id] }, { "commitment": commitment }]
+    });
+    socket.send(Message::Text(request.to_string())).await.map_err(connection_error)?;
+
+    let mut idle = false;
+    loop {
+        let message = match tokio::time::timeout(IDLE_TIMEOUT, socket.next()).await {
+            Ok(Some(message)) => message.map_err(connection_error)?,
+            Ok(None) => return Ok(()),
+            Err(_) if idle => return Err(RelayerError::RpcConnection("no response to ping".to_string())),
+            Err(_) => {
+                idle = true;
+                socket.send(Message::Ping(Vec::new())).await.map_err(connection_error)?;
+                continue;
+            }
+        };
+        idle = false;
+        let text = match message {
+            Message::Text(text) => text,
+            Message::Close(_) => return Ok(()),
+            _ => continue,
+        };
+        match parse_message(&text) {
+            Some(PubSubMessage::Notification(notification)) => {
+                match sender.try_send(notification) {
+                    Ok(()) => {}
+                    Err(mpsc::error::TrySendError::Full(_)) => state.resync.store(true, Ordering::SeqCst),
+                    Err(mpsc::error::TrySendError::Closed(_)) => return Ok(()),
+                }
+                state.notify.notify_one();
+            }
+            Some(PubSubMessage::Subscribed(subscription)) => {
+                info!(subscription = %subscription, "Subscribed to SVM program logs");
+                state.connected.store(true, Ordering::SeqCst);
+                state.resync.store(true, Ordering::SeqCst);
+                state.notify.notify_one();
+            }
+            Some(PubSubMessage::Rejected(error)) => {
+                return Err(RelayerError::RpcRequest(format!("logsSubscribe rejected: {}", error)));
+            }
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    fn notification(signature: &str, slot: u64) -> String {
+        json!({
+            "jsonrpc": "2.0",
+            "method": "logsNotification",
+            "params": {
+                "result": {
+                    "context": { "slot": slot },
+                    "value": { "signature": signature, "err": null, "logs": ["Program log: Instruction: Stake", "Log truncated"] }
+                },
+                "subscription": 7
+            }
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_parse_message() {
+        match parse_message(r#"{"jsonrpc":"2.0","result":7,"id":1}"#) {
+            Some(PubSubMessage::Subscribed(subscription)) => assert_eq!(subscription, json!(7)),
+            other => panic!("unexpected {:?}", other),
+        }
+        match parse_message(&notification("5sig", 42)) {
+            Some(PubSubMessage::Notification(notification)) => {
+                assert_eq!((notification.signature.as_str(), notification.slot), ("5sig", 42));
+                assert!(notification.err.is_none());
+                assert_eq!(notification.logs.last().map(String::as_str), Some("Log truncated"));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        let failed = r#"{"jsonrpc":"2.0","method":"logsNotification","params":{"result":{"context":{"slot":1},"value":{"signature":"5sig","err":{"InstructionError":[0,"Custom"]},"logs":[]}}}}"#;
+        match parse_message(failed) {
+            Some(PubSubMessage::Notification(notification)) => assert!(notification.err.is_some()),
+            other => panic!("unexpected {:?}", other),
+        }
+        let rejected = r#"{"jsonrpc":"2.0","error":{"code":-32601,"message":"Method not found"},"id":1}"#;
+        assert!(matches!(parse_message(rejected), Some(PubSubMessage::Rejected(_))));
+        assert!(parse_message(r#"{"jsonrpc":"2.0","method":"slotNotification"}"#).is_none());
+        assert!(parse_message("not json").is_none());
+    }
+
+    /// 模拟节点：每个连接确认订阅并推送一笔交易后断开
+    async fn serve(listener: TcpListener, connections: u64) {
+        for i in 0..connections {
+            let (tcp, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_async(tcp).await.unwrap();
+            let request = socket.next().await.unwrap().unwrap();
+            assert!(request.to_text().unwrap().contains("logsSubscribe"));
+            let subscribed = json!({ "jsonrpc": "2.0", "result": i, "id": 1 });
+            socket.send(Message::Text(subscribed.to_string())).await.unwrap();
+            socket.send(Message::Text(notification(&format!("sig{}", i), i))).await.unwrap();
+            let _ = socket.close(None).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resubscribe_after_disconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, 2));
+        let mut stream = LogStream::spawn(&url, "Program", "confirmed");
+
+        for i in 0..2 {
+            let notifications = loop {
+                tokio::time::timeout(Duration::from_secs(10), stream.notified()).await.expect("no notification");
+                let notifications = stream.drain();
+                if !notifications.is_empty() {
+                    break notifications;
+                }
+            };
+            assert_eq!(notifications[0].signature, format!("sig{}", i));
+            // 每次订阅建立（含重连）都要求走 HTTP 回补断开期间的交易
+            assert!(stream.take_resync());
+            assert!(!stream.take_resync());
+        }
+    }
+}
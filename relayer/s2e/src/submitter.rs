@@ -1,39 +1,44 @@
 use anyhow::{anyhow, Result};
 use ethers::{
-    core::types::Address,
-    middleware::SignerMiddleware,
+    core::types::{transaction::eip2718::TypedTransaction, Address, Signature, U256},
     prelude::*,
-    providers::{Http, Middleware, Provider},
-    signers::{LocalWallet, Signer as EthersSigner},
-    abi::{Token, encode},
+    providers::{Http, Middleware, Provider, ProviderError, RpcError},
+    abi::{decode, encode, ParamType, Token},
 };
+use async_trait::async_trait;
+use shared::retry::{RpcEndpoint, Swappable};
+use shared::signer::{AttestationSigner, SignatureScheme, TransactionPolicy};
 use shared::types::StakeEventData;
+use shared::{metrics, RelayerError, TargetSubmitter};
 use std::sync::Arc;
 use tracing::{info, warn};
 
 /// EVM 交易提交器
+///
+/// 交易由 relayer 签名器签名（合约要求 msg.sender 即证明签名者），
+/// 本地密钥与远程签名服务走同一路径。
 pub struct EvmSubmitter {
-    client: Arc<SignerMiddleware<Provider<Http>, LocalWallet>>,
+    client: Swappable<EvmRpc>,
+    signer: Arc<dyn AttestationSigner>,
+    relayer_address: Address,
+    chain_id: u64,
     contract_address: Address,
 }
 
 impl EvmSubmitter {
-    pub fn new(rpc_url: &str, contract_address: &str, private_key_hex: &str) -> Result<Self> {
+    pub fn new(
+        rpc_url: &str,
+        contract_address: &str,
+        chain_id: u64,
+        signer: Arc<dyn AttestationSigner>,
+    ) -> Result<Self> {
         // 创建 Provider
-        let provider = Provider::<Http>::try_from(rpc_url)
-            .map_err(|e| anyhow!("Failed to create provider: {}", e))?;
+        let client = EvmRpc::connect(rpc_url).map_err(|e| anyhow!("Failed to create provider: {}", e))?;
 
-        // 解析私钥
-        let private_key_hex = private_key_hex.strip_prefix("0x").unwrap_or(private_key_hex);
-        let wallet: LocalWallet = private_key_hex
+        let relayer_address: Address = signer
+            .identity()
             .parse()
-            .map_err(|e| anyhow!("Failed to parse wallet: {}", e))?;
-
-        // 设置链 ID (Arbitrum Sepolia)
-        let wallet = wallet.with_chain_id(421614u64);
-
-        // 创建签名中间件
-        let client = Arc::new(SignerMiddleware::new(provider, wallet));
+            .map_err(|e| anyhow!("Invalid relayer address: {}", e))?;
 
         // 解析合约地址
         let contract_address: Address = contract_address
@@ -41,13 +46,17 @@ impl EvmSubmitter {
             .map_err(|e| anyhow!("Invalid contract address: {}", e))?;
 
         info!(
-            relayer_address = %client.address(),
+            relayer_address = %relayer_address,
             contract_address = %contract_address,
+            chain_id,
             "EVM submitter initialized"
         );
 
         Ok(Self {
-            client,
+            client: Swappable::new(client),
+            signer,
+            relayer_address,
+            chain_id,
             contract_address,
         })
     }
@@ -57,115 +66,337 @@ impl EvmSubmitter {
         &self,
         event: &StakeEventData,
         signature: &[u8],
-    ) -> Result<String> {
+    ) -> shared::Result<String> {
         info!(nonce = event.nonce, "Submitting signature to EVM");
+        // 整笔提交使用同一端点，热加载只影响之后的提交
+        let client = self.client.get();
 
         // 构建合约调用数据
-        let call_data = self.encode_submit_signature(event, signature)?;
+        let call_data = encode_submit_signature(event, signature);
 
-        // 创建交易
+        // 创建并签名交易
         let tx = TransactionRequest::new()
+            .from(self.relayer_address)
             .to(self.contract_address)
             .data(call_data);
+        let raw_tx = self.sign_transaction(&client, event, tx.into()).await?;
+
+        // 发送交易只尝试一次，失败由任务级重试重新签名发送
+        let tx_hash = client
+            .rpc
+            .call_once("eth_sendRawTransaction", || async {
+                let pending = client.provider.send_raw_transaction(raw_tx.clone()).await.map_err(provider_error)?;
+                Ok(pending.tx_hash())
+            })
+            .await
+            .inspect_err(|e| warn!(nonce = event.nonce, error = %e, "Failed to send transaction"))?;
+        info!(nonce = event.nonce, tx_hash = %tx_hash, "Transaction sent, waiting for confirmation");
 
-        // 发送交易
-        match self.client.send_transaction(tx, None).await {
-            Ok(pending_tx) => {
-                info!(nonce = event.nonce, "Transaction sent, waiting for confirmation");
-                
-                match pending_tx.await {
-                    Ok(Some(receipt)) => {
-                        info!(
-                            nonce = event.nonce,
-                            tx_hash = %receipt.transaction_hash,
-                            "Transaction confirmed"
-                        );
-                        Ok(format!("{:?}", receipt.transaction_hash))
-                    }
-                    Ok(None) => {
-                        warn!(nonce = event.nonce, "Transaction pending (no receipt yet)");
-                        Err(anyhow!("Transaction pending"))
-                    }
-                    Err(e) => {
-                        warn!(nonce = event.nonce, error = %e, "Transaction failed");
-                        Err(anyhow!("Transaction failed: {}", e))
-                    }
+        // 等待回执可以安全重试
+        let receipt = client
+            .rpc
+            .call("eth_getTransactionReceipt", || async {
+                PendingTransaction::new(tx_hash, &client.provider).await.map_err(provider_error)
+            })
+            .await?
+            .ok_or_else(|| {
+                warn!(nonce = event.nonce, tx_hash = %tx_hash, "Transaction dropped from mempool");
+                RelayerError::TransactionFailed(format!("Transaction {:?} dropped", tx_hash))
+            })?;
+
+        if let (Some(gas_used), Some(gas_price)) = (receipt.gas_used, receipt.effective_gas_price) {
+            metrics::record_gas_spent("evm", wei_to_ether(gas_used * gas_price));
+        }
+        if receipt.status == Some(0.into()) {
+            warn!(nonce = event.nonce, tx_hash = %tx_hash, "Transaction reverted");
+            return Err(RelayerError::Reverted(format!("Transaction {:?} reverted", tx_hash)));
+        }
+        info!(nonce = event.nonce, tx_hash = %receipt.transaction_hash, "Transaction confirmed");
+        Ok(format!("{:?}", receipt.transaction_hash))
+    }
+
+    /// 填充 nonce / gas 并由签名器签名，返回已签名的原始交易
+    async fn sign_transaction(&self, client: &EvmRpc, event: &StakeEventData, mut tx: TypedTransaction) -> shared::Result<Bytes> {
+        tx.set_chain_id(self.chain_id);
+        let nonce = client
+            .rpc
+            .call("eth_getTransactionCount", || async {
+                client.provider.get_transaction_count(self.relayer_address, None).await.map_err(provider_error)
+            })
+            .await?;
+        tx.set_nonce(nonce);
+        // fill_transaction 内部调用 eth_gasPrice / eth_estimateGas，合约 revert 在这里提前暴露
+        let tx = client
+            .rpc
+            .call("fill_transaction", || {
+                let mut tx = tx.clone();
+                async move {
+                    client.provider.fill_transaction(&mut tx, None).await.map_err(provider_error)?;
+                    Ok(tx)
                 }
-            }
-            Err(e) => {
-                warn!(nonce = event.nonce, error = %e, "Failed to send transaction");
-                Err(anyhow!("Failed to send transaction: {}", e))
-            }
+            })
+            .await?;
+
+        // 签名器对 keccak256(RLP) 签名，返回 r||s||v（v = 27/28），这里转换为 EIP-155 v
+        let sig = self.signer.sign_transaction(event, &tx.rlp()).await?;
+        let recovery_id = match sig[64] {
+            v @ (27 | 28) => (v - 27) as u64,
+            v => return Err(RelayerError::Signature(format!("Invalid transaction signature recovery id {}", v))),
+        };
+        let signature = Signature {
+            r: U256::from_big_endian(&sig[0..32]),
+            s: U256::from_big_endian(&sig[32..64]),
+            v: recovery_id + 35 + self.chain_id * 2,
+        };
+        if signature.recover(tx.sighash()).ok() != Some(self.relayer_address) {
+            return Err(RelayerError::Signature(format!(
+                "Transaction signature does not match relayer {:?}",
+                self.relayer_address
+            )));
         }
+        Ok(tx.rlp_signed(&signature))
+    }
+
+    /// relayer 账户的 ETH 余额（支付提交交易的 gas）
+    pub async fn balance(&self) -> shared::Result<f64> {
+        let client = self.client.get();
+        let wei = client
+            .rpc
+            .call("eth_getBalance", || async {
+                client.provider.get_balance(self.relayer_address, None).await.map_err(provider_error)
+            })
+            .await?;
+        Ok(wei_to_ether(wei))
     }
+}
+
+/// function submitSignature((bytes32,bytes32,uint64,uint64,uint64,uint64,string,uint64) eventData, bytes signature)
+const SUBMIT_SIGNATURE: &str = "submitSignature((bytes32,bytes32,uint64,uint64,uint64,uint64,string,uint64),bytes)";
+
+/// 事件数据元组
+fn event_token(event: &StakeEventData) -> Token {
+    Token::Tuple(vec![
+        Token::FixedBytes(event.source_contract.to_bytes32().to_vec()),
+        Token::FixedBytes(event.target_contract.to_bytes32().to_vec()),
+        Token::Uint(event.source_chain_id.into()),
+        Token::Uint(event.target_chain_id.into()),
+        Token::Uint(event.block_height.into()),
+        Token::Uint(event.amount.into()),
+        Token::String(event.receiver_address.to_string()),
+        Token::Uint(event.nonce.into()),
+    ])
+}
+
+/// 编码 submitSignature 函数调用
+fn encode_submit_signature(event: &StakeEventData, signature: &[u8]) -> Bytes {
+    let selector = &ethers::utils::keccak256(SUBMIT_SIGNATURE.as_bytes())[0..4];
+
+    // 编码事件数据元组和签名
+    let encoded_params = encode(&[event_token(event), Token::Bytes(signature.to_vec())]);
+
+    // 组合选择器和参数
+    let mut call_data = Vec::with_capacity(4 + encoded_params.len());
+    call_data.extend_from_slice(selector);
+    call_data.extend_from_slice(&encoded_params);
+
+    Bytes::from(call_data)
+}
+
+/// 签名服务的交易校验：只签发往桥合约、不转账、调用 submitSignature 且事件数据与请求一致的交易
+pub struct SubmitSignaturePolicy {
+    contract_address: Address,
+    chain_id: u64,
+}
+
+impl SubmitSignaturePolicy {
+    pub fn new(contract_address: &str, chain_id: u64) -> Result<Self> {
+        let contract_address = contract_address
+            .parse()
+            .map_err(|e| anyhow!("Invalid contract address: {}", e))?;
+        Ok(Self { contract_address, chain_id })
+    }
+}
+
+impl TransactionPolicy for SubmitSignaturePolicy {
+    fn check(&self, event: &StakeEventData, transaction: &[u8]) -> shared::Result<()> {
+        let refuse = |reason: String| {
+            RelayerError::Signature(format!("Refusing to sign transaction for nonce {}: {}", event.nonce, reason))
+        };
+        let tx: TypedTransaction =
+            ethers::utils::rlp::decode(transaction).map_err(|e| refuse(format!("invalid transaction: {}", e)))?;
+        if tx.to_addr() != Some(&self.contract_address) {
+            return Err(refuse(format!("target {:?} is not the bridge contract", tx.to_addr())));
+        }
+        if tx.chain_id().map(|id| id.as_u64()) != Some(self.chain_id) {
+            return Err(refuse(format!("chain id {:?} is not {}", tx.chain_id(), self.chain_id)));
+        }
+        if tx.value().is_some_and(|value| !value.is_zero()) {
+            return Err(refuse("transaction transfers value".to_string()));
+        }
 
-    /// 编码 submitSignature 函数调用
-    fn encode_submit_signature(&self, event: &StakeEventData, signature: &[u8]) -> Result<Bytes> {
-        // submitSignature 函数签名
-        // function submitSignature((bytes32,bytes32,uint64,uint64,uint64,uint64,string,uint64) eventData, bytes signature)
-        let function_signature = "submitSignature((bytes32,bytes32,uint64,uint64,uint64,uint64,string,uint64),bytes)";
-        let selector = &ethers::utils::keccak256(function_signature.as_bytes())[0..4];
-
-        // 编码事件数据元组
-        let event_data_tuple = Token::Tuple(vec![
-            Token::FixedBytes(self.parse_bytes32(&event.source_contract)?.to_vec()),
-            Token::FixedBytes(self.parse_bytes32(&event.target_contract)?.to_vec()),
-            Token::Uint(event.source_chain_id.into()),
-            Token::Uint(event.target_chain_id.into()),
-            Token::Uint(event.block_height.into()),
-            Token::Uint(event.amount.into()),
-            Token::String(event.receiver_address.clone()),
-            Token::Uint(event.nonce.into()),
+        let data = tx.data().map(|data| data.as_ref()).unwrap_or_default();
+        let selector = &ethers::utils::keccak256(SUBMIT_SIGNATURE.as_bytes())[0..4];
+        let Some(params) = data.strip_prefix(selector) else {
+            return Err(refuse("not a submitSignature call".to_string()));
+        };
+        let event_type = ParamType::Tuple(vec![
+            ParamType::FixedBytes(32),
+            ParamType::FixedBytes(32),
+            ParamType::Uint(64),
+            ParamType::Uint(64),
+            ParamType::Uint(64),
+            ParamType::Uint(64),
+            ParamType::String,
+            ParamType::Uint(64),
         ]);
+        let tokens = decode(&[event_type, ParamType::Bytes], params).map_err(|e| refuse(format!("invalid calldata: {}", e)))?;
+        // 重新编码后必须与原调用数据一致，不允许附加数据
+        if encode(&tokens) != params {
+            return Err(refuse("non-canonical calldata".to_string()));
+        }
+        if tokens[0] != event_token(event) {
+            return Err(refuse("calldata does not match the event".to_string()));
+        }
+        Ok(())
+    }
+}
 
-        // 编码签名
-        let signature_token = Token::Bytes(signature.to_vec());
+/// EVM RPC 客户端，热加载 RPC 地址时整体替换
+struct EvmRpc {
+    url: String,
+    provider: Provider<Http>,
+    /// RPC 重试、熔断和指标
+    rpc: RpcEndpoint,
+}
 
-        // 编码所有参数
-        let encoded_params = encode(&[event_data_tuple, signature_token]);
+impl EvmRpc {
+    fn connect(url: &str) -> shared::Result<Self> {
+        let provider = Provider::<Http>::try_from(url)
+            .map_err(|e| RelayerError::Config(format!("Invalid EVM RPC URL '{}': {}", url, e)))?;
+        Ok(Self { url: url.to_string(), provider, rpc: RpcEndpoint::new("evm", url) })
+    }
+}
 
-        // 组合选择器和参数
-        let mut call_data = Vec::with_capacity(4 + encoded_params.len());
-        call_data.extend_from_slice(selector);
-        call_data.extend_from_slice(&encoded_params);
+#[async_trait]
+impl TargetSubmitter for EvmSubmitter {
+    fn scheme(&self) -> SignatureScheme {
+        SignatureScheme::Secp256k1
+    }
 
-        Ok(Bytes::from(call_data))
+    async fn submit(&self, event: &StakeEventData, attestation: &[u8]) -> shared::Result<String> {
+        self.submit_signature(event, attestation).await
     }
 
-    /// 解析字符串为 bytes32
-    /// 支持 hex 格式（0x...）和 Solana base58 格式
-    fn parse_bytes32(&self, s: &str) -> Result<[u8; 32]> {
-        // 如果是 hex 格式
-        if let Some(hex_str) = s.strip_prefix("0x") {
-            let bytes = hex::decode(hex_str)?;
-            
-            if bytes.len() > 32 {
-                return Err(anyhow!("Bytes too long: {} > 32", bytes.len()));
-            }
-            
-            let mut result = [0u8; 32];
-            result[..bytes.len()].copy_from_slice(&bytes);
-            return Ok(result);
+    /// 调用合约 getReceiverLastNonce()
+    async fn last_nonce(&self) -> shared::Result<Option<u64>> {
+        let selector = &ethers::utils::keccak256("getReceiverLastNonce()".as_bytes())[0..4];
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(self.contract_address)
+            .data(Bytes::from(selector.to_vec()))
+            .into();
+        let client = self.client.get();
+        let output = client
+            .rpc
+            .call("eth_call", || async { client.provider.call(&tx, None).await.map_err(provider_error) })
+            .await?;
+        if output.len() < 32 {
+            return Err(RelayerError::Serialization(format!("getReceiverLastNonce returned {} bytes", output.len())));
         }
-        
-        // 尝试解析为 Solana base58 格式（Pubkey）
-        match bs58::decode(s).into_vec() {
-            Ok(bytes) if bytes.len() == 32 => {
-                let mut result = [0u8; 32];
-                result.copy_from_slice(&bytes);
-                Ok(result)
-            }
-            Ok(bytes) => Err(anyhow!("Invalid Solana pubkey length: {}", bytes.len())),
-            Err(_) => {
-                // 最后尝试作为hex解析（无0x前缀）
-                let bytes = hex::decode(s)?;
-                if bytes.len() > 32 {
-                    return Err(anyhow!("Bytes too long: {} > 32", bytes.len()));
-                }
-                let mut result = [0u8; 32];
-                result[..bytes.len()].copy_from_slice(&bytes);
-                Ok(result)
+        Ok(Some(U256::from_big_endian(&output[..32]).low_u64()))
+    }
+
+    fn set_rpc_url(&self, url: &str) -> shared::Result<()> {
+        if self.client.get().url != url {
+            self.client.set(EvmRpc::connect(url)?);
+            info!(rpc = %shared::metrics::endpoint_label(url), "Switched EVM RPC endpoint");
+        }
+        Ok(())
+    }
+}
+
+/// ethers Provider 错误 → RelayerError：合约 revert 按桥合约自定义错误解码，JSON-RPC 错误按错误码分类
+fn provider_error(error: ProviderError) -> RelayerError {
+    if let Some(response) = RpcError::as_error_response(&error) {
+        return match response.as_revert_data() {
+            Some(data) if !data.is_empty() => RelayerError::from_revert_data(&data),
+            Some(_) => RelayerError::Reverted(response.message.clone()),
+            // geth 对余额不足只返回通用错误码 -32000
+            None if response.message.starts_with("insufficient funds") => {
+                RelayerError::InsufficientFunds(response.message.clone())
             }
+            None => RelayerError::from_rpc_error(response.code, &response.message),
+        };
+    }
+    match error {
+        ProviderError::HTTPError(e) => e.into(),
+        ProviderError::SerdeJson(e) => RelayerError::Serialization(e.to_string()),
+        // 响应体不是 JSON（网关错误页等）
+        ProviderError::JsonRpcClientError(e) => RelayerError::Network(e.to_string()),
+        other => RelayerError::RpcConnection(other.to_string()),
+    }
+}
+
+/// wei → ether（用于指标和余额检查，允许精度损失）
+fn wei_to_ether(wei: U256) -> f64 {
+    wei.to_string().parse::<f64>().unwrap_or(0.0) / 1e18
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::ChainAddress;
+
+    const CONTRACT: &str = "0x00000000000000000000000000000000000000cc";
+
+    fn event(nonce: u64) -> StakeEventData {
+        StakeEventData {
+            source_contract: ChainAddress::Svm([1u8; 32]),
+            target_contract: ChainAddress::Evm([0xcc; 20]),
+            source_chain_id: 91024,
+            target_chain_id: 421614,
+            block_height: 100,
+            amount: 1_000,
+            receiver_address: ChainAddress::Evm([2u8; 20]),
+            nonce,
         }
     }
+
+    /// 与 submit_signature 相同方式构造的未签名交易 RLP
+    fn transaction(to: &str, chain_id: u64, data: Bytes, value: u64) -> Vec<u8> {
+        let mut tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .from(Address::repeat_byte(0x11))
+            .to(to.parse::<Address>().unwrap())
+            .data(data)
+            .value(value)
+            .nonce(5)
+            .gas(100_000)
+            .into();
+        tx.set_chain_id(chain_id);
+        tx.rlp().to_vec()
+    }
+
+    #[test]
+    fn test_submit_signature_policy() {
+        let policy = SubmitSignaturePolicy::new(CONTRACT, 421614).unwrap();
+        let calldata = encode_submit_signature(&event(7), &[0xab; 65]);
+        policy.check(&event(7), &transaction(CONTRACT, 421614, calldata.clone(), 0)).unwrap();
+
+        // 旧式交易同样可以解码
+        let mut legacy: TypedTransaction = TransactionRequest::new().to(CONTRACT.parse::<Address>().unwrap()).data(calldata.clone()).into();
+        legacy.set_chain_id(421614u64);
+        policy.check(&event(7), &legacy.rlp()).unwrap();
+
+        let refused = |event: &StakeEventData, tx: Vec<u8>| policy.check(event, &tx).unwrap_err().to_string();
+        assert!(refused(&event(8), transaction(CONTRACT, 421614, calldata.clone(), 0)).contains("does not match the event"));
+        let other = "0x00000000000000000000000000000000000000dd";
+        assert!(refused(&event(7), transaction(other, 421614, calldata.clone(), 0)).contains("not the bridge contract"));
+        assert!(refused(&event(7), transaction(CONTRACT, 1, calldata.clone(), 0)).contains("chain id"));
+        assert!(refused(&event(7), transaction(CONTRACT, 421614, calldata.clone(), 1)).contains("transfers value"));
+        let transfer = Bytes::from(ethers::utils::keccak256("transfer(address,uint256)")[..4].to_vec());
+        assert!(refused(&event(7), transaction(CONTRACT, 421614, transfer, 0)).contains("not a submitSignature call"));
+        let mut padded = calldata.to_vec();
+        padded.extend_from_slice(&[0u8; 32]);
+        assert!(refused(&event(7), transaction(CONTRACT, 421614, padded.into(), 0)).contains("non-canonical"));
+        assert!(refused(&event(7), vec![0xde, 0xad]).contains("invalid transaction"));
+    }
 }
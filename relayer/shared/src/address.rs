@@ -0,0 +1,300 @@
+//! 链地址
+//!
+//! `StakeEventData` 中的合约与接收地址统一用 [`ChainAddress`] 表示：
+//! - `Evm`：20 字节地址，渲染为 EIP-55 校验和格式（`0x` + 40 位十六进制）
+//! - `Svm`：32 字节公钥，渲染为 base58
+//! - `Bytes32`：链无关的 32 字节标识（EVM 事件 topic），渲染为 64 位小写十六进制（无前缀）
+//!
+//! 渲染结果就是签名消息中使用的字符串，serde 与 Borsh 都按该字符串编码，
+//! 因此同一地址在所有 relayer 上得到逐字节一致的证明。
+
+use crate::error::{RelayerError, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha3::{Digest, Keccak256};
+use std::fmt;
+use std::str::FromStr;
+
+/// 地址类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AddressKind {
+    Evm,
+    Svm,
+    Bytes32,
+}
+
+impl fmt::Display for AddressKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AddressKind::Evm => write!(f, "evm"),
+            AddressKind::Svm => write!(f, "svm"),
+            AddressKind::Bytes32 => write!(f, "bytes32"),
+        }
+    }
+}
+
+/// 跨链事件中的地址
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChainAddress {
+    Evm([u8; 20]),
+    Svm([u8; 32]),
+    Bytes32([u8; 32]),
+}
+
+impl ChainAddress {
+    /// 按指定类型严格解析
+    ///
+    /// - `Evm`：40 位十六进制，`0x` 前缀可选（与 `Bridge1024._parseAddress` 一致）；
+    ///   大小写混合时必须是正确的 EIP-55 校验和
+    /// - `Svm`：base58，解码后恰好 32 字节
+    /// - `Bytes32`：64 位十六进制，`0x` 前缀可选
+    pub fn parse_as(value: &str, kind: AddressKind) -> Result<Self> {
+        let invalid = |reason: String| RelayerError::InvalidEvent(format!("{} address '{}': {}", kind, value, reason));
+
+        match kind {
+            AddressKind::Evm => {
+                let hex_str = strip_hex_prefix(value);
+                let mut bytes = [0u8; 20];
+                decode_hex(hex_str, &mut bytes).map_err(invalid)?;
+                let has_lower = hex_str.chars().any(|c| c.is_ascii_lowercase());
+                let has_upper = hex_str.chars().any(|c| c.is_ascii_uppercase());
+                if has_lower && has_upper && hex_str != eip55_hex(&bytes) {
+                    return Err(invalid("EIP-55 checksum mismatch".to_string()));
+                }
+                Ok(ChainAddress::Evm(bytes))
+            }
+            AddressKind::Svm => {
+                let decoded = bs58::decode(value)
+                    .into_vec()
+                    .map_err(|e| invalid(format!("invalid base58: {}", e)))?;
+                let bytes: [u8; 32] = decoded
+                    .try_into()
+                    .map_err(|v: Vec<u8>| invalid(format!("decodes to {} bytes, expected 32", v.len())))?;
+                Ok(ChainAddress::Svm(bytes))
+            }
+            AddressKind::Bytes32 => {
+                let mut bytes = [0u8; 32];
+                decode_hex(strip_hex_prefix(value), &mut bytes).map_err(invalid)?;
+                Ok(ChainAddress::Bytes32(bytes))
+            }
+        }
+    }
+
+    pub fn kind(&self) -> AddressKind {
+        match self {
+            ChainAddress::Evm(_) => AddressKind::Evm,
+            ChainAddress::Svm(_) => AddressKind::Svm,
+            ChainAddress::Bytes32(_) => AddressKind::Bytes32,
+        }
+    }
+
+    /// 要求地址为指定类型（listener 入口校验接收地址）
+    pub fn expect_kind(self, kind: AddressKind) -> Result<Self> {
+        if self.kind() != kind {
+            return Err(RelayerError::InvalidEvent(format!(
+                "expected {} address, got {} address '{}'",
+                kind,
+                self.kind(),
+                self
+            )));
+        }
+        Ok(self)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            ChainAddress::Evm(bytes) => bytes,
+            ChainAddress::Svm(bytes) | ChainAddress::Bytes32(bytes) => bytes,
+        }
+    }
+
+    /// EVM 合约中的 `bytes32`：EVM 地址左侧补零（同 `bytes32(uint256(uint160(addr)))`）
+    pub fn to_bytes32(&self) -> [u8; 32] {
+        match self {
+            ChainAddress::Evm(bytes) => {
+                let mut out = [0u8; 32];
+                out[12..].copy_from_slice(bytes);
+                out
+            }
+            ChainAddress::Svm(bytes) | ChainAddress::Bytes32(bytes) => *bytes,
+        }
+    }
+}
+
+/// 按字符串形式自动识别：`0x` + 40 位为 EVM，64 位十六进制（可带 `0x`）为 bytes32，其余按 base58 公钥解析
+///
+/// 无前缀的 40 位十六进制可能与 base58 混淆，只接受 [`ChainAddress::parse_as`] 显式指定 `Evm`。
+impl FromStr for ChainAddress {
+    type Err = RelayerError;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let hex_str = strip_hex_prefix(value);
+        if hex_str.len() != value.len() && hex_str.len() == 40 {
+            Self::parse_as(value, AddressKind::Evm)
+        } else if hex_str.len() == 64 {
+            Self::parse_as(value, AddressKind::Bytes32)
+        } else {
+            Self::parse_as(value, AddressKind::Svm)
+        }
+    }
+}
+
+impl fmt::Display for ChainAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChainAddress::Evm(bytes) => write!(f, "0x{}", eip55_hex(bytes)),
+            ChainAddress::Svm(bytes) => write!(f, "{}", bs58::encode(bytes).into_string()),
+            ChainAddress::Bytes32(bytes) => write!(f, "{}", hex::encode(bytes)),
+        }
+    }
+}
+
+impl fmt::Debug for ChainAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}({})", self.kind(), self)
+    }
+}
+
+impl Serialize for ChainAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ChainAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Borsh 编码为规范字符串，与 Anchor `StakeEventData` 的 `String` 字段布局一致
+#[cfg(feature = "borsh")]
+impl borsh::BorshSerialize for ChainAddress {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        borsh::BorshSerialize::serialize(&self.to_string(), writer)
+    }
+}
+
+#[cfg(feature = "borsh")]
+impl borsh::BorshDeserialize for ChainAddress {
+    fn deserialize_reader<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
+        let value = String::deserialize_reader(reader)?;
+        value
+            .parse()
+            .map_err(|e: RelayerError| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))
+    }
+}
+
+fn strip_hex_prefix(value: &str) -> &str {
+    value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value)
+}
+
+fn decode_hex(hex_str: &str, out: &mut [u8]) -> std::result::Result<(), String> {
+    if hex_str.len() != out.len() * 2 {
+        return Err(format!("expected {} hex characters, got {}", out.len() * 2, hex_str.len()));
+    }
+    hex::decode_to_slice(hex_str, out).map_err(|e| e.to_string())
+}
+
+/// EIP-55：小写十六进制的 keccak256 中对应半字节 >= 8 的字母大写
+fn eip55_hex(bytes: &[u8; 20]) -> String {
+    let lower = hex::encode(bytes);
+    let hash = Keccak256::digest(lower.as_bytes());
+    lower
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHECKSUMMED: &str = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8";
+
+    #[test]
+    fn test_evm_parse_and_render() {
+        let address = ChainAddress::parse_as(CHECKSUMMED, AddressKind::Evm).unwrap();
+        assert_eq!(address.to_string(), CHECKSUMMED);
+
+        // 全小写 / 全大写 / 无前缀都接受，并渲染为校验和格式
+        for input in [
+            CHECKSUMMED.to_lowercase(),
+            format!("0X{}", CHECKSUMMED[2..].to_uppercase()),
+            CHECKSUMMED[2..].to_lowercase(),
+        ] {
+            assert_eq!(ChainAddress::parse_as(&input, AddressKind::Evm).unwrap(), address, "{}", input);
+        }
+
+        let bytes32 = address.to_bytes32();
+        assert_eq!(&bytes32[..12], &[0u8; 12]);
+        assert_eq!(&bytes32[12..], address.as_bytes());
+    }
+
+    #[test]
+    fn test_evm_rejects_malformed() {
+        let bad_checksum = CHECKSUMMED.replace('C', "c").replacen('d', "D", 1);
+        for input in ["", "0x", "0x1234", &format!("{}00", CHECKSUMMED), &bad_checksum, "0xzz997970c51812dc3a010c7d01b50e0d17dc79c8"] {
+            assert!(ChainAddress::parse_as(input, AddressKind::Evm).is_err(), "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_svm_and_bytes32() {
+        let base58 = bs58::encode([9u8; 32]).into_string();
+        let svm: ChainAddress = base58.parse().unwrap();
+        assert_eq!(svm, ChainAddress::Svm([9u8; 32]));
+        assert_eq!(svm.to_string(), base58);
+        assert!(ChainAddress::parse_as(&bs58::encode([1u8; 20]).into_string(), AddressKind::Svm).is_err());
+        assert!(ChainAddress::parse_as("0OIl", AddressKind::Svm).is_err());
+
+        let upper: ChainAddress = format!("0x{}", "AB".repeat(32)).parse().unwrap();
+        assert_eq!(upper, ChainAddress::Bytes32([0xab; 32]));
+        assert_eq!(upper.to_string(), "ab".repeat(32));
+        assert!(ChainAddress::parse_as(&"0".repeat(63), AddressKind::Bytes32).is_err());
+    }
+
+    #[test]
+    fn test_expect_kind() {
+        let svm = ChainAddress::Svm([1u8; 32]);
+        assert!(svm.expect_kind(AddressKind::Svm).is_ok());
+        assert!(svm.expect_kind(AddressKind::Evm).is_err());
+    }
+
+    #[test]
+    fn test_serde_round_trip() {
+        for address in [
+            ChainAddress::Evm([0xcd; 20]),
+            ChainAddress::Svm([7u8; 32]),
+            ChainAddress::Bytes32([0xab; 32]),
+        ] {
+            let json = serde_json::to_string(&address).unwrap();
+            assert_eq!(json, format!("\"{}\"", address));
+            assert_eq!(serde_json::from_str::<ChainAddress>(&json).unwrap(), address);
+        }
+        assert!(serde_json::from_str::<ChainAddress>("\"receiver\"").is_err());
+    }
+
+    #[cfg(feature = "borsh")]
+    #[test]
+    fn test_borsh_round_trip() {
+        use borsh::{BorshDeserialize, BorshSerialize};
+
+        let address = ChainAddress::Evm([0xcd; 20]);
+        let encoded = address.try_to_vec().unwrap();
+        assert_eq!(encoded, address.to_string().try_to_vec().unwrap());
+        assert_eq!(ChainAddress::try_from_slice(&encoded).unwrap(), address);
+        assert!(ChainAddress::try_from_slice(&"not-an-address".to_string().try_to_vec().unwrap()).is_err());
+    }
+}
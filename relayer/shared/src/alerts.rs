@@ -0,0 +1,615 @@
+//! 告警
+//!
+//! 中继出现需要人工介入的问题（余额不足、连续永久失败、监听停滞、nonce 停滞）时通知值班人员：
+//! - [`AlertSink`]：通用 JSON webhook、聊天 webhook、SMTP 邮件、执行命令
+//! - [`Alerter`]：级别过滤、按 key 去重（级别升高时立即发送）、每小时限流（critical 不限流），问题解除后发送恢复通知
+//! - 进程内的全局告警器由 [`init_alerts`] 按配置创建，各模块通过 [`notify`] / [`resolve`] 上报；
+//!   未初始化时上报是空操作
+
+use crate::config::{AlertsConfig, Config};
+use crate::error::{RelayerError, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::process::Stdio;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tracing::{error, info, warn};
+
+/// 告警通道的发送超时
+const SEND_TIMEOUT: Duration = Duration::from_secs(30);
+/// 限流窗口
+const RATE_WINDOW: Duration = Duration::from_secs(3600);
+/// 超过该时长没有再上报也没有解除的告警不再跟踪（如 `screening-block:*` 这类不会解除的 key）
+const ACTIVE_TTL: Duration = Duration::from_secs(24 * 3600);
+
+/// 告警级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Severity {
+    type Err = RelayerError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "info" => Ok(Severity::Info),
+            "warning" | "warn" => Ok(Severity::Warning),
+            "critical" => Ok(Severity::Critical),
+            other => Err(RelayerError::Config(format!("Unknown alert severity: {}", other))),
+        }
+    }
+}
+
+/// 一条告警
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alert {
+    /// 去重键：同一问题的重复告警使用同一个 key
+    pub key: String,
+    pub severity: Severity,
+    pub title: String,
+    pub message: String,
+    /// 发出告警的服务，发送时填充
+    #[serde(default)]
+    pub service: String,
+    /// 问题已解除的恢复通知
+    #[serde(default)]
+    pub resolved: bool,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl Alert {
+    pub fn new(
+        severity: Severity,
+        key: impl Into<String>,
+        title: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            key: key.into(),
+            severity,
+            title: title.into(),
+            message: message.into(),
+            service: String::new(),
+            resolved: false,
+            timestamp: Utc::now(),
+        }
+    }
+
+    /// 单行摘要，例如 `[CRITICAL] s2e: Relayer underfunded`
+    pub fn summary(&self) -> String {
+        let status = if self.resolved { "RESOLVED".to_string() } else { self.severity.to_string().to_uppercase() };
+        format!("[{}] {}: {}", status, self.service, self.title)
+    }
+}
+
+/// 告警通道
+#[async_trait]
+pub trait AlertSink: Send + Sync {
+    /// 通道名称（用于日志）
+    fn name(&self) -> &str;
+
+    async fn send(&self, alert: &Alert) -> Result<()>;
+}
+
+/// webhook 消息格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookFormat {
+    /// 完整的告警 JSON
+    Json,
+    /// `{"text": "..."}`，聊天机器人 webhook
+    Chat,
+}
+
+/// HTTP webhook 通道
+pub struct WebhookSink {
+    client: reqwest::Client,
+    url: String,
+    format: WebhookFormat,
+}
+
+impl WebhookSink {
+    pub fn new(url: &str, format: WebhookFormat) -> Self {
+        Self {
+            client: reqwest::Client::builder().timeout(SEND_TIMEOUT).build().unwrap_or_default(),
+            url: url.to_string(),
+            format,
+        }
+    }
+
+    fn body(&self, alert: &Alert) -> serde_json::Value {
+        match self.format {
+            WebhookFormat::Json => json!(alert),
+            WebhookFormat::Chat => json!({ "text": format!("{}\n{}", alert.summary(), alert.message) }),
+        }
+    }
+}
+
+#[async_trait]
+impl AlertSink for WebhookSink {
+    fn name(&self) -> &str {
+        match self.format {
+            WebhookFormat::Json => "webhook",
+            WebhookFormat::Chat => "chat",
+        }
+    }
+
+    async fn send(&self, alert: &Alert) -> Result<()> {
+        self.client
+            .post(&self.url)
+            .json(&self.body(alert))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| RelayerError::Alert(format!("{} webhook: {}", self.name(), e)))?;
+        Ok(())
+    }
+}
+
+/// SMTP 邮件通道
+pub struct EmailSink {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl EmailSink {
+    pub fn new(config: &AlertsConfig) -> Result<Self> {
+        let host = config
+            .smtp_host
+            .as_deref()
+            .ok_or_else(|| RelayerError::Config("alerts.smtp_host is required for email alerts".to_string()))?;
+        let mailbox = |address: &str| {
+            address
+                .parse::<Mailbox>()
+                .map_err(|e| RelayerError::Config(format!("Invalid alert email address '{}': {}", address, e)))
+        };
+        let from = mailbox(config.email_from.as_deref().unwrap_or(""))?;
+        let to = config.email_to.iter().map(|address| mailbox(address)).collect::<Result<Vec<_>>>()?;
+
+        // 465 为隐式 TLS，其余端口使用 STARTTLS
+        let builder = if config.smtp_port == 465 {
+            AsyncSmtpTransport::<Tokio1Executor>::relay(host)
+        } else {
+            AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+        }
+        .map_err(|e| RelayerError::Config(format!("Invalid SMTP host {}: {}", host, e)))?;
+        let mut builder = builder.port(config.smtp_port).timeout(Some(SEND_TIMEOUT));
+        if let (Some(username), Some(password)) = (&config.smtp_username, &config.smtp_password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.expose().to_string()));
+        }
+
+        Ok(Self { transport: builder.build(), from, to })
+    }
+}
+
+#[async_trait]
+impl AlertSink for EmailSink {
+    fn name(&self) -> &str {
+        "email"
+    }
+
+    async fn send(&self, alert: &Alert) -> Result<()> {
+        let mut builder = Message::builder()
+            .from(self.from.clone())
+            .subject(alert.summary())
+            .header(ContentType::TEXT_PLAIN);
+        for to in &self.to {
+            builder = builder.to(to.clone());
+        }
+        let body = format!("{}\n\nkey: {}\ntime: {}\n", alert.message, alert.key, alert.timestamp.to_rfc3339());
+        let message = builder.body(body).map_err(|e| RelayerError::Alert(format!("email: {}", e)))?;
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| RelayerError::Alert(format!("email: {}", e)))?;
+        Ok(())
+    }
+}
+
+/// 执行命令通道：`sh -c <command>`，告警 JSON 写入 stdin，并设置 ALERT_* 环境变量
+pub struct CommandSink {
+    command: String,
+}
+
+impl CommandSink {
+    pub fn new(command: &str) -> Self {
+        Self { command: command.to_string() }
+    }
+}
+
+#[async_trait]
+impl AlertSink for CommandSink {
+    fn name(&self) -> &str {
+        "command"
+    }
+
+    async fn send(&self, alert: &Alert) -> Result<()> {
+        let failed = |e: &dyn fmt::Display| RelayerError::Alert(format!("command: {}", e));
+        let mut child = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .env("ALERT_KEY", &alert.key)
+            .env("ALERT_SEVERITY", alert.severity.to_string())
+            .env("ALERT_TITLE", &alert.title)
+            .env("ALERT_MESSAGE", &alert.message)
+            .env("ALERT_SERVICE", &alert.service)
+            .env("ALERT_RESOLVED", alert.resolved.to_string())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| failed(&e))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            let payload = serde_json::to_vec(alert).map_err(|e| RelayerError::Serialization(e.to_string()))?;
+            // 命令可以不读 stdin
+            let _ = stdin.write_all(&payload).await;
+        }
+        let status = tokio::time::timeout(SEND_TIMEOUT, child.wait())
+            .await
+            .map_err(|_| failed(&"timed out"))?
+            .map_err(|e| failed(&e))?;
+        if !status.success() {
+            return Err(failed(&status));
+        }
+        Ok(())
+    }
+}
+
+/// 已发送、尚未解除的告警
+struct ActiveAlert {
+    severity: Severity,
+    title: String,
+    sent_at: Instant,
+    /// 最近一次上报（含被去重的）
+    seen_at: Instant,
+}
+
+#[derive(Default)]
+struct AlertState {
+    active: HashMap<String, ActiveAlert>,
+    /// 限流窗口内的发送时间
+    sent: VecDeque<Instant>,
+}
+
+/// 告警器：过滤、去重、限流后发送到所有通道
+pub struct Alerter {
+    service: String,
+    sinks: Vec<Arc<dyn AlertSink>>,
+    min_severity: Severity,
+    dedup_window: Duration,
+    max_per_hour: u32,
+    stall_timeout: Duration,
+    state: Mutex<AlertState>,
+}
+
+impl Alerter {
+    pub fn new(service: &str, sinks: Vec<Arc<dyn AlertSink>>, config: &AlertsConfig) -> Result<Self> {
+        Ok(Self {
+            service: service.to_string(),
+            sinks,
+            min_severity: config.min_severity.parse()?,
+            dedup_window: Duration::from_millis(config.dedup_window),
+            max_per_hour: config.max_per_hour,
+            stall_timeout: Duration::from_millis(config.stall_timeout),
+            state: Mutex::new(AlertState::default()),
+        })
+    }
+
+    /// 按配置创建所有通道
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let alerts = &config.alerts;
+        let mut sinks: Vec<Arc<dyn AlertSink>> = Vec::new();
+        if let Some(url) = &alerts.webhook_url {
+            sinks.push(Arc::new(WebhookSink::new(url, WebhookFormat::Json)));
+        }
+        if let Some(url) = &alerts.chat_webhook_url {
+            sinks.push(Arc::new(WebhookSink::new(url, WebhookFormat::Chat)));
+        }
+        if alerts.smtp_host.is_some() {
+            sinks.push(Arc::new(EmailSink::new(alerts)?));
+        }
+        if let Some(command) = &alerts.command {
+            sinks.push(Arc::new(CommandSink::new(command)));
+        }
+        Self::new(&config.service.name, sinks, alerts)
+    }
+
+    pub fn stall_timeout(&self) -> Duration {
+        self.stall_timeout
+    }
+
+    /// 级别过滤、去重和限流，返回是否发送
+    fn admit(&self, alert: &Alert) -> bool {
+        if alert.severity < self.min_severity {
+            return false;
+        }
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        Self::prune(&mut state, now);
+        if let Some(active) = state.active.get_mut(&alert.key) {
+            active.seen_at = now;
+            if alert.severity <= active.severity && now.duration_since(active.sent_at) < self.dedup_window {
+                return false;
+            }
+        }
+        if !self.take_rate_slot(&mut state, alert.severity, now) {
+            warn!(key = %alert.key, max_per_hour = self.max_per_hour, "Alert rate limit reached, dropping alert");
+            return false;
+        }
+        state.active.insert(
+            alert.key.clone(),
+            ActiveAlert { severity: alert.severity, title: alert.title.clone(), sent_at: now, seen_at: now },
+        );
+        true
+    }
+
+    /// 丢弃长时间没有再上报的告警，避免不会解除的 key 无限累积
+    fn prune(state: &mut AlertState, now: Instant) {
+        state.active.retain(|_, active| now.duration_since(active.seen_at) < ACTIVE_TTL);
+    }
+
+    /// critical 告警及其恢复通知不限流，也不占用限流额度
+    fn take_rate_slot(&self, state: &mut AlertState, severity: Severity, now: Instant) -> bool {
+        if severity == Severity::Critical {
+            return true;
+        }
+        while state.sent.front().is_some_and(|sent| now.duration_since(*sent) >= RATE_WINDOW) {
+            state.sent.pop_front();
+        }
+        if state.sent.len() >= self.max_per_hour as usize {
+            return false;
+        }
+        state.sent.push_back(now);
+        true
+    }
+
+    /// 上报告警；被过滤、去重或限流时返回 None，否则返回待发送的告警
+    pub fn raise(&self, mut alert: Alert) -> Option<Alert> {
+        alert.service = self.service.clone();
+        if !self.admit(&alert) {
+            return None;
+        }
+        match alert.severity {
+            Severity::Critical => error!(key = %alert.key, message = %alert.message, "🚨 {}", alert.summary()),
+            _ => warn!(key = %alert.key, message = %alert.message, "⚠️  {}", alert.summary()),
+        }
+        Some(alert)
+    }
+
+    /// 问题解除：清除去重记录，之前发送过告警时返回恢复通知
+    pub fn clear(&self, key: &str, message: impl Into<String>) -> Option<Alert> {
+        let mut state = self.state.lock().unwrap();
+        let active = state.active.remove(key)?;
+        if !self.take_rate_slot(&mut state, active.severity, Instant::now()) {
+            return None;
+        }
+        let mut alert = Alert::new(active.severity, key, active.title, message);
+        alert.service = self.service.clone();
+        alert.resolved = true;
+        info!(key = %alert.key, "✅ {}", alert.summary());
+        Some(alert)
+    }
+
+    /// 发送到所有通道，单个通道失败只记录日志
+    pub async fn deliver(&self, alert: &Alert) {
+        for sink in &self.sinks {
+            if let Err(e) = sink.send(alert).await {
+                warn!(sink = sink.name(), key = %alert.key, error = %e, "Failed to deliver alert");
+            }
+        }
+    }
+}
+
+static ALERTER: OnceLock<Arc<Alerter>> = OnceLock::new();
+
+/// 按配置初始化进程内的全局告警器
+pub fn init_alerts(config: &Config) -> Result<()> {
+    let alerter = Alerter::from_config(config)?;
+    info!(sinks = alerter.sinks.len(), min_severity = %alerter.min_severity, "Alerting initialized");
+    let _ = ALERTER.set(Arc::new(alerter));
+    Ok(())
+}
+
+/// 上报告警（在后台发送，不阻塞调用方）
+pub fn notify(alert: Alert) {
+    let Some(alerter) = ALERTER.get() else {
+        return;
+    };
+    if let Some(alert) = alerter.raise(alert) {
+        spawn_delivery(alerter.clone(), alert);
+    }
+}
+
+/// 问题已解除；之前发送过告警时发送恢复通知
+pub fn resolve(key: &str, message: impl Into<String>) {
+    let Some(alerter) = ALERTER.get() else {
+        return;
+    };
+    if let Some(alert) = alerter.clear(key, message) {
+        spawn_delivery(alerter.clone(), alert);
+    }
+}
+
+fn spawn_delivery(alerter: Arc<Alerter>, alert: Alert) {
+    if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+        runtime.spawn(async move { alerter.deliver(&alert).await });
+    }
+}
+
+/// 停滞告警的阈值（未初始化时使用默认配置）
+pub fn stall_timeout() -> Duration {
+    ALERTER
+        .get()
+        .map(|alerter| alerter.stall_timeout())
+        .unwrap_or_else(|| Duration::from_millis(AlertsConfig::default().stall_timeout))
+}
+
+/// 进度停滞检测：进度值在 `timeout` 内没有变化时报告停滞时长
+#[derive(Debug)]
+pub struct StallDetector {
+    timeout: Duration,
+    last: Option<(u64, Instant)>,
+}
+
+impl StallDetector {
+    pub fn new(timeout: Duration) -> Self {
+        Self { timeout, last: None }
+    }
+
+    /// 记录当前进度；`waiting` 为 false（没有等待推进的工作）时重新计时。停滞超过阈值时返回停滞时长
+    pub fn observe(&mut self, value: u64, waiting: bool) -> Option<Duration> {
+        let now = Instant::now();
+        match self.last {
+            Some((last, since)) if last == value && waiting => {
+                let stalled = now.duration_since(since);
+                (stalled >= self.timeout).then_some(stalled)
+            }
+            _ => {
+                self.last = Some((value, now));
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 记录收到的告警
+    #[derive(Default)]
+    struct MemorySink {
+        alerts: Mutex<Vec<Alert>>,
+    }
+
+    #[async_trait]
+    impl AlertSink for MemorySink {
+        fn name(&self) -> &str {
+            "memory"
+        }
+
+        async fn send(&self, alert: &Alert) -> Result<()> {
+            self.alerts.lock().unwrap().push(alert.clone());
+            Ok(())
+        }
+    }
+
+    fn alerter(config: AlertsConfig) -> (Alerter, Arc<MemorySink>) {
+        let sink = Arc::new(MemorySink::default());
+        (Alerter::new("s2e", vec![sink.clone()], &config).unwrap(), sink)
+    }
+
+    fn alert(severity: Severity, key: &str) -> Alert {
+        Alert::new(severity, key, "Relayer underfunded", "balance 0.01 ETH")
+    }
+
+    #[tokio::test]
+    async fn test_dedup_escalation_and_resolve() {
+        let (alerter, sink) = alerter(AlertsConfig::default());
+
+        // 低于最低级别的告警被过滤
+        assert!(alerter.raise(alert(Severity::Info, "balance:evm")).is_none());
+        let first = alerter.raise(alert(Severity::Warning, "balance:evm")).unwrap();
+        assert_eq!(first.service, "s2e");
+        assert_eq!(first.summary(), "[WARNING] s2e: Relayer underfunded");
+        alerter.deliver(&first).await;
+
+        // 去重窗口内重复告警不发送，级别升高时立即发送
+        assert!(alerter.raise(alert(Severity::Warning, "balance:evm")).is_none());
+        assert!(alerter.raise(alert(Severity::Critical, "balance:evm")).is_some());
+        assert!(alerter.raise(alert(Severity::Warning, "nonce-stalled")).is_some());
+
+        let resolved = alerter.clear("balance:evm", "balance restored").unwrap();
+        assert!(resolved.resolved);
+        assert_eq!(resolved.severity, Severity::Critical);
+        assert!(alerter.clear("balance:evm", "again").is_none());
+        assert!(alerter.raise(alert(Severity::Warning, "balance:evm")).is_some());
+        assert_eq!(sink.alerts.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let (alerter, _) = alerter(AlertsConfig { max_per_hour: 2, dedup_window: 0, ..AlertsConfig::default() });
+        assert!(alerter.raise(alert(Severity::Warning, "a")).is_some());
+        assert!(alerter.raise(alert(Severity::Warning, "a")).is_some());
+        assert!(alerter.raise(alert(Severity::Warning, "b")).is_none());
+        // critical 不受限流影响
+        for key in ["c", "d", "e"] {
+            assert!(alerter.raise(alert(Severity::Critical, key)).is_some());
+        }
+        assert!(alerter.clear("c", "fixed").is_some());
+    }
+
+    #[test]
+    fn test_prunes_stale_active_alerts() {
+        let (alerter, _) = alerter(AlertsConfig::default());
+        assert!(alerter.raise(alert(Severity::Critical, "screening-block:1")).is_some());
+        assert!(alerter.raise(alert(Severity::Critical, "balance:evm")).is_some());
+
+        let mut state = alerter.state.lock().unwrap();
+        state.active.get_mut("balance:evm").unwrap().seen_at += Duration::from_secs(23 * 3600);
+        Alerter::prune(&mut state, Instant::now() + ACTIVE_TTL);
+        assert_eq!(state.active.keys().collect::<Vec<_>>(), ["balance:evm"]);
+    }
+
+    #[test]
+    fn test_webhook_formats() {
+        let mut alert = alert(Severity::Critical, "balance:evm");
+        alert.service = "s2e".to_string();
+
+        let body = WebhookSink::new("http://localhost/hook", WebhookFormat::Json).body(&alert);
+        assert_eq!(body["severity"], "critical");
+        assert_eq!(body["key"], "balance:evm");
+        let body = WebhookSink::new("http://localhost/hook", WebhookFormat::Chat).body(&alert);
+        assert_eq!(body["text"], "[CRITICAL] s2e: Relayer underfunded\nbalance 0.01 ETH");
+    }
+
+    #[tokio::test]
+    async fn test_command_sink() {
+        let path = std::env::temp_dir().join(format!("relayer-alert-{}.json", std::process::id()));
+        let command = format!("cat > {} && test \"$ALERT_SEVERITY\" = critical", path.display());
+        CommandSink::new(&command).send(&alert(Severity::Critical, "k")).await.unwrap();
+        let sent: Alert = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(sent.key, "k");
+
+        assert!(CommandSink::new("exit 3").send(&alert(Severity::Warning, "k")).await.is_err());
+    }
+
+    #[test]
+    fn test_stall_detector() {
+        let mut detector = StallDetector::new(Duration::from_millis(20));
+        assert!(detector.observe(5, true).is_none());
+        std::thread::sleep(Duration::from_millis(25));
+        assert!(detector.observe(5, true).is_some());
+        // 进度推进或没有待处理的工作时重新计时
+        assert!(detector.observe(6, true).is_none());
+        std::thread::sleep(Duration::from_millis(25));
+        assert!(detector.observe(6, false).is_none());
+        assert!(detector.observe(6, true).is_none());
+    }
+}
@@ -0,0 +1,492 @@
+//! 签名审计日志
+//!
+//! relayer 每次对 `StakeEventData` 签名都向只追加的 JSON Lines 文件写入一条记录：
+//! 签名的原始字节（EVM 为 `_hashEventData` 的 JSON 及实际签名的摘要，SVM 为 Borsh 消息）、签名、
+//! 源链交易 / 日志位置；提交结束后再追加一条目标链交易（或失败原因）记录。
+//! 命中合规拒绝名单而未签名的转账同样记录一条 `blocked`。
+//!
+//! 防篡改：
+//! - 每行是记录 JSON 加上末尾的 `"hash"` 字段，`hash = sha256(不含 hash 字段的记录 JSON)`，
+//!   记录中包含上一条的 `prev_hash`，修改或删除任意一行都会使之后的链断开
+//! - 最新记录的序号和哈希另存于 `<path>.head`，截断文件尾部会与它不一致
+//! - 记录先落盘再更新 head；两者之间崩溃时日志比 head 多一条，且该条正好接在 head 之后，
+//!   打开时把 head 前移
+//!
+//! `relayer-audit verify <path>` 校验整个文件。
+
+use crate::config::Config;
+use crate::encoding;
+use crate::error::{RelayerError, Result};
+use crate::screening::ScreeningMatch;
+use crate::signer::SignatureScheme;
+use crate::types::{SourceRef, StakeEventData, Task};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::{debug, info, warn};
+
+/// 第一条记录的 `prev_hash`
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+/// 每行末尾哈希字段的前缀
+const HASH_FIELD: &str = ",\"hash\":\"";
+
+/// 审计记录内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AuditRecord {
+    /// 对事件生成了证明签名
+    Signed {
+        /// 签名者身份（EVM 地址 / base58 公钥）
+        signer: String,
+        scheme: SignatureScheme,
+        /// `payload` 的编码：json（EVM）/ borsh（SVM）
+        encoding: String,
+        /// 签名的原始字节（hex）
+        payload: String,
+        /// secp256k1 实际签名的 32 字节摘要（hex）；Ed25519 直接签名 payload
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        digest: Option<String>,
+        signature: String,
+        event: Box<StakeEventData>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<SourceRef>,
+    },
+    /// 签名已提交到目标链
+    Submitted { target_tx: String },
+    /// 提交失败
+    Failed { error: String },
+    /// 命中合规拒绝名单，未签名
+    Blocked {
+        /// 命中的地址角色：receiver / sender
+        party: String,
+        address: String,
+        list: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        reason: Option<String>,
+        event: Box<StakeEventData>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        source: Option<SourceRef>,
+    },
+}
+
+/// 哈希链中的一条记录（不含 hash）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditBody {
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    pub prev_hash: String,
+    pub transfer_id: String,
+    #[serde(flatten)]
+    pub record: AuditRecord,
+}
+
+/// 已写入的记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    #[serde(flatten)]
+    pub body: AuditBody,
+    pub hash: String,
+}
+
+/// `<path>.head`：最新记录的位置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct AuditHead {
+    seq: u64,
+    hash: String,
+}
+
+/// 事件签名的原始字节：(编码, payload, secp256k1 摘要)
+fn signed_payload(scheme: SignatureScheme, event: &StakeEventData) -> (&'static str, Vec<u8>, Option<[u8; 32]>) {
+    match scheme {
+        SignatureScheme::Secp256k1 => (
+            "json",
+            encoding::evm_event_json(event).into_bytes(),
+            Some(encoding::evm_attestation_digest(event)),
+        ),
+        SignatureScheme::Ed25519 => ("borsh", encoding::svm_event_message(event), None),
+    }
+}
+
+/// 序列化一行：记录 JSON + 末尾的哈希字段
+fn seal(body: &AuditBody) -> Result<(String, String)> {
+    let json = serde_json::to_string(body).map_err(|e| RelayerError::Serialization(e.to_string()))?;
+    let hash = hex::encode(Sha256::digest(json.as_bytes()));
+    let line = format!("{}{}{}\"}}", &json[..json.len() - 1], HASH_FIELD, hash);
+    Ok((line, hash))
+}
+
+/// 拆分一行：返回 (记录 JSON, 记录中的哈希)
+fn unseal(line: &str) -> Option<(String, &str)> {
+    let split = line.rfind(HASH_FIELD)?;
+    let hash = line[split + HASH_FIELD.len()..].strip_suffix("\"}")?;
+    Some((format!("{}}}", &line[..split]), hash))
+}
+
+fn head_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".head");
+    PathBuf::from(name)
+}
+
+fn read_head(path: &Path) -> Result<Option<AuditHead>> {
+    match std::fs::read_to_string(head_path(path)) {
+        Ok(content) => serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| RelayerError::Serialization(format!("Invalid audit head file: {}", e))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(RelayerError::Internal(format!("Failed to read audit head file: {}", e))),
+    }
+}
+
+/// 原子地更新 head 文件（临时文件和所在目录都 fsync）
+fn write_head(path: &Path, head: &AuditHead) -> Result<()> {
+    let head_path = head_path(path);
+    let tmp = head_path.with_extension("head.tmp");
+    let json = serde_json::to_vec(head).map_err(|e| RelayerError::Serialization(e.to_string()))?;
+    File::create(&tmp)
+        .and_then(|mut f| {
+            f.write_all(&json)?;
+            f.sync_all()
+        })
+        .and_then(|_| std::fs::rename(&tmp, &head_path))
+        .and_then(|_| match head_path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => File::open(dir)?.sync_all(),
+            _ => Ok(()),
+        })
+        .map_err(|e| RelayerError::Internal(format!("Failed to write audit head file: {}", e)))
+}
+
+/// 最后一条记录是否正好接在 head 之后（追加后、更新 head 前崩溃）
+fn follows_head(head: Option<&AuditHead>, seq: u64, prev_hash: &str) -> bool {
+    match head {
+        Some(head) => seq == head.seq + 1 && prev_hash == head.hash,
+        None => seq == 0 && prev_hash == GENESIS_HASH,
+    }
+}
+
+struct AuditWriter {
+    file: File,
+    next_seq: u64,
+    last_hash: String,
+}
+
+/// 只追加的签名审计日志
+pub struct AuditLog {
+    path: PathBuf,
+    writer: Mutex<AuditWriter>,
+}
+
+impl AuditLog {
+    /// 打开（或创建）审计日志；文件尾部与 head 文件不一致时拒绝打开
+    ///
+    /// 日志恰好比 head 多一条且哈希链相接时视为写 head 前崩溃，前移 head 后打开
+    pub fn open(path: &Path) -> Result<Self> {
+        let io_error = |e: std::io::Error| RelayerError::Internal(format!("Audit log {}: {}", path.display(), e));
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(io_error)?;
+        }
+
+        let last = match File::open(path) {
+            Ok(file) => {
+                let mut last = None;
+                for line in BufReader::new(file).lines() {
+                    let line = line.map_err(io_error)?;
+                    if !line.trim().is_empty() {
+                        last = Some(line);
+                    }
+                }
+                last.map(|line| parse_entry(&line, 0)).transpose()?
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(io_error(e)),
+        };
+        let tail = last.as_ref().map(|entry| AuditHead { seq: entry.body.seq, hash: entry.hash.clone() });
+        let mut head = read_head(path)?;
+        if let (Some(entry), Some(tail)) = (&last, &tail) {
+            if head.as_ref() != Some(tail) && follows_head(head.as_ref(), entry.body.seq, &entry.body.prev_hash) {
+                warn!(path = %path.display(), seq = tail.seq, "Audit head is one entry behind the log, rolling it forward");
+                write_head(path, tail)?;
+                head = Some(tail.clone());
+            }
+        }
+        if head != tail {
+            return Err(RelayerError::Config(format!(
+                "Audit log {} does not match its head file (head {:?}, last entry {:?}); run `relayer-audit verify {}`",
+                path.display(),
+                head,
+                tail,
+                path.display()
+            )));
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path).map_err(io_error)?;
+        let (next_seq, last_hash) = match tail {
+            Some(head) => (head.seq + 1, head.hash),
+            None => (0, GENESIS_HASH.to_string()),
+        };
+        info!(path = %path.display(), entries = next_seq, "Audit log opened");
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer: Mutex::new(AuditWriter { file, next_seq, last_hash }),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 记录一次证明签名
+    pub fn record_signed(
+        &self,
+        task: &Task,
+        scheme: SignatureScheme,
+        signer: &str,
+        signature: &[u8],
+    ) -> Result<AuditEntry> {
+        let (encoding, payload, digest) = signed_payload(scheme, &task.event_data);
+        self.append(
+            &task.id,
+            AuditRecord::Signed {
+                signer: signer.to_string(),
+                scheme,
+                encoding: encoding.to_string(),
+                payload: hex::encode(payload),
+                digest: digest.map(hex::encode),
+                signature: hex::encode(signature),
+                event: Box::new(task.event_data.clone()),
+                source: task.source.clone(),
+            },
+        )
+    }
+
+    /// 记录提交结果
+    pub fn record_submitted(&self, transfer_id: &str, target_tx: &str) -> Result<AuditEntry> {
+        self.append(transfer_id, AuditRecord::Submitted { target_tx: target_tx.to_string() })
+    }
+
+    pub fn record_failed(&self, transfer_id: &str, error: &str) -> Result<AuditEntry> {
+        self.append(transfer_id, AuditRecord::Failed { error: error.to_string() })
+    }
+
+    /// 记录一次拒绝名单命中
+    pub fn record_blocked(&self, task: &Task, hit: &ScreeningMatch) -> Result<AuditEntry> {
+        self.append(
+            &task.id,
+            AuditRecord::Blocked {
+                party: hit.party.to_string(),
+                address: hit.address.to_string(),
+                list: hit.entry.list.clone(),
+                reason: hit.entry.reason.clone(),
+                event: Box::new(task.event_data.clone()),
+                source: task.source.clone(),
+            },
+        )
+    }
+
+    /// 追加一条记录并落盘，再更新 head 文件
+    fn append(&self, transfer_id: &str, record: AuditRecord) -> Result<AuditEntry> {
+        let mut writer = self.writer.lock().unwrap();
+        let body = AuditBody {
+            seq: writer.next_seq,
+            timestamp: Utc::now(),
+            prev_hash: writer.last_hash.clone(),
+            transfer_id: transfer_id.to_string(),
+            record,
+        };
+        let (line, hash) = seal(&body)?;
+        writer
+            .file
+            .write_all(format!("{}\n", line).as_bytes())
+            .and_then(|_| writer.file.sync_data())
+            .map_err(|e| RelayerError::Internal(format!("Failed to write audit log: {}", e)))?;
+        write_head(&self.path, &AuditHead { seq: body.seq, hash: hash.clone() })?;
+
+        writer.next_seq += 1;
+        writer.last_hash = hash.clone();
+        debug!(seq = body.seq, transfer = transfer_id, hash = %hash, "Audit entry written");
+        Ok(AuditEntry { body, hash })
+    }
+}
+
+/// 按配置打开审计日志，未启用时返回 None
+pub fn open_audit_log(config: &Config) -> Result<Option<AuditLog>> {
+    if !config.audit.enabled {
+        return Ok(None);
+    }
+    AuditLog::open(&config.audit.file_path(&config.service.name)).map(Some)
+}
+
+/// 解析并校验一行的哈希；`line_no` 用于错误信息
+fn parse_entry(line: &str, line_no: usize) -> Result<AuditEntry> {
+    let invalid = |reason: String| RelayerError::Internal(format!("line {}: {}", line_no, reason));
+    let (json, hash) = unseal(line).ok_or_else(|| invalid("missing hash field".to_string()))?;
+    let actual = hex::encode(Sha256::digest(json.as_bytes()));
+    if actual != hash {
+        return Err(invalid(format!("hash mismatch (recorded {}, computed {})", hash, actual)));
+    }
+    let body: AuditBody = serde_json::from_str(&json).map_err(|e| invalid(format!("invalid entry: {}", e)))?;
+    Ok(AuditEntry { body, hash: hash.to_string() })
+}
+
+/// 校验结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditReport {
+    pub entries: u64,
+    pub signatures: u64,
+    /// 最新记录的哈希（空文件为创世哈希）
+    pub last_hash: String,
+}
+
+/// 校验整个审计日志：逐行哈希、序号连续、哈希链、签名 payload 与事件编码一致、与 head 文件一致
+pub fn verify(path: &Path) -> Result<AuditReport> {
+    let file = File::open(path).map_err(|e| RelayerError::Internal(format!("{}: {}", path.display(), e)))?;
+    let mut report = AuditReport { entries: 0, signatures: 0, last_hash: GENESIS_HASH.to_string() };
+    let mut prev_hash = GENESIS_HASH.to_string();
+
+    for (index, line) in BufReader::new(file).lines().enumerate() {
+        let line_no = index + 1;
+        let line = line.map_err(|e| RelayerError::Internal(format!("line {}: {}", line_no, e)))?;
+        let entry = parse_entry(&line, line_no)?;
+        let invalid = |reason: String| RelayerError::Internal(format!("line {}: {}", line_no, reason));
+
+        if entry.body.seq != report.entries {
+            return Err(invalid(format!("expected seq {}, found {}", report.entries, entry.body.seq)));
+        }
+        if entry.body.prev_hash != report.last_hash {
+            return Err(invalid("prev_hash does not match the previous entry".to_string()));
+        }
+        if let AuditRecord::Signed { scheme, encoding, payload, digest, event, .. } = &entry.body.record {
+            let (expected_encoding, expected_payload, expected_digest) = signed_payload(*scheme, event);
+            if encoding != expected_encoding
+                || *payload != hex::encode(expected_payload)
+                || *digest != expected_digest.map(hex::encode)
+            {
+                return Err(invalid("signed payload does not match the recorded event".to_string()));
+            }
+            report.signatures += 1;
+        }
+
+        report.entries += 1;
+        prev_hash = std::mem::replace(&mut report.last_hash, entry.hash);
+    }
+
+    let tail = (report.entries > 0).then(|| AuditHead { seq: report.entries - 1, hash: report.last_hash.clone() });
+    match read_head(path)? {
+        head if head == tail => Ok(report),
+        // 写 head 前崩溃，下次打开时前移
+        head if tail.as_ref().is_some_and(|tail| follows_head(head.as_ref(), tail.seq, &prev_hash)) => Ok(report),
+        Some(head) => Err(RelayerError::Internal(format!(
+            "head file points to seq {} ({}), log ends at {:?}: entries were truncated or rewritten",
+            head.seq, head.hash, tail
+        ))),
+        None => Err(RelayerError::Internal("head file is missing".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{event, TempDir};
+
+    fn task(nonce: u64) -> Task {
+        let mut task = Task::new(event(nonce));
+        task.source = Some(SourceRef { tx: format!("0x{:064x}", nonce), log_index: Some(3), block: 100, sender: None });
+        task
+    }
+
+    fn write_log(dir: &Path) -> PathBuf {
+        let path = dir.join("audit.jsonl");
+
+        let log = AuditLog::open(&path).unwrap();
+        for nonce in 1..=2 {
+            let task = task(nonce);
+            log.record_signed(&task, SignatureScheme::Ed25519, "relayer", &[nonce as u8; 64]).unwrap();
+            log.record_submitted(&task.id, "5sig").unwrap();
+        }
+        log.record_signed(&task(3), SignatureScheme::Secp256k1, "0xrelayer", &[3u8; 65]).unwrap();
+        log.record_failed(&task(3).id, "Execution reverted").unwrap();
+        path
+    }
+
+    #[test]
+    fn test_append_reopen_and_verify() {
+        let dir = TempDir::new("audit-reopen");
+        let path = write_log(&dir);
+        let report = verify(&path).unwrap();
+        assert_eq!((report.entries, report.signatures), (6, 3));
+
+        // 重新打开后继续同一条链
+        let log = AuditLog::open(&path).unwrap();
+        let entry = log.record_submitted("x", "0xabc").unwrap();
+        assert_eq!(entry.body.seq, 6);
+        assert_eq!(entry.body.prev_hash, report.last_hash);
+        assert_eq!(verify(&path).unwrap().entries, 7);
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let first: AuditEntry = serde_json::from_str(content.lines().next().unwrap()).unwrap();
+        match first.body.record {
+            AuditRecord::Signed { encoding, payload, source, .. } => {
+                assert_eq!(encoding, "borsh");
+                assert_eq!(payload, hex::encode(encoding::svm_event_message(&task(1).event_data)));
+                assert_eq!(source.unwrap().log_index, Some(3));
+            }
+            other => panic!("unexpected record {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_detects_edits() {
+        let dir = TempDir::new("audit-edit");
+        let path = write_log(&dir);
+        let content = std::fs::read_to_string(&path).unwrap();
+
+        // 修改签名
+        std::fs::write(&path, content.replacen("\"signature\":\"01", "\"signature\":\"02", 1)).unwrap();
+        assert!(verify(&path).unwrap_err().to_string().contains("line 1: hash mismatch"));
+
+        // 删除中间一行
+        let lines: Vec<&str> = content.lines().collect();
+        let removed = [&lines[..2], &lines[3..]].concat().join("\n") + "\n";
+        std::fs::write(&path, removed).unwrap();
+        assert!(verify(&path).unwrap_err().to_string().contains("line 3: expected seq 2"));
+    }
+
+    #[test]
+    fn test_rolls_head_forward_after_crash() {
+        let dir = TempDir::new("audit-crash");
+        let path = write_log(&dir);
+        let head = std::fs::read(head_path(&path)).unwrap();
+        AuditLog::open(&path).unwrap().record_submitted("x", "0xabc").unwrap();
+        // 模拟记录已落盘、head 尚未更新时崩溃
+        std::fs::write(head_path(&path), head).unwrap();
+        assert_eq!(verify(&path).unwrap().entries, 7);
+
+        let log = AuditLog::open(&path).unwrap();
+        assert_eq!(read_head(&path).unwrap().unwrap().seq, 6);
+        assert_eq!(log.record_submitted("y", "0xdef").unwrap().body.seq, 7);
+        assert_eq!(verify(&path).unwrap().entries, 8);
+
+        // 落后两条仍视为截断
+        let head = std::fs::read(head_path(&path)).unwrap();
+        log.record_submitted("z", "0x123").unwrap();
+        log.record_submitted("w", "0x456").unwrap();
+        std::fs::write(head_path(&path), head).unwrap();
+        assert!(AuditLog::open(&path).is_err());
+    }
+
+    #[test]
+    fn test_detects_truncation() {
+        let dir = TempDir::new("audit-truncate");
+        let path = write_log(&dir);
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        std::fs::write(&path, lines[..4].join("\n") + "\n").unwrap();
+
+        assert!(verify(&path).unwrap_err().to_string().contains("truncated"));
+        assert!(AuditLog::open(&path).is_err());
+    }
+}
@@ -0,0 +1,37 @@
+//! 校验签名审计日志
+//!
+//! 用法: relayer-audit verify <审计日志文件>
+//!
+//! 检查每行哈希、序号连续性、哈希链、签名 payload 与事件编码是否一致，以及文件尾部与 `.head` 文件是否一致
+//! （检测截断）。校验失败时以非零状态退出并指出出错的行。
+
+use shared::audit;
+use std::path::Path;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: relayer-audit verify <file>";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let path = match args.as_slice() {
+        [command, path] if command == "verify" => Path::new(path),
+        _ => {
+            eprintln!("{}", USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match audit::verify(path) {
+        Ok(report) => {
+            println!(
+                "OK: {} entries ({} signatures), head {}",
+                report.entries, report.signatures, report.last_hash
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}: {}", path.display(), e);
+            ExitCode::FAILURE
+        }
+    }
+}
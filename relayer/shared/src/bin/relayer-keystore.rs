@@ -0,0 +1,74 @@
+//! 将明文私钥加密为 keystore 文件
+//!
+//! 用法: relayer-keystore <secp256k1|ed25519> <输出文件> [--password-file <文件>]
+//!
+//! 私钥从终端读取（不回显）：secp256k1 为十六进制，Ed25519 为十六进制、base58 或逗号分隔字节。
+
+use shared::keystore::{self, KeyKind, Secret};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use zeroize::Zeroizing;
+
+const USAGE: &str = "usage: relayer-keystore <secp256k1|ed25519> <output> [--password-file <file>]";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(path) => {
+            println!("Keystore written to {}", path.display());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<PathBuf, String> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (kind, output, password_file) = match args.as_slice() {
+        [kind, output] => (kind, output, None),
+        [kind, output, flag, file] if flag == "--password-file" => (kind, output, Some(PathBuf::from(file))),
+        _ => return Err(USAGE.to_string()),
+    };
+    let kind = match kind.as_str() {
+        "secp256k1" | "ecdsa" => KeyKind::Secp256k1,
+        "ed25519" => KeyKind::Ed25519,
+        _ => return Err(USAGE.to_string()),
+    };
+    let output = PathBuf::from(output);
+    if output.exists() {
+        return Err(format!("{} already exists", output.display()));
+    }
+
+    let key = Secret::from(rpassword::prompt_password("Private key: ").map_err(|e| e.to_string())?);
+    let key = parse_key(kind, key.expose()).ok_or("Invalid private key")?;
+
+    let password = match password_file {
+        Some(file) => keystore::read_password(Some(&file), "").map_err(|e| e.to_string())?,
+        None => {
+            let password = Secret::from(rpassword::prompt_password("Password: ").map_err(|e| e.to_string())?);
+            let confirm = Secret::from(rpassword::prompt_password("Confirm password: ").map_err(|e| e.to_string())?);
+            if password != confirm {
+                return Err("Passwords do not match".to_string());
+            }
+            password
+        }
+    };
+
+    keystore::encrypt_key(&output, kind, &key, &password).map_err(|e| e.to_string())?;
+    Ok(output)
+}
+
+/// 解析私钥，格式与 relayer 配置中的明文私钥一致
+fn parse_key(kind: KeyKind, key: &str) -> Option<Zeroizing<Vec<u8>>> {
+    let key = key.trim();
+    let bytes = if key.contains(',') {
+        key.split(',').map(|b| b.trim().parse::<u8>().ok()).collect::<Option<Vec<u8>>>()?
+    } else if kind == KeyKind::Secp256k1 || key.starts_with("0x") || key.len() == 64 || key.len() == 128 {
+        hex::decode(key.trim_start_matches("0x")).ok()?
+    } else {
+        bs58::decode(key).into_vec().ok()?
+    };
+    Some(Zeroizing::new(bytes))
+}
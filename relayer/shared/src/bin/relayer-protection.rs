@@ -0,0 +1,55 @@
+//! 导出 / 导入防重复签名数据库
+//!
+//! 用法: relayer-protection <export|import> <数据库目录> <文件>
+//!
+//! 迁移 relayer 时先在旧主机停止服务并导出，再在新主机导入后启动。导入与本地记录冲突时整体拒绝。
+
+use shared::protection::{Interchange, SlashingProtection};
+use std::path::Path;
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: relayer-protection <export|import> <db-dir> <file>";
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(message) => {
+            println!("{}", message);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run() -> Result<String, String> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [command, db, file] = args.as_slice() else {
+        return Err(USAGE.to_string());
+    };
+    let file = Path::new(file);
+
+    match command.as_str() {
+        "export" => {
+            if file.exists() {
+                return Err(format!("{} already exists", file.display()));
+            }
+            let db = SlashingProtection::open(db).map_err(|e| e.to_string())?;
+            let interchange = db.export().map_err(|e| e.to_string())?;
+            let json = serde_json::to_vec_pretty(&interchange).map_err(|e| e.to_string())?;
+            std::fs::write(file, json).map_err(|e| format!("{}: {}", file.display(), e))?;
+            let records: usize = interchange.keys.iter().map(|key| key.signed_events.len()).sum();
+            Ok(format!("Exported {} records for {} keys to {}", records, interchange.keys.len(), file.display()))
+        }
+        "import" => {
+            let content = std::fs::read(file).map_err(|e| format!("{}: {}", file.display(), e))?;
+            let interchange: Interchange =
+                serde_json::from_slice(&content).map_err(|e| format!("{}: {}", file.display(), e))?;
+            let db = SlashingProtection::open(db).map_err(|e| e.to_string())?;
+            let report = db.import(&interchange).map_err(|e| e.to_string())?;
+            Ok(format!("Imported {} records ({} already present)", report.imported, report.existing))
+        }
+        _ => Err(USAGE.to_string()),
+    }
+}
@@ -0,0 +1,116 @@
+//! 监听检查点：源链监听器已完整处理到的位置
+//!
+//! 监听器每次 `commit` 后写入，重启时从检查点回补期间错过的事件，而不是从链上最新位置开始。
+//! 文件通过 临时文件 + rename 原子替换，进程中途退出不会留下半截内容。
+
+use crate::config::Config;
+use crate::error::{RelayerError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// 已完整处理的位置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// 已处理的最新区块（EVM）或 slot（SVM）
+    pub block: u64,
+    /// 该位置最后处理的交易（SVM 按交易签名分页，作为下次查询的 `until` 游标）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_id: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Checkpoint {
+    pub fn new(block: u64, tx_id: Option<String>) -> Self {
+        Self { block, tx_id, updated_at: Utc::now() }
+    }
+}
+
+/// 检查点文件
+#[derive(Debug, Clone)]
+pub struct CheckpointFile {
+    path: PathBuf,
+}
+
+impl CheckpointFile {
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| RelayerError::Database(format!("Failed to create {}: {}", dir.display(), e)))?;
+        }
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 读取检查点，文件不存在时返回 None；内容损坏时报错，避免悄悄从链上最新位置开始而漏掉事件
+    pub fn load(&self) -> Result<Option<Checkpoint>> {
+        let content = match std::fs::read(&self.path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(RelayerError::Database(format!("Failed to read {}: {}", self.path.display(), e))),
+        };
+        serde_json::from_slice(&content)
+            .map(Some)
+            .map_err(|e| RelayerError::Serialization(format!("{}: {}", self.path.display(), e)))
+    }
+
+    pub fn save(&self, checkpoint: &Checkpoint) -> Result<()> {
+        let json = serde_json::to_vec_pretty(checkpoint).map_err(|e| RelayerError::Serialization(e.to_string()))?;
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(format!(".{}.tmp", std::process::id()));
+        let tmp = PathBuf::from(tmp);
+        std::fs::File::create(&tmp)
+            .and_then(|mut f| {
+                use std::io::Write;
+                f.write_all(&json)?;
+                f.sync_all()
+            })
+            .and_then(|_| std::fs::rename(&tmp, &self.path))
+            .and_then(|_| match self.path.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => std::fs::File::open(dir)?.sync_all(),
+                _ => Ok(()),
+            })
+            .map_err(|e| RelayerError::Database(format!("Failed to write {}: {}", self.path.display(), e)))
+    }
+}
+
+/// 按配置打开当前服务的检查点文件
+pub fn open_checkpoint(config: &Config) -> Result<CheckpointFile> {
+    CheckpointFile::open(config.checkpoint.file_path(&config.service.name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str) -> CheckpointFile {
+        let dir = std::env::temp_dir().join(format!("relayer-checkpoint-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        CheckpointFile::open(dir.join("s2e.json")).unwrap()
+    }
+
+    #[test]
+    fn test_checkpoint_roundtrip() {
+        let file = temp_file("roundtrip");
+        assert_eq!(file.load().unwrap(), None);
+
+        let checkpoint = Checkpoint::new(1234, Some("5VERv8NMvzbJMEkV8xnrLkEaWRtSz9CosKDYjCJjBRnbJLgp".to_string()));
+        file.save(&checkpoint).unwrap();
+        assert_eq!(file.load().unwrap(), Some(checkpoint));
+
+        file.save(&Checkpoint::new(1300, None)).unwrap();
+        let loaded = file.load().unwrap().unwrap();
+        assert_eq!((loaded.block, loaded.tx_id), (1300, None));
+    }
+
+    #[test]
+    fn test_corrupted_checkpoint_is_an_error() {
+        let file = temp_file("corrupted");
+        std::fs::write(file.path(), b"{\"block\": ").unwrap();
+        assert!(file.load().is_err());
+    }
+}
@@ -1,6 +1,10 @@
 use crate::error::{RelayerError, Result};
+use crate::keystore::Secret;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// 支持按服务分节配置的服务名
+pub const SERVICES: [&str; 3] = ["s2e", "e2s-listener", "e2s-submitter"];
 
 fn default_retry_delays() -> Vec<u64> {
     vec![0, 30000, 60000, 120000, 300000]
@@ -8,36 +12,94 @@ fn default_retry_delays() -> Vec<u64> {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    #[serde(default)]
     pub service: ServiceConfig,
+    #[serde(default)]
     pub source_chain: ChainConfig,
+    #[serde(default)]
     pub target_chain: ChainConfig,
     #[serde(default)]
     pub relayer: RelayerConfig,
     #[serde(default)]
+    pub signer: SignerConfig,
+    #[serde(default)]
+    pub hsm: HsmConfig,
+    #[serde(default)]
     pub queue: QueueConfig,
     #[serde(default)]
+    pub store: StoreConfig,
+    #[serde(default)]
     pub gas: GasConfig,
     #[serde(default)]
     pub api: ApiConfig,
     #[serde(default)]
     pub logging: LoggingConfig,
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    #[serde(default)]
+    pub alerts: AlertsConfig,
+    #[serde(default)]
+    pub audit: AuditConfig,
+    #[serde(default)]
+    pub protection: ProtectionConfig,
+    #[serde(default)]
+    pub risk: RiskConfig,
+    #[serde(default)]
+    pub screening: ScreeningConfig,
+    #[serde(default)]
+    pub checkpoint: CheckpointConfig,
+    /// 同一配置文件中其他服务的 API 端口，用于检测端口冲突
+    #[serde(skip)]
+    pub sibling_ports: Vec<(String, u16)>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ServiceConfig {
     pub name: String,
     pub version: String,
     pub worker_pool_size: usize,
+    /// 收到 SIGTERM 后等待进行中的签名和提交完成的最长时间（毫秒）
+    pub shutdown_timeout: u64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Default for ServiceConfig {
+    fn default() -> Self {
+        Self {
+            name: "relayer".to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            worker_pool_size: 5,
+            shutdown_timeout: 60000,
+        }
+    }
+}
+
+/// 链类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChainKind {
+    Evm,
+    Svm,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
 pub struct ChainConfig {
+    /// 未设置时按服务方向推断（s2e: SVM → EVM，e2s: EVM → SVM）
+    pub kind: Option<ChainKind>,
     pub name: String,
     pub chain_id: u64,
     pub rpc_url: String,
+    /// PubSub WebSocket 地址；SVM 源链设置后用 `logsSubscribe` 推送事件，HTTP 轮询作为后备
+    pub ws_url: Option<String>,
     pub contract_address: String,
+    /// EVM 源链：事件所在区块之上还需要的确认数，达到前不签名（默认 12）
     pub confirmation_blocks: Option<u64>,
-    pub commitment: Option<String>, // For SVM: "finalized", "confirmed", etc.
+    /// 源链签名前要求的最终性。SVM：processed / confirmed / finalized（默认 finalized）；
+    /// EVM：设为 finalized 时以节点的 finalized 区块为准，忽略 `confirmation_blocks`
+    pub commitment: Option<String>,
+    /// EVM 源链：单次 `eth_getLogs` 查询的最大区块跨度（按 RPC 服务商的限制设置，默认 1000）
+    pub max_log_range: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -46,39 +108,153 @@ pub struct RelayerConfig {
     #[serde(default)]
     pub svm_wallet_path: Option<PathBuf>,
     #[serde(default)]
-    pub evm_private_key: Option<String>,
+    pub evm_private_key: Option<Secret>,
     #[serde(default)]
-    pub ecdsa_private_key: Option<String>,
+    pub ecdsa_private_key: Option<Secret>,
     #[serde(default)]
-    pub ed25519_private_key: Option<String>,
+    pub ed25519_private_key: Option<Secret>,
+    // 加密密钥库（优先于明文私钥）
+    #[serde(default)]
+    pub ecdsa_keystore: Option<PathBuf>,
+    #[serde(default)]
+    pub ed25519_keystore: Option<PathBuf>,
+    #[serde(default)]
+    pub keystore_password_file: Option<PathBuf>,
+}
+
+/// 签名后端配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SignerConfig {
+    pub backend: String, // "local", "remote" or "pkcs11"
+    /// 远程签名服务地址，例如 https://signer.internal:9000
+    pub url: Option<String>,
+    /// 签名者身份：s2e 为 EVM 地址，e2s-submitter 为 base58 公钥
+    pub identifier: Option<String>,
+    /// 远程签名服务的 Bearer token（`--serve-signer` 模式必须设置）
+    pub auth_token: Option<Secret>,
+    pub timeout: u64, // milliseconds
+}
+
+impl Default for SignerConfig {
+    fn default() -> Self {
+        Self {
+            backend: "local".to_string(),
+            url: None,
+            identifier: None,
+            auth_token: None,
+            timeout: 10000,
+        }
+    }
+}
+
+/// PKCS#11 HSM 配置（`signer.backend = "pkcs11"`，需要 `pkcs11` feature）
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct HsmConfig {
+    /// PKCS#11 模块路径，例如 /usr/lib/softhsm/libsofthsm2.so
+    pub module: Option<PathBuf>,
+    pub token_label: Option<String>,
+    /// 用户 PIN
+    pub pin: Option<Secret>,
+    /// 私钥和公钥的 CKA_LABEL
+    pub key_label: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueueConfig {
+    #[serde(default = "default_queue_backend")]
+    pub backend: String, // "file" or "redis"
     #[serde(default = "default_queue_path")]
     pub path: PathBuf,
+    #[serde(default)]
+    pub redis_url: Option<String>,
+    #[serde(default = "default_redis_prefix")]
+    pub redis_prefix: String,
+    #[serde(default = "default_visibility_timeout")]
+    pub visibility_timeout: u64, // milliseconds
+    #[serde(default = "default_queue_max_size")]
     pub max_size: usize,
+    #[serde(default = "default_retry_limit")]
     pub retry_limit: u32,
     #[serde(default = "default_retry_delays")]
     pub retry_delays: Vec<u64>, // milliseconds
 }
 
+fn default_queue_backend() -> String {
+    "file".to_string()
+}
+
 fn default_queue_path() -> PathBuf {
     PathBuf::from(".relayer/queue")
 }
 
+fn default_redis_prefix() -> String {
+    "bridge1024:e2s".to_string()
+}
+
+fn default_visibility_timeout() -> u64 {
+    300000
+}
+
+fn default_queue_max_size() -> usize {
+    1000
+}
+
+fn default_retry_limit() -> u32 {
+    5
+}
+
 impl Default for QueueConfig {
     fn default() -> Self {
         Self {
+            backend: default_queue_backend(),
             path: default_queue_path(),
-            max_size: 1000,
-            retry_limit: 5,
+            redis_url: None,
+            redis_prefix: default_redis_prefix(),
+            visibility_timeout: default_visibility_timeout(),
+            max_size: default_queue_max_size(),
+            retry_limit: default_retry_limit(),
             retry_delays: default_retry_delays(),
         }
     }
 }
 
+/// 任务存储配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreConfig {
+    #[serde(default = "default_store_backend")]
+    pub backend: String, // "file" or "postgres"
+    /// 文件存储目录，默认为 `<queue.path>/tasks`
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    #[serde(default)]
+    pub database_url: Option<String>,
+}
+
+fn default_store_backend() -> String {
+    "file".to_string()
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        Self {
+            backend: default_store_backend(),
+            path: None,
+            database_url: None,
+        }
+    }
+}
+
+impl StoreConfig {
+    /// 文件存储的实际目录
+    pub fn file_path(&self, queue: &QueueConfig) -> PathBuf {
+        self.path.clone().unwrap_or_else(|| queue.path.join("tasks"))
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct GasConfig {
     pub min_svm_balance: f64,  // SOL
     pub min_evm_balance: f64,  // ETH
@@ -103,6 +279,9 @@ pub struct ApiConfig {
     pub cors_enabled: bool,
     #[serde(default)]
     pub cors_origins: Vec<String>,
+    /// 管理 API（批准 / 拒绝风控暂扣的转账）的 Bearer token，未设置时管理 API 不可用
+    #[serde(default)]
+    pub admin_token: Option<Secret>,
 }
 
 fn default_api_port() -> u16 {
@@ -115,6 +294,7 @@ impl Default for ApiConfig {
             port: 8080,
             cors_enabled: false,
             cors_origins: vec![],
+            admin_token: None,
         }
     }
 }
@@ -144,123 +324,656 @@ impl Default for LoggingConfig {
     }
 }
 
+/// 分布式追踪导出配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    /// 导出方式：none / otlp / file
+    pub exporter: String,
+    /// OTLP/HTTP 端点，例如 http://localhost:4318/v1/traces
+    pub otlp_endpoint: Option<String>,
+    /// file 导出时写入的 JSON Lines 文件
+    pub file_path: PathBuf,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            exporter: "none".to_string(),
+            otlp_endpoint: None,
+            file_path: PathBuf::from(".relayer/traces.jsonl"),
+        }
+    }
+}
+
+/// 告警配置：配置了哪个通道就启用哪个
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertsConfig {
+    /// 最低发送级别：info / warning / critical
+    pub min_severity: String,
+    /// 同一告警在该时间窗口内只发送一次（毫秒），级别升高时立即发送
+    pub dedup_window: u64,
+    /// 每小时最多发送的告警数（所有通道合计，critical 不计入）
+    pub max_per_hour: u32,
+    /// 监听进度或目标链 nonce 停滞多久后告警（毫秒）
+    pub stall_timeout: u64,
+    /// 通用 JSON webhook
+    pub webhook_url: Option<String>,
+    /// 聊天 webhook，发送 `{"text": ...}`（Slack / Mattermost / Rocket.Chat 兼容）
+    pub chat_webhook_url: Option<String>,
+    /// SMTP 服务器；465 端口使用 TLS，其他端口使用 STARTTLS
+    pub smtp_host: Option<String>,
+    pub smtp_port: u16,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<Secret>,
+    pub email_from: Option<String>,
+    pub email_to: Vec<String>,
+    /// 告警时通过 `sh -c` 执行的命令，告警 JSON 写入 stdin，并设置 ALERT_* 环境变量
+    pub command: Option<String>,
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        Self {
+            min_severity: "warning".to_string(),
+            dedup_window: 900000,
+            max_per_hour: 30,
+            stall_timeout: 600000,
+            webhook_url: None,
+            chat_webhook_url: None,
+            smtp_host: None,
+            smtp_port: 587,
+            smtp_username: None,
+            smtp_password: None,
+            email_from: None,
+            email_to: Vec::new(),
+            command: None,
+        }
+    }
+}
+
+/// 签名审计日志配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditConfig {
+    pub enabled: bool,
+    /// 审计日志文件，默认为 `.relayer/audit/<service>.jsonl`
+    pub path: Option<PathBuf>,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self { enabled: true, path: None }
+    }
+}
+
+impl AuditConfig {
+    /// 审计日志的实际路径
+    pub fn file_path(&self, service: &str) -> PathBuf {
+        self.path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(".relayer/audit").join(format!("{}.jsonl", service)))
+    }
+}
+
+/// 防重复签名数据库配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProtectionConfig {
+    /// 已签名事件记录目录（迁移 relayer 时用 `relayer-protection export / import` 搬迁）
+    pub path: PathBuf,
+}
+
+impl Default for ProtectionConfig {
+    fn default() -> Self {
+        Self { path: PathBuf::from(".relayer/protection") }
+    }
+}
+
+/// 签名前风控配置，金额均为代币最小单位，未设置的上限不检查
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RiskConfig {
+    /// 单笔上限
+    pub max_transfer: Option<u64>,
+    /// 滚动 1 小时签名总量上限
+    pub max_volume_1h: Option<u64>,
+    /// 滚动 24 小时签名总量上限
+    pub max_volume_24h: Option<u64>,
+    /// 单个接收地址滚动 24 小时总量上限
+    pub max_receiver_volume_24h: Option<u64>,
+    /// 金额超过最近 24 小时中位数的倍数时暂扣，0 表示关闭
+    pub anomaly_factor: f64,
+    /// 异常检测至少需要的样本数
+    pub anomaly_min_samples: usize,
+}
+
+impl Default for RiskConfig {
+    fn default() -> Self {
+        Self {
+            max_transfer: None,
+            max_volume_1h: None,
+            max_volume_24h: None,
+            max_receiver_volume_24h: None,
+            anomaly_factor: 10.0,
+            anomaly_min_samples: 20,
+        }
+    }
+}
+
+/// 合规筛查配置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScreeningConfig {
+    /// 拒绝名单文件或目录（目录下所有 `*.csv` / `*.json`），为空时不筛查
+    pub lists: Vec<PathBuf>,
+    /// 检查名单文件是否修改的间隔（毫秒）
+    pub reload_interval: u64,
+}
+
+impl Default for ScreeningConfig {
+    fn default() -> Self {
+        Self { lists: Vec::new(), reload_interval: 60000 }
+    }
+}
+
+/// 监听检查点配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CheckpointConfig {
+    /// 检查点文件，默认为 `.relayer/checkpoints/<service>.json`
+    pub path: Option<PathBuf>,
+    /// 没有检查点（首次部署）时 EVM 监听器的起始区块，未设置时从当前区块开始
+    pub start_block: Option<u64>,
+}
+
+impl CheckpointConfig {
+    /// 检查点文件的实际路径
+    pub fn file_path(&self, service: &str) -> PathBuf {
+        self.path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(".relayer/checkpoints").join(format!("{}.json", service)))
+    }
+}
+
 impl Config {
-    /// 从环境变量和配置文件加载配置
-    pub fn load() -> Result<Self> {
+    /// 加载配置：默认值 < TOML 配置文件（公共部分 < `[<service>]` 分节）< 环境变量
+    pub fn load(service: &str, config_file: Option<&Path>) -> Result<Self> {
         // 加载 .env 文件
         dotenvy::dotenv().ok();
-        
-        // 暂时移除数组字段，稍后手动处理
-        let cors_origins_str = std::env::var("API__CORS_ORIGINS").ok();
-        let retry_delays_str = std::env::var("QUEUE__RETRY_DELAYS").ok();
-        
-        if cors_origins_str.is_some() {
-            std::env::remove_var("API__CORS_ORIGINS");
-        }
-        if retry_delays_str.is_some() {
-            std::env::remove_var("QUEUE__RETRY_DELAYS");
-        }
-
-        let config = config::Config::builder()
-            .add_source(
-                config::Environment::default()
-                    .separator("__")
-                    .try_parsing(true)
-                    .ignore_empty(true)
-            )
-            .build()
-            .map_err(|e| RelayerError::Config(format!("Failed to build config: {}", e)))?;
-
-        let mut config: Config = config
-            .try_deserialize()
-            .map_err(|e| RelayerError::Config(format!("Failed to deserialize config: {}.\n\nMake sure all required fields are set in your .env file:\n- SOURCE_CHAIN__NAME\n- SOURCE_CHAIN__CHAIN_ID\n- SOURCE_CHAIN__RPC_URL\n- SOURCE_CHAIN__CONTRACT_ADDRESS\n- TARGET_CHAIN__NAME\n- TARGET_CHAIN__CHAIN_ID\n- TARGET_CHAIN__RPC_URL\n- TARGET_CHAIN__CONTRACT_ADDRESS", e)))?;
-        
-        // 手动解析数组字段
-        if let Some(origins_str) = cors_origins_str {
-            if !origins_str.is_empty() {
-                config.api.cors_origins = origins_str
-                    .split(',')
-                    .map(|s| s.trim().to_string())
-                    .collect();
-            }
-        }
-        
-        if let Some(delays_str) = retry_delays_str {
-            if !delays_str.is_empty() {
-                config.queue.retry_delays = delays_str
-                    .split(',')
-                    .filter_map(|s| s.trim().parse::<u64>().ok())
-                    .collect();
-                if config.queue.retry_delays.is_empty() {
-                    config.queue.retry_delays = default_retry_delays();
+        Self::load_with_env(service, config_file, None)
+    }
+
+    /// `env` 为 None 时读取进程环境变量（测试时可传入固定的变量表）
+    fn load_with_env(
+        service: &str,
+        config_file: Option<&Path>,
+        env: Option<config::Map<String, String>>,
+    ) -> Result<Self> {
+        let mut builder = config::Config::builder();
+        let mut sibling_ports = Vec::new();
+
+        if let Some(path) = config_file {
+            let content = std::fs::read_to_string(path).map_err(|e| {
+                RelayerError::Config(format!("Failed to read config file {}: {}", path.display(), e))
+            })?;
+            let file: toml::Table = content.parse().map_err(|e| {
+                RelayerError::Config(format!("Failed to parse config file {}: {}", path.display(), e))
+            })?;
+
+            for other in SERVICES.iter().filter(|s| **s != service) {
+                let merged = merge_service_section(&file, other);
+                if let Some(port) = merged
+                    .get("api")
+                    .and_then(|api| api.get("port"))
+                    .and_then(|port| port.as_integer())
+                {
+                    sibling_ports.push((other.to_string(), port as u16));
                 }
             }
+
+            let merged = toml::to_string(&merge_service_section(&file, service))
+                .map_err(|e| RelayerError::Config(format!("Failed to merge config file: {}", e)))?;
+            builder = builder.add_source(config::File::from_str(&merged, config::FileFormat::Toml));
+        }
+
+        let environment = config::Environment::default()
+            .separator("__")
+            .try_parsing(true)
+            .ignore_empty(true)
+            .list_separator(",")
+            .with_list_parse_key("api.cors_origins")
+            .with_list_parse_key("queue.retry_delays")
+            .with_list_parse_key("alerts.email_to")
+            .with_list_parse_key("screening.lists")
+            .source(env);
+
+        let mut config: Config = builder
+            .add_source(environment)
+            .build()
+            .and_then(|c| c.try_deserialize())
+            .map_err(|e| RelayerError::Config(format!("Failed to load config: {}", e)))?;
+
+        for origin in config.api.cors_origins.iter_mut() {
+            *origin = origin.trim().to_string();
+        }
+        for recipient in config.alerts.email_to.iter_mut() {
+            *recipient = recipient.trim().to_string();
         }
-        
+        config.sibling_ports = sibling_ports;
         Ok(config)
     }
 
-    /// 验证配置
+    /// 链类型：显式配置优先，否则按服务方向推断
+    pub fn chain_kinds(&self) -> (Option<ChainKind>, Option<ChainKind>) {
+        let (source, target) = match self.service.name.as_str() {
+            "s2e" => (Some(ChainKind::Svm), Some(ChainKind::Evm)),
+            "e2s-listener" | "e2s-submitter" => (Some(ChainKind::Evm), Some(ChainKind::Svm)),
+            _ => (None, None),
+        };
+        (self.source_chain.kind.or(source), self.target_chain.kind.or(target))
+    }
+
+    /// SIGHUP 热加载：以当前配置为基础，只采用 `new` 中的日志级别、重试间隔、风控上限、
+    /// 拒绝名单和 RPC 端点，返回合并后的配置和需要重启才能生效的其他变化（配置节名）
+    pub fn reloaded(&self, new: &Config) -> (Config, Vec<String>) {
+        let mut merged = self.clone();
+        merged.logging.level = new.logging.level.clone();
+        merged.queue.retry_delays = new.queue.retry_delays.clone();
+        merged.risk = new.risk.clone();
+        merged.screening = new.screening.clone();
+        merged.source_chain.rpc_url = new.source_chain.rpc_url.clone();
+        merged.target_chain.rpc_url = new.target_chain.rpc_url.clone();
+
+        // 密钥序列化为 [REDACTED]，只替换密钥的变化无法察觉，同样在重启后生效
+        let sections = |config: &Config| match serde_json::to_value(config) {
+            Ok(serde_json::Value::Object(map)) => map,
+            _ => serde_json::Map::new(),
+        };
+        let (before, after) = (sections(&merged), sections(new));
+        let mut ignored: Vec<String> = after
+            .iter()
+            .filter(|(key, value)| before.get(*key) != Some(*value))
+            .map(|(key, _)| key.clone())
+            .collect();
+        ignored.sort();
+        (merged, ignored)
+    }
+
+    /// 验证配置，一次性报告所有问题
     pub fn validate(&self) -> Result<()> {
-        // 验证 RPC URLs
-        if self.source_chain.rpc_url.is_empty() {
-            return Err(RelayerError::Config("Source chain RPC URL is empty".to_string()));
+        let problems = self.problems();
+        if problems.is_empty() {
+            return Ok(());
+        }
+        let mut report = format!("Invalid configuration ({} problems):", problems.len());
+        for problem in &problems {
+            report.push_str("\n  - ");
+            report.push_str(problem);
+        }
+        Err(RelayerError::Config(report))
+    }
+
+    /// 收集所有配置问题
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let service = self.service.name.as_str();
+        let (source_kind, target_kind) = self.chain_kinds();
+
+        // 链配置：e2s-listener 只需目标链 ID，e2s-submitter 不使用源链
+        if service != "e2s-submitter" {
+            check_chain(&mut problems, "source_chain", &self.source_chain, source_kind, true);
+        }
+        check_chain(&mut problems, "target_chain", &self.target_chain, target_kind, service != "e2s-listener");
+
+        // 签名后端：远程签名时本地不需要证明密钥
+        let local_signer = match self.signer.backend.as_str() {
+            "local" => true,
+            "remote" => {
+                let signer = &self.signer;
+                if !signer.url.as_deref().is_some_and(|url| url.starts_with("http://") || url.starts_with("https://")) {
+                    problems.push("signer.url: expected http(s) URL for the remote signer".to_string());
+                }
+                match (service, signer.identifier.as_deref()) {
+                    ("s2e", Some(id)) if !is_evm_address(id) => {
+                        problems.push(format!("signer.identifier: '{}' is not an EVM address", id))
+                    }
+                    ("e2s-submitter", Some(id)) if !is_svm_address(id) => {
+                        problems.push(format!("signer.identifier: '{}' is not a base58 public key", id))
+                    }
+                    (_, None) => problems.push("signer.identifier is required for the remote signer".to_string()),
+                    _ => {}
+                }
+                if signer.timeout == 0 {
+                    problems.push("signer.timeout must be greater than 0".to_string());
+                }
+                false
+            }
+            "pkcs11" => {
+                let hsm = &self.hsm;
+                match &hsm.module {
+                    Some(path) => check_file(&mut problems, "hsm.module", path),
+                    None => problems.push("hsm.module is required for the pkcs11 signer".to_string()),
+                }
+                if hsm.token_label.as_deref().unwrap_or("").is_empty() {
+                    problems.push("hsm.token_label is required for the pkcs11 signer".to_string());
+                }
+                if hsm.key_label.as_deref().unwrap_or("").is_empty() {
+                    problems.push("hsm.key_label is required for the pkcs11 signer".to_string());
+                }
+                if hsm.pin.is_none() {
+                    problems.push("hsm.pin is required for the pkcs11 signer".to_string());
+                }
+                false
+            }
+            other => {
+                problems.push(format!("signer.backend: unknown backend '{}' (local, remote, pkcs11)", other));
+                false
+            }
+        };
+
+        // 密钥配置 (根据服务类型检查)
+        let keys = &self.relayer;
+        match (service, keys.ecdsa_private_key.as_ref(), &keys.ecdsa_keystore) {
+            (_, _, Some(path)) => check_file(&mut problems, "relayer.ecdsa_keystore", path),
+            (_, Some(key), None) => check_secp256k1_key(&mut problems, "relayer.ecdsa_private_key", key.expose()),
+            ("s2e", None, None) if local_signer => problems
+                .push("relayer.ecdsa_private_key or relayer.ecdsa_keystore is required for s2e".to_string()),
+            _ => {}
+        }
+        if let Some(key) = &keys.evm_private_key {
+            check_secp256k1_key(&mut problems, "relayer.evm_private_key", key.expose());
+        }
+        match (service, keys.ed25519_private_key.as_ref(), &keys.ed25519_keystore) {
+            (_, _, Some(path)) => check_file(&mut problems, "relayer.ed25519_keystore", path),
+            (_, Some(key), None) => check_ed25519_key(&mut problems, "relayer.ed25519_private_key", key.expose()),
+            ("e2s-submitter", None, None) if local_signer => problems.push(
+                "relayer.ed25519_private_key or relayer.ed25519_keystore is required for e2s-submitter".to_string(),
+            ),
+            _ => {}
+        }
+        if let Some(path) = &keys.keystore_password_file {
+            check_file(&mut problems, "relayer.keystore_password_file", path);
+        }
+        if let Some(path) = &keys.svm_wallet_path {
+            check_file(&mut problems, "relayer.svm_wallet_path", path);
+        }
+
+        // 工作队列配置
+        let queue = &self.queue;
+        match queue.backend.as_str() {
+            "file" => {}
+            "redis" => {
+                if queue.redis_url.as_deref().unwrap_or("").is_empty() {
+                    problems.push("queue.redis_url is required for the redis backend".to_string());
+                }
+            }
+            other => problems.push(format!("queue.backend: unknown backend '{}' (file, redis)", other)),
+        }
+        if queue.max_size == 0 {
+            problems.push("queue.max_size must be greater than 0".to_string());
+        }
+        if queue.retry_limit == 0 {
+            problems.push("queue.retry_limit must be greater than 0".to_string());
+        }
+        if queue.retry_delays.len() != queue.retry_limit as usize {
+            problems.push(format!(
+                "queue.retry_delays has {} entries but queue.retry_limit is {}",
+                queue.retry_delays.len(),
+                queue.retry_limit
+            ));
+        }
+        if queue.visibility_timeout == 0 {
+            problems.push("queue.visibility_timeout must be greater than 0".to_string());
+        }
+
+        // 任务存储配置
+        match self.store.backend.as_str() {
+            "file" => {}
+            "postgres" => {
+                if self.store.database_url.as_deref().unwrap_or("").is_empty() {
+                    problems.push("store.database_url is required for the postgres backend".to_string());
+                }
+            }
+            other => problems.push(format!("store.backend: unknown backend '{}' (file, postgres)", other)),
+        }
+
+        // Gas 配置
+        if self.gas.min_svm_balance < 0.0 || self.gas.min_evm_balance < 0.0 {
+            problems.push("gas.min_*_balance must not be negative".to_string());
+        }
+        if self.gas.balance_check_interval == 0 {
+            problems.push("gas.balance_check_interval must be greater than 0".to_string());
+        }
+
+        // API 端口
+        if self.api.port == 0 {
+            problems.push("api.port must not be 0".to_string());
+        }
+        for (other, port) in &self.sibling_ports {
+            if *port == self.api.port {
+                problems.push(format!("api.port {} clashes with service {}", port, other));
+            }
+        }
+
+        // 日志配置
+        if !["trace", "debug", "info", "warn", "error"].contains(&self.logging.level.to_lowercase().as_str()) {
+            problems.push(format!("logging.level: unknown level '{}'", self.logging.level));
+        }
+        if !["json", "text", "pretty"].contains(&self.logging.format.as_str()) {
+            problems.push(format!("logging.format: unknown format '{}' (json, text)", self.logging.format));
+        }
+
+        // 分布式追踪
+        match self.telemetry.exporter.as_str() {
+            "none" | "file" => {}
+            "otlp" => {
+                if !self
+                    .telemetry
+                    .otlp_endpoint
+                    .as_deref()
+                    .is_some_and(|url| url.starts_with("http://") || url.starts_with("https://"))
+                {
+                    problems.push("telemetry.otlp_endpoint: expected http(s) URL for the otlp exporter".to_string());
+                }
+            }
+            other => problems.push(format!("telemetry.exporter: unknown exporter '{}' (none, otlp, file)", other)),
+        }
+
+        // 告警
+        let alerts = &self.alerts;
+        if alerts.min_severity.parse::<crate::alerts::Severity>().is_err() {
+            problems.push(format!(
+                "alerts.min_severity: unknown severity '{}' (info, warning, critical)",
+                alerts.min_severity
+            ));
+        }
+        if alerts.max_per_hour == 0 {
+            problems.push("alerts.max_per_hour must be greater than 0".to_string());
+        }
+        if alerts.stall_timeout == 0 {
+            problems.push("alerts.stall_timeout must be greater than 0".to_string());
+        }
+        for (field, url) in [("webhook_url", &alerts.webhook_url), ("chat_webhook_url", &alerts.chat_webhook_url)] {
+            if let Some(url) = url.as_deref().filter(|url| !(url.starts_with("http://") || url.starts_with("https://"))) {
+                problems.push(format!("alerts.{}: '{}' is not an http(s) URL", field, url));
+            }
+        }
+        if alerts.smtp_host.is_some() {
+            if alerts.email_from.as_deref().unwrap_or("").is_empty() {
+                problems.push("alerts.email_from is required when alerts.smtp_host is set".to_string());
+            }
+            if alerts.email_to.is_empty() {
+                problems.push("alerts.email_to is required when alerts.smtp_host is set".to_string());
+            }
         }
-        if self.target_chain.rpc_url.is_empty() {
-            return Err(RelayerError::Config("Target chain RPC URL is empty".to_string()));
+
+        // 风控
+        let risk = &self.risk;
+        if !(risk.anomaly_factor == 0.0 || risk.anomaly_factor >= 1.0) {
+            problems.push(format!("risk.anomaly_factor must be 0 (disabled) or at least 1, got {}", risk.anomaly_factor));
+        }
+        if risk.anomaly_factor > 0.0 && risk.anomaly_min_samples == 0 {
+            problems.push("risk.anomaly_min_samples must be greater than 0".to_string());
+        }
+
+        problems
+    }
+}
+
+/// 合并配置文件：公共部分 + `[<service>]` 分节（深度合并）
+fn merge_service_section(file: &toml::Table, service: &str) -> toml::Table {
+    let mut base: toml::Table = file
+        .iter()
+        .filter(|(key, _)| !SERVICES.contains(&key.as_str()))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect();
+    if let Some(toml::Value::Table(section)) = file.get(service) {
+        merge_tables(&mut base, section);
+    }
+    base
+}
+
+fn merge_tables(base: &mut toml::Table, overlay: &toml::Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(key), value) {
+            (Some(toml::Value::Table(existing)), toml::Value::Table(overlay)) => merge_tables(existing, overlay),
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
         }
+    }
+}
 
-        // 验证合约地址
-        if self.source_chain.contract_address.is_empty() {
-            return Err(RelayerError::Config("Source contract address is empty".to_string()));
+fn check_chain(
+    problems: &mut Vec<String>,
+    section: &str,
+    chain: &ChainConfig,
+    kind: Option<ChainKind>,
+    endpoints_required: bool,
+) {
+    if chain.chain_id == 0 {
+        problems.push(format!("{}.chain_id is not set", section));
+    }
+    if endpoints_required || !chain.rpc_url.is_empty() {
+        let rpc = chain.rpc_url.as_str();
+        if rpc.is_empty() {
+            problems.push(format!("{}.rpc_url is empty", section));
+        } else if !["http://", "https://", "ws://", "wss://"].iter().any(|p| rpc.starts_with(p)) {
+            problems.push(format!("{}.rpc_url '{}' is not an http(s)/ws(s) URL", section, rpc));
+        }
+    }
+    if let Some(ws) = &chain.ws_url {
+        if !["ws://", "wss://"].iter().any(|p| ws.starts_with(p)) {
+            problems.push(format!("{}.ws_url '{}' is not a ws(s) URL", section, ws));
         }
-        if self.target_chain.contract_address.is_empty() {
-            return Err(RelayerError::Config("Target contract address is empty".to_string()));
+    }
+    if endpoints_required || !chain.contract_address.is_empty() {
+        let address = chain.contract_address.as_str();
+        if address.is_empty() {
+            problems.push(format!("{}.contract_address is empty", section));
+        } else {
+            match kind {
+                Some(ChainKind::Evm) if !is_evm_address(address) => problems.push(format!(
+                    "{}.contract_address '{}' is not a 0x-prefixed 20-byte EVM address",
+                    section, address
+                )),
+                Some(ChainKind::Svm) if !is_svm_address(address) => problems.push(format!(
+                    "{}.contract_address '{}' is not a base58 32-byte SVM address",
+                    section, address
+                )),
+                _ => {}
+            }
+        }
+    }
+    if let Some(commitment) = chain.commitment.as_deref() {
+        if !["processed", "confirmed", "finalized"].contains(&commitment) {
+            problems.push(format!(
+                "{}.commitment '{}' must be processed, confirmed or finalized",
+                section, commitment
+            ));
         }
+    }
+    if chain.max_log_range == Some(0) {
+        problems.push(format!("{}.max_log_range must be at least 1", section));
+    }
+}
+
+fn is_evm_address(address: &str) -> bool {
+    address
+        .strip_prefix("0x")
+        .is_some_and(|hex| hex.len() == 40 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+fn is_svm_address(address: &str) -> bool {
+    bs58::decode(address).into_vec().is_ok_and(|bytes| bytes.len() == 32)
+}
+
+fn check_file(problems: &mut Vec<String>, field: &str, path: &Path) {
+    if !path.is_file() {
+        problems.push(format!("{}: {} does not exist", field, path.display()));
+    }
+}
 
-        // 验证密钥配置 (根据服务类型检查)
-        // 这里可以添加更多验证逻辑
+fn check_secp256k1_key(problems: &mut Vec<String>, field: &str, key: &str) {
+    let hex_key = key.trim().strip_prefix("0x").unwrap_or(key.trim());
+    match hex::decode(hex_key) {
+        Ok(bytes) if bytes.len() == 32 && bytes.iter().any(|b| *b != 0) => {}
+        Ok(bytes) if bytes.len() == 32 => problems.push(format!("{}: key must not be zero", field)),
+        _ => problems.push(format!("{}: expected 32-byte hex key (64 hex characters)", field)),
+    }
+}
 
-        Ok(())
+fn check_ed25519_key(problems: &mut Vec<String>, field: &str, key: &str) {
+    let key = key.trim();
+    let bytes = if key.contains(',') {
+        key.split(',').map(|b| b.trim().parse::<u8>().ok()).collect::<Option<Vec<u8>>>()
+    } else if key.starts_with("0x") || key.len() == 64 || key.len() == 128 {
+        hex::decode(key.trim_start_matches("0x")).ok()
+    } else {
+        bs58::decode(key).into_vec().ok()
+    };
+    match bytes {
+        Some(bytes) if bytes.len() == 32 || bytes.len() == 64 => {}
+        _ => problems.push(format!(
+            "{}: expected 32-byte seed or 64-byte keypair (hex, base58 or comma-separated bytes)",
+            field
+        )),
     }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
-            service: ServiceConfig {
-                name: "relayer".to_string(),
-                version: env!("CARGO_PKG_VERSION").to_string(),
-                worker_pool_size: 5,
-            },
+            service: ServiceConfig::default(),
             source_chain: ChainConfig {
+                kind: None,
                 name: "Source Chain".to_string(),
                 chain_id: 0,
                 rpc_url: String::new(),
+                ws_url: None,
                 contract_address: String::new(),
                 confirmation_blocks: Some(12),
                 commitment: None,
+                max_log_range: None,
             },
             target_chain: ChainConfig {
+                kind: None,
                 name: "Target Chain".to_string(),
                 chain_id: 0,
                 rpc_url: String::new(),
+                ws_url: None,
                 contract_address: String::new(),
                 confirmation_blocks: Some(12),
                 commitment: None,
+                max_log_range: None,
             },
-            relayer: RelayerConfig {
-                svm_wallet_path: None,
-                evm_private_key: None,
-                ecdsa_private_key: None,
-                ed25519_private_key: None,
-            },
-            queue: QueueConfig {
-                path: PathBuf::from(".relayer/queue"),
-                max_size: 1000,
-                retry_limit: 5,
-                retry_delays: vec![0, 30000, 60000, 120000, 300000],
-            },
+            relayer: RelayerConfig::default(),
+            signer: SignerConfig::default(),
+            hsm: HsmConfig::default(),
+            queue: QueueConfig::default(),
+            store: StoreConfig::default(),
             gas: GasConfig {
                 min_svm_balance: 5.0,
                 min_evm_balance: 0.1,
@@ -270,12 +983,149 @@ impl Default for Config {
                 port: 8080,
                 cors_enabled: true,
                 cors_origins: vec!["*".to_string()],
+                admin_token: None,
             },
             logging: LoggingConfig {
                 level: "info".to_string(),
                 format: "json".to_string(),
             },
+            telemetry: TelemetryConfig::default(),
+            alerts: AlertsConfig::default(),
+            audit: AuditConfig::default(),
+            protection: ProtectionConfig::default(),
+            risk: RiskConfig::default(),
+            screening: ScreeningConfig::default(),
+            checkpoint: CheckpointConfig::default(),
+            sibling_ports: Vec::new(),
         }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PROGRAM: &str = "7vQa4SSDkbSCgCwzPFjVMc5X9Ms3FdFL1uoYbVMPgHc2";
+    const EVM_CONTRACT: &str = "0x5FbDB2315678afecb367f032d93F642f64180aa3";
+    const ECDSA_KEY: &str = "0x1234567890abcdef1234567890abcdef1234567890abcdef1234567890abcdef";
+
+    fn env(vars: &[(&str, &str)]) -> Option<config::Map<String, String>> {
+        Some(vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect())
+    }
+
+    fn write_file(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("relayer-config-{}-{}.toml", name, std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    const FILE: &str = r#"
+[source_chain]
+name = "1024chain"
+chain_id = 91024
+rpc_url = "https://testnet-rpc.1024chain.com/rpc/"
+contract_address = "7vQa4SSDkbSCgCwzPFjVMc5X9Ms3FdFL1uoYbVMPgHc2"
+
+[target_chain]
+name = "Arbitrum Sepolia"
+chain_id = 421614
+rpc_url = "https://sepolia-rollup.arbitrum.io/rpc"
+contract_address = "0x5FbDB2315678afecb367f032d93F642f64180aa3"
+
+[queue]
+retry_limit = 3
+retry_delays = [0, 1000, 2000]
+
+[s2e.api]
+port = 8081
+
+[e2s-submitter.api]
+port = 8082
+"#;
+
+    #[test]
+    fn test_file_section_and_env_override() {
+        let path = write_file("layered", FILE);
+        let config = Config::load_with_env(
+            "s2e",
+            Some(&path),
+            env(&[
+                ("SERVICE__NAME", "s2e"),
+                ("RELAYER__ECDSA_PRIVATE_KEY", ECDSA_KEY),
+                ("QUEUE__RETRY_DELAYS", "0,5000,10000"),
+                ("API__CORS_ORIGINS", "https://a.example, https://b.example"),
+                ("API__ADMIN_TOKEN", "admin-token"),
+                ("RISK__MAX_VOLUME_24H", "5000000000"),
+                ("RISK__ANOMALY_FACTOR", "5"),
+                ("SCREENING__LISTS", "/etc/relayer/deny/ofac.csv,/etc/relayer/deny/internal"),
+            ]),
+        )
+        .unwrap();
+
+        assert_eq!(config.api.port, 8081);
+        assert_eq!(config.source_chain.contract_address, PROGRAM);
+        assert_eq!(config.queue.retry_delays, vec![0, 5000, 10000]);
+        assert_eq!(config.api.cors_origins, vec!["https://a.example", "https://b.example"]);
+        assert_eq!(config.sibling_ports, vec![("e2s-submitter".to_string(), 8082)]);
+        assert_eq!(config.api.admin_token.as_ref().map(Secret::expose), Some("admin-token"));
+        assert_eq!((config.risk.max_volume_24h, config.risk.max_transfer), (Some(5_000_000_000), None));
+        assert_eq!(config.risk.anomaly_factor, 5.0);
+        assert_eq!(
+            config.screening.lists,
+            vec![PathBuf::from("/etc/relayer/deny/ofac.csv"), PathBuf::from("/etc/relayer/deny/internal")]
+        );
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn test_reload_keeps_structural_settings() {
+        let mut current = Config::default();
+        current.source_chain.rpc_url = "https://old.example".to_string();
+        let mut new = current.clone();
+        new.logging.level = "debug".to_string();
+        new.queue.retry_delays = vec![0, 500];
+        new.risk.max_transfer = Some(1_000);
+        new.screening.lists = vec![PathBuf::from("/etc/relayer/deny")];
+        new.source_chain.rpc_url = "https://new.example".to_string();
+        new.source_chain.contract_address = PROGRAM.to_string();
+        new.api.port = 9000;
+
+        let (merged, ignored) = current.reloaded(&new);
+        assert_eq!(merged.logging.level, "debug");
+        assert_eq!(merged.queue.retry_delays, vec![0, 500]);
+        assert_eq!(merged.risk.max_transfer, Some(1_000));
+        assert_eq!(merged.screening.lists, new.screening.lists);
+        assert_eq!(merged.source_chain.rpc_url, "https://new.example");
+        assert_eq!((merged.source_chain.contract_address.as_str(), merged.api.port), ("", current.api.port));
+        assert_eq!(ignored, vec!["api", "source_chain"]);
+        assert!(current.reloaded(&current).1.is_empty());
+    }
+
+    #[test]
+    fn test_validate_reports_all_problems() {
+        let mut config = Config::default();
+        config.service.name = "s2e".to_string();
+        config.source_chain.chain_id = 91024;
+        config.source_chain.rpc_url = "https://rpc.example".to_string();
+        config.source_chain.ws_url = Some("https://rpc.example".to_string());
+        config.source_chain.contract_address = EVM_CONTRACT.to_string();
+        config.target_chain.chain_id = 421614;
+        config.target_chain.rpc_url = "https://rpc.example".to_string();
+        config.target_chain.contract_address = PROGRAM.to_string();
+        config.relayer.ecdsa_private_key = Some("0x1234".into());
+        config.queue.retry_limit = 3;
+        config.sibling_ports = vec![("e2s-submitter".to_string(), config.api.port)];
+
+        let problems = config.problems();
+        assert_eq!(problems.len(), 6, "{:?}", problems);
+        assert!(problems.iter().any(|p| p.starts_with("source_chain.ws_url")));
+        assert!(problems.iter().any(|p| p.starts_with("source_chain.contract_address")));
+        assert!(problems.iter().any(|p| p.starts_with("target_chain.contract_address")));
+        assert!(problems.iter().any(|p| p.starts_with("relayer.ecdsa_private_key")));
+        assert!(problems.iter().any(|p| p.starts_with("queue.retry_delays")));
+        assert!(problems.iter().any(|p| p.contains("clashes with service e2s-submitter")));
+
+        let report = config.validate().unwrap_err().to_string();
+        assert!(report.contains("6 problems"));
+    }
+}
@@ -0,0 +1,106 @@
+//! 证明消息编码
+//!
+//! relayer 签名的字节必须与目标链合约逐字节一致，两种编码都只在这里定义：
+//! - EVM（`Bridge1024._hashEventData`）：JSON → SHA-256，再加 EIP-191 前缀做 keccak256
+//! - SVM（Anchor `StakeEventData`）：Borsh 序列化，Ed25519Program 直接验证原始消息
+//!
+//! `testdata/attestation_vectors.json` 中的 golden vectors 由独立实现生成，
+//! EVM 部分同时在 `evm/bridge1024/test/Bridge1024.t.sol` 中校验。
+
+use crate::address::ChainAddress;
+use crate::types::StakeEventData;
+use sha2::Sha256;
+use sha3::{Digest, Keccak256};
+
+/// EIP-191 个人签名前缀（32 字节消息）
+pub const EIP191_PREFIX: &[u8] = b"\x19Ethereum Signed Message:\n32";
+
+/// `_hashEventData` 哈希的 JSON（字段顺序固定，数字为十进制字符串，合约地址为 bytes32 小写 hex）
+pub fn evm_event_json(event: &StakeEventData) -> String {
+    format!(
+        r#"{{"sourceContract":"{}","targetContract":"{}","chainId":"{}","blockHeight":"{}","amount":"{}","receiverAddress":"{}","nonce":"{}"}}"#,
+        hex::encode(event.source_contract.to_bytes32()),
+        hex::encode(event.target_contract.to_bytes32()),
+        event.source_chain_id,
+        event.block_height,
+        event.amount,
+        event.receiver_address,
+        event.nonce
+    )
+}
+
+/// `Bridge1024._hashEventData`：sha256(JSON)
+pub fn evm_event_hash(event: &StakeEventData) -> [u8; 32] {
+    Sha256::digest(evm_event_json(event).as_bytes()).into()
+}
+
+/// EVM 证明的待签名摘要：keccak256(EIP-191 前缀 || sha256(JSON))
+pub fn evm_attestation_digest(event: &StakeEventData) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    hasher.update(EIP191_PREFIX);
+    hasher.update(evm_event_hash(event));
+    hasher.finalize().into()
+}
+
+/// SVM 证明消息：Anchor `StakeEventData` 的 Borsh 编码
+///
+/// 地址为规范字符串（u32 小端长度 + UTF-8 字节），整数为 u64 小端，字段顺序与 Anchor 结构体一致。
+pub fn svm_event_message(event: &StakeEventData) -> Vec<u8> {
+    fn string(out: &mut Vec<u8>, value: &ChainAddress) {
+        let value = value.to_string();
+        out.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        out.extend_from_slice(value.as_bytes());
+    }
+
+    let mut out = Vec::with_capacity(3 * (4 + 64) + 5 * 8);
+    string(&mut out, &event.source_contract);
+    string(&mut out, &event.target_contract);
+    out.extend_from_slice(&event.source_chain_id.to_le_bytes());
+    out.extend_from_slice(&event.target_chain_id.to_le_bytes());
+    out.extend_from_slice(&event.block_height.to_le_bytes());
+    out.extend_from_slice(&event.amount.to_le_bytes());
+    string(&mut out, &event.receiver_address);
+    out.extend_from_slice(&event.nonce.to_le_bytes());
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct Vector {
+        name: String,
+        event: StakeEventData,
+        evm_json: String,
+        evm_hash: String,
+        evm_digest: String,
+        svm_message: String,
+    }
+
+    fn vectors() -> Vec<Vector> {
+        serde_json::from_str(include_str!("../testdata/attestation_vectors.json")).unwrap()
+    }
+
+    #[test]
+    fn test_golden_vectors() {
+        for vector in vectors() {
+            let event = &vector.event;
+            assert_eq!(evm_event_json(event), vector.evm_json, "{}", vector.name);
+            assert_eq!(hex::encode(evm_event_hash(event)), vector.evm_hash, "{}", vector.name);
+            assert_eq!(hex::encode(evm_attestation_digest(event)), vector.evm_digest, "{}", vector.name);
+            assert_eq!(hex::encode(svm_event_message(event)), vector.svm_message, "{}", vector.name);
+        }
+    }
+
+    #[cfg(feature = "borsh")]
+    #[test]
+    fn test_svm_message_matches_borsh_derive() {
+        use borsh::BorshSerialize;
+
+        for vector in vectors() {
+            assert_eq!(svm_event_message(&vector.event), vector.event.try_to_vec().unwrap(), "{}", vector.name);
+        }
+    }
+}
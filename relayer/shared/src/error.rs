@@ -1,3 +1,4 @@
+use sha3::{Digest, Keccak256};
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, RelayerError>;
@@ -58,9 +59,37 @@ pub enum RelayerError {
     #[error("Queue error: {0}")]
     Queue(String),
 
+    #[error("Invalid task transition for {id}: {from} -> {to}")]
+    InvalidTaskTransition {
+        id: String,
+        from: crate::types::TaskStatus,
+        to: crate::types::TaskStatus,
+    },
+
+    #[error("Refusing to double-sign: {0}")]
+    DoubleSign(String),
+
+    #[error("Alert delivery failed: {0}")]
+    Alert(String),
+
     #[error("HTTP error: {0}")]
     Http(String),
 
+    #[error("RPC rate limited: {0}")]
+    RateLimited(String),
+
+    #[error("RPC rejected request: {0}")]
+    RpcRequest(String),
+
+    #[error("Circuit open for RPC endpoint {0}")]
+    CircuitOpen(String),
+
+    #[error("Contract error: {0:?}")]
+    Contract(ContractError),
+
+    #[error("Execution reverted: {0}")]
+    Reverted(String),
+
     #[error("Internal error: {0}")]
     Internal(String),
 
@@ -69,7 +98,7 @@ pub enum RelayerError {
 }
 
 impl RelayerError {
-    /// Check if error is retryable
+    /// 瞬时错误：同一次 RPC 调用可以立即退避重试，并计入熔断器
     pub fn is_retryable(&self) -> bool {
         matches!(
             self,
@@ -77,7 +106,169 @@ impl RelayerError {
                 | RelayerError::RpcConnection(_)
                 | RelayerError::Network(_)
                 | RelayerError::GasEstimation(_)
+                | RelayerError::RateLimited(_)
         )
     }
+
+    /// 永久错误：合约拒绝了该事件，任务重试也不会成功
+    pub fn is_permanent(&self) -> bool {
+        match self {
+            // 金库余额不足可由运维补充后重试
+            RelayerError::Contract(error) => *error != ContractError::InsufficientBalance,
+            RelayerError::Reverted(_)
+            | RelayerError::InvalidNonce { .. }
+            | RelayerError::NonceAlreadyProcessed(_)
+            | RelayerError::InvalidContract
+            | RelayerError::InvalidChainId { .. }
+            | RelayerError::InvalidEvent(_)
+            | RelayerError::NotWhitelisted
+            | RelayerError::UsdcNotConfigured
+            // 同一转账已签过不同的事件数据，重试只会再次被拒绝
+            | RelayerError::DoubleSign(_) => true,
+            _ => false,
+        }
+    }
+
+    /// JSON-RPC 错误对象：请求本身的错误（格式、方法、参数）不可重试，
+    /// 限流和服务端错误（-32000..=-32099、-32603）可重试
+    pub fn from_rpc_error(code: i64, message: &str) -> Self {
+        match code {
+            429 | -32005 => RelayerError::RateLimited(format!("{} ({})", message, code)),
+            -32700 | -32600 | -32601 | -32602 => RelayerError::RpcRequest(format!("{} ({})", message, code)),
+            _ => RelayerError::RpcConnection(format!("{} ({})", message, code)),
+        }
+    }
+
+    /// EVM revert 数据：桥合约自定义错误映射为 [`ContractError`]，`Error(string)` 解出原因
+    pub fn from_revert_data(data: &[u8]) -> Self {
+        if let Some(error) = ContractError::from_evm_revert(data) {
+            return error.into();
+        }
+        // Error(string) = 0x08c379a0 || abi.encode(string)
+        if data.len() >= 68 && data[..4] == [0x08, 0xc3, 0x79, 0xa0] {
+            let len = u64::from_be_bytes(data[60..68].try_into().unwrap()) as usize;
+            if let Some(reason) = data.get(68..68 + len) {
+                return RelayerError::Reverted(String::from_utf8_lossy(reason).into_owned());
+            }
+        }
+        RelayerError::Reverted(format!("0x{}", hex::encode(data)))
+    }
+}
+
+impl From<reqwest::Error> for RelayerError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_timeout() {
+            RelayerError::RpcTimeout
+        } else if error.status() == Some(reqwest::StatusCode::TOO_MANY_REQUESTS) {
+            RelayerError::RateLimited(error.to_string())
+        } else if error.is_decode() {
+            RelayerError::Serialization(error.to_string())
+        } else {
+            RelayerError::Network(error.to_string())
+        }
+    }
+}
+
+/// 桥合约错误：EVM 合约的自定义错误与 SVM 程序的 Anchor 错误码同名
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContractError {
+    Unauthorized,
+    UsdcNotConfigured,
+    InsufficientBalance,
+    RelayerAlreadyExists,
+    RelayerNotFound,
+    InvalidNonce,
+    InvalidSignature,
+    InvalidSourceContract,
+    InvalidChainId,
+    TooManyRelayers,
+    RelayerAlreadySigned,
+    InvalidEventData,
+    /// 仅 EVM 合约
+    AlreadyInitialized,
+}
+
+impl ContractError {
+    /// SVM 程序 `ErrorCode` 的声明顺序（Anchor 错误码从 6000 开始）
+    const ANCHOR_CODES: [ContractError; 12] = [
+        ContractError::Unauthorized,
+        ContractError::UsdcNotConfigured,
+        ContractError::InsufficientBalance,
+        ContractError::RelayerAlreadyExists,
+        ContractError::RelayerNotFound,
+        ContractError::InvalidNonce,
+        ContractError::InvalidSignature,
+        ContractError::InvalidSourceContract,
+        ContractError::InvalidChainId,
+        ContractError::TooManyRelayers,
+        ContractError::RelayerAlreadySigned,
+        ContractError::InvalidEventData,
+    ];
+
+    /// SVM 程序的 `Custom(code)` 错误码
+    pub fn from_anchor_code(code: u32) -> Option<Self> {
+        code.checked_sub(6000).and_then(|index| Self::ANCHOR_CODES.get(index as usize).copied())
+    }
+
+    /// EVM 合约 revert 数据的前 4 字节选择器
+    pub fn from_evm_revert(data: &[u8]) -> Option<Self> {
+        let selector = data.get(..4)?;
+        Self::ANCHOR_CODES
+            .iter()
+            .chain(&[ContractError::AlreadyInitialized])
+            .copied()
+            .find(|error| Keccak256::digest(format!("{:?}()", error).as_bytes())[..4] == *selector)
+    }
+}
+
+impl From<ContractError> for RelayerError {
+    fn from(error: ContractError) -> Self {
+        match error {
+            ContractError::Unauthorized => RelayerError::NotWhitelisted,
+            ContractError::UsdcNotConfigured => RelayerError::UsdcNotConfigured,
+            other => RelayerError::Contract(other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contract_error_codes() {
+        assert_eq!(ContractError::from_anchor_code(6005), Some(ContractError::InvalidNonce));
+        assert_eq!(ContractError::from_anchor_code(6011), Some(ContractError::InvalidEventData));
+        assert_eq!(ContractError::from_anchor_code(6012), None);
+        assert_eq!(ContractError::from_anchor_code(3012), None);
+
+        // bytes4(keccak256("InvalidNonce()"))
+        let revert = RelayerError::from_revert_data(&hex::decode("756688fe").unwrap());
+        assert!(matches!(revert, RelayerError::Contract(ContractError::InvalidNonce)));
+        assert!(revert.is_permanent());
+        assert!(matches!(
+            RelayerError::from(ContractError::Unauthorized),
+            RelayerError::NotWhitelisted
+        ));
+        assert!(!RelayerError::Contract(ContractError::InsufficientBalance).is_permanent());
+    }
+
+    #[test]
+    fn test_revert_reason_and_rpc_codes() {
+        // Error("Transfer failed")
+        let data = hex::decode(concat!(
+            "08c379a0",
+            "0000000000000000000000000000000000000000000000000000000000000020",
+            "000000000000000000000000000000000000000000000000000000000000000f",
+            "5472616e73666572206661696c65640000000000000000000000000000000000",
+        ))
+        .unwrap();
+        assert!(matches!(RelayerError::from_revert_data(&data), RelayerError::Reverted(r) if r == "Transfer failed"));
+
+        assert!(RelayerError::from_rpc_error(-32005, "limit exceeded").is_retryable());
+        assert!(RelayerError::from_rpc_error(-32000, "header not found").is_retryable());
+        let invalid = RelayerError::from_rpc_error(-32602, "invalid params");
+        assert!(!invalid.is_retryable() && !invalid.is_permanent());
+    }
 }
 
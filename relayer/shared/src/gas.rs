@@ -1,6 +1,48 @@
+use crate::alerts::{self, Alert, Severity};
+use crate::config::GasConfig;
 use crate::error::{RelayerError, Result};
+use crate::metrics;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
 use tracing::{error, info, warn};
 
+/// 提交账户的余额状态：余额监控写入，提交流程和 `/status` 读取
+#[derive(Debug, Clone, Default)]
+pub struct BalanceStatus {
+    inner: Arc<Mutex<BalanceState>>,
+}
+
+#[derive(Debug, Default)]
+struct BalanceState {
+    /// 最近一次查询到的余额（SOL / ETH）
+    balance: Option<f64>,
+    /// 余额低于下限，暂停提交
+    underfunded: bool,
+}
+
+impl BalanceStatus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 最近一次查询到的余额，尚未查询时为 None
+    pub fn balance(&self) -> Option<f64> {
+        self.inner.lock().unwrap().balance
+    }
+
+    /// 余额低于下限，提交已暂停
+    pub fn is_underfunded(&self) -> bool {
+        self.inner.lock().unwrap().underfunded
+    }
+
+    /// 更新状态，返回之前是否处于欠费状态
+    fn update(&self, balance: f64, underfunded: bool) -> bool {
+        let mut state = self.inner.lock().unwrap();
+        state.balance = Some(balance);
+        std::mem::replace(&mut state.underfunded, underfunded)
+    }
+}
+
 /// Gas 管理器
 #[derive(Debug, Clone)]
 pub struct GasManager {
@@ -18,6 +60,46 @@ impl GasManager {
         }
     }
 
+    pub fn from_config(config: &GasConfig) -> Self {
+        Self::new(config.min_svm_balance, config.min_evm_balance, config.balance_check_interval)
+    }
+
+    /// 按链（"svm" / "evm"）检查余额
+    pub fn check_balance(&self, chain: &str, balance: f64) -> Result<()> {
+        match chain {
+            "svm" => self.check_svm_balance(balance),
+            "evm" => self.check_evm_balance(balance),
+            other => Err(RelayerError::Config(format!("Unknown chain for balance check: {}", other))),
+        }
+    }
+
+    /// 记录一次余额查询结果：更新 `relayer_balance` 指标和余额状态，低于下限时返回 `InsufficientFunds`
+    ///
+    /// 余额跌破下限时暂停提交并发送 critical 告警，低于 2 倍下限时发送 warning 告警，恢复后自动继续
+    pub fn observe(&self, chain: &str, balance: f64, status: &BalanceStatus) -> Result<()> {
+        metrics::record_balance(chain, balance);
+        let result = self.check_balance(chain, balance);
+        let was_underfunded = status.update(balance, result.is_err());
+        match (was_underfunded, result.is_err()) {
+            (false, true) => warn!(chain, balance, "Relayer underfunded, pausing submissions"),
+            (true, false) => info!(chain, balance, "Relayer balance restored, resuming submissions"),
+            _ => {}
+        }
+
+        let minimum = if chain == "svm" { self.min_svm_balance } else { self.min_evm_balance };
+        let key = format!("balance:{}", chain);
+        let symbol = if chain == "svm" { "SOL" } else { "ETH" };
+        let message = format!("{} relayer balance {} {} (minimum {} {})", chain, balance, symbol, minimum, symbol);
+        if result.is_err() {
+            alerts::notify(Alert::new(Severity::Critical, key, "Relayer underfunded, submissions paused", message));
+        } else if balance < minimum * 2.0 {
+            alerts::notify(Alert::new(Severity::Warning, key, "Relayer balance low", message));
+        } else {
+            alerts::resolve(&key, message);
+        }
+        result
+    }
+
     /// 检查 SVM 余额
     pub fn check_svm_balance(&self, balance_sol: f64) -> Result<()> {
         info!(balance_sol = balance_sol, "Checking SVM balance");
@@ -76,19 +158,29 @@ impl GasManager {
         Ok(())
     }
 
-    /// 启动余额监控任务
-    pub fn start_balance_monitor<F>(&self, check_balance_fn: F) -> tokio::task::JoinHandle<()>
+    /// 启动余额监控任务：每 `check_interval` 毫秒查询一次余额并更新 `status`
+    ///
+    /// 查询失败时保留上一次的状态
+    pub fn start_balance_monitor<F, Fut>(
+        &self,
+        chain: &'static str,
+        status: BalanceStatus,
+        fetch_balance: F,
+    ) -> tokio::task::JoinHandle<()>
     where
-        F: Fn() -> std::pin::Pin<Box<dyn std::future::Future<Output = Result<()>> + Send>>
-            + Send
-            + 'static,
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<f64>> + Send,
     {
-        let interval = self.check_interval;
+        let manager = self.clone();
         tokio::spawn(async move {
             loop {
-                tokio::time::sleep(tokio::time::Duration::from_millis(interval)).await;
-                if let Err(e) = check_balance_fn().await {
-                    error!(error = %e, "Balance check failed");
+                tokio::time::sleep(tokio::time::Duration::from_millis(manager.check_interval)).await;
+                match fetch_balance().await {
+                    // 欠费已在 observe 中记录
+                    Ok(balance) => {
+                        let _ = manager.observe(chain, balance, &status);
+                    }
+                    Err(e) => error!(chain, error = %e, "Balance check failed"),
                 }
             }
         })
@@ -122,5 +214,19 @@ mod tests {
         let manager = GasManager::new(5.0, 0.1, 300000);
         assert!(manager.check_evm_balance(0.05).is_err());
     }
-}
 
+    #[test]
+    fn test_observe_pauses_and_resumes() {
+        let manager = GasManager::new(5.0, 0.1, 300000);
+        let status = BalanceStatus::new();
+        assert_eq!(status.balance(), None);
+
+        assert!(manager.observe("evm", 0.05, &status).is_err());
+        assert!(status.is_underfunded());
+        assert_eq!(status.balance(), Some(0.05));
+
+        manager.observe("evm", 0.5, &status).unwrap();
+        assert!(!status.is_underfunded());
+        assert!(manager.check_balance("btc", 1.0).is_err());
+    }
+}
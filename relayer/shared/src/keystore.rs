@@ -0,0 +1,243 @@
+//! 加密密钥库
+//!
+//! - secp256k1 (ECDSA) 私钥使用以太坊 V3 JSON keystore（scrypt + AES-128-CTR），
+//!   与 geth / ethers / evm-gateway-service 通用
+//! - Ed25519 私钥使用同样的 scrypt 加密容器，明文为 32 字节种子或 64 字节 keypair
+//!
+//! 口令优先从 `relayer.keystore_password_file` 读取，未配置时在终端交互输入。
+
+use crate::config::RelayerConfig;
+use crate::error::{RelayerError, Result};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::io::IsTerminal;
+use std::path::Path;
+use subtle::ConstantTimeEq;
+use zeroize::{Zeroize, Zeroizing};
+
+/// 敏感字符串：Debug / Serialize 时输出 `[REDACTED]`，释放时清零
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// 取出明文（仅在真正使用密钥时调用）
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// 常数时间比较（校验请求携带的 token，避免按字节提前返回泄露匹配长度）
+    pub fn matches(&self, provided: &str) -> bool {
+        self.0.as_bytes().ct_eq(provided.as_bytes()).into()
+    }
+}
+
+impl From<String> for Secret {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for Secret {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str("[REDACTED]")
+    }
+}
+
+impl<'de> Deserialize<'de> for Secret {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Secret)
+    }
+}
+
+/// 密钥类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyKind {
+    Secp256k1,
+    Ed25519,
+}
+
+impl KeyKind {
+    fn check_len(self, len: usize) -> Result<()> {
+        let ok = match self {
+            KeyKind::Secp256k1 => len == 32,
+            KeyKind::Ed25519 => len == 32 || len == 64,
+        };
+        if ok {
+            Ok(())
+        } else {
+            Err(RelayerError::Config(format!(
+                "Keystore holds {} bytes, not a valid {:?} key",
+                len, self
+            )))
+        }
+    }
+}
+
+/// 将私钥加密写入 keystore 文件
+pub fn encrypt_key(path: &Path, kind: KeyKind, key: &[u8], password: &Secret) -> Result<()> {
+    kind.check_len(key.len())?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| RelayerError::Config(format!("Invalid keystore path: {}", path.display())))?;
+    let io_error = |e: std::io::Error| RelayerError::Config(format!("Failed to write keystore {}: {}", path.display(), e));
+    std::fs::create_dir_all(dir).map_err(io_error)?;
+    eth_keystore::encrypt_key(dir, &mut rand::thread_rng(), key, password.expose(), Some(name))
+        .map_err(|e| RelayerError::Config(format!("Failed to write keystore {}: {}", path.display(), e)))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)).map_err(io_error)?;
+    }
+    Ok(())
+}
+
+/// 解密 keystore 文件
+pub fn decrypt_key(path: &Path, kind: KeyKind, password: &Secret) -> Result<Zeroizing<Vec<u8>>> {
+    let key = Zeroizing::new(
+        eth_keystore::decrypt_key(path, password.expose())
+            .map_err(|e| RelayerError::Config(format!("Failed to decrypt keystore {}: {}", path.display(), e)))?,
+    );
+    kind.check_len(key.len())?;
+    Ok(key)
+}
+
+/// 读取 keystore 口令：优先读取口令文件（去掉末尾换行），否则在终端提示输入
+pub fn read_password(password_file: Option<&Path>, prompt: &str) -> Result<Secret> {
+    if let Some(path) = password_file {
+        let mut content = std::fs::read_to_string(path).map_err(|e| {
+            RelayerError::Config(format!("Failed to read password file {}: {}", path.display(), e))
+        })?;
+        let password = Secret::new(content.trim_end_matches(['\r', '\n']));
+        content.zeroize();
+        return Ok(password);
+    }
+    if !std::io::stdin().is_terminal() {
+        return Err(RelayerError::Config(
+            "Keystore password required: set relayer.keystore_password_file or run interactively".to_string(),
+        ));
+    }
+    rpassword::prompt_password(prompt)
+        .map(Secret::from)
+        .map_err(|e| RelayerError::Config(format!("Failed to read password: {}", e)))
+}
+
+/// 解析 ECDSA (secp256k1) 私钥：明文配置或 keystore，返回十六进制字符串
+pub fn ecdsa_private_key(keys: &RelayerConfig) -> Result<Secret> {
+    resolve(
+        keys,
+        KeyKind::Secp256k1,
+        keys.ecdsa_private_key.as_ref(),
+        keys.ecdsa_keystore.as_deref(),
+        "relayer.ecdsa_private_key or relayer.ecdsa_keystore",
+    )
+}
+
+/// 解析 Ed25519 私钥：明文配置或 keystore，返回十六进制字符串
+pub fn ed25519_private_key(keys: &RelayerConfig) -> Result<Secret> {
+    resolve(
+        keys,
+        KeyKind::Ed25519,
+        keys.ed25519_private_key.as_ref(),
+        keys.ed25519_keystore.as_deref(),
+        "relayer.ed25519_private_key or relayer.ed25519_keystore",
+    )
+}
+
+fn resolve(
+    keys: &RelayerConfig,
+    kind: KeyKind,
+    plain: Option<&Secret>,
+    keystore: Option<&Path>,
+    fields: &str,
+) -> Result<Secret> {
+    match (plain, keystore) {
+        (_, Some(path)) => {
+            let prompt = format!("Password for {}: ", path.display());
+            let password = read_password(keys.keystore_password_file.as_deref(), &prompt)?;
+            let key = decrypt_key(path, kind, &password)?;
+            Ok(Secret::new(format!("0x{}", hex::encode(key.as_slice()))))
+        }
+        (Some(key), None) => Ok(key.clone()),
+        (None, None) => Err(RelayerError::Config(format!("{} is not configured", fields))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_is_redacted() {
+        let secret = Secret::new("0xdeadbeef");
+        assert_eq!(format!("{:?}", secret), "[REDACTED]");
+        assert_eq!(serde_json::to_string(&secret).unwrap(), "\"[REDACTED]\"");
+        let parsed: Secret = serde_json::from_str("\"0xdeadbeef\"").unwrap();
+        assert_eq!(parsed.expose(), "0xdeadbeef");
+        assert!(parsed.matches("0xdeadbeef"));
+        assert!(!parsed.matches("0xdeadbee") && !parsed.matches("0xdeadbeeg"));
+
+        let keys = RelayerConfig {
+            ecdsa_private_key: Some(secret),
+            ..Default::default()
+        };
+        assert!(!format!("{:?}", keys).contains("deadbeef"));
+    }
+
+    #[test]
+    fn test_keystore_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("relayer-keystore-{}", std::process::id()));
+        let password_file = dir.join("password");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(&password_file, "correct horse\n").unwrap();
+        let password = read_password(Some(&password_file), "").unwrap();
+
+        let ecdsa = [7u8; 32];
+        let ed25519 = [9u8; 64];
+        encrypt_key(&dir.join("ecdsa.json"), KeyKind::Secp256k1, &ecdsa, &password).unwrap();
+        encrypt_key(&dir.join("ed25519.json"), KeyKind::Ed25519, &ed25519, &password).unwrap();
+        assert!(encrypt_key(&dir.join("bad.json"), KeyKind::Secp256k1, &ed25519, &password).is_err());
+
+        let keys = RelayerConfig {
+            ecdsa_keystore: Some(dir.join("ecdsa.json")),
+            ed25519_keystore: Some(dir.join("ed25519.json")),
+            keystore_password_file: Some(password_file),
+            ..Default::default()
+        };
+        assert_eq!(ecdsa_private_key(&keys).unwrap().expose(), format!("0x{}", hex::encode(ecdsa)));
+        assert_eq!(ed25519_private_key(&keys).unwrap().expose(), format!("0x{}", hex::encode(ed25519)));
+
+        let wrong = Secret::new("wrong");
+        assert!(decrypt_key(&dir.join("ecdsa.json"), KeyKind::Secp256k1, &wrong).is_err());
+        // Ed25519 keystore 不能当作 secp256k1 私钥使用
+        assert!(decrypt_key(&dir.join("ed25519.json"), KeyKind::Secp256k1, &password).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,11 +1,36 @@
+pub mod address;
 pub mod config;
 pub mod error;
 pub mod types;
+pub mod encoding;
 pub mod logger;
 pub mod retry;
 pub mod gas;
 pub mod metrics;
+pub mod store;
+pub mod queue;
+pub mod keystore;
+pub mod signer;
+pub mod pipeline;
+pub mod telemetry;
+pub mod alerts;
+pub mod audit;
+pub mod protection;
+pub mod risk;
+pub mod screening;
+pub mod lifecycle;
+pub mod checkpoint;
+#[cfg(feature = "pkcs11")]
+pub mod pkcs11;
+#[cfg(test)]
+pub(crate) mod test_util;
 
+pub use address::{AddressKind, ChainAddress};
 pub use config::Config;
-pub use error::{RelayerError, Result};
+pub use error::{ContractError, RelayerError, Result};
+pub use store::TaskStore;
+pub use queue::WorkQueue;
+pub use keystore::Secret;
+pub use signer::AttestationSigner;
+pub use pipeline::{Relay, SourceListener, TargetSubmitter};
 pub use types::*;
@@ -0,0 +1,256 @@
+//! 进程生命周期：SIGHUP 热加载配置，SIGTERM / Ctrl-C 优雅退出
+//!
+//! - 热加载只采用非结构性配置（见 [`Config::reloaded`]）：日志级别、重试间隔、风控上限、拒绝名单和
+//!   RPC 端点；其他变化记录警告，重启后生效。新配置无法加载或校验失败时保留当前配置。
+//!   环境变量在进程启动时已确定，热加载只会读到配置文件的变化
+//! - 收到退出信号后服务不再拉取新事件和任务，进行中的签名和提交完成、监听游标推进后退出；
+//!   超过 `service.shutdown_timeout` 仍未完成时直接退出，处理中的任务在下次启动时由 `recover` 恢复
+//! - 排空期间再次收到退出信号时立即退出
+
+use crate::config::Config;
+use crate::error::Result;
+use crate::logger;
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tracing::{error, info, warn};
+
+/// 退出信号的接收端
+#[derive(Clone)]
+pub struct Shutdown(watch::Receiver<bool>);
+
+impl Shutdown {
+    pub fn is_requested(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// 等待退出信号
+    pub async fn requested(&mut self) {
+        // 发送端随 Lifecycle 存活到进程退出，出错时同样视为退出
+        let _ = self.0.wait_for(|requested| *requested).await;
+    }
+
+    /// 等待 `duration`；期间收到退出信号时提前返回 true
+    pub async fn sleep(&mut self, duration: Duration) -> bool {
+        tokio::select! {
+            _ = self.requested() => true,
+            _ = tokio::time::sleep(duration) => self.is_requested(),
+        }
+    }
+}
+
+/// 热加载配置的接收端
+pub struct ConfigWatch(watch::Receiver<Arc<Config>>);
+
+impl ConfigWatch {
+    pub fn current(&self) -> Arc<Config> {
+        self.0.borrow().clone()
+    }
+
+    /// 上次调用以来配置已热加载时返回新配置
+    pub fn changed(&mut self) -> Option<Arc<Config>> {
+        match self.0.has_changed() {
+            Ok(true) => Some(self.0.borrow_and_update().clone()),
+            _ => None,
+        }
+    }
+}
+
+/// 当前配置与退出标志
+pub struct Lifecycle {
+    config: watch::Sender<Arc<Config>>,
+    shutdown: watch::Sender<bool>,
+}
+
+impl Lifecycle {
+    pub fn new(config: Config) -> Arc<Self> {
+        Arc::new(Self {
+            config: watch::Sender::new(Arc::new(config)),
+            shutdown: watch::Sender::new(false),
+        })
+    }
+
+    pub fn config(&self) -> Arc<Config> {
+        self.config.borrow().clone()
+    }
+
+    pub fn watch_config(&self) -> ConfigWatch {
+        ConfigWatch(self.config.subscribe())
+    }
+
+    pub fn shutdown(&self) -> Shutdown {
+        Shutdown(self.shutdown.subscribe())
+    }
+
+    pub fn request_shutdown(&self) {
+        self.shutdown.send_replace(true);
+    }
+
+    /// 应用重新加载的配置，返回被忽略（需要重启才能生效）的配置节
+    pub fn reload(&self, new: &Config) -> Result<Vec<String>> {
+        let current = self.config();
+        let (merged, ignored) = current.reloaded(new);
+        merged.validate()?;
+        if merged.logging.level != current.logging.level {
+            logger::set_level(&merged.logging.level)?;
+        }
+        self.config.send_replace(Arc::new(merged));
+        Ok(ignored)
+    }
+
+    /// 在后台处理进程信号；`load` 重新读取配置（与启动时相同的加载流程）
+    pub fn spawn_signal_handler<F, E>(self: &Arc<Self>, load: F)
+    where
+        F: Fn() -> std::result::Result<Config, E> + Send + 'static,
+        E: Display,
+    {
+        let lifecycle = self.clone();
+        let mut signals = match Signals::install() {
+            Ok(signals) => signals,
+            Err(e) => {
+                error!(error = %e, "Failed to install signal handlers, reload and graceful shutdown disabled");
+                return;
+            }
+        };
+        tokio::spawn(async move {
+            loop {
+                match signals.next().await {
+                    Signal::Reload => match load() {
+                        Ok(new) => match lifecycle.reload(&new) {
+                            Ok(ignored) if ignored.is_empty() => info!("Configuration reloaded"),
+                            Ok(ignored) => warn!(
+                                sections = %ignored.join(", "),
+                                "Configuration reloaded; changes to these sections take effect after a restart"
+                            ),
+                            Err(e) => error!(error = %e, "Rejected reloaded configuration, keeping current settings"),
+                        },
+                        Err(e) => error!(error = %e, "Failed to reload configuration, keeping current settings"),
+                    },
+                    Signal::Shutdown if lifecycle.shutdown().is_requested() => {
+                        warn!("Second shutdown signal, exiting without waiting for in-flight tasks");
+                        std::process::exit(130);
+                    }
+                    Signal::Shutdown => {
+                        info!("Shutdown signal received, draining in-flight tasks");
+                        lifecycle.request_shutdown();
+                    }
+                }
+            }
+        });
+    }
+
+    /// 运行服务主循环直到其返回；收到退出信号后最多再等待 `service.shutdown_timeout`，超时返回 None
+    pub async fn run<F: Future>(&self, worker: F) -> Option<F::Output> {
+        tokio::pin!(worker);
+        let mut shutdown = self.shutdown();
+        tokio::select! {
+            output = &mut worker => return Some(output),
+            _ = shutdown.requested() => {}
+        }
+        let timeout = Duration::from_millis(self.config().service.shutdown_timeout);
+        let output = tokio::time::timeout(timeout, worker).await.ok();
+        if output.is_none() {
+            warn!(
+                timeout_ms = timeout.as_millis() as u64,
+                "Drain timed out; tasks still processing will be recovered on next start"
+            );
+        }
+        output
+    }
+}
+
+enum Signal {
+    Reload,
+    Shutdown,
+}
+
+/// 已注册的信号流
+struct Signals {
+    #[cfg(unix)]
+    hangup: tokio::signal::unix::Signal,
+    #[cfg(unix)]
+    terminate: tokio::signal::unix::Signal,
+}
+
+impl Signals {
+    #[cfg(unix)]
+    fn install() -> std::io::Result<Self> {
+        use tokio::signal::unix::{signal, SignalKind};
+        Ok(Self { hangup: signal(SignalKind::hangup())?, terminate: signal(SignalKind::terminate())? })
+    }
+
+    #[cfg(not(unix))]
+    fn install() -> std::io::Result<Self> {
+        Ok(Self {})
+    }
+
+    #[cfg(unix)]
+    async fn next(&mut self) -> Signal {
+        tokio::select! {
+            _ = self.hangup.recv() => Signal::Reload,
+            _ = self.terminate.recv() => Signal::Shutdown,
+            _ = tokio::signal::ctrl_c() => Signal::Shutdown,
+        }
+    }
+
+    #[cfg(not(unix))]
+    async fn next(&mut self) -> Signal {
+        let _ = tokio::signal::ctrl_c().await;
+        Signal::Shutdown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reload_applies_settings_and_rejects_invalid() {
+        let mut config = Config::default();
+        config.service.name = "e2s-listener".to_string();
+        config.target_chain.chain_id = 91024;
+        config.source_chain.chain_id = 421614;
+        config.source_chain.rpc_url = "https://old.example".to_string();
+        config.source_chain.contract_address = "0x5FbDB2315678afecb367f032d93F642f64180aa3".to_string();
+        config.validate().unwrap();
+        let lifecycle = Lifecycle::new(config.clone());
+        let mut watch = lifecycle.watch_config();
+        assert!(watch.changed().is_none());
+
+        let mut new = config.clone();
+        new.source_chain.rpc_url = "https://new.example".to_string();
+        new.service.worker_pool_size = 9;
+        assert_eq!(lifecycle.reload(&new).unwrap(), vec!["service"]);
+        let current = watch.changed().unwrap();
+        assert_eq!((current.source_chain.rpc_url.as_str(), current.service.worker_pool_size), ("https://new.example", 5));
+        assert!(watch.changed().is_none());
+
+        new.source_chain.rpc_url = "ftp://bad".to_string();
+        assert!(lifecycle.reload(&new).is_err());
+        assert!(watch.changed().is_none());
+        assert_eq!(lifecycle.config().source_chain.rpc_url, "https://new.example");
+    }
+
+    #[tokio::test]
+    async fn test_run_drains_until_timeout() {
+        let mut config = Config::default();
+        config.service.shutdown_timeout = 50;
+        let lifecycle = Lifecycle::new(config);
+
+        // 收到退出信号后主循环自行结束
+        let mut shutdown = lifecycle.shutdown();
+        let worker = async move {
+            while !shutdown.sleep(Duration::from_secs(60)).await {}
+            "drained"
+        };
+        let stopper = lifecycle.clone();
+        tokio::spawn(async move { stopper.request_shutdown() });
+        assert_eq!(lifecycle.run(worker).await, Some("drained"));
+
+        // 不响应退出信号的主循环在超时后放弃
+        let stuck = tokio::time::sleep(Duration::from_secs(60));
+        assert_eq!(lifecycle.run(stuck).await, None);
+    }
+}
@@ -1,26 +1,55 @@
-use crate::error::Result;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+use crate::config::TelemetryConfig;
+use crate::error::{RelayerError, Result};
+use crate::telemetry::{self, TelemetryGuard};
+use std::sync::OnceLock;
+use tracing_subscriber::{layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Registry};
 
-/// 初始化日志系统
-pub fn init_logger(level: &str, format: &str) -> Result<()> {
+/// 日志级别过滤器的热加载句柄
+static FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+/// 初始化日志系统；按 `telemetry` 配置同时把 span 导出到 OpenTelemetry，
+/// 返回的 guard 需保留到进程退出，drop 时刷新未导出的 span
+pub fn init_logger(level: &str, format: &str, telemetry: &TelemetryConfig, service: &str) -> Result<TelemetryGuard> {
     let env_filter = EnvFilter::try_from_default_env()
         .unwrap_or_else(|_| EnvFilter::new(level));
+    let (env_filter, handle) = reload::Layer::new(env_filter);
+    let _ = FILTER.set(handle);
+    let tracer = telemetry::init_tracer(telemetry, service)?;
+    let guard = TelemetryGuard::new(tracer.is_some());
 
     if format == "json" {
         // JSON 格式 (生产环境)
         tracing_subscriber::registry()
             .with(env_filter)
+            .with(tracer.map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer)))
             .with(tracing_subscriber::fmt::layer().json())
             .init();
     } else {
         // Pretty 格式 (开发环境)
         tracing_subscriber::registry()
             .with(env_filter)
+            .with(tracer.map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer)))
             .with(tracing_subscriber::fmt::layer().pretty())
             .init();
     }
 
-    Ok(())
+    Ok(guard)
+}
+
+/// 热加载日志级别；设置了 `RUST_LOG` 时以环境变量为准，不做修改
+pub fn set_level(level: &str) -> Result<()> {
+    let Some(handle) = FILTER.get() else {
+        return Ok(());
+    };
+    if std::env::var_os("RUST_LOG").is_some() {
+        tracing::warn!(level, "RUST_LOG is set, ignoring logging.level change");
+        return Ok(());
+    }
+    let filter = EnvFilter::try_new(level)
+        .map_err(|e| RelayerError::Config(format!("Invalid logging.level '{}': {}", level, e)))?;
+    handle
+        .reload(filter)
+        .map_err(|e| RelayerError::Internal(format!("Failed to reload log filter: {}", e)))
 }
 
 /// 日志宏的简化封装
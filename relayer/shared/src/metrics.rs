@@ -1,107 +1,337 @@
+//! Prometheus 指标
+//!
+//! 每个进程只负责一个方向（s2e 或 e2s），`init_metrics` 把方向作为常量标签 `direction`
+//! 加到本进程的所有指标上；未初始化时记录函数为空操作。
+
+use crate::error::{RelayerError, Result};
 use prometheus::{
-    register_counter_vec, register_gauge_vec, register_histogram_vec, CounterVec, GaugeVec,
-    HistogramVec, TextEncoder, Encoder,
+    CounterVec, Encoder, Gauge, GaugeVec, Histogram, HistogramOpts, HistogramVec, Opts, Registry, TextEncoder,
 };
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::OnceLock;
+use std::time::Instant;
+use tracing::Instrument;
 
+static REGISTRY: OnceLock<Registry> = OnceLock::new();
 static EVENTS_TOTAL: OnceLock<CounterVec> = OnceLock::new();
-static LATENCY_SECONDS: OnceLock<HistogramVec> = OnceLock::new();
+static LATENCY_SECONDS: OnceLock<Histogram> = OnceLock::new();
 static QUEUE_SIZE: OnceLock<GaugeVec> = OnceLock::new();
 static BALANCE: OnceLock<GaugeVec> = OnceLock::new();
+static RPC_LATENCY_SECONDS: OnceLock<HistogramVec> = OnceLock::new();
+static LAST_PROCESSED_BLOCK: OnceLock<GaugeVec> = OnceLock::new();
+static NONCE: OnceLock<GaugeVec> = OnceLock::new();
+static NONCE_LAG: OnceLock<Gauge> = OnceLock::new();
+static GAS_SPENT: OnceLock<CounterVec> = OnceLock::new();
+static REORGS_TOTAL: OnceLock<CounterVec> = OnceLock::new();
+
+/// 本进程最近一次观察到的源链 / 目标链 nonce（u64::MAX 表示尚未观察到）
+static SOURCE_NONCE: AtomicU64 = AtomicU64::new(u64::MAX);
+static TARGET_NONCE: AtomicU64 = AtomicU64::new(u64::MAX);
+
+/// 事件在流水线中的阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventStage {
+    /// 监听器在源链上观察到
+    Seen,
+    /// 已生成证明签名
+    Signed,
+    /// 已在目标链确认
+    Submitted,
+    /// 一次处理失败（可能随后重试）
+    Failed,
+    /// 源链回滚后撤回
+    Retracted,
+}
+
+impl EventStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventStage::Seen => "seen",
+            EventStage::Signed => "signed",
+            EventStage::Submitted => "submitted",
+            EventStage::Failed => "failed",
+            EventStage::Retracted => "retracted",
+        }
+    }
+}
+
+/// 初始化 Prometheus 指标，`direction` 为本进程的中继方向（s2e / e2s）
+pub fn init_metrics(direction: &str) {
+    let registry = REGISTRY.get_or_init(|| {
+        let labels = HashMap::from([("direction".to_string(), direction.to_string())]);
+        Registry::new_custom(None, Some(labels)).unwrap()
+    });
 
-/// 初始化 Prometheus 指标
-pub fn init_metrics() {
     EVENTS_TOTAL.get_or_init(|| {
-        register_counter_vec!(
-            "relayer_events_total",
-            "Total number of events processed",
-            &["service", "status"]
+        register(
+            registry,
+            CounterVec::new(Opts::new("relayer_events_total", "Events by pipeline stage"), &["stage"]).unwrap(),
         )
-        .unwrap()
     });
 
     LATENCY_SECONDS.get_or_init(|| {
-        register_histogram_vec!(
-            "relayer_latency_seconds",
-            "Event processing latency in seconds",
-            &["service"]
+        register(
+            registry,
+            Histogram::with_opts(
+                HistogramOpts::new("relayer_latency_seconds", "Time from event capture to target chain confirmation")
+                    .buckets(vec![1.0, 5.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1800.0, 3600.0]),
+            )
+            .unwrap(),
         )
-        .unwrap()
     });
 
     QUEUE_SIZE.get_or_init(|| {
-        register_gauge_vec!(
-            "relayer_queue_size",
-            "Current queue size",
-            &["service", "status"]
+        register(
+            registry,
+            GaugeVec::new(Opts::new("relayer_queue_size", "Tasks in the task store by status"), &["status"]).unwrap(),
         )
-        .unwrap()
     });
 
     BALANCE.get_or_init(|| {
-        register_gauge_vec!(
-            "relayer_balance",
-            "Relayer account balance",
-            &["service", "chain"]
+        register(
+            registry,
+            GaugeVec::new(Opts::new("relayer_balance", "Relayer account balance in native units"), &["chain"]).unwrap(),
+        )
+    });
+
+    RPC_LATENCY_SECONDS.get_or_init(|| {
+        register(
+            registry,
+            HistogramVec::new(
+                HistogramOpts::new("relayer_rpc_latency_seconds", "RPC call latency in seconds"),
+                &["chain", "method", "endpoint", "status"],
+            )
+            .unwrap(),
+        )
+    });
+
+    LAST_PROCESSED_BLOCK.get_or_init(|| {
+        register(
+            registry,
+            GaugeVec::new(
+                Opts::new("relayer_last_processed_block", "Last source block (EVM) or slot (SVM) committed by the listener"),
+                &["chain"],
+            )
+            .unwrap(),
+        )
+    });
+
+    NONCE.get_or_init(|| {
+        register(
+            registry,
+            GaugeVec::new(
+                Opts::new("relayer_nonce", "Source sender nonce and target last_nonce as seen by this process"),
+                &["side"],
+            )
+            .unwrap(),
+        )
+    });
+
+    NONCE_LAG.get_or_init(|| {
+        register(
+            registry,
+            Gauge::new("relayer_nonce_lag", "Source sender nonce minus target last_nonce").unwrap(),
+        )
+    });
+
+    GAS_SPENT.get_or_init(|| {
+        register(
+            registry,
+            CounterVec::new(Opts::new("relayer_gas_spent_total", "Transaction fees paid in native units"), &["chain"])
+                .unwrap(),
         )
-        .unwrap()
     });
+
+    REORGS_TOTAL.get_or_init(|| {
+        register(
+            registry,
+            CounterVec::new(Opts::new("relayer_reorgs_total", "Source chain reorgs detected by the listener"), &["chain"])
+                .unwrap(),
+        )
+    });
+}
+
+fn register<T: prometheus::core::Collector + Clone + 'static>(registry: &Registry, metric: T) -> T {
+    registry.register(Box::new(metric.clone())).unwrap();
+    metric
 }
 
-/// 记录事件处理
-pub fn record_event(service: &str, status: &str) {
+/// 记录事件进入某个阶段
+pub fn record_event(stage: EventStage) {
     if let Some(counter) = EVENTS_TOTAL.get() {
-        counter.with_label_values(&[service, status]).inc();
+        counter.with_label_values(&[stage.as_str()]).inc();
     }
 }
 
-/// 记录延迟
-pub fn record_latency(service: &str, duration_secs: f64) {
+/// 记录端到端延迟（事件入库到目标链确认）
+pub fn record_latency(duration_secs: f64) {
     if let Some(histogram) = LATENCY_SECONDS.get() {
-        histogram.with_label_values(&[service]).observe(duration_secs);
+        histogram.observe(duration_secs);
     }
 }
 
 /// 记录队列大小
-pub fn record_queue_size(service: &str, status: &str, size: i64) {
+pub fn record_queue_size(status: &str, size: i64) {
     if let Some(gauge) = QUEUE_SIZE.get() {
-        gauge.with_label_values(&[service, status]).set(size as f64);
+        gauge.with_label_values(&[status]).set(size as f64);
     }
 }
 
 /// 记录余额
-pub fn record_balance(service: &str, chain: &str, balance: f64) {
+pub fn record_balance(chain: &str, balance: f64) {
     if let Some(gauge) = BALANCE.get() {
-        gauge.with_label_values(&[service, chain]).set(balance);
+        gauge.with_label_values(&[chain]).set(balance);
+    }
+}
+
+/// 记录一次 RPC 调用
+pub fn record_rpc(chain: &str, method: &str, endpoint: &str, duration_secs: f64, success: bool) {
+    if let Some(histogram) = RPC_LATENCY_SECONDS.get() {
+        let status = if success { "ok" } else { "error" };
+        histogram
+            .with_label_values(&[chain, method, endpoint, status])
+            .observe(duration_secs);
+    }
+}
+
+/// 计时一次 RPC 调用（阻塞的 solana `RpcClient` 调用包在 async 块中传入）
+///
+/// 在转账的 span 内调用时同时记录一个 `rpc` 子 span；监听器轮询等不属于转账的调用不产生 span
+pub async fn observe_rpc<T, E>(
+    chain: &str,
+    method: &str,
+    endpoint: &str,
+    call: impl Future<Output = std::result::Result<T, E>>,
+) -> std::result::Result<T, E> {
+    let span = if tracing::Span::current().is_none() {
+        tracing::Span::none()
+    } else {
+        tracing::info_span!("rpc", chain, method, endpoint)
+    };
+    let start = Instant::now();
+    let result = call.instrument(span).await;
+    record_rpc(chain, method, endpoint, start.elapsed().as_secs_f64(), result.is_ok());
+    result
+}
+
+/// RPC 端点标签：只保留 host[:port]，路径和查询参数中常带 API key
+pub fn endpoint_label(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(url) => match (url.host_str(), url.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            _ => "unknown".to_string(),
+        },
+        Err(_) => "unknown".to_string(),
+    }
+}
+
+/// 记录监听器已提交的最新区块 / slot
+pub fn record_last_processed(chain: &str, block: u64) {
+    if let Some(gauge) = LAST_PROCESSED_BLOCK.get() {
+        gauge.with_label_values(&[chain]).set(block as f64);
+    }
+}
+
+/// 记录源链 sender nonce
+pub fn record_source_nonce(nonce: u64) {
+    SOURCE_NONCE.store(nonce, Ordering::Relaxed);
+    record_nonce("source", nonce);
+}
+
+/// 记录目标链 last_nonce
+pub fn record_target_nonce(nonce: u64) {
+    TARGET_NONCE.store(nonce, Ordering::Relaxed);
+    record_nonce("target", nonce);
+}
+
+/// 更新 nonce 指标；两侧都由本进程观察到时（s2e）同时更新 nonce 差值，
+/// e2s 两侧分属 listener 和 submitter，差值在 Prometheus 中按 `relayer_nonce` 计算
+fn record_nonce(side: &str, nonce: u64) {
+    if let Some(gauge) = NONCE.get() {
+        gauge.with_label_values(&[side]).set(nonce as f64);
+    }
+    if let (Some(lag), Some(gauge)) = (nonce_lag(), NONCE_LAG.get()) {
+        gauge.set(lag as f64);
+    }
+}
+
+/// 源链 sender nonce 与目标链 last_nonce 的差值；两侧未都由本进程观察到时为 None
+pub fn nonce_lag() -> Option<u64> {
+    let (source, target) = (SOURCE_NONCE.load(Ordering::Relaxed), TARGET_NONCE.load(Ordering::Relaxed));
+    (source != u64::MAX && target != u64::MAX).then(|| source.saturating_sub(target))
+}
+
+/// 记录交易手续费（原生代币单位）
+pub fn record_gas_spent(chain: &str, amount: f64) {
+    if let Some(counter) = GAS_SPENT.get() {
+        counter.with_label_values(&[chain]).inc_by(amount);
+    }
+}
+
+/// 记录一次源链回滚
+pub fn record_reorg(chain: &str) {
+    if let Some(counter) = REORGS_TOTAL.get() {
+        counter.with_label_values(&[chain]).inc();
     }
 }
 
 /// 导出指标 (Prometheus 格式)
 pub fn export_metrics() -> String {
     let encoder = TextEncoder::new();
-    let metric_families = prometheus::gather();
+    let metric_families = REGISTRY.get().map(Registry::gather).unwrap_or_default();
     let mut buffer = Vec::new();
     encoder.encode(&metric_families, &mut buffer).unwrap();
     String::from_utf8(buffer).unwrap()
 }
 
+/// 只提供 `/metrics` 的 HTTP 服务（没有 API 服务器的进程使用）
+pub async fn serve_metrics(addr: std::net::SocketAddr) -> Result<()> {
+    let app = axum::Router::new().route("/metrics", axum::routing::get(|| async { export_metrics() }));
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .map_err(|e| RelayerError::Config(format!("Failed to bind metrics server on {}: {}", addr, e)))?;
+    tracing::info!(addr = %addr, "Metrics server listening");
+    axum::serve(listener, app)
+        .await
+        .map_err(|e| RelayerError::Network(format!("Metrics server error: {}", e)))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_metrics_init() {
-        init_metrics();
-        record_event("s2e", "success");
-        record_latency("s2e", 1.5);
-        record_queue_size("s2e", "pending", 10);
-        record_balance("s2e", "svm", 10.5);
-        
+        init_metrics("s2e");
+        record_event(EventStage::Seen);
+        record_latency(1.5);
+        record_queue_size("pending", 10);
+        record_balance("svm", 10.5);
+        record_rpc("evm", "eth_getLogs", "rpc.example.com", 0.2, true);
+        record_last_processed("evm", 1234);
+        record_source_nonce(10);
+        record_target_nonce(7);
+        record_gas_spent("evm", 0.001);
+
         let metrics = export_metrics();
-        assert!(metrics.contains("relayer_events_total"));
+        // 其他测试也会记录事件，这里只检查标签
+        assert!(metrics.contains(r#"relayer_events_total{stage="seen",direction="s2e"}"#));
         assert!(metrics.contains("relayer_latency_seconds"));
         assert!(metrics.contains("relayer_queue_size"));
         assert!(metrics.contains("relayer_balance"));
+        assert!(metrics.contains(r#"endpoint="rpc.example.com""#));
+        assert!(metrics.contains(r#"relayer_last_processed_block{chain="evm",direction="s2e"} 1234"#));
+        assert!(metrics.contains(r#"relayer_nonce_lag{direction="s2e"} 3"#));
+        assert!(metrics.contains("relayer_gas_spent_total"));
     }
-}
 
+    #[test]
+    fn test_endpoint_label_strips_path() {
+        assert_eq!(endpoint_label("https://arb-sepolia.g.alchemy.com/v2/SECRET_KEY"), "arb-sepolia.g.alchemy.com");
+        assert_eq!(endpoint_label("http://127.0.0.1:8899"), "127.0.0.1:8899");
+        assert_eq!(endpoint_label("not a url"), "unknown");
+    }
+}
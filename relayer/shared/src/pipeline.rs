@@ -0,0 +1,1425 @@
+//! 链无关的中继流水线
+//!
+//! 每个方向都由三个组件组合而成：
+//! - [`SourceListener`]：从源链拉取 `StakeEventData`，附带最终性信息
+//! - [`AttestationSigner`]：对事件生成证明签名（见 `signer` 模块）
+//! - [`TargetSubmitter`]：把事件和证明提交到目标链
+//!
+//! | 方向 | SourceListener | AttestationSigner | TargetSubmitter |
+//! |------|----------------|-------------------|-----------------|
+//! | s2e  | `SvmListener` (s2e) | secp256k1 签名器 | `EvmSubmitter` (s2e) |
+//! | e2s  | `EvmListener` (e2s-listener) | Ed25519 签名器 | `SvmSubmitter` (e2s-submitter) |
+//!
+//! EVM / SVM 适配器分别依赖 ethers 和 solana-sdk，两者的依赖树无法共存于同一个 crate，
+//! 因此适配器放在各服务 crate 中；接入新链只需要实现对应的 trait。
+//!
+//! 监听器产生的事件写入 [`EventSink`]（同进程的 `TaskStore`，或跨进程的 `WorkQueue`），
+//! [`Relay`] 再从中取出任务完成签名和提交。
+
+use crate::address::ChainAddress;
+use crate::alerts::{self, Alert, Severity, StallDetector};
+use crate::audit::AuditLog;
+use crate::config::{Config, QueueConfig};
+use crate::error::{RelayerError, Result};
+use crate::gas::BalanceStatus;
+use crate::lifecycle::Shutdown;
+use crate::metrics::{self, EventStage};
+use crate::queue::{QueueMessage, WorkQueue};
+use crate::risk::{RiskDecision, RiskEngine};
+use crate::screening::Screener;
+use crate::signer::{AttestationSigner, SignatureScheme};
+use crate::store::TaskStore;
+use crate::telemetry::{self, TraceContext};
+use crate::types::{SourceRef, StakeEventData, Task, TaskStatus};
+use async_trait::async_trait;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, error, info, info_span, warn, Instrument};
+
+/// 事件的最终性信息
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Finality {
+    /// 事件所在区块号（EVM）或 slot（SVM）
+    pub block: u64,
+    /// 观察到事件时的确认数（链头 - 事件区块）
+    pub confirmations: u64,
+    /// 源链已将该区块标记为 finalized
+    pub finalized: bool,
+    /// 尚未达到配置要求的最终性（`commitment` / `confirmation_blocks`），只登记任务，不签名
+    pub awaiting: bool,
+}
+
+/// 源链上观察到的一个事件
+#[derive(Debug, Clone)]
+pub struct SourceEvent {
+    pub event: StakeEventData,
+    /// 源链交易哈希（EVM）或交易签名（SVM）
+    pub tx_id: String,
+    /// EVM 日志在区块中的序号
+    pub log_index: Option<u64>,
+    /// 源链交易发起者（用于合规筛查），未知时为 None
+    pub sender: Option<ChainAddress>,
+    pub finality: Finality,
+    /// 事件字段不合法（如接收地址校验失败）时的拒绝原因：登记为 blocked 任务并告警，不签名。
+    /// 不合法的字段在 `event` 中以全零地址占位
+    pub rejected: Option<String>,
+}
+
+impl SourceEvent {
+    /// 事件在源链上的位置
+    pub fn source_ref(&self) -> SourceRef {
+        SourceRef {
+            tx: self.tx_id.clone(),
+            log_index: self.log_index,
+            block: self.finality.block,
+            sender: self.sender,
+        }
+    }
+}
+
+/// 源链事件监听器
+#[async_trait]
+pub trait SourceListener: Send + Sync {
+    /// 源链名称（用于日志和指标标签）
+    fn chain(&self) -> &str;
+
+    /// 拉取自上次 `commit` 以来的新事件
+    async fn poll(&mut self) -> Result<Vec<SourceEvent>>;
+
+    /// 上一次 `poll` 返回的事件已全部写入下游，推进监听游标
+    fn commit(&mut self);
+
+    /// 检测到源链回滚后、重新扫描时不再出现的事件，`commit` 后清空
+    fn retracted(&self) -> &[SourceEvent] {
+        &[]
+    }
+
+    /// 已提交的最新区块（EVM）或 slot（SVM）
+    fn cursor(&self) -> u64;
+
+    /// 源链 sender 当前 nonce（用于 nonce 差值指标），不支持时返回 None
+    async fn sender_nonce(&self) -> Result<Option<u64>> {
+        Ok(None)
+    }
+
+    /// 热加载时切换源链 RPC 端点（地址不变时不做任何事）
+    fn set_rpc_url(&mut self, _url: &str) -> Result<()> {
+        Err(RelayerError::Config(format!("{} listener does not support RPC endpoint reload", self.chain())))
+    }
+}
+
+/// 目标链提交器
+#[async_trait]
+pub trait TargetSubmitter: Send + Sync {
+    /// 目标链要求的证明签名算法
+    fn scheme(&self) -> SignatureScheme;
+
+    /// 提交事件及其证明签名，返回目标链交易哈希
+    async fn submit(&self, event: &StakeEventData, attestation: &[u8]) -> Result<String>;
+
+    /// 提交失败后任务是否值得重试（默认：合约明确拒绝的永久错误不重试）
+    fn is_retryable(&self, error: &RelayerError) -> bool {
+        !error.is_permanent()
+    }
+
+    /// 目标链已处理的最新 nonce（用于 nonce 差值指标），不支持时返回 None
+    async fn last_nonce(&self) -> Result<Option<u64>> {
+        Ok(None)
+    }
+
+    /// 热加载时切换目标链 RPC 端点（地址不变时不做任何事），进行中的提交继续使用原端点
+    fn set_rpc_url(&self, _url: &str) -> Result<()> {
+        Err(RelayerError::Config("Target submitter does not support RPC endpoint reload".to_string()))
+    }
+}
+
+/// 监听器事件的去向
+#[async_trait]
+pub trait EventSink: Send + Sync {
+    /// 写入事件及其源链位置；`awaiting` 的事件只登记为等待最终性的任务。重复事件返回 false
+    async fn accept(&self, event: StakeEventData, source: SourceRef, awaiting: bool) -> Result<bool>;
+
+    /// 撤回被源链回滚的事件；没有可撤回的任务时返回 false
+    async fn retract(&self, event: StakeEventData, source: SourceRef) -> Result<bool>;
+
+    /// 登记入口校验失败的事件（blocked 任务，不签名）。重复事件返回 false
+    async fn reject(&self, event: StakeEventData, source: SourceRef, reason: &str) -> Result<bool>;
+}
+
+#[async_trait]
+impl EventSink for dyn TaskStore {
+    async fn accept(&self, event: StakeEventData, source: SourceRef, awaiting: bool) -> Result<bool> {
+        let mut task = Task::new(event);
+        task.source = Some(source);
+        if awaiting {
+            task.await_finality()?;
+        }
+        if self.insert(&task).await? {
+            return Ok(true);
+        }
+        // 之前登记为等待最终性或已撤回的任务
+        let Some(mut existing) = self.get(&task.id).await? else {
+            return Ok(false);
+        };
+        if !reobserve(&mut existing, awaiting)? {
+            return Ok(false);
+        }
+        existing.source = task.source;
+        self.update(&existing).await?;
+        Ok(true)
+    }
+
+    async fn retract(&self, event: StakeEventData, source: SourceRef) -> Result<bool> {
+        retract_task(self, event, Some(source), chrono::Utc::now()).await
+    }
+
+    async fn reject(&self, event: StakeEventData, source: SourceRef, reason: &str) -> Result<bool> {
+        let mut task = Task::new(event);
+        task.source = Some(source);
+        task.block(reason)?;
+        self.insert(&task).await
+    }
+}
+
+#[async_trait]
+impl EventSink for dyn WorkQueue {
+    async fn accept(&self, event: StakeEventData, source: SourceRef, awaiting: bool) -> Result<bool> {
+        // 队列消息以任务 ID 去重，重复投递由 submitter 丢弃
+        let mut message = QueueMessage::new(event);
+        message.source = Some(source);
+        message.awaiting_finality = awaiting;
+        self.push(&message).await?;
+        Ok(true)
+    }
+
+    async fn retract(&self, event: StakeEventData, source: SourceRef) -> Result<bool> {
+        let mut message = QueueMessage::new(event);
+        message.source = Some(source);
+        message.retracted = true;
+        self.push(&message).await?;
+        Ok(true)
+    }
+
+    async fn reject(&self, event: StakeEventData, source: SourceRef, reason: &str) -> Result<bool> {
+        let mut message = QueueMessage::new(event);
+        message.source = Some(source);
+        message.rejected = Some(reason.to_string());
+        self.push(&message).await?;
+        Ok(true)
+    }
+}
+
+/// 尚未达到最终性的事件只转发一次（登记为等待最终性的任务），达到最终性后再转发
+///
+/// 监听器每轮都会重新扫描最终性前沿之后的区块，用它避免重复写入和重复日志
+#[derive(Debug, Default)]
+pub struct AwaitingEvents {
+    announced: HashSet<String>,
+}
+
+impl AwaitingEvents {
+    /// 事件被撤回后重新出现时需要再次登记
+    pub fn forget(&mut self, event: &StakeEventData) {
+        self.announced.remove(&Task::id_for(event));
+    }
+
+    /// 去掉已转发过的未最终事件
+    pub fn filter(&mut self, events: Vec<SourceEvent>) -> Vec<SourceEvent> {
+        // 回滚掉的事件不会再出现，定期清空避免无限增长（清空后最多重复登记一次）
+        if self.announced.len() > 10_000 {
+            self.announced.clear();
+        }
+        events
+            .into_iter()
+            .filter(|source_event| {
+                let id = Task::id_for(&source_event.event);
+                if source_event.finality.awaiting {
+                    self.announced.insert(id)
+                } else {
+                    self.announced.remove(&id);
+                    true
+                }
+            })
+            .collect()
+    }
+}
+
+/// 拉取一批事件写入 `sink`，全部写入成功后才推进监听游标，返回新事件数
+///
+/// 每个事件在以转账 ID 为根的 `capture` span 中写入，任务和队列消息从该 span 获取追踪上下文。
+/// 被源链回滚的事件先于新事件撤回，同一轮重新出现的事件随后恢复。
+/// 入口校验失败的事件达到最终性后登记为 blocked 任务，并发送 `event-rejected:<转账 ID>` critical 告警
+pub async fn forward_events<S: EventSink + ?Sized>(source: &mut dyn SourceListener, sink: &S) -> Result<usize> {
+    let events = source.poll().await?;
+    let mut accepted = 0;
+
+    let retracted = source.retracted().to_vec();
+    let mut withdrawn = 0;
+    for source_event in &retracted {
+        let transfer_id = telemetry::transfer_id(&source_event.event);
+        warn!(
+            chain = source.chain(),
+            transfer = %transfer_id,
+            tx = %source_event.tx_id,
+            block = source_event.finality.block,
+            nonce = source_event.event.nonce,
+            "Event reorged out of source chain, retracting"
+        );
+        if sink.retract(source_event.event.clone(), source_event.source_ref()).await? {
+            metrics::record_event(EventStage::Retracted);
+            withdrawn += 1;
+        }
+    }
+    if withdrawn > 0 {
+        let nonces: Vec<String> = retracted.iter().map(|e| e.event.nonce.to_string()).collect();
+        alerts::notify(Alert::new(
+            Severity::Warning,
+            format!("reorg-retracted:{}", source.chain()),
+            "Source events retracted by reorg",
+            format!("{} {} listener retracted events with nonces {}", withdrawn, source.chain(), nonces.join(", ")),
+        ));
+    }
+
+    for source_event in events {
+        let source_ref = source_event.source_ref();
+        let SourceEvent { event, tx_id, finality, rejected, .. } = source_event;
+        let transfer_id = telemetry::transfer_id(&event);
+        if let Some(reason) = rejected {
+            // 未达到最终性的事件可能被回滚，最终后再登记
+            if finality.awaiting {
+                continue;
+            }
+            error!(chain = source.chain(), transfer = %transfer_id, tx = %tx_id, nonce = event.nonce, reason = %reason, "Rejecting malformed StakeEvent");
+            alerts::notify(Alert::new(
+                Severity::Critical,
+                format!("event-rejected:{}", transfer_id),
+                "Malformed stake event rejected",
+                format!(
+                    "{} event nonce {} amount {} in tx {} will not be relayed: {}",
+                    source.chain(),
+                    event.nonce,
+                    event.amount,
+                    tx_id,
+                    reason
+                ),
+            ));
+            if sink.reject(event, source_ref, &reason).await? {
+                accepted += 1;
+            }
+            continue;
+        }
+        let span = info_span!(
+            "capture",
+            transfer_id = %transfer_id,
+            chain = source.chain(),
+            tx = %tx_id,
+            block = finality.block,
+            nonce = event.nonce,
+        );
+        telemetry::attach(&span, &transfer_id, &TraceContext::new());
+
+        let chain = source.chain();
+        let queued = async {
+            info!(
+                chain,
+                tx = %tx_id,
+                block = finality.block,
+                confirmations = finality.confirmations,
+                finalized = finality.finalized,
+                awaiting_finality = finality.awaiting,
+                nonce = event.nonce,
+                amount = event.amount,
+                receiver = %event.receiver_address,
+                "📥 Captured StakeEvent"
+            );
+            // 等待最终性的事件达到最终性后再计数一次
+            if !finality.awaiting {
+                metrics::record_event(EventStage::Seen);
+            }
+            sink.accept(event, source_ref, finality.awaiting).await
+        }
+        .instrument(span)
+        .await;
+
+        // 写入失败时不推进游标，下次重新拉取同一范围
+        if queued? {
+            if finality.awaiting {
+                info!(transfer = %transfer_id, "Event awaiting finality");
+            } else {
+                info!(transfer = %transfer_id, "Event queued");
+            }
+            accepted += 1;
+        } else {
+            debug!(transfer = %transfer_id, "Event already queued");
+        }
+    }
+
+    source.commit();
+    metrics::record_last_processed(source.chain(), source.cursor());
+
+    // nonce 指标只用于观测，查询失败不影响事件转发
+    match source.sender_nonce().await {
+        Ok(Some(nonce)) => metrics::record_source_nonce(nonce),
+        Ok(None) => {}
+        Err(e) => debug!(chain = source.chain(), error = %e, "Failed to query sender nonce"),
+    }
+    Ok(accepted)
+}
+
+/// 检查监听游标是否推进，停滞超过 `alerts.stall_timeout` 时发送 critical 告警
+pub fn watch_progress(source: &dyn SourceListener, detector: &mut StallDetector) {
+    let key = format!("listener-stalled:{}", source.chain());
+    match detector.observe(source.cursor(), true) {
+        Some(stalled) => alerts::notify(Alert::new(
+            Severity::Critical,
+            key,
+            "Listener stopped advancing",
+            format!(
+                "{} listener cursor stuck at {} for {}s",
+                source.chain(),
+                source.cursor(),
+                stalled.as_secs()
+            ),
+        )),
+        None => alerts::resolve(&key, format!("{} listener advanced to {}", source.chain(), source.cursor())),
+    }
+}
+
+/// 连续多少个任务永久失败后告警
+const PERMANENT_FAILURE_ALERT_THRESHOLD: u32 = 3;
+
+/// 签名器 + 目标链提交器
+pub struct Relay {
+    signer: Arc<dyn AttestationSigner>,
+    submitter: Arc<dyn TargetSubmitter>,
+    /// 提交账户余额状态，欠费时暂停处理任务
+    balance: Option<BalanceStatus>,
+    /// 签名审计日志
+    audit: Option<Arc<AuditLog>>,
+    /// 签名前风控
+    risk: Option<Arc<RiskEngine>>,
+    /// 拒绝名单筛查
+    screening: Option<Arc<Screener>>,
+    /// 收到退出信号后不再开始新任务
+    shutdown: Option<Shutdown>,
+    /// 连续永久失败的任务数
+    permanent_failures: AtomicU32,
+    /// 有待处理任务时目标链 nonce 的停滞检测
+    nonce_watch: Mutex<StallDetector>,
+}
+
+impl Relay {
+    pub fn new(signer: Arc<dyn AttestationSigner>, submitter: Arc<dyn TargetSubmitter>) -> Result<Self> {
+        if signer.scheme() != submitter.scheme() {
+            return Err(RelayerError::Config(format!(
+                "Signer scheme {:?} does not match target chain scheme {:?}",
+                signer.scheme(),
+                submitter.scheme()
+            )));
+        }
+        Ok(Self {
+            signer,
+            submitter,
+            balance: None,
+            audit: None,
+            risk: None,
+            screening: None,
+            shutdown: None,
+            permanent_failures: AtomicU32::new(0),
+            nonce_watch: Mutex::new(StallDetector::new(alerts::stall_timeout())),
+        })
+    }
+
+    /// 提交账户余额低于下限时暂停处理，任务留在存储 / 队列中，不消耗重试次数
+    pub fn with_balance_status(mut self, status: BalanceStatus) -> Self {
+        self.balance = Some(status);
+        self
+    }
+
+    /// 每次签名和提交结果写入审计日志；签名记录写入失败时不提交
+    pub fn with_audit_log(mut self, audit: Arc<AuditLog>) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    /// 签名前执行风控，未通过的任务进入 Held 等待操作员批准
+    pub fn with_risk_engine(mut self, risk: Arc<RiskEngine>) -> Self {
+        self.risk = Some(risk);
+        self
+    }
+
+    /// 签名前按拒绝名单筛查接收地址和发起者，命中的任务进入 Blocked，不签名
+    pub fn with_screening(mut self, screening: Arc<Screener>) -> Self {
+        self.screening = Some(screening);
+        self
+    }
+
+    /// 收到退出信号后不再开始新任务，进行中的任务照常完成
+    pub fn with_shutdown(mut self, shutdown: Shutdown) -> Self {
+        self.shutdown = Some(shutdown);
+        self
+    }
+
+    /// 应用热加载的配置：风控上限、拒绝名单和目标链 RPC 端点（重试间隔由调用方每轮传入）
+    pub fn reload(&self, config: &Config) {
+        if let Some(risk) = &self.risk {
+            risk.set_config(config.risk.clone());
+        }
+        if let Some(screening) = &self.screening {
+            let interval = Duration::from_millis(config.screening.reload_interval);
+            if let Err(e) = screening.configure(config.screening.lists.clone(), interval) {
+                error!(error = %e, "Failed to load reloaded deny lists, keeping previous lists");
+            }
+        }
+        if let Err(e) = self.submitter.set_rpc_url(&config.target_chain.rpc_url) {
+            error!(error = %e, "Failed to switch target RPC endpoint");
+        }
+    }
+
+    fn stopping(&self) -> bool {
+        self.shutdown.as_ref().is_some_and(Shutdown::is_requested)
+    }
+
+    fn paused(&self) -> bool {
+        let paused = self.balance.as_ref().is_some_and(BalanceStatus::is_underfunded);
+        if paused {
+            debug!("Relayer underfunded, skipping submissions");
+        }
+        paused
+    }
+
+    pub fn signer(&self) -> &dyn AttestationSigner {
+        self.signer.as_ref()
+    }
+
+    /// 对任务事件签名并提交，返回目标链交易哈希
+    pub async fn relay(&self, task: &mut Task) -> Result<String> {
+        let event = task.event_data.clone();
+        info!(nonce = event.nonce, retries = task.retries, "🔄 Processing event");
+
+        let signature = self.signer.sign_event(&event).instrument(info_span!("sign")).await?;
+        task.signature = Some(hex::encode(&signature));
+        metrics::record_event(EventStage::Signed);
+        info!(nonce = event.nonce, "✍️  Generated signature");
+        if let Some(audit) = &self.audit {
+            audit.record_signed(task, self.signer.scheme(), &self.signer.identity(), &signature)?;
+        }
+
+        let result = self
+            .submitter
+            .submit(&event, &signature)
+            .instrument(info_span!("submit"))
+            .await;
+        if let Some(audit) = &self.audit {
+            // 交易可能已上链，审计写入失败不影响任务结果
+            let recorded = match &result {
+                Ok(tx_hash) => audit.record_submitted(&task.id, tx_hash),
+                Err(e) => audit.record_failed(&task.id, &e.to_string()),
+            };
+            if let Err(e) = recorded {
+                error!(task = %task.id, error = %e, "Failed to write audit log");
+            }
+        }
+        let tx_hash = result?;
+        metrics::record_event(EventStage::Submitted);
+        info!(nonce = event.nonce, tx = %tx_hash, "✅ Submitted to target chain");
+        Ok(tx_hash)
+    }
+
+    /// 执行一次任务并记录结果（Completed，或按重试策略回到 Pending / 标记 Failed）
+    ///
+    /// 每次执行是任务追踪上下文下的一个 `relay` span
+    async fn run(&self, task: &mut Task, store: &dyn TaskStore, config: &QueueConfig) -> Result<()> {
+        let span = info_span!(
+            "relay",
+            transfer_id = %task.id,
+            nonce = task.nonce,
+            retries = task.retries,
+            otel.status_code = tracing::field::Empty,
+        );
+        telemetry::attach(&span, &task.id, &task.trace_context);
+        self.run_in_span(task, store, config).instrument(span).await
+    }
+
+    async fn run_in_span(&self, task: &mut Task, store: &dyn TaskStore, config: &QueueConfig) -> Result<()> {
+        // 合规筛查先于风控，操作员批准暂扣也不能绕过
+        if let Some(hit) = self.screening.as_ref().and_then(|screening| screening.check(task)) {
+            warn!(task = %task.id, nonce = task.nonce, party = %hit.party, address = %hit.address, list = %hit.entry.list, "⛔ Transfer blocked by deny list");
+            task.block(&hit)?;
+            if let Some(audit) = &self.audit {
+                if let Err(e) = audit.record_blocked(task, &hit) {
+                    error!(task = %task.id, error = %e, "Failed to write audit log");
+                }
+            }
+            alerts::notify(Alert::new(
+                Severity::Critical,
+                format!("screening-block:{}", task.id),
+                "Transfer blocked by deny list",
+                format!("Task {} nonce {} amount {}: {}", task.id, task.nonce, task.event_data.amount, hit),
+            ));
+            return claim(task, store).await.map(|_| ());
+        }
+
+        let decision = match &self.risk {
+            Some(risk) => risk.evaluate(task, store).await?,
+            None => RiskDecision::Allow,
+        };
+        if let RiskDecision::Hold(reason) = decision {
+            warn!(task = %task.id, nonce = task.nonce, amount = task.event_data.amount, reason = %reason, "⏸️  Transfer held by risk engine");
+            task.hold(&reason)?;
+            alerts::notify(Alert::new(
+                Severity::Warning,
+                format!("risk-hold:{}", task.id),
+                "Transfer held for operator approval",
+                format!("Task {} nonce {} amount {}: {}", task.id, task.nonce, task.event_data.amount, reason),
+            ));
+            return claim(task, store).await.map(|_| ());
+        }
+
+        task.start()?;
+        if !claim(task, store).await? {
+            return Ok(());
+        }
+
+        match self.relay(task).await {
+            Ok(tx_hash) => {
+                task.complete(tx_hash)?;
+                let latency = task.updated_at - task.created_at;
+                metrics::record_latency(latency.num_milliseconds() as f64 / 1000.0);
+                if self.permanent_failures.swap(0, Ordering::Relaxed) > 0 {
+                    alerts::resolve("permanent-failures", format!("Task {} completed", task.id));
+                }
+            }
+            Err(e) => {
+                tracing::Span::current().record("otel.status_code", "ERROR");
+                metrics::record_event(EventStage::Failed);
+                let retryable = self.submitter.is_retryable(&e);
+                if retryable {
+                    error!(task = %task.id, nonce = task.nonce, retries = task.retries, error = %e, "Retryable error, keeping task for retry");
+                } else {
+                    warn!(task = %task.id, nonce = task.nonce, error = %e, "Non-retryable error, marking task failed");
+                    let failures = self.permanent_failures.fetch_add(1, Ordering::Relaxed) + 1;
+                    if failures >= PERMANENT_FAILURE_ALERT_THRESHOLD {
+                        alerts::notify(Alert::new(
+                            Severity::Critical,
+                            "permanent-failures",
+                            "Repeated non-retryable submission errors",
+                            format!(
+                                "{} consecutive tasks failed permanently, last: task {} nonce {}: {}",
+                                failures, task.id, task.nonce, e
+                            ),
+                        ));
+                    }
+                }
+                task.record_failure(&e, retryable, config.retry_limit)?;
+            }
+        }
+
+        store.update(task).await
+    }
+
+    /// 处理任务存储中所有到期的 Pending 任务（同进程监听 + 提交），返回成功数
+    pub async fn process_pending(&self, store: &dyn TaskStore, config: &QueueConfig) -> Result<usize> {
+        if self.paused() {
+            self.record_status(store).await;
+            return Ok(0);
+        }
+        let mut completed = 0;
+        recover_stale(store, config).await?;
+        let mut lanes = OpenLanes::load(store).await?;
+        for mut task in store.due(&config.retry_delays, config.max_size).await? {
+            if self.stopping() {
+                break;
+            }
+            if lanes.blocks(&task) {
+                debug!(task = %task.id, nonce = task.nonce, "Lower nonce still open in the same lane, keeping task pending");
+                continue;
+            }
+            self.run(&mut task, store, config).await?;
+            lanes.record(&task);
+            if task.status == TaskStatus::Completed {
+                completed += 1;
+            }
+        }
+        self.record_status(store).await;
+        Ok(completed)
+    }
+
+    /// 消费工作队列（监听器在其他进程），返回成功数
+    pub async fn process_queue(&self, store: &dyn TaskStore, queue: &dyn WorkQueue, config: &QueueConfig) -> Result<usize> {
+        if self.paused() {
+            self.record_status(store).await;
+            return Ok(0);
+        }
+        let mut completed = 0;
+        let visibility = Duration::from_millis(config.visibility_timeout);
+        recover_stale(store, config).await?;
+        let mut lanes = OpenLanes::load(store).await?;
+        // 本轮因通道阻塞推迟的消息，再次取到时说明已轮过一遍
+        let mut deferred = HashSet::new();
+
+        for _ in 0..config.max_size {
+            if self.stopping() {
+                break;
+            }
+            let Some(delivery) = queue.pop(visibility).await? else {
+                break;
+            };
+            if deferred.contains(&delivery.message.id) {
+                let error = delivery.message.last_error.clone().unwrap_or_default();
+                queue.nack(&delivery, &error, Duration::ZERO).await?;
+                break;
+            }
+            if delivery.message.retracted {
+                let message = &delivery.message;
+                retract_task(store, message.event.clone(), message.source.clone(), message.enqueued_at).await?;
+                queue.ack(&delivery).await?;
+                continue;
+            }
+            let mut task = load_task(store, &delivery.message).await?;
+            lanes.record(&task);
+
+            match task.status {
+                TaskStatus::Completed
+                | TaskStatus::Failed
+                | TaskStatus::Held
+                | TaskStatus::Blocked
+                | TaskStatus::Retracted => {
+                    // 重复投递，任务已有最终结果；暂扣的任务批准后由管理 API 重新投递
+                    debug!(task = %task.id, status = %task.status, "Task already finished or held, dropping message");
+                    queue.ack(&delivery).await?;
+                    continue;
+                }
+                TaskStatus::Processing => {
+                    // 其他 submitter 正在处理；超过可见性超时仍未结束的，下一轮由 recover_stale 放回 Pending
+                    debug!(task = %task.id, "Task is being processed elsewhere");
+                    continue;
+                }
+                TaskStatus::AwaitingFinality => {
+                    // 监听器在事件达到最终性后重新投递
+                    debug!(task = %task.id, "Task awaiting source finality");
+                    queue.ack(&delivery).await?;
+                    continue;
+                }
+                TaskStatus::Pending if lanes.blocks(&task) => {
+                    // 同一通道的低 nonce 尚未完成（等待重试、处理中、等待最终性或暂扣），之前不提交更高的 nonce
+                    debug!(task = %task.id, nonce = task.nonce, "Lower nonce still open in the same lane, delaying message");
+                    let delay = Duration::from_millis(task.retry_delay(&config.retry_delays));
+                    queue.nack(&delivery, "Lower nonce still open in the same lane", delay).await?;
+                    deferred.insert(task.id);
+                    continue;
+                }
+                TaskStatus::Pending => {}
+            }
+
+            self.run(&mut task, store, config).await?;
+            lanes.record(&task);
+            match task.status {
+                TaskStatus::Completed => {
+                    queue.ack(&delivery).await?;
+                    completed += 1;
+                }
+                TaskStatus::Held | TaskStatus::Blocked => queue.ack(&delivery).await?,
+                // 其他实例抢先认领，保留消息等待其确认或可见性超时
+                TaskStatus::Processing => {}
+                TaskStatus::Pending => {
+                    let error = task.error_message.clone().unwrap_or_default();
+                    let delay = Duration::from_millis(task.retry_delay(&config.retry_delays));
+                    queue.nack(&delivery, &error, delay).await?;
+                }
+                _ => {
+                    let error = task.error_message.clone().unwrap_or_default();
+                    queue.dead_letter(&delivery, &error).await?;
+                }
+            }
+        }
+
+        self.record_status(store).await;
+        Ok(completed)
+    }
+
+    /// 更新任务数量和目标链 nonce 指标（只用于观测，失败时忽略）
+    ///
+    /// 有待处理任务（或源链 nonce 领先）而目标链 nonce 长时间不变时告警
+    async fn record_status(&self, store: &dyn TaskStore) {
+        let mut pending = 0;
+        if let Ok(counts) = store.counts().await {
+            for (status, count) in counts {
+                metrics::record_queue_size(&status.to_string(), count as i64);
+                if matches!(status, TaskStatus::Pending | TaskStatus::Processing) {
+                    pending += count;
+                }
+            }
+        }
+        match self.submitter.last_nonce().await {
+            Ok(Some(nonce)) => {
+                metrics::record_target_nonce(nonce);
+                self.watch_nonce(nonce, pending);
+            }
+            Ok(None) => {}
+            Err(e) => debug!(error = %e, "Failed to query target last_nonce"),
+        }
+    }
+
+    fn watch_nonce(&self, nonce: u64, pending: u64) {
+        let lag = metrics::nonce_lag().unwrap_or(0);
+        let waiting = pending > 0 || lag > 0;
+        match self.nonce_watch.lock().unwrap().observe(nonce, waiting) {
+            Some(stalled) => alerts::notify(Alert::new(
+                Severity::Critical,
+                "nonce-stalled",
+                "Target nonce not advancing",
+                format!(
+                    "Target last_nonce stuck at {} for {}s with {} pending tasks (source lag {})",
+                    nonce,
+                    stalled.as_secs(),
+                    pending,
+                    lag
+                ),
+            )),
+            None => alerts::resolve("nonce-stalled", format!("Target last_nonce at {}", nonce)),
+        }
+    }
+}
+
+/// 把停留在 Processing 超过可见性超时的任务放回 Pending
+///
+/// 多个实例共享任务存储时，其他实例正在处理的任务更新时间较新，不会被抢走；
+/// 崩溃实例遗留的任务超时后由任意实例接手。
+async fn recover_stale(store: &dyn TaskStore, config: &QueueConfig) -> Result<()> {
+    let recovered = store.recover(Duration::from_millis(config.visibility_timeout)).await?;
+    if recovered > 0 {
+        warn!(count = recovered, "Recovered interrupted tasks");
+    }
+    Ok(())
+}
+
+/// 每个通道（源链 + 源合约）中尚未结束的任务 nonce
+///
+/// 目标合约要求 nonce 递增，更高的 nonce 先上链后，低 nonce 的重试或批准都会被永久拒绝。
+/// 因此同一通道中还有更低的 nonce 处于 Pending（包括等待重试）、Processing、AwaitingFinality 或 Held 时，
+/// 后续任务保持 Pending，直到低 nonce 完成或最终失败
+struct OpenLanes(HashMap<(u64, ChainAddress), BTreeSet<u64>>);
+
+impl OpenLanes {
+    async fn load(store: &dyn TaskStore) -> Result<Self> {
+        let mut lanes = Self(HashMap::new());
+        for status in [
+            TaskStatus::Pending,
+            TaskStatus::Processing,
+            TaskStatus::AwaitingFinality,
+            TaskStatus::Held,
+        ] {
+            for task in store.list(Some(status), usize::MAX).await? {
+                lanes.record(&task);
+            }
+        }
+        Ok(lanes)
+    }
+
+    fn lane(task: &Task) -> (u64, ChainAddress) {
+        (task.event_data.source_chain_id, task.event_data.source_contract)
+    }
+
+    /// 按任务当前状态登记或移除其 nonce
+    fn record(&mut self, task: &Task) {
+        let nonces = self.0.entry(Self::lane(task)).or_default();
+        match task.status {
+            TaskStatus::Pending | TaskStatus::Processing | TaskStatus::AwaitingFinality | TaskStatus::Held => {
+                nonces.insert(task.nonce);
+            }
+            TaskStatus::Completed | TaskStatus::Failed | TaskStatus::Blocked | TaskStatus::Retracted => {
+                nonces.remove(&task.nonce);
+            }
+        }
+    }
+
+    fn blocks(&self, task: &Task) -> bool {
+        self.0
+            .get(&Self::lane(task))
+            .and_then(|nonces| nonces.first())
+            .is_some_and(|&lowest| lowest < task.nonce)
+    }
+}
+
+/// 事件再次被监听器观察到：已撤回的任务恢复，达到最终性时等待最终性的任务转为 Pending。状态有变化时返回 true
+fn reobserve(task: &mut Task, awaiting: bool) -> Result<bool> {
+    match task.status {
+        TaskStatus::Retracted => task.reinstate(awaiting)?,
+        TaskStatus::AwaitingFinality if !awaiting => task.finalize()?,
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// 撤回被源链回滚的事件对应的任务
+///
+/// `retracted_at` 为监听器发现回滚的时间，之前入队的原始消息不会恢复任务；任务尚不存在时登记为已撤回。
+/// 已签名的任务无法撤回，发送 critical 告警
+async fn retract_task(
+    store: &dyn TaskStore,
+    event: StakeEventData,
+    source: Option<SourceRef>,
+    retracted_at: chrono::DateTime<chrono::Utc>,
+) -> Result<bool> {
+    let reason = match &source {
+        Some(source) => format!("Source event reorged out (tx {} block {})", source.tx, source.block),
+        None => "Source event reorged out".to_string(),
+    };
+    let Some(mut task) = store.get(&Task::id_for(&event)).await? else {
+        let mut task = Task::new(event);
+        task.source = source;
+        task.retract(reason)?;
+        task.updated_at = retracted_at;
+        return store.insert(&task).await;
+    };
+    match task.status {
+        TaskStatus::Pending | TaskStatus::AwaitingFinality | TaskStatus::Held => {
+            task.retract(reason)?;
+            task.updated_at = retracted_at;
+            store.update(&task).await?;
+            info!(task = %task.id, nonce = task.nonce, "Task retracted");
+            Ok(true)
+        }
+        TaskStatus::Processing | TaskStatus::Completed => {
+            error!(task = %task.id, nonce = task.nonce, status = %task.status, "Reorged-out event was already signed");
+            alerts::notify(Alert::new(
+                Severity::Critical,
+                format!("reorg-signed:{}", task.id),
+                "Reorged-out transfer already signed",
+                format!(
+                    "Task {} nonce {} amount {} is {} but its source event is no longer on the canonical chain: {}",
+                    task.id, task.nonce, task.event_data.amount, task.status, reason
+                ),
+            ));
+            Ok(false)
+        }
+        TaskStatus::Failed | TaskStatus::Blocked | TaskStatus::Retracted => Ok(false),
+    }
+}
+
+/// 保存任务离开 Pending 的状态迁移；其他实例已抢先迁移时改为读取存储中的最新状态，返回 false
+async fn claim(task: &mut Task, store: &dyn TaskStore) -> Result<bool> {
+    if store.update_if(task, TaskStatus::Pending).await? {
+        return Ok(true);
+    }
+    debug!(task = %task.id, "Task already claimed by another instance");
+    if let Some(current) = store.get(&task.id).await? {
+        *task = current;
+    }
+    Ok(false)
+}
+
+/// 读取或创建队列消息对应的任务；事件再次出现或达到最终性时更新已有任务
+async fn load_task(store: &dyn TaskStore, message: &QueueMessage) -> Result<Task> {
+    if let Some(mut task) = store.get(&message.id).await? {
+        // 撤回之前入队的消息已过时，不恢复任务
+        let stale = task.status == TaskStatus::Retracted && message.enqueued_at <= task.updated_at;
+        if !stale && reobserve(&mut task, message.awaiting_finality)? {
+            task.source = message.source.clone().or(task.source);
+            store.update(&task).await?;
+        }
+        return Ok(task);
+    }
+    let mut task = Task::new(message.event.clone());
+    task.trace_context = message.trace_context.clone();
+    task.source = message.source.clone();
+    if let Some(reason) = &message.rejected {
+        task.block(reason)?;
+    } else if message.awaiting_finality {
+        task.await_finality()?;
+    }
+    if store.insert(&task).await? {
+        return Ok(task);
+    }
+    // 并发插入，读取已有任务
+    store
+        .get(&message.id)
+        .await?
+        .ok_or_else(|| RelayerError::Internal(format!("Task {} disappeared", message.id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::queue::FileWorkQueue;
+    use crate::store::FileTaskStore;
+    use crate::test_util::{event, TempDir};
+    use std::sync::Mutex;
+
+    fn queue_config() -> QueueConfig {
+        QueueConfig {
+            retry_limit: 2,
+            retry_delays: vec![0],
+            ..crate::Config::default().queue
+        }
+    }
+
+    /// 每次 poll 返回同一批事件，直到 commit
+    struct MockListener {
+        pending: Vec<u64>,
+        commits: usize,
+    }
+
+    #[async_trait]
+    impl SourceListener for MockListener {
+        fn chain(&self) -> &str {
+            "mock"
+        }
+
+        async fn poll(&mut self) -> Result<Vec<SourceEvent>> {
+            Ok(self
+                .pending
+                .iter()
+                .map(|&nonce| SourceEvent {
+                    event: event(nonce),
+                    tx_id: format!("tx{}", nonce),
+                    log_index: None,
+                    sender: None,
+                    finality: Finality::default(),
+                    rejected: None,
+                })
+                .collect())
+        }
+
+        fn commit(&mut self) {
+            self.pending.clear();
+            self.commits += 1;
+        }
+
+        fn cursor(&self) -> u64 {
+            self.commits as u64
+        }
+    }
+
+    struct MockSigner;
+
+    #[async_trait]
+    impl AttestationSigner for MockSigner {
+        fn scheme(&self) -> SignatureScheme {
+            SignatureScheme::Ed25519
+        }
+
+        fn identity(&self) -> String {
+            "mock".to_string()
+        }
+
+        async fn sign_event(&self, event: &StakeEventData) -> Result<Vec<u8>> {
+            Ok(vec![event.nonce as u8; 64])
+        }
+
+        async fn sign_transaction(&self, _event: &StakeEventData, _transaction: &[u8]) -> Result<Vec<u8>> {
+            Ok(vec![0; 64])
+        }
+    }
+
+    /// nonce 为奇数时失败，`flaky` 中的 nonce 第一次提交失败，`retryable` 决定失败类型
+    struct MockSubmitter {
+        retryable: bool,
+        flaky: Mutex<HashSet<u64>>,
+        submitted: Mutex<Vec<u64>>,
+    }
+
+    #[async_trait]
+    impl TargetSubmitter for MockSubmitter {
+        fn scheme(&self) -> SignatureScheme {
+            SignatureScheme::Ed25519
+        }
+
+        async fn submit(&self, event: &StakeEventData, attestation: &[u8]) -> Result<String> {
+            assert_eq!(attestation, vec![event.nonce as u8; 64]);
+            if event.nonce % 2 == 1 {
+                return Err(RelayerError::TransactionFailed("odd nonce".to_string()));
+            }
+            if self.flaky.lock().unwrap().remove(&event.nonce) {
+                return Err(RelayerError::Network("rpc timeout".to_string()));
+            }
+            self.submitted.lock().unwrap().push(event.nonce);
+            Ok(format!("0x{}", event.nonce))
+        }
+
+        fn is_retryable(&self, _error: &RelayerError) -> bool {
+            self.retryable
+        }
+    }
+
+    fn relay(retryable: bool) -> (Relay, Arc<MockSubmitter>) {
+        let submitter = Arc::new(MockSubmitter {
+            retryable,
+            flaky: Mutex::new(HashSet::new()),
+            submitted: Mutex::new(Vec::new()),
+        });
+        (Relay::new(Arc::new(MockSigner), submitter.clone()).unwrap(), submitter)
+    }
+
+    #[tokio::test]
+    async fn test_forward_and_process_pending() {
+        let dir = TempDir::new("pipeline-pending");
+        let store: Arc<dyn TaskStore> = Arc::new(FileTaskStore::open(&*dir).unwrap());
+        let mut listener = MockListener { pending: vec![1, 2, 2], commits: 0 };
+
+        assert_eq!(forward_events(&mut listener, store.as_ref()).await.unwrap(), 2);
+        assert_eq!(listener.commits, 1);
+        assert_eq!(forward_events(&mut listener, store.as_ref()).await.unwrap(), 0);
+
+        let (relay, submitter) = relay(true);
+        let config = queue_config();
+        // nonce 1 失败后等待重试，nonce 2 不能抢先提交
+        assert_eq!(relay.process_pending(store.as_ref(), &config).await.unwrap(), 0);
+        assert!(submitter.submitted.lock().unwrap().is_empty());
+        assert_eq!(store.count(TaskStatus::Pending).await.unwrap(), 2);
+
+        // 第二次失败达到重试上限，nonce 2 随后提交
+        assert_eq!(relay.process_pending(store.as_ref(), &config).await.unwrap(), 1);
+        assert_eq!(*submitter.submitted.lock().unwrap(), vec![2]);
+        assert_eq!(store.count(TaskStatus::Failed).await.unwrap(), 1);
+        assert_eq!(store.count(TaskStatus::Completed).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_retrying_nonce_blocks_later_nonces() {
+        let dir = TempDir::new("pipeline-retry-lane");
+        let (relay, submitter) = relay(true);
+        let waiting = QueueConfig { retry_delays: vec![0, 60_000], ..queue_config() };
+
+        // 同进程：nonce 2 失败后等待 60s 重试，期间到达的 nonce 4 保持 Pending
+        let store: Arc<dyn TaskStore> = Arc::new(FileTaskStore::open(dir.join("tasks")).unwrap());
+        submitter.flaky.lock().unwrap().insert(2);
+        let mut listener = MockListener { pending: vec![2], commits: 0 };
+        forward_events(&mut listener, store.as_ref()).await.unwrap();
+        assert_eq!(relay.process_pending(store.as_ref(), &waiting).await.unwrap(), 0);
+        listener.pending = vec![4];
+        forward_events(&mut listener, store.as_ref()).await.unwrap();
+        assert_eq!(relay.process_pending(store.as_ref(), &waiting).await.unwrap(), 0);
+        assert!(submitter.submitted.lock().unwrap().is_empty());
+        // 重试时间到达后按 nonce 顺序提交
+        assert_eq!(relay.process_pending(store.as_ref(), &queue_config()).await.unwrap(), 2);
+        assert_eq!(*submitter.submitted.lock().unwrap(), vec![2, 4]);
+
+        // 跨进程：nonce 6 被 nack 延迟重试，随后取出的 nonce 8 同样被推迟
+        let queue: Arc<dyn WorkQueue> = Arc::new(FileWorkQueue::open(&dir.join("queue")).unwrap());
+        let tasks = FileTaskStore::open(dir.join("submitter")).unwrap();
+        submitter.flaky.lock().unwrap().insert(6);
+        listener.pending = vec![6, 8];
+        forward_events(&mut listener, queue.as_ref()).await.unwrap();
+        assert_eq!(relay.process_queue(&tasks, queue.as_ref(), &waiting).await.unwrap(), 0);
+        assert_eq!(*submitter.submitted.lock().unwrap(), vec![2, 4]);
+        let later = tasks.get(&Task::id_for(&event(8))).await.unwrap().unwrap();
+        assert_eq!((later.status, later.signature.is_none()), (TaskStatus::Pending, true));
+        assert_eq!(queue.depth().await.unwrap().inflight, 2);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_event_is_blocked() {
+        let dir = TempDir::new("pipeline-rejected");
+        let source = SourceRef { tx: "tx".to_string(), log_index: None, block: 100, sender: None };
+        let (relay, submitter) = relay(true);
+        let config = queue_config();
+
+        // 同进程：入口校验失败的事件登记为 blocked，不阻塞同一通道的后续 nonce
+        let store: Arc<dyn TaskStore> = Arc::new(FileTaskStore::open(dir.join("tasks")).unwrap());
+        assert!(store.as_ref().reject(event(2), source.clone(), "invalid receiver_address").await.unwrap());
+        assert!(!store.as_ref().reject(event(2), source.clone(), "invalid receiver_address").await.unwrap());
+        store.as_ref().accept(event(4), source.clone(), false).await.unwrap();
+        assert_eq!(relay.process_pending(store.as_ref(), &config).await.unwrap(), 1);
+        let blocked = store.get(&Task::id_for(&event(2))).await.unwrap().unwrap();
+        assert_eq!(blocked.status, TaskStatus::Blocked);
+        assert_eq!(blocked.error_message.as_deref(), Some("invalid receiver_address"));
+
+        // 跨进程：submitter 登记 blocked 任务并确认消息
+        let queue: Arc<dyn WorkQueue> = Arc::new(FileWorkQueue::open(&dir.join("queue")).unwrap());
+        let tasks = FileTaskStore::open(dir.join("submitter")).unwrap();
+        queue.as_ref().reject(event(6), source.clone(), "invalid receiver_address").await.unwrap();
+        queue.as_ref().accept(event(8), source, false).await.unwrap();
+        assert_eq!(relay.process_queue(&tasks, queue.as_ref(), &config).await.unwrap(), 1);
+        assert_eq!(tasks.count(TaskStatus::Blocked).await.unwrap(), 1);
+        assert_eq!(*submitter.submitted.lock().unwrap(), vec![4, 8]);
+        let depth = queue.depth().await.unwrap();
+        assert_eq!((depth.pending, depth.inflight), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_process_queue_acks_and_dead_letters() {
+        let dir = TempDir::new("pipeline-queue");
+        let store = FileTaskStore::open(dir.join("tasks")).unwrap();
+        let queue: Arc<dyn WorkQueue> = Arc::new(FileWorkQueue::open(&dir.join("queue")).unwrap());
+        let mut listener = MockListener { pending: vec![3, 4], commits: 0 };
+        assert_eq!(forward_events(&mut listener, queue.as_ref()).await.unwrap(), 2);
+
+        let (relay, submitter) = relay(false);
+        assert_eq!(relay.process_queue(&store, queue.as_ref(), &queue_config()).await.unwrap(), 1);
+        assert_eq!(*submitter.submitted.lock().unwrap(), vec![4]);
+
+        let depth = queue.depth().await.unwrap();
+        assert_eq!((depth.pending, depth.inflight, depth.dead), (0, 0, 1));
+        assert_eq!(store.count(TaskStatus::Failed).await.unwrap(), 1);
+        let task = store.get(&Task::id_for(&event(4))).await.unwrap().unwrap();
+        assert_eq!(task.signature, Some(hex::encode([4u8; 64])));
+    }
+
+    #[tokio::test]
+    async fn test_awaiting_finality_is_not_signed() {
+        let dir = TempDir::new("pipeline-finality");
+        let store: Arc<dyn TaskStore> = Arc::new(FileTaskStore::open(dir.join("tasks")).unwrap());
+        let source = SourceRef { tx: "tx".to_string(), log_index: None, block: 100, sender: None };
+        let (relay, submitter) = relay(true);
+        let config = queue_config();
+
+        // 同进程：等待最终性的任务不签名，达到最终性后转为 Pending
+        assert!(store.as_ref().accept(event(2), source.clone(), true).await.unwrap());
+        assert!(!store.as_ref().accept(event(2), source.clone(), true).await.unwrap());
+        assert_eq!(relay.process_pending(store.as_ref(), &config).await.unwrap(), 0);
+        assert_eq!(store.count(TaskStatus::AwaitingFinality).await.unwrap(), 1);
+        assert!(store.as_ref().accept(event(2), source.clone(), false).await.unwrap());
+        assert!(!store.as_ref().accept(event(2), source.clone(), false).await.unwrap());
+        assert_eq!(relay.process_pending(store.as_ref(), &config).await.unwrap(), 1);
+
+        // 跨进程：submitter 登记等待最终性的任务并确认消息，监听器重新投递后签名
+        let queue: Arc<dyn WorkQueue> = Arc::new(FileWorkQueue::open(&dir.join("queue")).unwrap());
+        let tasks = FileTaskStore::open(dir.join("submitter")).unwrap();
+        queue.as_ref().accept(event(4), source.clone(), true).await.unwrap();
+        assert_eq!(relay.process_queue(&tasks, queue.as_ref(), &config).await.unwrap(), 0);
+        assert_eq!(tasks.count(TaskStatus::AwaitingFinality).await.unwrap(), 1);
+        assert_eq!(queue.depth().await.unwrap().pending, 0);
+        queue.as_ref().accept(event(4), source, false).await.unwrap();
+        assert_eq!(relay.process_queue(&tasks, queue.as_ref(), &config).await.unwrap(), 1);
+        assert_eq!(*submitter.submitted.lock().unwrap(), vec![2, 4]);
+    }
+
+    #[tokio::test]
+    async fn test_retracted_event_is_not_signed() {
+        let dir = TempDir::new("pipeline-retract");
+        let store: Arc<dyn TaskStore> = Arc::new(FileTaskStore::open(dir.join("tasks")).unwrap());
+        let source = SourceRef { tx: "tx".to_string(), log_index: None, block: 100, sender: None };
+        let (relay, submitter) = relay(true);
+        let config = queue_config();
+
+        // 同进程：撤回的任务不签名，事件重新出现后恢复
+        store.as_ref().accept(event(2), source.clone(), true).await.unwrap();
+        assert!(store.as_ref().retract(event(2), source.clone()).await.unwrap());
+        assert!(!store.as_ref().retract(event(2), source.clone()).await.unwrap());
+        assert!(store.as_ref().accept(event(2), source.clone(), false).await.unwrap());
+        assert_eq!(relay.process_pending(store.as_ref(), &config).await.unwrap(), 1);
+        // 已签名的任务无法撤回
+        assert!(!store.as_ref().retract(event(2), source.clone()).await.unwrap());
+
+        // 跨进程：撤回消息先到时登记为已撤回，撤回之前入队的原始消息不恢复任务
+        let queue: Arc<dyn WorkQueue> = Arc::new(FileWorkQueue::open(&dir.join("queue")).unwrap());
+        let tasks = FileTaskStore::open(dir.join("submitter")).unwrap();
+        let mut original = QueueMessage::new(event(4));
+        queue.as_ref().retract(event(4), source.clone()).await.unwrap();
+        assert_eq!(relay.process_queue(&tasks, queue.as_ref(), &config).await.unwrap(), 0);
+        assert_eq!(tasks.count(TaskStatus::Retracted).await.unwrap(), 1);
+        original.source = Some(source.clone());
+        queue.push(&original).await.unwrap();
+        assert_eq!(relay.process_queue(&tasks, queue.as_ref(), &config).await.unwrap(), 0);
+        assert_eq!(tasks.count(TaskStatus::Retracted).await.unwrap(), 1);
+        queue.as_ref().accept(event(4), source, false).await.unwrap();
+        assert_eq!(relay.process_queue(&tasks, queue.as_ref(), &config).await.unwrap(), 1);
+        assert_eq!(*submitter.submitted.lock().unwrap(), vec![2, 4]);
+    }
+
+    #[test]
+    fn test_awaiting_events_forwarded_once() {
+        let observed = |nonce, awaiting| SourceEvent {
+            event: event(nonce),
+            tx_id: format!("tx{}", nonce),
+            log_index: None,
+            sender: None,
+            finality: Finality { awaiting, ..Finality::default() },
+            rejected: None,
+        };
+        let mut filter = AwaitingEvents::default();
+        assert_eq!(filter.filter(vec![observed(1, true), observed(2, false)]).len(), 2);
+        assert_eq!(filter.filter(vec![observed(1, true)]).len(), 0);
+        assert_eq!(filter.filter(vec![observed(1, false)]).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_relay_writes_audit_log() {
+        let dir = TempDir::new("pipeline-audit");
+        let store: Arc<dyn TaskStore> = Arc::new(FileTaskStore::open(dir.join("tasks")).unwrap());
+        let mut listener = MockListener { pending: vec![1, 2], commits: 0 };
+        forward_events(&mut listener, store.as_ref()).await.unwrap();
+
+        let path = dir.join("audit.jsonl");
+        let (relay, _) = relay(false);
+        let relay = relay.with_audit_log(Arc::new(AuditLog::open(&path).unwrap()));
+        relay.process_pending(store.as_ref(), &queue_config()).await.unwrap();
+
+        // 每个任务一条签名记录 + 一条提交 / 失败记录
+        let report = crate::audit::verify(&path).unwrap();
+        assert_eq!((report.entries, report.signatures), (4, 2));
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("\"target_tx\":\"0x2\""));
+        assert!(content.contains("odd nonce"));
+    }
+
+    #[tokio::test]
+    async fn test_risk_hold_and_approve() {
+        let dir = TempDir::new("pipeline-risk");
+        let store = FileTaskStore::open(dir.join("tasks")).unwrap();
+        let queue: Arc<dyn WorkQueue> = Arc::new(FileWorkQueue::open(&dir.join("queue")).unwrap());
+        let mut listener = MockListener { pending: vec![2], commits: 0 };
+        forward_events(&mut listener, queue.as_ref()).await.unwrap();
+
+        let risk = crate::config::RiskConfig { max_transfer: Some(1_000), ..Default::default() };
+        let (relay, submitter) = relay(true);
+        let relay = relay.with_risk_engine(Arc::new(RiskEngine::new(risk)));
+        let config = queue_config();
+
+        // 超过单笔上限：不签名，消息确认，任务暂扣
+        assert_eq!(relay.process_queue(&store, queue.as_ref(), &config).await.unwrap(), 0);
+        assert!(submitter.submitted.lock().unwrap().is_empty());
+        assert_eq!(queue.depth().await.unwrap().pending, 0);
+        let mut task = store.get(&Task::id_for(&event(2))).await.unwrap().unwrap();
+        assert_eq!((task.status, task.signature.is_none()), (TaskStatus::Held, true));
+
+        // 操作员批准后重新投递
+        task.approve().unwrap();
+        store.update(&task).await.unwrap();
+        queue.push(&QueueMessage::for_task(&task)).await.unwrap();
+        assert_eq!(relay.process_queue(&store, queue.as_ref(), &config).await.unwrap(), 1);
+        assert_eq!(*submitter.submitted.lock().unwrap(), vec![2]);
+    }
+
+    #[tokio::test]
+    async fn test_risk_hold_blocks_later_nonces() {
+        let dir = TempDir::new("pipeline-risk-lane");
+        let store = FileTaskStore::open(dir.join("tasks")).unwrap();
+        let queue: Arc<dyn WorkQueue> = Arc::new(FileWorkQueue::open(&dir.join("queue")).unwrap());
+        let mut listener = MockListener { pending: vec![2], commits: 0 };
+        forward_events(&mut listener, queue.as_ref()).await.unwrap();
+
+        let risk = Arc::new(RiskEngine::new(crate::config::RiskConfig { max_transfer: Some(1_000), ..Default::default() }));
+        let (relay, submitter) = relay(true);
+        let relay = relay.with_risk_engine(risk.clone());
+        let config = queue_config();
+        relay.process_queue(&store, queue.as_ref(), &config).await.unwrap();
+        let mut held = store.get(&Task::id_for(&event(2))).await.unwrap().unwrap();
+        assert_eq!(held.status, TaskStatus::Held);
+
+        // nonce 2 暂扣期间到达的 nonce 4 保持 Pending，不会抢先提交
+        risk.set_config(Default::default());
+        listener.pending = vec![4];
+        forward_events(&mut listener, queue.as_ref()).await.unwrap();
+        assert_eq!(relay.process_queue(&store, queue.as_ref(), &config).await.unwrap(), 0);
+        assert!(submitter.submitted.lock().unwrap().is_empty());
+        let later = store.get(&Task::id_for(&event(4))).await.unwrap().unwrap();
+        assert_eq!(later.status, TaskStatus::Pending);
+        let depth = queue.depth().await.unwrap();
+        assert_eq!(depth.pending + depth.inflight, 1);
+
+        // 批准 nonce 2 后按 nonce 顺序提交
+        held.approve().unwrap();
+        store.update(&held).await.unwrap();
+        queue.push(&QueueMessage::for_task(&held)).await.unwrap();
+        assert_eq!(relay.process_queue(&store, queue.as_ref(), &config).await.unwrap(), 2);
+        assert_eq!(*submitter.submitted.lock().unwrap(), vec![2, 4]);
+
+        // 同进程模式同样按通道等待
+        let dir = TempDir::new("pipeline-risk-lane-pending");
+        let store: Arc<dyn TaskStore> = Arc::new(FileTaskStore::open(dir.join("tasks")).unwrap());
+        risk.set_config(crate::config::RiskConfig { max_transfer: Some(1_000), ..Default::default() });
+        let mut listener = MockListener { pending: vec![6], commits: 0 };
+        forward_events(&mut listener, store.as_ref()).await.unwrap();
+        relay.process_pending(store.as_ref(), &config).await.unwrap();
+        risk.set_config(Default::default());
+        listener.pending = vec![8];
+        forward_events(&mut listener, store.as_ref()).await.unwrap();
+        assert_eq!(relay.process_pending(store.as_ref(), &config).await.unwrap(), 0);
+        let mut held = store.get(&Task::id_for(&event(6))).await.unwrap().unwrap();
+        held.approve().unwrap();
+        store.update(&held).await.unwrap();
+        assert_eq!(relay.process_pending(store.as_ref(), &config).await.unwrap(), 2);
+        assert_eq!(*submitter.submitted.lock().unwrap(), vec![2, 4, 6, 8]);
+    }
+
+    #[tokio::test]
+    async fn test_screening_blocks_before_signing() {
+        let dir = TempDir::new("pipeline-screening");
+        let store: Arc<dyn TaskStore> = Arc::new(FileTaskStore::open(dir.join("tasks")).unwrap());
+        let mut listener = MockListener { pending: vec![2], commits: 0 };
+        forward_events(&mut listener, store.as_ref()).await.unwrap();
+
+        let list = dir.join("sanctions.csv");
+        std::fs::write(&list, format!("address,reason\n{},ofac\n", ChainAddress::Svm([2u8; 32]))).unwrap();
+        let screener = Screener::load(vec![list], Duration::from_secs(60)).unwrap();
+        let path = dir.join("audit.jsonl");
+        let (relay, submitter) = relay(true);
+        let relay = relay
+            .with_audit_log(Arc::new(AuditLog::open(&path).unwrap()))
+            .with_screening(Arc::new(screener));
+
+        assert_eq!(relay.process_pending(store.as_ref(), &queue_config()).await.unwrap(), 0);
+        assert!(submitter.submitted.lock().unwrap().is_empty());
+        let task = store.get(&Task::id_for(&event(2))).await.unwrap().unwrap();
+        assert_eq!((task.status, task.signature.is_none()), (TaskStatus::Blocked, true));
+        assert!(task.error_message.unwrap().contains("deny list 'sanctions' (ofac)"));
+
+        let report = crate::audit::verify(&path).unwrap();
+        assert_eq!((report.entries, report.signatures), (1, 0));
+        assert!(std::fs::read_to_string(&path).unwrap().contains("\"kind\":\"blocked\""));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_stops_taking_tasks() {
+        let dir = TempDir::new("pipeline-shutdown");
+        let store: Arc<dyn TaskStore> = Arc::new(FileTaskStore::open(&*dir).unwrap());
+        let mut listener = MockListener { pending: vec![2, 4], commits: 0 };
+        forward_events(&mut listener, store.as_ref()).await.unwrap();
+
+        let lifecycle = crate::lifecycle::Lifecycle::new(Config::default());
+        let (relay, submitter) = relay(true);
+        let relay = relay.with_shutdown(lifecycle.shutdown());
+        lifecycle.request_shutdown();
+
+        assert_eq!(relay.process_pending(store.as_ref(), &queue_config()).await.unwrap(), 0);
+        assert!(submitter.submitted.lock().unwrap().is_empty());
+        assert_eq!(store.count(TaskStatus::Pending).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_underfunded_relay_pauses() {
+        let dir = TempDir::new("pipeline-underfunded");
+        let store: Arc<dyn TaskStore> = Arc::new(FileTaskStore::open(&*dir).unwrap());
+        let mut listener = MockListener { pending: vec![2], commits: 0 };
+        forward_events(&mut listener, store.as_ref()).await.unwrap();
+
+        let gas = crate::gas::GasManager::new(5.0, 0.1, 300000);
+        let status = BalanceStatus::new();
+        gas.observe("svm", 1.0, &status).unwrap_err();
+        let (relay, submitter) = relay(true);
+        let relay = relay.with_balance_status(status.clone());
+        let config = queue_config();
+
+        // 欠费期间任务保持 Pending，不计重试
+        assert_eq!(relay.process_pending(store.as_ref(), &config).await.unwrap(), 0);
+        assert!(submitter.submitted.lock().unwrap().is_empty());
+        let task = store.get(&Task::id_for(&event(2))).await.unwrap().unwrap();
+        assert_eq!((task.status, task.retries), (TaskStatus::Pending, 0));
+
+        gas.observe("svm", 10.0, &status).unwrap();
+        assert_eq!(relay.process_pending(store.as_ref(), &config).await.unwrap(), 1);
+    }
+
+    #[test]
+    fn test_relay_rejects_mismatched_scheme() {
+        struct EvmSubmitter;
+
+        #[async_trait]
+        impl TargetSubmitter for EvmSubmitter {
+            fn scheme(&self) -> SignatureScheme {
+                SignatureScheme::Secp256k1
+            }
+
+            async fn submit(&self, _event: &StakeEventData, _attestation: &[u8]) -> Result<String> {
+                unreachable!()
+            }
+        }
+
+        assert!(Relay::new(Arc::new(MockSigner), Arc::new(EvmSubmitter)).is_err());
+    }
+}
@@ -0,0 +1,696 @@
+//! PKCS#11 HSM 签名（`pkcs11` feature）
+//!
+//! 直接通过 `C_GetFunctionList` 调用 PKCS#11 模块（例如 SoftHSM 的 `libsofthsm2.so`），
+//! 按 token 标签选择 slot，按 `CKA_LABEL` 查找私钥和对应的公钥：
+//! - secp256k1（`pkcs11-secp256k1` feature）：`CKM_ECDSA` 对 32 字节摘要签名，规范化为 low-s
+//!   并通过公钥试算恢复 ID，输出与 `EcdsaSigner` 相同的 65 字节 r||s||v（v = 27/28）
+//! - Ed25519：`CKM_EDDSA` 对原始消息签名，输出 64 字节签名
+
+use crate::config::HsmConfig;
+use crate::error::{RelayerError, Result};
+use crate::keystore::Secret;
+use std::collections::HashMap;
+use std::ffi::{c_void, CStr, CString};
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::Mutex;
+
+/// 每个模块已打开的会话数：C_Initialize / C_Finalize 是进程级的，最后一个会话关闭时才 Finalize
+static MODULE_SESSIONS: Mutex<Option<HashMap<PathBuf, usize>>> = Mutex::new(None);
+
+type CkUlong = libc::c_ulong;
+type CkRv = CkUlong;
+type CkSlotId = CkUlong;
+type CkSessionHandle = CkUlong;
+type CkObjectHandle = CkUlong;
+
+const CKR_OK: CkRv = 0x0;
+const CKR_USER_ALREADY_LOGGED_IN: CkRv = 0x100;
+const CKR_CRYPTOKI_ALREADY_INITIALIZED: CkRv = 0x191;
+
+const CKF_RW_SESSION: CkUlong = 0x2;
+const CKF_SERIAL_SESSION: CkUlong = 0x4;
+const CKF_OS_LOCKING_OK: CkUlong = 0x2;
+const CKU_USER: CkUlong = 1;
+
+const CKA_CLASS: CkUlong = 0x0;
+const CKA_LABEL: CkUlong = 0x3;
+const CKA_KEY_TYPE: CkUlong = 0x100;
+const CKA_EC_POINT: CkUlong = 0x181;
+
+const CKO_PUBLIC_KEY: CkUlong = 2;
+const CKO_PRIVATE_KEY: CkUlong = 3;
+#[cfg(feature = "pkcs11-secp256k1")]
+const CKK_EC: CkUlong = 0x3;
+const CKK_EC_EDWARDS: CkUlong = 0x40;
+
+#[cfg(feature = "pkcs11-secp256k1")]
+const CKM_ECDSA: CkUlong = 0x1041;
+const CKM_EDDSA: CkUlong = 0x1057;
+
+#[repr(C)]
+struct CkVersion {
+    major: u8,
+    minor: u8,
+}
+
+#[repr(C)]
+struct CkAttribute {
+    attr_type: CkUlong,
+    value: *mut c_void,
+    value_len: CkUlong,
+}
+
+#[repr(C)]
+struct CkMechanism {
+    mechanism: CkUlong,
+    parameter: *mut c_void,
+    parameter_len: CkUlong,
+}
+
+#[repr(C)]
+struct CkInitializeArgs {
+    create_mutex: *mut c_void,
+    destroy_mutex: *mut c_void,
+    lock_mutex: *mut c_void,
+    unlock_mutex: *mut c_void,
+    flags: CkUlong,
+    reserved: *mut c_void,
+}
+
+#[repr(C)]
+struct CkTokenInfo {
+    label: [u8; 32],
+    manufacturer_id: [u8; 32],
+    model: [u8; 16],
+    serial_number: [u8; 16],
+    flags: CkUlong,
+    counters: [CkUlong; 10],
+    hardware_version: CkVersion,
+    firmware_version: CkVersion,
+    utc_time: [u8; 16],
+}
+
+type Unused = Option<unsafe extern "C" fn()>;
+
+/// `CK_FUNCTION_LIST`（PKCS#11 v2.40 顺序，只声明用到的函数签名）
+#[repr(C)]
+struct CkFunctionList {
+    version: CkVersion,
+    c_initialize: unsafe extern "C" fn(*mut c_void) -> CkRv,
+    c_finalize: unsafe extern "C" fn(*mut c_void) -> CkRv,
+    c_get_info: Unused,
+    c_get_function_list: Unused,
+    c_get_slot_list: unsafe extern "C" fn(u8, *mut CkSlotId, *mut CkUlong) -> CkRv,
+    c_get_slot_info: Unused,
+    c_get_token_info: unsafe extern "C" fn(CkSlotId, *mut CkTokenInfo) -> CkRv,
+    c_get_mechanism_list: Unused,
+    c_get_mechanism_info: Unused,
+    c_init_token: Unused,
+    c_init_pin: Unused,
+    c_set_pin: Unused,
+    c_open_session:
+        unsafe extern "C" fn(CkSlotId, CkUlong, *mut c_void, *mut c_void, *mut CkSessionHandle) -> CkRv,
+    c_close_session: unsafe extern "C" fn(CkSessionHandle) -> CkRv,
+    c_close_all_sessions: Unused,
+    c_get_session_info: Unused,
+    c_get_operation_state: Unused,
+    c_set_operation_state: Unused,
+    c_login: unsafe extern "C" fn(CkSessionHandle, CkUlong, *const u8, CkUlong) -> CkRv,
+    c_logout: Unused,
+    c_create_object: Unused,
+    c_copy_object: Unused,
+    c_destroy_object: unsafe extern "C" fn(CkSessionHandle, CkObjectHandle) -> CkRv,
+    c_get_object_size: Unused,
+    c_get_attribute_value: unsafe extern "C" fn(CkSessionHandle, CkObjectHandle, *mut CkAttribute, CkUlong) -> CkRv,
+    c_set_attribute_value: Unused,
+    c_find_objects_init: unsafe extern "C" fn(CkSessionHandle, *mut CkAttribute, CkUlong) -> CkRv,
+    c_find_objects: unsafe extern "C" fn(CkSessionHandle, *mut CkObjectHandle, CkUlong, *mut CkUlong) -> CkRv,
+    c_find_objects_final: unsafe extern "C" fn(CkSessionHandle) -> CkRv,
+    c_encrypt_init: Unused,
+    c_encrypt: Unused,
+    c_encrypt_update: Unused,
+    c_encrypt_final: Unused,
+    c_decrypt_init: Unused,
+    c_decrypt: Unused,
+    c_decrypt_update: Unused,
+    c_decrypt_final: Unused,
+    c_digest_init: Unused,
+    c_digest: Unused,
+    c_digest_update: Unused,
+    c_digest_key: Unused,
+    c_digest_final: Unused,
+    c_sign_init: unsafe extern "C" fn(CkSessionHandle, *const CkMechanism, CkObjectHandle) -> CkRv,
+    c_sign: unsafe extern "C" fn(CkSessionHandle, *const u8, CkUlong, *mut u8, *mut CkUlong) -> CkRv,
+    c_sign_update: Unused,
+    c_sign_final: Unused,
+    c_sign_recover_init: Unused,
+    c_sign_recover: Unused,
+    c_verify_init: Unused,
+    c_verify: Unused,
+    c_verify_update: Unused,
+    c_verify_final: Unused,
+    c_verify_recover_init: Unused,
+    c_verify_recover: Unused,
+    c_digest_encrypt_update: Unused,
+    c_decrypt_digest_update: Unused,
+    c_sign_encrypt_update: Unused,
+    c_decrypt_verify_update: Unused,
+    c_generate_key: Unused,
+    #[allow(clippy::type_complexity)]
+    c_generate_key_pair: unsafe extern "C" fn(
+        CkSessionHandle,
+        *const CkMechanism,
+        *mut CkAttribute,
+        CkUlong,
+        *mut CkAttribute,
+        CkUlong,
+        *mut CkObjectHandle,
+        *mut CkObjectHandle,
+    ) -> CkRv,
+}
+
+fn check(rv: CkRv, call: &str) -> Result<()> {
+    if rv == CKR_OK {
+        Ok(())
+    } else {
+        Err(RelayerError::Signature(format!("PKCS#11 {} failed: CKR 0x{:x}", call, rv)))
+    }
+}
+
+fn attribute<T>(attr_type: CkUlong, value: &T) -> CkAttribute {
+    CkAttribute {
+        attr_type,
+        value: value as *const T as *mut c_void,
+        value_len: std::mem::size_of::<T>() as CkUlong,
+    }
+}
+
+fn bytes_attribute(attr_type: CkUlong, value: &[u8]) -> CkAttribute {
+    CkAttribute {
+        attr_type,
+        value: value.as_ptr() as *mut c_void,
+        value_len: value.len() as CkUlong,
+    }
+}
+
+/// 已登录的 PKCS#11 会话
+struct Session {
+    module: PathBuf,
+    library: *mut c_void,
+    functions: *const CkFunctionList,
+    initialized: bool,
+    handle: CkSessionHandle,
+}
+
+// PKCS#11 以 CKF_OS_LOCKING_OK 初始化，会话句柄由外层 Mutex 串行访问
+unsafe impl Send for Session {}
+
+impl Session {
+    fn open(module: &Path, token_label: &str, pin: &Secret) -> Result<Self> {
+        let path = CString::new(module.as_os_str().as_encoded_bytes())
+            .map_err(|_| RelayerError::Config(format!("Invalid PKCS#11 module path: {}", module.display())))?;
+
+        // SAFETY: 按 PKCS#11 规范调用模块导出的 C 接口，所有指针在调用期间有效
+        unsafe {
+            let library = libc::dlopen(path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL);
+            if library.is_null() {
+                let error = CStr::from_ptr(libc::dlerror()).to_string_lossy().into_owned();
+                return Err(RelayerError::Config(format!("Failed to load PKCS#11 module: {}", error)));
+            }
+            let mut session = Session {
+                module: module.to_path_buf(),
+                library,
+                functions: ptr::null(),
+                initialized: false,
+                handle: 0,
+            };
+
+            let symbol = libc::dlsym(library, c"C_GetFunctionList".as_ptr());
+            if symbol.is_null() {
+                return Err(RelayerError::Config("PKCS#11 module has no C_GetFunctionList".to_string()));
+            }
+            let get_function_list: unsafe extern "C" fn(*mut *const CkFunctionList) -> CkRv =
+                std::mem::transmute(symbol);
+            let mut functions = ptr::null();
+            check(get_function_list(&mut functions), "C_GetFunctionList")?;
+            session.functions = functions;
+            let f = &*functions;
+
+            let mut args = CkInitializeArgs {
+                create_mutex: ptr::null_mut(),
+                destroy_mutex: ptr::null_mut(),
+                lock_mutex: ptr::null_mut(),
+                unlock_mutex: ptr::null_mut(),
+                flags: CKF_OS_LOCKING_OK,
+                reserved: ptr::null_mut(),
+            };
+            {
+                let mut sessions = MODULE_SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+                let count = sessions.get_or_insert_with(HashMap::new).entry(module.to_path_buf()).or_insert(0);
+                if *count == 0 {
+                    let rv = (f.c_initialize)(&mut args as *mut CkInitializeArgs as *mut c_void);
+                    if rv != CKR_CRYPTOKI_ALREADY_INITIALIZED {
+                        check(rv, "C_Initialize")?;
+                    }
+                }
+                *count += 1;
+                session.initialized = true;
+            }
+
+            let slot = session.find_slot(token_label)?;
+            let mut handle = 0;
+            check(
+                (f.c_open_session)(slot, CKF_SERIAL_SESSION | CKF_RW_SESSION, ptr::null_mut(), ptr::null_mut(), &mut handle),
+                "C_OpenSession",
+            )?;
+            session.handle = handle;
+
+            let pin = pin.expose().as_bytes();
+            let rv = (f.c_login)(handle, CKU_USER, pin.as_ptr(), pin.len() as CkUlong);
+            if rv != CKR_USER_ALREADY_LOGGED_IN {
+                check(rv, "C_Login")?;
+            }
+            Ok(session)
+        }
+    }
+
+    fn functions(&self) -> &CkFunctionList {
+        // SAFETY: functions 在 open 成功后指向模块内的静态函数表
+        unsafe { &*self.functions }
+    }
+
+    /// 按 token 标签查找 slot（标签以空格补齐到 32 字节）
+    unsafe fn find_slot(&self, token_label: &str) -> Result<CkSlotId> {
+        let f = self.functions();
+        let mut count: CkUlong = 0;
+        check((f.c_get_slot_list)(1, ptr::null_mut(), &mut count), "C_GetSlotList")?;
+        let mut slots = vec![0 as CkSlotId; count as usize];
+        check((f.c_get_slot_list)(1, slots.as_mut_ptr(), &mut count), "C_GetSlotList")?;
+        slots.truncate(count as usize);
+
+        for slot in slots {
+            let mut info: CkTokenInfo = std::mem::zeroed();
+            if (f.c_get_token_info)(slot, &mut info) != CKR_OK {
+                continue;
+            }
+            let label = String::from_utf8_lossy(&info.label);
+            if label.trim_end_matches([' ', '\0']) == token_label {
+                return Ok(slot);
+            }
+        }
+        Err(RelayerError::Config(format!("PKCS#11 token '{}' not found", token_label)))
+    }
+
+    /// 按标签和类型查找唯一的对象
+    fn find_object(&self, class: CkUlong, label: &str) -> Result<CkObjectHandle> {
+        let f = self.functions();
+        let mut template = [attribute(CKA_CLASS, &class), bytes_attribute(CKA_LABEL, label.as_bytes())];
+        let mut objects = [0 as CkObjectHandle; 2];
+        let mut found: CkUlong = 0;
+        // SAFETY: 模板和输出缓冲区在调用期间有效
+        unsafe {
+            check((f.c_find_objects_init)(self.handle, template.as_mut_ptr(), template.len() as CkUlong), "C_FindObjectsInit")?;
+            let rv = (f.c_find_objects)(self.handle, objects.as_mut_ptr(), objects.len() as CkUlong, &mut found);
+            (f.c_find_objects_final)(self.handle);
+            check(rv, "C_FindObjects")?;
+        }
+        let kind = if class == CKO_PRIVATE_KEY { "private" } else { "public" };
+        match found {
+            1 => Ok(objects[0]),
+            0 => Err(RelayerError::Config(format!("PKCS#11 {} key '{}' not found", kind, label))),
+            _ => Err(RelayerError::Config(format!("PKCS#11 {} key label '{}' is ambiguous", kind, label))),
+        }
+    }
+
+    fn attribute_bytes(&self, object: CkObjectHandle, attr_type: CkUlong) -> Result<Vec<u8>> {
+        let f = self.functions();
+        let mut template = [CkAttribute { attr_type, value: ptr::null_mut(), value_len: 0 }];
+        // SAFETY: 先查询长度再分配缓冲区
+        unsafe {
+            check((f.c_get_attribute_value)(self.handle, object, template.as_mut_ptr(), 1), "C_GetAttributeValue")?;
+            let mut value = vec![0u8; template[0].value_len as usize];
+            template[0].value = value.as_mut_ptr() as *mut c_void;
+            check((f.c_get_attribute_value)(self.handle, object, template.as_mut_ptr(), 1), "C_GetAttributeValue")?;
+            value.truncate(template[0].value_len as usize);
+            Ok(value)
+        }
+    }
+
+    fn attribute_ulong(&self, object: CkObjectHandle, attr_type: CkUlong) -> Result<CkUlong> {
+        let f = self.functions();
+        let mut value: CkUlong = 0;
+        let mut template = [attribute(attr_type, &value)];
+        template[0].value = &mut value as *mut CkUlong as *mut c_void;
+        // SAFETY: value 在调用期间有效
+        unsafe {
+            check((f.c_get_attribute_value)(self.handle, object, template.as_mut_ptr(), 1), "C_GetAttributeValue")?;
+        }
+        Ok(value)
+    }
+
+    fn sign(&self, key: CkObjectHandle, mechanism: CkUlong, data: &[u8]) -> Result<Vec<u8>> {
+        let f = self.functions();
+        let mechanism = CkMechanism { mechanism, parameter: ptr::null_mut(), parameter_len: 0 };
+        let mut signature = vec![0u8; 128];
+        let mut len = signature.len() as CkUlong;
+        // SAFETY: 输入和输出缓冲区在调用期间有效
+        unsafe {
+            check((f.c_sign_init)(self.handle, &mechanism, key), "C_SignInit")?;
+            check(
+                (f.c_sign)(self.handle, data.as_ptr(), data.len() as CkUlong, signature.as_mut_ptr(), &mut len),
+                "C_Sign",
+            )?;
+        }
+        signature.truncate(len as usize);
+        Ok(signature)
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        // SAFETY: 只清理 open 中成功获取的资源
+        unsafe {
+            if self.initialized {
+                let f = &*self.functions;
+                if self.handle != 0 {
+                    (f.c_close_session)(self.handle);
+                }
+                let mut sessions = MODULE_SESSIONS.lock().unwrap_or_else(|e| e.into_inner());
+                if let Some(count) = sessions.as_mut().and_then(|s| s.get_mut(&self.module)) {
+                    *count -= 1;
+                    if *count == 0 {
+                        (f.c_finalize)(ptr::null_mut());
+                    }
+                }
+            }
+            libc::dlclose(self.library);
+        }
+    }
+}
+
+/// 去掉 CKA_EC_POINT 的 DER OCTET STRING 包装（部分模块直接返回原始点）
+fn unwrap_ec_point(point: &[u8]) -> &[u8] {
+    match point {
+        [0x04, len, rest @ ..] if *len as usize == rest.len() && *len < 0x80 => rest,
+        [0x04, 0x81, len, rest @ ..] if *len as usize == rest.len() => rest,
+        _ => point,
+    }
+}
+
+/// HSM 中的 relayer 密钥
+pub struct Pkcs11Key {
+    session: Mutex<Session>,
+    private_key: CkObjectHandle,
+    key_type: CkUlong,
+    public_key: Vec<u8>,
+    label: String,
+}
+
+impl Pkcs11Key {
+    /// 打开 token 并按标签查找密钥
+    pub fn open(config: &HsmConfig) -> Result<Self> {
+        let required = |value: Option<&str>, field: &str| {
+            value
+                .map(str::to_string)
+                .ok_or_else(|| RelayerError::Config(format!("hsm.{} is required for the pkcs11 signer", field)))
+        };
+        let module = config
+            .module
+            .as_deref()
+            .ok_or_else(|| RelayerError::Config("hsm.module is required for the pkcs11 signer".to_string()))?;
+        let token_label = required(config.token_label.as_deref(), "token_label")?;
+        let label = required(config.key_label.as_deref(), "key_label")?;
+        let pin = config
+            .pin
+            .as_ref()
+            .ok_or_else(|| RelayerError::Config("hsm.pin is required for the pkcs11 signer".to_string()))?;
+
+        let session = Session::open(module, &token_label, pin)?;
+        let private_key = session.find_object(CKO_PRIVATE_KEY, &label)?;
+        let public = session.find_object(CKO_PUBLIC_KEY, &label)?;
+        let key_type = session.attribute_ulong(private_key, CKA_KEY_TYPE)?;
+        let public_key = unwrap_ec_point(&session.attribute_bytes(public, CKA_EC_POINT)?).to_vec();
+
+        Ok(Self {
+            session: Mutex::new(session),
+            private_key,
+            key_type,
+            public_key,
+            label,
+        })
+    }
+
+    /// 公钥：secp256k1 为 65 字节未压缩点，Ed25519 为 32 字节
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    fn expect_type(&self, key_type: CkUlong, name: &str) -> Result<()> {
+        if self.key_type == key_type {
+            Ok(())
+        } else {
+            Err(RelayerError::Config(format!("PKCS#11 key '{}' is not a {} key", self.label, name)))
+        }
+    }
+
+    fn sign_raw(&self, mechanism: CkUlong, data: &[u8]) -> Result<Vec<u8>> {
+        let session = self
+            .session
+            .lock()
+            .map_err(|_| RelayerError::Signature("PKCS#11 session poisoned".to_string()))?;
+        session.sign(self.private_key, mechanism, data)
+    }
+
+    /// secp256k1：对 32 字节摘要签名，返回 r||s||v（low-s，v = 27/28）
+    #[cfg(feature = "pkcs11-secp256k1")]
+    pub fn sign_secp256k1_digest(&self, digest: &[u8; 32]) -> Result<Vec<u8>> {
+        use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+
+        self.expect_type(CKK_EC, "secp256k1")?;
+        let raw = self.sign_raw(CKM_ECDSA, digest)?;
+        let signature = Signature::from_slice(&raw)
+            .map_err(|e| RelayerError::Signature(format!("Invalid HSM ECDSA signature: {}", e)))?;
+        // EVM 只接受 low-s 签名
+        let signature = signature.normalize_s().unwrap_or(signature);
+
+        let verifying_key = VerifyingKey::from_sec1_bytes(&self.public_key)
+            .map_err(|e| RelayerError::Signature(format!("Invalid HSM public key: {}", e)))?;
+        let recovery_id = RecoveryId::trial_recovery_from_prehash(&verifying_key, digest, &signature)
+            .map_err(|e| RelayerError::Signature(format!("Failed to recover HSM signature: {}", e)))?;
+
+        let mut result = signature.to_bytes().to_vec();
+        result.push(recovery_id.to_byte() + 27);
+        Ok(result)
+    }
+
+    /// Ed25519：对原始消息签名，返回 64 字节签名
+    pub fn sign_ed25519(&self, message: &[u8]) -> Result<Vec<u8>> {
+        self.expect_type(CKK_EC_EDWARDS, "Ed25519")?;
+        let signature = self.sign_raw(CKM_EDDSA, message)?;
+        if signature.len() != 64 {
+            return Err(RelayerError::Signature(format!(
+                "HSM returned {}-byte Ed25519 signature",
+                signature.len()
+            )));
+        }
+        Ok(signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    //! 需要 SoftHSM：
+    //!
+    //! ```text
+    //! softhsm2-util --init-token --free --label relayer-test --pin 1234 --so-pin 5678
+    //! PKCS11_MODULE=/usr/lib/softhsm/libsofthsm2.so PKCS11_TOKEN=relayer-test PKCS11_PIN=1234 \
+    //!     cargo test --features pkcs11-secp256k1 pkcs11
+    //! ```
+    //!
+    //! 未设置环境变量时跳过；CI 同时设置 `PKCS11_REQUIRED=1`，缺少 SoftHSM 时测试失败而不是跳过。
+
+    use super::*;
+
+    const CKA_TOKEN: CkUlong = 0x1;
+    const CKA_EC_PARAMS: CkUlong = 0x180;
+    #[cfg(feature = "pkcs11-secp256k1")]
+    const CKM_EC_KEY_PAIR_GEN: CkUlong = 0x1040;
+    const CKM_EC_EDWARDS_KEY_PAIR_GEN: CkUlong = 0x1055;
+    const CKA_PRIVATE: CkUlong = 0x2;
+    const CKA_SIGN: CkUlong = 0x108;
+    /// secp256k1 曲线 OID 1.3.132.0.10 (DER)
+    #[cfg(feature = "pkcs11-secp256k1")]
+    const SECP256K1_PARAMS: [u8; 7] = [0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x0a];
+    /// Ed25519 OID 1.3.101.112 (DER)
+    const ED25519_PARAMS: [u8; 5] = [0x06, 0x03, 0x2b, 0x65, 0x70];
+
+    fn softhsm() -> Option<HsmConfig> {
+        Some(HsmConfig {
+            module: Some(std::env::var("PKCS11_MODULE").ok()?.into()),
+            token_label: Some(std::env::var("PKCS11_TOKEN").ok()?),
+            pin: Some(Secret::new(std::env::var("PKCS11_PIN").ok()?)),
+            key_label: None,
+        })
+    }
+
+    /// `CK_FUNCTION_LIST` 中用到的函数在 PKCS#11 v2.40 规范里的序号（version 之后从 0 开始）
+    const FUNCTION_SLOTS: [(&str, usize, usize); 16] = [
+        ("C_Initialize", 0, std::mem::offset_of!(CkFunctionList, c_initialize)),
+        ("C_Finalize", 1, std::mem::offset_of!(CkFunctionList, c_finalize)),
+        ("C_GetSlotList", 4, std::mem::offset_of!(CkFunctionList, c_get_slot_list)),
+        ("C_GetTokenInfo", 6, std::mem::offset_of!(CkFunctionList, c_get_token_info)),
+        ("C_OpenSession", 12, std::mem::offset_of!(CkFunctionList, c_open_session)),
+        ("C_CloseSession", 13, std::mem::offset_of!(CkFunctionList, c_close_session)),
+        ("C_Login", 18, std::mem::offset_of!(CkFunctionList, c_login)),
+        ("C_DestroyObject", 22, std::mem::offset_of!(CkFunctionList, c_destroy_object)),
+        ("C_GetAttributeValue", 24, std::mem::offset_of!(CkFunctionList, c_get_attribute_value)),
+        ("C_FindObjectsInit", 26, std::mem::offset_of!(CkFunctionList, c_find_objects_init)),
+        ("C_FindObjects", 27, std::mem::offset_of!(CkFunctionList, c_find_objects)),
+        ("C_FindObjectsFinal", 28, std::mem::offset_of!(CkFunctionList, c_find_objects_final)),
+        ("C_SignInit", 42, std::mem::offset_of!(CkFunctionList, c_sign_init)),
+        ("C_Sign", 43, std::mem::offset_of!(CkFunctionList, c_sign)),
+        ("C_GenerateKey", 58, std::mem::offset_of!(CkFunctionList, c_generate_key)),
+        ("C_GenerateKeyPair", 59, std::mem::offset_of!(CkFunctionList, c_generate_key_pair)),
+    ];
+
+    #[test]
+    fn test_function_list_layout() {
+        // CK_VERSION 两个字节，补齐到指针对齐后依次排列函数指针
+        let pointer = std::mem::size_of::<usize>();
+        assert_eq!(std::mem::size_of::<Unused>(), pointer);
+        for (name, index, offset) in FUNCTION_SLOTS {
+            assert_eq!(offset, pointer * (index + 1), "{} is in the wrong CK_FUNCTION_LIST slot", name);
+        }
+        // 函数表以 C_GenerateKeyPair 结尾，只读取规范函数表的前缀
+        assert_eq!(std::mem::size_of::<CkFunctionList>(), pointer * 61);
+        assert_eq!(std::mem::align_of::<CkFunctionList>(), pointer);
+    }
+
+    /// 模块返回的函数表中每个用到的槽位都指向同名的导出函数
+    fn assert_exported_functions(session: &Session) {
+        let table = session.functions as *const u8;
+        for (name, _, offset) in FUNCTION_SLOTS {
+            let symbol = CString::new(name).unwrap();
+            // SAFETY: 函数表至少包含到 C_GenerateKeyPair 的全部槽位，library 在会话关闭前有效
+            let (slot, exported) = unsafe {
+                (*(table.add(offset) as *const *mut c_void), libc::dlsym(session.library, symbol.as_ptr()))
+            };
+            assert!(!exported.is_null(), "module does not export {}", name);
+            assert_eq!(slot, exported, "CK_FUNCTION_LIST slot for {} points elsewhere", name);
+        }
+    }
+
+    /// 在 token 中生成一对会话外持久化的测试密钥，返回 (公钥句柄, 私钥句柄)
+    fn generate(session: &Session, mechanism: CkUlong, params: &[u8], label: &str) -> (CkObjectHandle, CkObjectHandle) {
+        let yes: u8 = 1;
+        let mechanism = CkMechanism { mechanism, parameter: ptr::null_mut(), parameter_len: 0 };
+        let mut public = [
+            attribute(CKA_TOKEN, &yes),
+            bytes_attribute(CKA_EC_PARAMS, params),
+            bytes_attribute(CKA_LABEL, label.as_bytes()),
+        ];
+        let mut private = [
+            attribute(CKA_TOKEN, &yes),
+            attribute(CKA_PRIVATE, &yes),
+            attribute(CKA_SIGN, &yes),
+            bytes_attribute(CKA_LABEL, label.as_bytes()),
+        ];
+        let (mut public_key, mut private_key) = (0, 0);
+        let f = session.functions();
+        unsafe {
+            check(
+                (f.c_generate_key_pair)(
+                    session.handle,
+                    &mechanism,
+                    public.as_mut_ptr(),
+                    public.len() as CkUlong,
+                    private.as_mut_ptr(),
+                    private.len() as CkUlong,
+                    &mut public_key,
+                    &mut private_key,
+                ),
+                "C_GenerateKeyPair",
+            )
+            .unwrap();
+        }
+        (public_key, private_key)
+    }
+
+    #[test]
+    fn test_pkcs11_softhsm_signatures() {
+        let Some(mut config) = softhsm() else {
+            assert!(std::env::var_os("PKCS11_REQUIRED").is_none(), "PKCS11_REQUIRED is set but SoftHSM is not configured");
+            eprintln!("PKCS11_MODULE/PKCS11_TOKEN/PKCS11_PIN not set, skipping SoftHSM test");
+            return;
+        };
+        let label = format!("relayer-test-{}", std::process::id());
+        let admin = Session::open(
+            config.module.as_deref().unwrap(),
+            config.token_label.as_deref().unwrap(),
+            config.pin.as_ref().unwrap(),
+        )
+        .unwrap();
+        assert_exported_functions(&admin);
+
+        // secp256k1：65 字节 r||s||v，可恢复出 HSM 公钥
+        #[cfg(feature = "pkcs11-secp256k1")]
+        let ecdsa_handles = {
+            use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+
+            let ecdsa_label = format!("{}-ecdsa", label);
+            let handles = generate(&admin, CKM_EC_KEY_PAIR_GEN, &SECP256K1_PARAMS, &ecdsa_label);
+            config.key_label = Some(ecdsa_label);
+            let key = Pkcs11Key::open(&config).unwrap();
+            assert_eq!(key.public_key().len(), 65);
+            let digest = [0x42u8; 32];
+            for _ in 0..8 {
+                let signature = key.sign_secp256k1_digest(&digest).unwrap();
+                assert_eq!(signature.len(), 65);
+                assert!(signature[64] == 27 || signature[64] == 28);
+                let parsed = Signature::from_slice(&signature[..64]).unwrap();
+                assert!(parsed.normalize_s().is_none(), "signature must be low-s");
+                let recovery_id = RecoveryId::from_byte(signature[64] - 27).unwrap();
+                let recovered = VerifyingKey::recover_from_prehash(&digest, &parsed, recovery_id).unwrap();
+                assert_eq!(recovered.to_encoded_point(false).as_bytes(), key.public_key());
+            }
+            assert!(key.sign_ed25519(b"message").is_err());
+            handles
+        };
+
+        // Ed25519：64 字节确定性签名
+        let ed25519_label = format!("{}-ed25519", label);
+        let ed25519_handles = generate(&admin, CKM_EC_EDWARDS_KEY_PAIR_GEN, &ED25519_PARAMS, &ed25519_label);
+        config.key_label = Some(ed25519_label);
+        let key = Pkcs11Key::open(&config).unwrap();
+        assert_eq!(key.public_key().len(), 32);
+        let first = key.sign_ed25519(b"borsh event").unwrap();
+        assert_eq!(first.len(), 64);
+        assert_eq!(first, key.sign_ed25519(b"borsh event").unwrap());
+        drop(key);
+
+        // 未知标签
+        config.key_label = Some(format!("{}-missing", label));
+        assert!(Pkcs11Key::open(&config).is_err());
+
+        let f = admin.functions();
+        #[cfg(feature = "pkcs11-secp256k1")]
+        for handle in [ecdsa_handles.0, ecdsa_handles.1] {
+            unsafe { (f.c_destroy_object)(admin.handle, handle) };
+        }
+        for handle in [ed25519_handles.0, ed25519_handles.1] {
+            unsafe { (f.c_destroy_object)(admin.handle, handle) };
+        }
+    }
+
+    #[test]
+    fn test_unwrap_ec_point() {
+        let mut der = vec![0x04, 0x20];
+        der.extend_from_slice(&[7u8; 32]);
+        assert_eq!(unwrap_ec_point(&der), &[7u8; 32]);
+        let mut der = vec![0x04, 0x41, 0x04];
+        der.extend_from_slice(&[9u8; 64]);
+        assert_eq!(unwrap_ec_point(&der).len(), 65);
+        assert_eq!(unwrap_ec_point(&[1, 2, 3]), &[1, 2, 3]);
+    }
+}
@@ -0,0 +1,413 @@
+//! 防重复签名（slashing protection）数据库
+//!
+//! 同一 (源链, 源合约, nonce) 只能对一份 `StakeEventData` 签名：对不同的事件数据签名正是链上
+//! `InvalidEventData` 防范的 equivocation。签名前先持久化事件的签名根（`sha256(Borsh 序列化的事件)`），
+//! 记录已存在且签名根不同时拒绝签名；相同事件可以重复签名（任务重试）。
+//!
+//! 存储：`<path>/<scheme>-<签名者>/<转账 ID>.json`，每条记录一个文件。临时文件 fsync 后以 hard link
+//! 创建目标文件（已存在则失败，不会覆盖），崩溃不会留下半条记录，多个进程共用目录时也只有一份事件能写入。
+//! 记录先于签名落盘：签名前崩溃只会留下一条记录，重启后对同一事件仍可签名。
+//!
+//! [`ProtectedSigner`] 包装本地 / HSM / 远程签名器，relayer 和签名服务都通过它签名。
+//! 迁移主机时用 `relayer-protection export / import` 搬迁记录（格式见 [`Interchange`]）。
+
+use crate::address::ChainAddress;
+use crate::alerts::{self, Alert, Severity};
+use crate::config::Config;
+use crate::encoding;
+use crate::error::{RelayerError, Result};
+use crate::signer::{AttestationSigner, SignatureScheme};
+use crate::types::{self, StakeEventData};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::{error, warn};
+
+/// 交换格式版本
+pub const INTERCHANGE_VERSION: u32 = 1;
+
+/// 一条已签名记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedEvent {
+    pub source_chain_id: u64,
+    pub source_contract: ChainAddress,
+    pub nonce: u64,
+    /// sha256(Borsh 序列化的事件)，hex
+    pub signing_root: String,
+}
+
+impl SignedEvent {
+    pub fn for_event(event: &StakeEventData) -> Self {
+        Self {
+            source_chain_id: event.source_chain_id,
+            source_contract: event.source_contract,
+            nonce: event.nonce,
+            signing_root: hex::encode(Sha256::digest(encoding::svm_event_message(event))),
+        }
+    }
+
+    fn id(&self) -> String {
+        types::transfer_id(self.source_chain_id, &self.source_contract, self.nonce)
+    }
+}
+
+/// 导出 / 导入的交换格式
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Interchange {
+    pub version: u32,
+    pub keys: Vec<KeyHistory>,
+}
+
+/// 一个签名密钥的签名历史
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyHistory {
+    pub scheme: SignatureScheme,
+    pub identity: String,
+    pub signed_events: Vec<SignedEvent>,
+}
+
+/// 导入结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    /// 本地已有相同记录
+    pub existing: usize,
+}
+
+fn scheme_name(scheme: SignatureScheme) -> &'static str {
+    match scheme {
+        SignatureScheme::Secp256k1 => "secp256k1",
+        SignatureScheme::Ed25519 => "ed25519",
+    }
+}
+
+fn conflict(existing: &SignedEvent, record: &SignedEvent) -> RelayerError {
+    RelayerError::DoubleSign(format!(
+        "{} was already signed with signing root {}, refusing {}",
+        record.id(),
+        existing.signing_root,
+        record.signing_root
+    ))
+}
+
+/// 文件防重复签名数据库
+#[derive(Debug)]
+pub struct SlashingProtection {
+    dir: PathBuf,
+    /// 串行化同一进程内的 检查 + 写入
+    lock: Mutex<()>,
+}
+
+impl SlashingProtection {
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| RelayerError::Database(format!("Failed to create {}: {}", dir.display(), e)))?;
+        Ok(Self { dir, lock: Mutex::new(()) })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn key_dir(&self, scheme: SignatureScheme, identity: &str) -> PathBuf {
+        self.dir.join(format!("{}-{}", scheme_name(scheme), scheme.normalize(identity)))
+    }
+
+    /// 签名前调用：记录事件的签名根，同一转账已签过不同事件时返回 [`RelayerError::DoubleSign`]
+    pub fn check_and_record(&self, scheme: SignatureScheme, identity: &str, event: &StakeEventData) -> Result<()> {
+        let _guard = self.lock.lock().unwrap();
+        Self::insert(&self.key_dir(scheme, identity), &SignedEvent::for_event(event)).map(|_| ())
+    }
+
+    /// 写入一条记录，返回是否新写入；已有不同签名根时报错
+    fn insert(dir: &Path, record: &SignedEvent) -> Result<bool> {
+        let db_error = |e: std::io::Error| RelayerError::Database(format!("{}: {}", dir.display(), e));
+        let path = dir.join(format!("{}.json", record.id()));
+        if let Some(existing) = Self::read_record(&path)? {
+            return if existing.signing_root == record.signing_root { Ok(false) } else { Err(conflict(&existing, record)) };
+        }
+
+        std::fs::create_dir_all(dir).map_err(db_error)?;
+        let tmp = dir.join(format!(".{}.{}.tmp", record.id(), std::process::id()));
+        let json = serde_json::to_vec_pretty(record).map_err(|e| RelayerError::Serialization(e.to_string()))?;
+        File::create(&tmp)
+            .and_then(|mut f| {
+                f.write_all(&json)?;
+                f.sync_all()
+            })
+            .map_err(db_error)?;
+
+        // hard link 在目标已存在时失败，不会覆盖其他进程刚写入的记录
+        let linked = std::fs::hard_link(&tmp, &path);
+        let _ = std::fs::remove_file(&tmp);
+        match linked {
+            Ok(()) => {
+                File::open(dir).and_then(|d| d.sync_all()).map_err(db_error)?;
+                Ok(true)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => match Self::read_record(&path)? {
+                Some(existing) if existing.signing_root == record.signing_root => Ok(false),
+                Some(existing) => Err(conflict(&existing, record)),
+                None => Err(db_error(e)),
+            },
+            Err(e) => Err(db_error(e)),
+        }
+    }
+
+    fn read_record(path: &Path) -> Result<Option<SignedEvent>> {
+        match std::fs::read(path) {
+            Ok(content) => serde_json::from_slice(&content)
+                .map(Some)
+                .map_err(|e| RelayerError::Serialization(format!("{}: {}", path.display(), e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(RelayerError::Database(format!("Failed to read {}: {}", path.display(), e))),
+        }
+    }
+
+    fn read_dir(dir: &Path) -> Result<Vec<PathBuf>> {
+        let entries = std::fs::read_dir(dir)
+            .map_err(|e| RelayerError::Database(format!("Failed to read {}: {}", dir.display(), e)))?;
+        let mut paths: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| !path.file_name().and_then(|s| s.to_str()).is_some_and(|s| s.starts_with('.')))
+            .collect();
+        paths.sort();
+        Ok(paths)
+    }
+
+    /// 导出所有签名密钥的签名历史
+    pub fn export(&self) -> Result<Interchange> {
+        let _guard = self.lock.lock().unwrap();
+        let mut keys = Vec::new();
+        for key_dir in Self::read_dir(&self.dir)?.into_iter().filter(|path| path.is_dir()) {
+            let name = key_dir.file_name().and_then(|s| s.to_str()).unwrap_or_default();
+            let Some((scheme, identity)) = name
+                .split_once('-')
+                .and_then(|(scheme, identity)| Some((serde_json::from_value(scheme.into()).ok()?, identity)))
+            else {
+                warn!(dir = %key_dir.display(), "Skipping unrecognized protection directory");
+                continue;
+            };
+
+            let mut signed_events = Vec::new();
+            for path in Self::read_dir(&key_dir)? {
+                if path.extension().and_then(|s| s.to_str()) == Some("json") {
+                    signed_events.extend(Self::read_record(&path)?);
+                }
+            }
+            signed_events.sort_by_key(|record| (record.source_chain_id, record.source_contract.to_string(), record.nonce));
+            keys.push(KeyHistory { scheme, identity: identity.to_string(), signed_events });
+        }
+        Ok(Interchange { version: INTERCHANGE_VERSION, keys })
+    }
+
+    /// 导入签名历史；任何一条与本地记录（或导入文件自身）冲突时整体拒绝，不写入任何记录
+    pub fn import(&self, interchange: &Interchange) -> Result<ImportReport> {
+        if interchange.version != INTERCHANGE_VERSION {
+            return Err(RelayerError::Config(format!(
+                "Unsupported interchange version {} (expected {})",
+                interchange.version, INTERCHANGE_VERSION
+            )));
+        }
+
+        let _guard = self.lock.lock().unwrap();
+        let mut conflicts = Vec::new();
+        let mut seen: HashMap<PathBuf, &SignedEvent> = HashMap::new();
+        for key in &interchange.keys {
+            let dir = self.key_dir(key.scheme, &key.identity);
+            for record in &key.signed_events {
+                let path = dir.join(format!("{}.json", record.id()));
+                let existing = match seen.get(&path) {
+                    Some(existing) => Some((*existing).clone()),
+                    None => Self::read_record(&path)?,
+                };
+                match existing {
+                    Some(existing) if existing.signing_root != record.signing_root => {
+                        conflicts.push(conflict(&existing, record).to_string());
+                    }
+                    _ => {
+                        seen.insert(path, record);
+                    }
+                }
+            }
+        }
+        if !conflicts.is_empty() {
+            return Err(RelayerError::DoubleSign(format!(
+                "{} conflicting records, nothing imported: {}",
+                conflicts.len(),
+                conflicts.join("; ")
+            )));
+        }
+
+        let mut report = ImportReport::default();
+        for key in &interchange.keys {
+            let dir = self.key_dir(key.scheme, &key.identity);
+            for record in &key.signed_events {
+                if Self::insert(&dir, record)? {
+                    report.imported += 1;
+                } else {
+                    report.existing += 1;
+                }
+            }
+        }
+        Ok(report)
+    }
+}
+
+/// 签名前检查防重复签名数据库的签名器
+pub struct ProtectedSigner {
+    inner: Arc<dyn AttestationSigner>,
+    protection: Arc<SlashingProtection>,
+}
+
+impl ProtectedSigner {
+    pub fn new(inner: Arc<dyn AttestationSigner>, protection: Arc<SlashingProtection>) -> Self {
+        Self { inner, protection }
+    }
+
+    fn check(&self, event: &StakeEventData) -> Result<()> {
+        let result = self.protection.check_and_record(self.inner.scheme(), &self.inner.identity(), event);
+        if let Err(e @ RelayerError::DoubleSign(_)) = &result {
+            error!(nonce = event.nonce, error = %e, "🛑 Refused conflicting attestation");
+            alerts::notify(Alert::new(
+                Severity::Critical,
+                format!("double-sign:{}", types::transfer_id(event.source_chain_id, &event.source_contract, event.nonce)),
+                "Refused to double-sign an attestation",
+                e.to_string(),
+            ));
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl AttestationSigner for ProtectedSigner {
+    fn scheme(&self) -> SignatureScheme {
+        self.inner.scheme()
+    }
+
+    fn identity(&self) -> String {
+        self.inner.identity()
+    }
+
+    async fn sign_event(&self, event: &StakeEventData) -> Result<Vec<u8>> {
+        self.check(event)?;
+        self.inner.sign_event(event).await
+    }
+
+    async fn sign_transaction(&self, event: &StakeEventData, transaction: &[u8]) -> Result<Vec<u8>> {
+        // 提交交易同样代表对该事件的证明
+        self.check(event)?;
+        self.inner.sign_transaction(event, transaction).await
+    }
+}
+
+/// 按 `protection.path` 打开数据库并包装签名器
+pub fn protect(signer: Arc<dyn AttestationSigner>, config: &Config) -> Result<Arc<dyn AttestationSigner>> {
+    let protection = SlashingProtection::open(&config.protection.path)?;
+    Ok(Arc::new(ProtectedSigner::new(signer, Arc::new(protection))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{event_with_amount, TempDir};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const ADDRESS: &str = "0xAbCd000000000000000000000000000000000001";
+
+    #[test]
+    fn test_refuses_conflicting_event() {
+        let dir = TempDir::new("protection-conflict");
+        let db = SlashingProtection::open(&*dir).unwrap();
+        let scheme = SignatureScheme::Secp256k1;
+        db.check_and_record(scheme, ADDRESS, &event_with_amount(1, 100)).unwrap();
+        // 相同事件可以重复签名
+        db.check_and_record(scheme, &ADDRESS.to_lowercase(), &event_with_amount(1, 100)).unwrap();
+        db.check_and_record(scheme, ADDRESS, &event_with_amount(2, 500)).unwrap();
+
+        let err = db.check_and_record(scheme, ADDRESS, &event_with_amount(1, 999)).unwrap_err();
+        assert!(matches!(err, RelayerError::DoubleSign(_)) && err.is_permanent(), "{}", err);
+        // 其他密钥不受影响
+        db.check_and_record(SignatureScheme::Ed25519, "MockKey", &event_with_amount(1, 999)).unwrap();
+
+        // 重新打开后仍然生效
+        let db = SlashingProtection::open(db.dir()).unwrap();
+        assert!(db.check_and_record(scheme, ADDRESS, &event_with_amount(2, 501)).is_err());
+    }
+
+    #[test]
+    fn test_export_import() {
+        let old_dir = TempDir::new("protection-export");
+        let old = SlashingProtection::open(&*old_dir).unwrap();
+        for nonce in 1..=3 {
+            old.check_and_record(SignatureScheme::Secp256k1, ADDRESS, &event_with_amount(nonce, 100)).unwrap();
+        }
+        old.check_and_record(SignatureScheme::Ed25519, "MockKey", &event_with_amount(7, 100)).unwrap();
+        let exported = old.export().unwrap();
+        assert_eq!(exported.keys.len(), 2);
+        assert_eq!(exported.keys.iter().map(|k| k.signed_events.len()).sum::<usize>(), 4);
+
+        let json = serde_json::to_string(&exported).unwrap();
+        let new_dir = TempDir::new("protection-import");
+        let new = SlashingProtection::open(&*new_dir).unwrap();
+        new.check_and_record(SignatureScheme::Secp256k1, ADDRESS, &event_with_amount(1, 100)).unwrap();
+        let report = new.import(&serde_json::from_str(&json).unwrap()).unwrap();
+        assert_eq!(report, ImportReport { imported: 3, existing: 1 });
+        assert_eq!(new.export().unwrap(), exported);
+        assert!(new.check_and_record(SignatureScheme::Secp256k1, ADDRESS, &event_with_amount(3, 1)).is_err());
+
+        // 冲突的历史整体拒绝
+        let conflicting_dir = TempDir::new("protection-import-conflict");
+        let conflicting = SlashingProtection::open(&*conflicting_dir).unwrap();
+        conflicting.check_and_record(SignatureScheme::Ed25519, "MockKey", &event_with_amount(7, 1)).unwrap();
+        let err = conflicting.import(&exported).unwrap_err();
+        assert!(err.to_string().contains("1 conflicting records"), "{}", err);
+        assert_eq!(conflicting.export().unwrap().keys[0].signed_events.len(), 1);
+        assert!(conflicting.export().unwrap().keys.iter().all(|k| k.scheme == SignatureScheme::Ed25519));
+    }
+
+    struct CountingSigner(AtomicUsize);
+
+    #[async_trait]
+    impl AttestationSigner for CountingSigner {
+        fn scheme(&self) -> SignatureScheme {
+            SignatureScheme::Ed25519
+        }
+
+        fn identity(&self) -> String {
+            "MockKey".to_string()
+        }
+
+        async fn sign_event(&self, event: &StakeEventData) -> Result<Vec<u8>> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(vec![event.nonce as u8; 64])
+        }
+
+        async fn sign_transaction(&self, _event: &StakeEventData, transaction: &[u8]) -> Result<Vec<u8>> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(vec![transaction[0]; 64])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_protected_signer_checks_before_signing() {
+        let inner = Arc::new(CountingSigner(AtomicUsize::new(0)));
+        let dir = TempDir::new("protection-signer");
+        let db = Arc::new(SlashingProtection::open(&*dir).unwrap());
+        let signer = ProtectedSigner::new(inner.clone(), db);
+
+        signer.sign_event(&event_with_amount(5, 100)).await.unwrap();
+        signer.sign_transaction(&event_with_amount(5, 100), &[9]).await.unwrap();
+        assert!(signer.sign_event(&event_with_amount(5, 200)).await.is_err());
+        assert!(signer.sign_transaction(&event_with_amount(5, 200), &[9]).await.is_err());
+        assert_eq!(inner.0.load(Ordering::Relaxed), 2);
+    }
+}
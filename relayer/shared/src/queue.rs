@@ -0,0 +1,742 @@
+use crate::config::Config;
+use crate::error::{RelayerError, Result};
+use crate::telemetry::{self, TraceContext};
+use crate::types::{SourceRef, StakeEventData, Task};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// 队列消息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueMessage {
+    /// 与任务 ID 相同，用于幂等去重
+    pub id: String,
+    pub event: StakeEventData,
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    pub enqueued_at: chrono::DateTime<chrono::Utc>,
+    /// 监听器捕获事件时的追踪上下文
+    #[serde(default, skip_serializing_if = "TraceContext::is_empty")]
+    pub trace_context: TraceContext,
+    /// 事件在源链上的位置
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceRef>,
+    /// 事件尚未达到最终性：submitter 只登记任务，不签名
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub awaiting_finality: bool,
+    /// 事件已被源链回滚：submitter 撤回对应任务，不签名
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub retracted: bool,
+    /// 事件入口校验失败的原因：submitter 登记为 blocked 任务，不签名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rejected: Option<String>,
+}
+
+impl QueueMessage {
+    pub fn new(event: StakeEventData) -> Self {
+        Self {
+            id: Task::id_for(&event),
+            event,
+            attempts: 0,
+            last_error: None,
+            enqueued_at: chrono::Utc::now(),
+            trace_context: telemetry::current_context(),
+            source: None,
+            awaiting_finality: false,
+            retracted: false,
+            rejected: None,
+        }
+    }
+
+    /// 重新投递已有任务（沿用其追踪上下文和源链位置）
+    pub fn for_task(task: &Task) -> Self {
+        Self {
+            trace_context: task.trace_context.clone(),
+            source: task.source.clone(),
+            ..Self::new(task.event_data.clone())
+        }
+    }
+}
+
+/// 已取出、等待确认的消息
+#[derive(Debug, Clone)]
+pub struct Delivery {
+    pub message: QueueMessage,
+    /// 后端相关的回执（文件名 / Redis 原始负载）
+    receipt: String,
+}
+
+/// 队列深度
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct QueueDepth {
+    pub pending: u64,
+    pub inflight: u64,
+    pub dead: u64,
+}
+
+/// listener 与 submitter 之间的工作队列
+///
+/// 取出的消息在可见性超时内未被 ack 时会重新出队，因此 submitter 崩溃不会丢失消息。
+/// 只有 `RedisWorkQueue` 按通道加租约、支持多个 submitter 并发消费；
+/// [`FileWorkQueue`] 只支持单个 submitter。
+#[async_trait]
+pub trait WorkQueue: Send + Sync {
+    /// 入队
+    async fn push(&self, message: &QueueMessage) -> Result<()>;
+
+    /// 出队，消息在 `visibility` 时间内对其他消费者不可见
+    async fn pop(&self, visibility: Duration) -> Result<Option<Delivery>>;
+
+    /// 确认处理完成，删除消息
+    async fn ack(&self, delivery: &Delivery) -> Result<()>;
+
+    /// 处理失败，`delay` 之后重新可见（attempts + 1）
+    async fn nack(&self, delivery: &Delivery, error: &str, delay: Duration) -> Result<()>;
+
+    /// 移入死信队列，不再投递
+    async fn dead_letter(&self, delivery: &Delivery, error: &str) -> Result<()>;
+
+    /// 当前队列深度
+    async fn depth(&self) -> Result<QueueDepth>;
+}
+
+/// 根据配置打开工作队列
+pub async fn open_work_queue(config: &Config) -> Result<Arc<dyn WorkQueue>> {
+    match config.queue.backend.as_str() {
+        "file" => {
+            info!(path = %config.queue.path.display(), "Using file work queue");
+            Ok(Arc::new(FileWorkQueue::open(&config.queue.path)?))
+        }
+        #[cfg(feature = "queue")]
+        "redis" => {
+            let url = config
+                .queue
+                .redis_url
+                .as_deref()
+                .ok_or_else(|| RelayerError::Config("QUEUE__REDIS_URL is not set".to_string()))?;
+            info!(prefix = %config.queue.redis_prefix, "Using redis work queue");
+            Ok(Arc::new(RedisWorkQueue::connect(url, &config.queue.redis_prefix).await?))
+        }
+        other => Err(RelayerError::Config(format!(
+            "Unsupported queue backend: {} (redis requires the `queue` feature)",
+            other
+        ))),
+    }
+}
+
+/// fsync 文件所在目录，使 rename 落盘
+fn sync_dir(path: &Path) -> std::io::Result<()> {
+    match path.parent() {
+        Some(dir) => std::fs::File::open(dir)?.sync_all(),
+        None => Ok(()),
+    }
+}
+
+fn now_ms() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+fn queue_err(action: &str, e: impl std::fmt::Display) -> RelayerError {
+    RelayerError::Queue(format!("{}: {}", action, e))
+}
+
+/// 消息所在的 nonce 通道：任务 ID `<源链>-<源合约>-<nonce>` 去掉 nonce
+fn lane(id: &str) -> &str {
+    id.rsplit_once('-').map_or(id, |(lane, _)| lane)
+}
+
+/// 文件工作队列
+///
+/// 目录布局：
+/// - `pending/<id>.json`：待处理
+/// - `inflight/<deadline_ms>-<id>.json`：已被取出，deadline 之后重新可见
+/// - `dead/<id>.json`：死信
+///
+/// 出队通过 rename 原子地认领单条消息，但没有通道租约：一个 submitter 持有 nonce N 时，
+/// 另一个 submitter 可以取出同一通道的 N+1 并先行提交，目标合约随后会永久拒绝 N。
+/// 因此只支持单个 submitter 消费，多实例部署需使用 Redis 后端。
+#[derive(Debug, Clone)]
+pub struct FileWorkQueue {
+    pending: PathBuf,
+    inflight: PathBuf,
+    dead: PathBuf,
+}
+
+impl FileWorkQueue {
+    pub fn open(dir: &Path) -> Result<Self> {
+        let queue = Self {
+            pending: dir.join("pending"),
+            inflight: dir.join("inflight"),
+            dead: dir.join("dead"),
+        };
+        for sub in [&queue.pending, &queue.inflight, &queue.dead] {
+            std::fs::create_dir_all(sub).map_err(|e| queue_err("create queue directory", e))?;
+        }
+        Ok(queue)
+    }
+
+    /// 先写临时文件并 fsync，rename 后再 fsync 目录：返回时消息已落盘，
+    /// 监听器随后推进的检查点不会越过崩溃时丢失的消息
+    fn write_atomic(path: &Path, message: &QueueMessage) -> Result<()> {
+        let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+        let json = serde_json::to_vec_pretty(message).map_err(|e| RelayerError::Serialization(e.to_string()))?;
+        std::fs::File::create(&tmp)
+            .and_then(|mut f| {
+                use std::io::Write;
+                f.write_all(&json)?;
+                f.sync_all()
+            })
+            .and_then(|_| std::fs::rename(&tmp, path))
+            .and_then(|_| sync_dir(path))
+            .map_err(|e| queue_err("write message", e))
+    }
+
+    fn read(path: &Path) -> Result<QueueMessage> {
+        let content = std::fs::read(path).map_err(|e| queue_err("read message", e))?;
+        serde_json::from_slice(&content).map_err(|e| RelayerError::Serialization(e.to_string()))
+    }
+
+    fn json_files(dir: &Path) -> Result<Vec<PathBuf>> {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(dir)
+            .map_err(|e| queue_err("list queue directory", e))?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().and_then(|s| s.to_str()) == Some("json"))
+            .collect();
+        files.sort();
+        Ok(files)
+    }
+
+    /// 待处理消息的认领顺序：同一源合约按 nonce 数值升序（文件名按字典序会把 `-10` 排在 `-9` 之前），
+    /// 目标合约要求 nonce 递增，乱序提交的低 nonce 会被永久拒绝
+    fn pending_files(&self) -> Result<Vec<PathBuf>> {
+        let mut files = Self::json_files(&self.pending)?;
+        files.sort_by_cached_key(|path| {
+            let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+            let nonce = stem.rsplit_once('-').and_then(|(_, nonce)| nonce.parse::<u64>().ok());
+            (lane(stem).to_string(), nonce.unwrap_or(u64::MAX))
+        });
+        Ok(files)
+    }
+
+    fn parse_inflight(path: &Path) -> Option<(i64, String)> {
+        let stem = path.file_stem()?.to_str()?;
+        let (deadline, id) = stem.split_once('-')?;
+        Some((deadline.parse().ok()?, id.to_string()))
+    }
+
+    /// 把超过可见性期限的消息放回 pending
+    ///
+    /// 用 hard link 而不是 rename：pending 中已有同一 ID 的消息时，那是监听器之后推送的
+    /// 更新版本（已确认或已撤回），保留它并丢弃过期的旧副本
+    fn requeue_expired(&self) -> Result<()> {
+        let now = now_ms();
+        for path in Self::json_files(&self.inflight)? {
+            let Some((deadline, id)) = Self::parse_inflight(&path) else {
+                continue;
+            };
+            if deadline > now {
+                continue;
+            }
+            match std::fs::hard_link(&path, self.pending.join(format!("{}.json", id))) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    warn!(id = %id, "Dropping expired inflight message superseded by a newer pending message");
+                }
+                // 其他消费者可能已抢先处理
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(queue_err("requeue message", e)),
+            }
+            let _ = std::fs::remove_file(&path);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl WorkQueue for FileWorkQueue {
+    async fn push(&self, message: &QueueMessage) -> Result<()> {
+        Self::write_atomic(&self.pending.join(format!("{}.json", message.id)), message)
+    }
+
+    async fn pop(&self, visibility: Duration) -> Result<Option<Delivery>> {
+        self.requeue_expired()?;
+
+        for path in self.pending_files()? {
+            let Some(id) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let receipt = format!("{}-{}.json", now_ms() + visibility.as_millis() as i64, id);
+            let claimed = self.inflight.join(&receipt);
+            // rename 失败说明已被其他消费者认领
+            if std::fs::rename(&path, &claimed).is_err() {
+                continue;
+            }
+            match Self::read(&claimed) {
+                Ok(message) => return Ok(Some(Delivery { message, receipt })),
+                Err(e) => {
+                    warn!(path = %claimed.display(), error = %e, "Moving unreadable message to dead letter");
+                    let _ = std::fs::rename(&claimed, self.dead.join(format!("{}.json", id)));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    async fn ack(&self, delivery: &Delivery) -> Result<()> {
+        match std::fs::remove_file(self.inflight.join(&delivery.receipt)) {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                warn!(id = %delivery.message.id, "Ack after visibility timeout, message may be redelivered");
+                Ok(())
+            }
+            Err(e) => Err(queue_err("ack message", e)),
+        }
+    }
+
+    async fn nack(&self, delivery: &Delivery, error: &str, delay: Duration) -> Result<()> {
+        let mut message = delivery.message.clone();
+        message.attempts += 1;
+        message.last_error = Some(error.to_string());
+
+        let current = self.inflight.join(&delivery.receipt);
+        let delayed = self
+            .inflight
+            .join(format!("{}-{}.json", now_ms() + delay.as_millis() as i64, message.id));
+        Self::write_atomic(&delayed, &message)?;
+        if current != delayed {
+            let _ = std::fs::remove_file(current);
+        }
+        Ok(())
+    }
+
+    async fn dead_letter(&self, delivery: &Delivery, error: &str) -> Result<()> {
+        let mut message = delivery.message.clone();
+        message.last_error = Some(error.to_string());
+        Self::write_atomic(&self.dead.join(format!("{}.json", message.id)), &message)?;
+        let _ = std::fs::remove_file(self.inflight.join(&delivery.receipt));
+        Ok(())
+    }
+
+    async fn depth(&self) -> Result<QueueDepth> {
+        Ok(QueueDepth {
+            pending: Self::json_files(&self.pending)?.len() as u64,
+            inflight: Self::json_files(&self.inflight)?.len() as u64,
+            dead: Self::json_files(&self.dead)?.len() as u64,
+        })
+    }
+}
+
+/// Redis 工作队列（`queue` feature）
+///
+/// - `<prefix>:lanes`：SET，有待处理消息的通道（源链 + 源合约，即任务 ID 去掉 nonce）
+/// - `<prefix>:pending:<lane>`：ZSET，通道内待处理的消息，score 为 nonce
+/// - `<prefix>:inflight`：ZSET，score 为重新可见的时间戳（毫秒）
+/// - `<prefix>:leases`：HASH，通道 → 持有该通道的 inflight 消息
+/// - `<prefix>:dead`：LIST，死信
+///
+/// 目标合约要求 nonce 递增，因此每个通道同时只有一条消息可以被取出：
+/// 消息被取出或 nack 延迟重试期间，同一通道的更高 nonce 不会投递给任何 submitter，
+/// 确认、死信或可见性超时后才释放通道。
+/// 出队、确认、重试都通过 Lua 脚本原子执行，支持多个 submitter 并发消费。
+/// 出队脚本会访问由前缀拼出、不在 `KEYS` 中的通道键，因此需要单机（或主从）Redis，不支持 Redis Cluster。
+#[cfg(feature = "queue")]
+#[derive(Clone)]
+pub struct RedisWorkQueue {
+    conn: redis::aio::ConnectionManager,
+    lanes: String,
+    pending: String,
+    inflight: String,
+    leases: String,
+    dead: String,
+}
+
+#[cfg(feature = "queue")]
+impl RedisWorkQueue {
+    /// 按 nonce 加入通道
+    const PUSH_SCRIPT: &'static str = r#"
+        redis.call('ZADD', KEYS[2], ARGV[2], ARGV[3])
+        redis.call('SADD', KEYS[1], ARGV[1])
+        return 1
+    "#;
+
+    /// 把到期的 inflight 消息放回所在通道并释放通道，再从未被占用的通道中取出 nonce 最小的一条
+    const POP_SCRIPT: &'static str = r#"
+        local expired = redis.call('ZRANGEBYSCORE', KEYS[2], '-inf', ARGV[1])
+        for _, msg in ipairs(expired) do
+            redis.call('ZREM', KEYS[2], msg)
+            local message = cjson.decode(msg)
+            local lane = string.match(message.id, '^(.*)%-%d+$') or message.id
+            if redis.call('HGET', KEYS[3], lane) == msg then
+                redis.call('HDEL', KEYS[3], lane)
+            end
+            redis.call('ZADD', ARGV[3] .. lane, message.event.nonce, msg)
+            redis.call('SADD', KEYS[1], lane)
+        end
+        for _, lane in ipairs(redis.call('SMEMBERS', KEYS[1])) do
+            local holder = redis.call('HGET', KEYS[3], lane)
+            if holder and not redis.call('ZSCORE', KEYS[2], holder) then
+                redis.call('HDEL', KEYS[3], lane)
+                holder = false
+            end
+            if not holder then
+                local pending = ARGV[3] .. lane
+                local msg = redis.call('ZRANGE', pending, 0, 0)[1]
+                if redis.call('ZCARD', pending) <= 1 then
+                    redis.call('SREM', KEYS[1], lane)
+                end
+                if msg then
+                    redis.call('ZREM', pending, msg)
+                    redis.call('ZADD', KEYS[2], ARGV[2], msg)
+                    redis.call('HSET', KEYS[3], lane, msg)
+                    return msg
+                end
+            end
+        end
+        return false
+    "#;
+
+    /// 确认：从 inflight 删除并释放通道
+    const ACK_SCRIPT: &'static str = r#"
+        local removed = redis.call('ZREM', KEYS[1], ARGV[2])
+        if redis.call('HGET', KEYS[2], ARGV[1]) == ARGV[2] then
+            redis.call('HDEL', KEYS[2], ARGV[1])
+        end
+        return removed
+    "#;
+
+    /// 替换 inflight 中的消息（nack），延迟期间继续占用通道
+    const REPLACE_SCRIPT: &'static str = r#"
+        redis.call('ZREM', KEYS[1], ARGV[2])
+        redis.call('ZADD', KEYS[1], ARGV[3], ARGV[4])
+        if redis.call('HGET', KEYS[2], ARGV[1]) == ARGV[2] then
+            redis.call('HSET', KEYS[2], ARGV[1], ARGV[4])
+        end
+        return 1
+    "#;
+
+    /// 从 inflight 移入死信并释放通道
+    const DEAD_SCRIPT: &'static str = r#"
+        redis.call('ZREM', KEYS[1], ARGV[2])
+        redis.call('LPUSH', KEYS[3], ARGV[3])
+        if redis.call('HGET', KEYS[2], ARGV[1]) == ARGV[2] then
+            redis.call('HDEL', KEYS[2], ARGV[1])
+        end
+        return 1
+    "#;
+
+    /// 各通道待处理消息数之和、inflight 数、死信数
+    const DEPTH_SCRIPT: &'static str = r#"
+        local pending = 0
+        for _, lane in ipairs(redis.call('SMEMBERS', KEYS[1])) do
+            pending = pending + redis.call('ZCARD', ARGV[1] .. lane)
+        end
+        return {pending, redis.call('ZCARD', KEYS[2]), redis.call('LLEN', KEYS[3])}
+    "#;
+
+    pub async fn connect(url: &str, prefix: &str) -> Result<Self> {
+        let client = redis::Client::open(url).map_err(|e| queue_err("invalid redis url", e))?;
+        let conn = redis::aio::ConnectionManager::new(client)
+            .await
+            .map_err(|e| queue_err("connect to redis", e))?;
+        Ok(Self {
+            conn,
+            lanes: format!("{}:lanes", prefix),
+            pending: format!("{}:pending:", prefix),
+            inflight: format!("{}:inflight", prefix),
+            leases: format!("{}:leases", prefix),
+            dead: format!("{}:dead", prefix),
+        })
+    }
+
+    fn encode(message: &QueueMessage) -> Result<String> {
+        serde_json::to_string(message).map_err(|e| RelayerError::Serialization(e.to_string()))
+    }
+}
+
+#[cfg(feature = "queue")]
+#[async_trait]
+impl WorkQueue for RedisWorkQueue {
+    async fn push(&self, message: &QueueMessage) -> Result<()> {
+        let lane = lane(&message.id);
+        let mut conn = self.conn.clone();
+        redis::Script::new(Self::PUSH_SCRIPT)
+            .key(&self.lanes)
+            .key(format!("{}{}", self.pending, lane))
+            .arg(lane)
+            .arg(message.event.nonce)
+            .arg(Self::encode(message)?)
+            .invoke_async::<_, ()>(&mut conn)
+            .await
+            .map_err(|e| queue_err("push message", e))
+    }
+
+    async fn pop(&self, visibility: Duration) -> Result<Option<Delivery>> {
+        let mut conn = self.conn.clone();
+        let now = now_ms();
+        let raw: Option<String> = redis::Script::new(Self::POP_SCRIPT)
+            .key(&self.lanes)
+            .key(&self.inflight)
+            .key(&self.leases)
+            .arg(now)
+            .arg(now + visibility.as_millis() as i64)
+            .arg(&self.pending)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| queue_err("pop message", e))?;
+
+        let Some(raw) = raw else {
+            return Ok(None);
+        };
+        match serde_json::from_str::<QueueMessage>(&raw) {
+            Ok(message) => Ok(Some(Delivery { message, receipt: raw })),
+            Err(e) => {
+                warn!(error = %e, "Moving unreadable message to dead letter");
+                redis::Script::new(Self::DEAD_SCRIPT)
+                    .key(&self.inflight)
+                    .key(&self.leases)
+                    .key(&self.dead)
+                    .arg("")
+                    .arg(&raw)
+                    .arg(&raw)
+                    .invoke_async::<_, ()>(&mut conn)
+                    .await
+                    .map_err(|e| queue_err("dead-letter message", e))?;
+                Ok(None)
+            }
+        }
+    }
+
+    async fn ack(&self, delivery: &Delivery) -> Result<()> {
+        let mut conn = self.conn.clone();
+        let removed: u64 = redis::Script::new(Self::ACK_SCRIPT)
+            .key(&self.inflight)
+            .key(&self.leases)
+            .arg(lane(&delivery.message.id))
+            .arg(&delivery.receipt)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| queue_err("ack message", e))?;
+        if removed == 0 {
+            warn!(id = %delivery.message.id, "Ack after visibility timeout, message may be redelivered");
+        }
+        Ok(())
+    }
+
+    async fn nack(&self, delivery: &Delivery, error: &str, delay: Duration) -> Result<()> {
+        let mut message = delivery.message.clone();
+        message.attempts += 1;
+        message.last_error = Some(error.to_string());
+
+        let mut conn = self.conn.clone();
+        redis::Script::new(Self::REPLACE_SCRIPT)
+            .key(&self.inflight)
+            .key(&self.leases)
+            .arg(lane(&message.id))
+            .arg(&delivery.receipt)
+            .arg(now_ms() + delay.as_millis() as i64)
+            .arg(Self::encode(&message)?)
+            .invoke_async::<_, ()>(&mut conn)
+            .await
+            .map_err(|e| queue_err("nack message", e))
+    }
+
+    async fn dead_letter(&self, delivery: &Delivery, error: &str) -> Result<()> {
+        let mut message = delivery.message.clone();
+        message.last_error = Some(error.to_string());
+
+        let mut conn = self.conn.clone();
+        redis::Script::new(Self::DEAD_SCRIPT)
+            .key(&self.inflight)
+            .key(&self.leases)
+            .key(&self.dead)
+            .arg(lane(&message.id))
+            .arg(&delivery.receipt)
+            .arg(Self::encode(&message)?)
+            .invoke_async::<_, ()>(&mut conn)
+            .await
+            .map_err(|e| queue_err("dead-letter message", e))
+    }
+
+    async fn depth(&self) -> Result<QueueDepth> {
+        let mut conn = self.conn.clone();
+        let (pending, inflight, dead): (u64, u64, u64) = redis::Script::new(Self::DEPTH_SCRIPT)
+            .key(&self.lanes)
+            .key(&self.inflight)
+            .key(&self.dead)
+            .arg(&self.pending)
+            .invoke_async(&mut conn)
+            .await
+            .map_err(|e| queue_err("read queue depth", e))?;
+        Ok(QueueDepth { pending, inflight, dead })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::address::ChainAddress;
+    use crate::test_util::{event, TempDir};
+
+    #[tokio::test]
+    async fn test_file_queue_pops_in_nonce_order() {
+        let dir = TempDir::new("queue-order");
+        let queue = FileWorkQueue::open(&dir).unwrap();
+        queue.push(&QueueMessage::new(event(10))).await.unwrap();
+        queue.push(&QueueMessage::new(event(9))).await.unwrap();
+
+        let first = queue.pop(Duration::from_secs(60)).await.unwrap().unwrap();
+        let second = queue.pop(Duration::from_secs(60)).await.unwrap().unwrap();
+        assert_eq!((first.message.event.nonce, second.message.event.nonce), (9, 10));
+    }
+
+    /// 另一个源合约（nonce 通道）的事件
+    fn other_lane(nonce: u64) -> StakeEventData {
+        StakeEventData { source_contract: ChainAddress::Evm([0x22; 20]), ..event(nonce) }
+    }
+
+    async fn exercise(queue: &dyn WorkQueue) {
+        queue.push(&QueueMessage::new(event(1))).await.unwrap();
+        queue.push(&QueueMessage::new(other_lane(2))).await.unwrap();
+
+        // 取出后在可见性超时内不可见
+        let first = queue.pop(Duration::from_secs(60)).await.unwrap().unwrap();
+        let second = queue.pop(Duration::from_secs(60)).await.unwrap().unwrap();
+        assert_ne!(first.message.id, second.message.id);
+        assert!(queue.pop(Duration::from_secs(60)).await.unwrap().is_none());
+
+        // ack 删除，nack 立即重新可见
+        queue.ack(&first).await.unwrap();
+        queue.nack(&second, "rpc timeout", Duration::ZERO).await.unwrap();
+        let retried = queue.pop(Duration::from_secs(60)).await.unwrap().unwrap();
+        assert_eq!(retried.message.id, second.message.id);
+        assert_eq!(retried.message.attempts, 1);
+
+        queue.dead_letter(&retried, "invalid nonce").await.unwrap();
+        let depth = queue.depth().await.unwrap();
+        assert_eq!((depth.pending, depth.inflight, depth.dead), (0, 0, 1));
+    }
+
+    #[tokio::test]
+    async fn test_file_queue() {
+        let dir = TempDir::new("queue");
+        let queue = FileWorkQueue::open(&dir).unwrap();
+        exercise(&queue).await;
+    }
+
+    #[tokio::test]
+    async fn test_file_queue_visibility_timeout() {
+        let dir = TempDir::new("queue-vis");
+        let queue = FileWorkQueue::open(&dir).unwrap();
+
+        queue.push(&QueueMessage::new(event(7))).await.unwrap();
+        let lost = queue.pop(Duration::ZERO).await.unwrap().unwrap();
+        // 消费者未确认即"崩溃"，消息重新出队
+        let redelivered = queue.pop(Duration::from_secs(60)).await.unwrap().unwrap();
+        assert_eq!(lost.message.id, redelivered.message.id);
+    }
+
+    #[tokio::test]
+    async fn test_expired_inflight_keeps_newer_pending() {
+        let dir = TempDir::new("queue-superseded");
+        let queue = FileWorkQueue::open(&dir).unwrap();
+
+        let mut awaiting = QueueMessage::new(event(7));
+        awaiting.awaiting_finality = true;
+        queue.push(&awaiting).await.unwrap();
+        let stale = queue.pop(Duration::ZERO).await.unwrap().unwrap();
+        assert!(stale.message.awaiting_finality);
+
+        // 未确认期间监听器推送了已确认的版本，过期的旧副本不能覆盖它
+        queue.push(&QueueMessage::new(event(7))).await.unwrap();
+        let delivery = queue.pop(Duration::from_secs(60)).await.unwrap().unwrap();
+        assert!(!delivery.message.awaiting_finality);
+        queue.ack(&delivery).await.unwrap();
+        let depth = queue.depth().await.unwrap();
+        assert_eq!((depth.pending, depth.inflight), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_trace_context_survives_queue() {
+        let dir = TempDir::new("queue-trace");
+        let queue = FileWorkQueue::open(&dir).unwrap();
+
+        let mut message = QueueMessage::new(event(9));
+        let traceparent = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+        message.trace_context.insert("traceparent".to_string(), traceparent.to_string());
+        queue.push(&message).await.unwrap();
+        let delivery = queue.pop(Duration::from_secs(60)).await.unwrap().unwrap();
+        assert_eq!(delivery.message.trace_context["traceparent"], traceparent);
+
+        // 升级前写入的消息没有追踪上下文
+        let mut legacy = serde_json::to_value(QueueMessage::new(event(10))).unwrap();
+        legacy.as_object_mut().unwrap().remove("trace_context");
+        let legacy: QueueMessage = serde_json::from_value(legacy).unwrap();
+        assert!(legacy.trace_context.is_empty());
+    }
+
+    /// 需要本地 redis-server：`REDIS_URL=redis://127.0.0.1/ cargo test --features queue`
+    #[cfg(feature = "queue")]
+    #[tokio::test]
+    async fn test_redis_queue() {
+        let Ok(url) = std::env::var("REDIS_URL") else {
+            return;
+        };
+        let prefix = format!("relayer-test:{}", std::process::id());
+        let queue = RedisWorkQueue::connect(&url, &prefix).await.unwrap();
+        exercise(&queue).await;
+    }
+
+    /// 同一通道同时只投递一条消息：低 nonce 被取出或延迟重试期间，任何 submitter 都取不到更高的 nonce
+    #[cfg(feature = "queue")]
+    #[tokio::test]
+    async fn test_redis_queue_leases_lanes() {
+        let Ok(url) = std::env::var("REDIS_URL") else {
+            return;
+        };
+        let prefix = format!("relayer-test-lanes:{}", std::process::id());
+        let first = RedisWorkQueue::connect(&url, &prefix).await.unwrap();
+        let second = RedisWorkQueue::connect(&url, &prefix).await.unwrap();
+        let visibility = Duration::from_secs(60);
+        for nonce in [10, 9, 11] {
+            first.push(&QueueMessage::new(event(nonce))).await.unwrap();
+        }
+        first.push(&QueueMessage::new(other_lane(1))).await.unwrap();
+
+        // 两个 submitter 各取到一个通道中 nonce 最小的消息
+        let a = first.pop(visibility).await.unwrap().unwrap();
+        let b = second.pop(visibility).await.unwrap().unwrap();
+        let mut nonces = [a.message.event.nonce, b.message.event.nonce];
+        nonces.sort();
+        assert_eq!(nonces, [1, 9]);
+        assert!(second.pop(visibility).await.unwrap().is_none());
+        let (low, other) = if a.message.event.nonce == 9 { (a, b) } else { (b, a) };
+        second.ack(&other).await.unwrap();
+
+        // nonce 9 延迟重试期间仍占用通道，延迟结束后先于 nonce 10 重新投递
+        first.nack(&low, "rpc timeout", Duration::from_millis(200)).await.unwrap();
+        assert!(second.pop(visibility).await.unwrap().is_none());
+        assert_eq!(first.depth().await.unwrap().pending, 2);
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let retried = second.pop(visibility).await.unwrap().unwrap();
+        assert_eq!((retried.message.event.nonce, retried.message.attempts), (9, 1));
+
+        // 确认后按 nonce 顺序释放后续消息
+        second.ack(&retried).await.unwrap();
+        let next = first.pop(visibility).await.unwrap().unwrap();
+        assert_eq!(next.message.event.nonce, 10);
+        assert!(second.pop(visibility).await.unwrap().is_none());
+        first.dead_letter(&next, "invalid nonce").await.unwrap();
+        let last = second.pop(visibility).await.unwrap().unwrap();
+        assert_eq!(last.message.event.nonce, 11);
+        second.ack(&last).await.unwrap();
+
+        let depth = first.depth().await.unwrap();
+        assert_eq!((depth.pending, depth.inflight, depth.dead), (0, 0, 1));
+    }
+}
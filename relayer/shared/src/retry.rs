@@ -1,82 +1,294 @@
+//! RPC 重试与熔断
+//!
+//! - [`RetryStrategy`]：指数退避 + 抖动，只重试 [`RelayerError::is_retryable`] 的瞬时错误
+//! - [`CircuitBreaker`]：同一端点连续瞬时失败达到阈值后熔断，冷却期内直接返回 `CircuitOpen`，
+//!   冷却结束后放行一次探测调用，成功则恢复
+//! - [`RpcEndpoint`]：各服务的 RPC 调用入口，组合重试、熔断和 RPC 指标
+//! - [`Swappable`]：SIGHUP 热加载 RPC 地址时替换客户端
+
 use crate::error::{RelayerError, Result};
-use std::time::Duration;
+use crate::metrics;
+use rand::Rng;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::{Duration, Instant};
 use tokio::time::sleep;
-use tracing::warn;
+use tracing::{info, warn};
 
 /// 重试策略
 #[derive(Debug, Clone)]
 pub struct RetryStrategy {
+    /// 最多尝试次数（含第一次）
     pub max_retries: u32,
-    pub delays: Vec<u64>, // milliseconds
+    /// 第一次重试前的基准延迟（毫秒），之后每次翻倍
+    pub base_delay: u64,
+    /// 单次延迟上限（毫秒）
+    pub max_delay: u64,
+    /// 抖动比例 0.0-1.0：实际延迟在 [delay * (1 - jitter), delay] 之间随机
+    pub jitter: f64,
 }
 
 impl Default for RetryStrategy {
     fn default() -> Self {
         Self {
-            max_retries: 5,
-            delays: vec![0, 30000, 60000, 120000, 300000], // 0s, 30s, 1m, 2m, 5m
+            max_retries: 4,
+            base_delay: 250,
+            max_delay: 8000,
+            jitter: 0.5,
         }
     }
 }
 
 impl RetryStrategy {
+    /// 只尝试一次（例如发送交易：重复发送由任务级重试负责）
+    pub fn once() -> Self {
+        Self {
+            max_retries: 1,
+            ..Self::default()
+        }
+    }
+
+    /// 第 `attempt` 次失败后的等待时间（attempt 从 0 开始）
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponential = self.base_delay.saturating_mul(1u64 << attempt.min(32)).min(self.max_delay);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let factor = 1.0 - jitter * rand::thread_rng().gen::<f64>();
+        Duration::from_millis((exponential as f64 * factor) as u64)
+    }
+
     /// 执行带重试的异步操作
     pub async fn retry<F, Fut, T>(&self, mut operation: F) -> Result<T>
     where
         F: FnMut() -> Fut,
-        Fut: std::future::Future<Output = Result<T>>,
+        Fut: Future<Output = Result<T>>,
     {
-        let mut last_error = None;
-
-        for attempt in 0..self.max_retries {
+        let mut attempt = 0;
+        loop {
             match operation().await {
                 Ok(result) => return Ok(result),
+                // 不可重试的错误或已达上限，直接返回
+                Err(err) if !err.is_retryable() || attempt + 1 >= self.max_retries => return Err(err),
                 Err(err) => {
-                    // 检查是否可重试
-                    if !err.is_retryable() {
-                        return Err(err);
-                    }
-
-                    last_error = Some(err);
-
-                    // 如果不是最后一次尝试，等待后重试
-                    if attempt < self.max_retries - 1 {
-                        let delay_ms = self.delays.get(attempt as usize).copied().unwrap_or(300000);
-                        warn!(
-                            attempt = attempt + 1,
-                            max_retries = self.max_retries,
-                            delay_ms = delay_ms,
-                            error = ?last_error,
-                            "Retrying after error"
-                        );
-                        sleep(Duration::from_millis(delay_ms)).await;
-                    }
+                    let delay = self.delay(attempt);
+                    attempt += 1;
+                    warn!(
+                        attempt,
+                        max_retries = self.max_retries,
+                        delay_ms = delay.as_millis() as u64,
+                        error = %err,
+                        "Retrying after error"
+                    );
+                    sleep(delay).await;
                 }
             }
         }
+    }
+}
+
+/// 熔断器状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CircuitState {
+    /// 正常，记录连续失败次数
+    Closed { failures: u32 },
+    /// 熔断中，直到指定时间
+    Open { until: Instant },
+    /// 冷却结束，已放行一次探测调用
+    HalfOpen,
+}
+
+/// 单个 RPC 端点的熔断器
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    name: String,
+    failure_threshold: u32,
+    cooldown: Duration,
+    state: Mutex<CircuitState>,
+}
+
+impl CircuitBreaker {
+    pub fn new(name: impl Into<String>, failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            name: name.into(),
+            failure_threshold,
+            cooldown,
+            state: Mutex::new(CircuitState::Closed { failures: 0 }),
+        }
+    }
+
+    /// 调用前检查：熔断中返回 `CircuitOpen`；冷却结束后只放行一个探测调用
+    pub fn check(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        match *state {
+            CircuitState::Closed { .. } => Ok(()),
+            CircuitState::Open { until } if Instant::now() >= until => {
+                *state = CircuitState::HalfOpen;
+                Ok(())
+            }
+            CircuitState::Open { .. } | CircuitState::HalfOpen => Err(RelayerError::CircuitOpen(self.name.clone())),
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        if !matches!(*state, CircuitState::Closed { .. }) {
+            info!(endpoint = %self.name, "RPC endpoint recovered, circuit closed");
+        }
+        *state = CircuitState::Closed { failures: 0 };
+    }
+
+    /// 记录一次瞬时失败（请求错误、合约 revert 等说明端点本身正常，不计入）
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        let failures = match *state {
+            CircuitState::Closed { failures } => failures + 1,
+            // 探测失败，重新熔断
+            CircuitState::HalfOpen => self.failure_threshold,
+            CircuitState::Open { .. } => return,
+        };
+        if failures >= self.failure_threshold {
+            warn!(
+                endpoint = %self.name,
+                failures,
+                cooldown_ms = self.cooldown.as_millis() as u64,
+                "RPC endpoint failing, circuit opened"
+            );
+            *state = CircuitState::Open { until: Instant::now() + self.cooldown };
+        } else {
+            *state = CircuitState::Closed { failures };
+        }
+    }
+}
+
+/// 连续失败多少次后熔断
+const CIRCUIT_FAILURE_THRESHOLD: u32 = 5;
+/// 熔断冷却时间
+const CIRCUIT_COOLDOWN: Duration = Duration::from_secs(30);
+
+static BREAKERS: OnceLock<Mutex<HashMap<String, Arc<CircuitBreaker>>>> = OnceLock::new();
+
+/// 进程内按端点共享的熔断器（同一端点的多个客户端共用一个）
+pub fn circuit_breaker(name: &str) -> Arc<CircuitBreaker> {
+    let breakers = BREAKERS.get_or_init(|| Mutex::new(HashMap::new()));
+    breakers
+        .lock()
+        .unwrap()
+        .entry(name.to_string())
+        .or_insert_with(|| Arc::new(CircuitBreaker::new(name, CIRCUIT_FAILURE_THRESHOLD, CIRCUIT_COOLDOWN)))
+        .clone()
+}
+
+/// 一个 RPC 端点：重试 + 熔断 + 指标
+#[derive(Debug, Clone)]
+pub struct RpcEndpoint {
+    chain: &'static str,
+    /// 指标中的端点标签（只含 host[:port]）
+    label: String,
+    breaker: Arc<CircuitBreaker>,
+    strategy: RetryStrategy,
+}
+
+impl RpcEndpoint {
+    pub fn new(chain: &'static str, url: &str) -> Self {
+        let label = metrics::endpoint_label(url);
+        Self {
+            chain,
+            breaker: circuit_breaker(&format!("{}/{}", chain, label)),
+            label,
+            strategy: RetryStrategy::default(),
+        }
+    }
+
+    pub fn with_strategy(mut self, strategy: RetryStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// 调用可重试的 RPC 方法（查询类调用）
+    pub async fn call<F, Fut, T>(&self, method: &str, operation: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        self.call_with(&self.strategy, method, operation).await
+    }
 
-        Err(last_error.unwrap_or_else(|| {
-            RelayerError::Internal("Retry failed without error".to_string())
-        }))
+    /// 只调用一次（发送交易等非幂等调用），仍经过熔断和指标
+    pub async fn call_once<F, Fut, T>(&self, method: &str, operation: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        self.call_with(&RetryStrategy::once(), method, operation).await
+    }
+
+    async fn call_with<F, Fut, T>(&self, strategy: &RetryStrategy, method: &str, mut operation: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        strategy
+            .retry(|| {
+                let attempt = self.breaker.check().map(|_| operation());
+                async move {
+                    let result = metrics::observe_rpc(self.chain, method, &self.label, attempt?).await;
+                    match &result {
+                        Ok(_) => self.breaker.record_success(),
+                        Err(e) if e.is_retryable() => self.breaker.record_failure(),
+                        // 端点正常返回了错误（请求错误、revert 等）
+                        Err(_) => self.breaker.record_success(),
+                    }
+                    result
+                }
+            })
+            .await
+    }
+}
+
+/// 可热切换的 RPC 客户端：调用方取出当前客户端的 `Arc`，切换端点不影响进行中的调用
+pub struct Swappable<T> {
+    current: RwLock<Arc<T>>,
+}
+
+impl<T> Swappable<T> {
+    pub fn new(value: T) -> Self {
+        Self { current: RwLock::new(Arc::new(value)) }
+    }
+
+    pub fn get(&self) -> Arc<T> {
+        self.current.read().unwrap().clone()
+    }
+
+    pub fn set(&self, value: T) {
+        *self.current.write().unwrap() = Arc::new(value);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
 
-    #[tokio::test]
-    async fn test_retry_success() {
-        let strategy = RetryStrategy {
+    fn fast() -> RetryStrategy {
+        RetryStrategy {
             max_retries: 3,
-            delays: vec![0, 10, 20],
-        };
+            base_delay: 1,
+            max_delay: 10,
+            jitter: 0.5,
+        }
+    }
 
-        let mut attempts = 0;
-        let result = strategy
+    #[tokio::test]
+    async fn test_retry_success() {
+        let attempts = AtomicU32::new(0);
+        let result = fast()
             .retry(|| async {
-                attempts += 1;
-                if attempts < 3 {
+                let attempt = attempts.fetch_add(1, Ordering::SeqCst) + 1;
+                if attempt < 3 {
                     Err(RelayerError::RpcTimeout)
                 } else {
                     Ok(42)
@@ -85,26 +297,79 @@ mod tests {
             .await;
 
         assert_eq!(result.unwrap(), 42);
-        assert_eq!(attempts, 3);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
     }
 
     #[tokio::test]
     async fn test_retry_non_retryable() {
-        let strategy = RetryStrategy {
-            max_retries: 3,
-            delays: vec![0, 10, 20],
-        };
-
-        let mut attempts = 0;
-        let result: Result<i32> = strategy
+        let attempts = AtomicU32::new(0);
+        let result: Result<i32> = fast()
             .retry(|| async {
-                attempts += 1;
+                attempts.fetch_add(1, Ordering::SeqCst);
                 Err(RelayerError::NotWhitelisted)
             })
             .await;
 
         assert!(result.is_err());
-        assert_eq!(attempts, 1); // Should fail immediately
+        assert_eq!(attempts.load(Ordering::SeqCst), 1); // Should fail immediately
     }
-}
 
+    #[test]
+    fn test_backoff_with_jitter() {
+        let strategy = RetryStrategy {
+            max_retries: 10,
+            base_delay: 100,
+            max_delay: 1000,
+            jitter: 0.5,
+        };
+        for attempt in 0..10 {
+            let expected = (100u64 << attempt).min(1000);
+            let delay = strategy.delay(attempt).as_millis() as u64;
+            assert!(delay <= expected && delay >= expected / 2, "attempt {}: {}ms", attempt, delay);
+        }
+        let exact = RetryStrategy { jitter: 0.0, ..strategy };
+        assert_eq!(exact.delay(2), Duration::from_millis(400));
+    }
+
+    #[test]
+    fn test_circuit_breaker_opens_and_recovers() {
+        let breaker = CircuitBreaker::new("test", 2, Duration::from_millis(20));
+        breaker.record_failure();
+        breaker.check().unwrap();
+        breaker.record_failure();
+        assert!(matches!(breaker.check(), Err(RelayerError::CircuitOpen(_))));
+
+        // 冷却后只放行一个探测调用
+        std::thread::sleep(Duration::from_millis(25));
+        breaker.check().unwrap();
+        assert!(breaker.check().is_err());
+        breaker.record_success();
+        breaker.check().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_endpoint_skips_calls_while_open() {
+        let endpoint = RpcEndpoint::new("evm", "http://breaker-test.invalid:8545").with_strategy(fast());
+        let calls = AtomicU32::new(0);
+        for _ in 0..2 {
+            let result: Result<()> = endpoint
+                .call("eth_blockNumber", || async {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    Err(RelayerError::Network("connection refused".to_string()))
+                })
+                .await;
+            assert!(result.is_err());
+        }
+        // 第 5 次失败后熔断，第二轮的最后一次调用没有发出
+        assert_eq!(calls.load(Ordering::SeqCst), CIRCUIT_FAILURE_THRESHOLD);
+
+        // 合约 revert 不计入熔断
+        let reverts = RpcEndpoint::new("evm", "http://revert-test.invalid:8545").with_strategy(fast());
+        for _ in 0..CIRCUIT_FAILURE_THRESHOLD + 1 {
+            let result: Result<()> = reverts
+                .call("eth_call", || async { Err(RelayerError::Reverted("nope".to_string())) })
+                .await;
+            assert!(matches!(result, Err(RelayerError::Reverted(_))));
+        }
+    }
+}
@@ -0,0 +1,433 @@
+//! 签名前风控
+//!
+//! 两个方向都在签名前按 `[risk]` 配置检查事件（金额为代币最小单位）：
+//! - 单笔上限 `max_transfer`
+//! - 滚动 1 小时 / 24 小时签名总量上限 `max_volume_1h` / `max_volume_24h`
+//! - 单个接收地址 24 小时总量上限 `max_receiver_volume_24h`
+//! - 异常大额：超过最近 24 小时签名金额中位数的 `anomaly_factor` 倍（样本不少于 `anomaly_min_samples`）
+//!
+//! 未通过的任务进入 Held 状态、不签名，由操作员通过管理 API（[`admin_router`]，需要 `api.admin_token`）
+//! 批准（回到 Pending，跳过风控）或拒绝（Failed）。放行的转账立即计入滚动窗口。
+//!
+//! 滚动窗口由共享任务存储中已通过风控的任务（见 [`TaskStore::signed_since`]，按首次开始处理的时间 `signed_at` 计时）
+//! 与本进程刚放行、尚未落盘的转账合并计算，多个 submitter 共享同一存储时共用总量上限。
+
+use crate::address::ChainAddress;
+use crate::alerts;
+use crate::config::RiskConfig;
+use crate::error::{RelayerError, Result};
+use crate::keystore::Secret;
+use crate::queue::{QueueMessage, WorkQueue};
+use crate::store::TaskStore;
+use crate::types::{Task, TaskStatus};
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use tracing::info;
+
+/// 风控结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RiskDecision {
+    Allow,
+    /// 暂扣，附原因
+    Hold(String),
+}
+
+/// 已放行的一笔转账
+#[derive(Debug, Clone)]
+struct Exposure {
+    id: String,
+    at: DateTime<Utc>,
+    receiver: ChainAddress,
+    amount: u64,
+}
+
+/// 风控引擎：维护最近 24 小时放行的转账
+pub struct RiskEngine {
+    config: Mutex<RiskConfig>,
+    window: Mutex<VecDeque<Exposure>>,
+}
+
+impl RiskEngine {
+    pub fn new(config: RiskConfig) -> Self {
+        Self { config: Mutex::new(config), window: Mutex::new(VecDeque::new()) }
+    }
+
+    /// 热加载风控上限，滚动窗口保留
+    pub fn set_config(&self, config: RiskConfig) {
+        *self.config.lock().unwrap() = config;
+    }
+
+    /// 统计任务存储中最近 24 小时已通过风控的任务数（启动日志用）
+    pub async fn load(&self, store: &dyn TaskStore) -> Result<usize> {
+        Ok(store.signed_since(Utc::now() - Duration::hours(24)).await?.len())
+    }
+
+    /// 检查任务；放行时计入滚动窗口。已计入的任务（重试）和操作员批准的任务直接放行
+    pub async fn evaluate(&self, task: &Task, store: &dyn TaskStore) -> Result<RiskDecision> {
+        let now = Utc::now();
+        let shared = store.signed_since(now - Duration::hours(24)).await?;
+        Ok(self.evaluate_at(task, &shared, now))
+    }
+
+    fn evaluate_at(&self, task: &Task, shared: &[Task], now: DateTime<Utc>) -> RiskDecision {
+        let mut window = self.window.lock().unwrap();
+        while window.front().is_some_and(|exposure| exposure.at <= now - Duration::hours(24)) {
+            window.pop_front();
+        }
+        if window.iter().any(|exposure| exposure.id == task.id) || shared.iter().any(|other| other.id == task.id) {
+            return RiskDecision::Allow;
+        }
+
+        let event = &task.event_data;
+        if !task.approved {
+            // 存储中的任务优先，本进程窗口只补充尚未落盘的
+            let mut exposures: Vec<Exposure> = shared
+                .iter()
+                .map(|other| Exposure {
+                    id: other.id.clone(),
+                    at: other.exposed_at().unwrap_or(other.updated_at),
+                    receiver: other.event_data.receiver_address,
+                    amount: other.event_data.amount,
+                })
+                .collect();
+            exposures.extend(window.iter().filter(|exposure| shared.iter().all(|other| other.id != exposure.id)).cloned());
+            if let Some(reason) = self.check(&exposures, event.amount, &event.receiver_address, now) {
+                return RiskDecision::Hold(reason);
+            }
+        }
+        window.push_back(Exposure {
+            id: task.id.clone(),
+            at: now,
+            receiver: event.receiver_address,
+            amount: event.amount,
+        });
+        RiskDecision::Allow
+    }
+
+    fn check(&self, window: &[Exposure], amount: u64, receiver: &ChainAddress, now: DateTime<Utc>) -> Option<String> {
+        let config = self.config.lock().unwrap().clone();
+        if let Some(max) = config.max_transfer.filter(|max| amount > *max) {
+            return Some(format!("amount {} exceeds per-transfer limit {}", amount, max));
+        }
+
+        if config.anomaly_factor > 0.0 && window.len() >= config.anomaly_min_samples.max(1) {
+            let mut amounts: Vec<u64> = window.iter().map(|exposure| exposure.amount).collect();
+            amounts.sort_unstable();
+            let median = amounts[amounts.len() / 2];
+            if amount as f64 > median as f64 * config.anomaly_factor {
+                return Some(format!(
+                    "amount {} is more than {}x the 24h median {}",
+                    amount, config.anomaly_factor, median
+                ));
+            }
+        }
+
+        let volume = |since: DateTime<Utc>, receiver: Option<&ChainAddress>| -> u128 {
+            window
+                .iter()
+                .filter(|exposure| exposure.at > since && receiver.is_none_or(|r| exposure.receiver == *r))
+                .map(|exposure| exposure.amount as u128)
+                .sum::<u128>()
+                + amount as u128
+        };
+        let limits = [
+            ("receiver 24h", config.max_receiver_volume_24h, volume(now - Duration::hours(24), Some(receiver))),
+            ("1h", config.max_volume_1h, volume(now - Duration::hours(1), None)),
+            ("24h", config.max_volume_24h, volume(now - Duration::hours(24), None)),
+        ];
+        limits.into_iter().find_map(|(name, max, total)| {
+            max.filter(|max| total > *max as u128)
+                .map(|max| format!("{} volume {} would exceed limit {}", name, total, max))
+        })
+    }
+}
+
+/// 管理 API 状态
+#[derive(Clone)]
+struct AdminService {
+    store: Arc<dyn TaskStore>,
+    /// 队列模式下批准后重新投递
+    queue: Option<Arc<dyn WorkQueue>>,
+    admin_token: Option<Secret>,
+}
+
+type AdminError = (StatusCode, String);
+
+#[derive(Debug, Default, Deserialize)]
+struct RejectRequest {
+    reason: Option<String>,
+}
+
+/// 风控管理 API：
+///
+/// | 方法 | 路径 | 说明 |
+/// |------|------|------|
+/// | GET  | `/tasks/held` | 暂扣中的任务 |
+/// | POST | `/tasks/{id}/approve` | 批准，任务回到 Pending 并跳过风控 |
+/// | POST | `/tasks/{id}/reject` | 拒绝（可选 `{"reason": "..."}`），任务标记 Failed |
+///
+/// 所有请求需要 `Authorization: Bearer <api.admin_token>`，未配置 token 时返回 403
+pub fn admin_router(store: Arc<dyn TaskStore>, queue: Option<Arc<dyn WorkQueue>>, admin_token: Option<Secret>) -> Router {
+    Router::new()
+        .route("/tasks/held", get(held_tasks))
+        .route("/tasks/:id/approve", post(approve_task))
+        .route("/tasks/:id/reject", post(reject_task))
+        .with_state(AdminService { store, queue, admin_token })
+}
+
+fn internal(e: RelayerError) -> AdminError {
+    (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+}
+
+impl AdminService {
+    fn authorize(&self, headers: &HeaderMap) -> std::result::Result<(), AdminError> {
+        let Some(token) = &self.admin_token else {
+            return Err((StatusCode::FORBIDDEN, "Admin API disabled: set api.admin_token".to_string()));
+        };
+        let provided = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if provided.is_some_and(|provided| token.matches(provided)) {
+            Ok(())
+        } else {
+            Err((StatusCode::UNAUTHORIZED, "Unauthorized".to_string()))
+        }
+    }
+
+    /// 取出暂扣中的任务并执行状态迁移
+    async fn decide(
+        &self,
+        headers: &HeaderMap,
+        id: &str,
+        decide: impl FnOnce(&mut Task) -> Result<()>,
+    ) -> std::result::Result<Json<Task>, AdminError> {
+        self.authorize(headers)?;
+        let mut task = self
+            .store
+            .get(id)
+            .await
+            .map_err(internal)?
+            .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Task not found: {}", id)))?;
+        if task.status != TaskStatus::Held {
+            return Err((StatusCode::CONFLICT, format!("Task {} is {}, not held", id, task.status)));
+        }
+        decide(&mut task).map_err(|e| (StatusCode::CONFLICT, e.to_string()))?;
+        // 并发的批准 / 拒绝只有一个生效
+        if !self.store.update_if(&task, TaskStatus::Held).await.map_err(internal)? {
+            return Err((StatusCode::CONFLICT, format!("Task {} is no longer held", id)));
+        }
+        alerts::resolve(&format!("risk-hold:{}", task.id), format!("Task {} is now {}", task.id, task.status));
+        Ok(Json(task))
+    }
+}
+
+async fn held_tasks(
+    State(service): State<AdminService>,
+    headers: HeaderMap,
+) -> std::result::Result<Json<Vec<Task>>, AdminError> {
+    service.authorize(&headers)?;
+    service.store.list(Some(TaskStatus::Held), usize::MAX).await.map(Json).map_err(internal)
+}
+
+async fn approve_task(
+    State(service): State<AdminService>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> std::result::Result<Json<Task>, AdminError> {
+    let task = service.decide(&headers, &id, Task::approve).await?;
+    if let Some(queue) = &service.queue {
+        queue.push(&QueueMessage::for_task(&task)).await.map_err(internal)?;
+    }
+    info!(task = %id, nonce = task.nonce, amount = task.event_data.amount, "✅ Held transfer approved by operator");
+    Ok(task)
+}
+
+async fn reject_task(
+    State(service): State<AdminService>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    request: Option<Json<RejectRequest>>,
+) -> std::result::Result<Json<Task>, AdminError> {
+    let reason = request
+        .and_then(|Json(request)| request.reason)
+        .unwrap_or_else(|| "Rejected by operator".to_string());
+    let task = service.decide(&headers, &id, |task| task.reject(&reason)).await?;
+    info!(task = %id, nonce = task.nonce, reason = %reason, "Held transfer rejected by operator");
+    Ok(task)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::FileTaskStore;
+    use crate::test_util::{event_with_amount, TempDir};
+    use crate::types::StakeEventData;
+
+    fn task(nonce: u64, amount: u64, receiver: u8) -> Task {
+        Task::new(StakeEventData {
+            receiver_address: ChainAddress::Svm([receiver; 32]),
+            ..event_with_amount(nonce, amount)
+        })
+    }
+
+    fn limits() -> RiskConfig {
+        RiskConfig {
+            max_transfer: Some(1_000),
+            max_volume_1h: Some(2_000),
+            max_volume_24h: Some(3_000),
+            max_receiver_volume_24h: Some(1_500),
+            anomaly_factor: 0.0,
+            anomaly_min_samples: 20,
+        }
+    }
+
+    fn is_hold(decision: RiskDecision, reason: &str) -> bool {
+        matches!(&decision, RiskDecision::Hold(r) if r.contains(reason))
+    }
+
+    #[test]
+    fn test_limits() {
+        let engine = RiskEngine::new(limits());
+        let t0 = Utc::now();
+        assert!(is_hold(engine.evaluate_at(&task(1, 1_001, 1), &[], t0), "per-transfer"));
+        assert_eq!(engine.evaluate_at(&task(2, 1_000, 1), &[], t0), RiskDecision::Allow);
+        // 重试不重复计量
+        assert_eq!(engine.evaluate_at(&task(2, 1_000, 1), &[], t0), RiskDecision::Allow);
+        assert!(is_hold(engine.evaluate_at(&task(3, 600, 1), &[], t0), "receiver 24h"));
+        assert_eq!(engine.evaluate_at(&task(4, 1_000, 2), &[], t0), RiskDecision::Allow);
+        assert!(is_hold(engine.evaluate_at(&task(5, 1, 3), &[], t0), "1h volume 2001"));
+
+        // 1 小时后 1h 窗口释放，24h 窗口仍然生效
+        let t1 = t0 + Duration::minutes(61);
+        assert_eq!(engine.evaluate_at(&task(6, 1_000, 3), &[], t1), RiskDecision::Allow);
+        assert!(is_hold(engine.evaluate_at(&task(7, 1, 4), &[], t1), "24h volume"));
+
+        // 操作员批准的任务跳过检查，但计入窗口
+        let mut approved = task(7, 1, 4);
+        approved.approved = true;
+        assert_eq!(engine.evaluate_at(&approved, &[], t1), RiskDecision::Allow);
+        assert_eq!(engine.evaluate_at(&task(8, 1, 4), &[], t0 + Duration::hours(25)), RiskDecision::Allow);
+    }
+
+    #[test]
+    fn test_anomaly_hold() {
+        let engine = RiskEngine::new(RiskConfig { anomaly_factor: 10.0, anomaly_min_samples: 3, ..RiskConfig::default() });
+        let now = Utc::now();
+        // 样本不足时不检查
+        assert_eq!(engine.evaluate_at(&task(1, 100, 1), &[], now), RiskDecision::Allow);
+        assert_eq!(engine.evaluate_at(&task(2, 5_000, 1), &[], now), RiskDecision::Allow);
+        assert_eq!(engine.evaluate_at(&task(3, 120, 1), &[], now), RiskDecision::Allow);
+        assert!(is_hold(engine.evaluate_at(&task(4, 1_300, 1), &[], now), "24h median 120"));
+        assert_eq!(engine.evaluate_at(&task(5, 1_200, 1), &[], now), RiskDecision::Allow);
+    }
+
+    #[tokio::test]
+    async fn test_window_shared_through_store() {
+        let dir = TempDir::new("risk-shared");
+        let store: Arc<dyn TaskStore> = Arc::new(FileTaskStore::open(&*dir).unwrap());
+        let mut signed = task(1, 700, 1);
+        signed.signature = Some("00".to_string());
+        store.insert(&signed).await.unwrap();
+        store.insert(&task(2, 1_000, 1)).await.unwrap();
+
+        let engine = RiskEngine::new(limits());
+        assert_eq!(engine.load(store.as_ref()).await.unwrap(), 1);
+
+        // 另一实例放行并开始处理（尚未签名）的转账同样计入
+        let other = RiskEngine::new(limits());
+        let mut processing = task(3, 700, 1);
+        assert_eq!(other.evaluate(&processing, store.as_ref()).await.unwrap(), RiskDecision::Allow);
+        processing.start().unwrap();
+        store.insert(&processing).await.unwrap();
+
+        assert!(is_hold(engine.evaluate(&task(4, 200, 1), store.as_ref()).await.unwrap(), "receiver 24h volume 1600"));
+        // 重试不重复计量
+        assert_eq!(engine.evaluate(&processing, store.as_ref()).await.unwrap(), RiskDecision::Allow);
+    }
+
+    #[tokio::test]
+    async fn test_window_uses_signed_at() {
+        let dir = TempDir::new("risk-signed-at");
+        let store: Arc<dyn TaskStore> = Arc::new(FileTaskStore::open(&*dir).unwrap());
+        // 25 小时前签名、刚刚才确认的转账已滑出窗口
+        let mut late = task(1, 1_000, 1);
+        late.start().unwrap();
+        late.signed_at = Some(Utc::now() - Duration::hours(25));
+        late.signature = Some("00".to_string());
+        late.complete("0xabc".to_string()).unwrap();
+        store.insert(&late).await.unwrap();
+
+        let engine = RiskEngine::new(limits());
+        assert_eq!(engine.evaluate(&task(2, 600, 1), store.as_ref()).await.unwrap(), RiskDecision::Allow);
+    }
+
+    #[tokio::test]
+    async fn test_admin_api() {
+        let dir = TempDir::new("risk-admin");
+        let store: Arc<dyn TaskStore> = Arc::new(FileTaskStore::open(&*dir).unwrap());
+        let mut held = task(1, 5_000, 1);
+        held.hold("amount 5000 exceeds per-transfer limit 1000").unwrap();
+        store.insert(&held).await.unwrap();
+        let mut rejected = task(2, 5_000, 1);
+        rejected.hold("too large").unwrap();
+        store.insert(&rejected).await.unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let router = admin_router(store.clone(), None, Some(Secret::new("admin")));
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        let client = reqwest::Client::new();
+
+        let response = client.get(format!("{}/tasks/held", url)).send().await.unwrap();
+        assert_eq!(response.status(), 401);
+        let response = client.get(format!("{}/tasks/held", url)).bearer_auth("admin").send().await.unwrap();
+        assert_eq!(response.json::<Vec<Task>>().await.unwrap().len(), 2);
+
+        let approve = format!("{}/tasks/{}/approve", url, held.id);
+        let task: Task = client.post(&approve).bearer_auth("admin").send().await.unwrap().json().await.unwrap();
+        assert_eq!((task.status, task.approved), (TaskStatus::Pending, true));
+        // 已不在暂扣状态
+        assert_eq!(client.post(&approve).bearer_auth("admin").send().await.unwrap().status(), 409);
+
+        let reject = format!("{}/tasks/{}/reject", url, rejected.id);
+        let response = client
+            .post(&reject)
+            .bearer_auth("admin")
+            .json(&serde_json::json!({"reason": "suspicious"}))
+            .send()
+            .await
+            .unwrap();
+        let task: Task = response.json().await.unwrap();
+        assert_eq!((task.status, task.error_message.as_deref()), (TaskStatus::Failed, Some("suspicious")));
+        assert_eq!(store.count(TaskStatus::Held).await.unwrap(), 0);
+
+        // 同时批准和拒绝，只有一个成功
+        let mut contested = self::task(3, 5_000, 1);
+        contested.hold("too large").unwrap();
+        store.insert(&contested).await.unwrap();
+        let (approved, rejected) = tokio::join!(
+            client.post(format!("{}/tasks/{}/approve", url, contested.id)).bearer_auth("admin").send(),
+            client
+                .post(format!("{}/tasks/{}/reject", url, contested.id))
+                .bearer_auth("admin")
+                .json(&serde_json::json!({"reason": "suspicious"}))
+                .send(),
+        );
+        let mut statuses = [approved.unwrap().status().as_u16(), rejected.unwrap().status().as_u16()];
+        statuses.sort();
+        assert_eq!(statuses, [200, 409]);
+
+        // 未配置 token 时管理 API 关闭
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let disabled = format!("http://{}/tasks/held", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, admin_router(store, None, None)).await });
+        assert_eq!(client.get(&disabled).bearer_auth("admin").send().await.unwrap().status(), 403);
+    }
+}
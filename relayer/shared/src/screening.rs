@@ -0,0 +1,397 @@
+//! 合规筛查：签名前检查接收地址（及已知的源链发起者）是否在拒绝名单中
+//!
+//! 名单是本地文件，由合规团队维护，`screening.lists` 中可以列出文件或目录（目录下所有
+//! `*.csv` / `*.json` 都会加载），每个文件是一份名单，名单名为文件名（不含扩展名）：
+//!
+//! - CSV：每行一个地址；首行含 `address` 列时按列名取 `address` 和可选的 `reason`，
+//!   否则第一列为地址、第二列为原因；空行和 `#` 开头的行忽略
+//! - JSON：地址字符串数组、`{"address", "reason"}` 对象数组，或 `{"addresses": [...]}`
+//!
+//! 地址同时接受 EVM（`0x` + 40 位十六进制）、bytes32 和 SVM（base58）格式，统一按 bytes32 比较，
+//! 因此 EVM 地址与其左侧补零的 bytes32 形式互相匹配。
+//!
+//! 名单文件按 `screening.reload_interval` 检查修改时间，变化后重新加载；新名单解析失败时
+//! 继续使用旧名单并告警。
+
+use crate::address::ChainAddress;
+use crate::alerts::{self, Alert, Severity};
+use crate::config::Config;
+use crate::error::{RelayerError, Result};
+use crate::types::Task;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{info, warn};
+
+/// 名单中的一条记录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DenyEntry {
+    /// 名单名（文件名，不含扩展名）
+    pub list: String,
+    pub reason: Option<String>,
+}
+
+/// 命中的地址在转账中的角色
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Party {
+    Receiver,
+    Sender,
+}
+
+impl fmt::Display for Party {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Party::Receiver => write!(f, "receiver"),
+            Party::Sender => write!(f, "sender"),
+        }
+    }
+}
+
+/// 筛查命中
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScreeningMatch {
+    pub party: Party,
+    pub address: ChainAddress,
+    pub entry: DenyEntry,
+}
+
+impl fmt::Display for ScreeningMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} is on deny list '{}'", self.party, self.address, self.entry.list)?;
+        if let Some(reason) = &self.entry.reason {
+            write!(f, " ({})", reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// 已加载的名单：地址（bytes32）→ 记录，以及各文件的修改时间
+#[derive(Default)]
+struct DenyLists {
+    entries: HashMap<[u8; 32], DenyEntry>,
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+/// 名单来源配置
+struct Sources {
+    paths: Vec<PathBuf>,
+    reload_interval: Duration,
+}
+
+/// 拒绝名单筛查
+pub struct Screener {
+    sources: RwLock<Sources>,
+    lists: RwLock<DenyLists>,
+    last_check: Mutex<Instant>,
+}
+
+impl Screener {
+    /// 加载名单；启动时任何名单无法解析都视为错误
+    pub fn load(paths: Vec<PathBuf>, reload_interval: Duration) -> Result<Self> {
+        let files = list_files(&paths)?;
+        let lists = load_lists(&files)?;
+        info!(lists = files.len(), addresses = lists.entries.len(), "Deny lists loaded");
+        Ok(Self {
+            sources: RwLock::new(Sources { paths, reload_interval }),
+            lists: RwLock::new(lists),
+            last_check: Mutex::new(Instant::now()),
+        })
+    }
+
+    /// 名单中的地址数
+    pub fn len(&self) -> usize {
+        self.lists.read().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 检查任务的接收地址和源链发起者，返回第一个命中
+    pub fn check(&self, task: &Task) -> Option<ScreeningMatch> {
+        self.reload_if_due();
+        let sender = task.source.as_ref().and_then(|source| source.sender.as_ref());
+        let parties = [(Party::Receiver, Some(&task.event_data.receiver_address)), (Party::Sender, sender)];
+
+        let lists = self.lists.read().unwrap();
+        parties.into_iter().find_map(|(party, address)| {
+            let address = address?;
+            lists.entries.get(&address.to_bytes32()).map(|entry| ScreeningMatch {
+                party,
+                address: *address,
+                entry: entry.clone(),
+            })
+        })
+    }
+
+    /// 更换名单路径（SIGHUP 热加载）：立即加载，失败时保留原有名单和路径
+    pub fn configure(&self, paths: Vec<PathBuf>, reload_interval: Duration) -> Result<()> {
+        let files = list_files(&paths)?;
+        let lists = load_lists(&files)?;
+        info!(lists = files.len(), addresses = lists.entries.len(), "Deny list sources reconfigured");
+        *self.sources.write().unwrap() = Sources { paths, reload_interval };
+        *self.lists.write().unwrap() = lists;
+        Ok(())
+    }
+
+    /// 名单文件（或目录内容）有变化时重新加载，返回是否已替换名单
+    ///
+    /// 新名单无法解析时保留旧名单，发送 warning 告警并在下次检查时重试
+    pub fn reload(&self) -> Result<bool> {
+        let paths = self.sources.read().unwrap().paths.clone();
+        let files = list_files(&paths)?;
+        if files == self.lists.read().unwrap().files {
+            return Ok(false);
+        }
+        let lists = load_lists(&files)?;
+        info!(lists = files.len(), addresses = lists.entries.len(), "Deny lists reloaded");
+        *self.lists.write().unwrap() = lists;
+        alerts::resolve("screening-reload", "Deny lists reloaded");
+        Ok(true)
+    }
+
+    fn reload_if_due(&self) {
+        {
+            let mut last_check = self.last_check.lock().unwrap();
+            if last_check.elapsed() < self.sources.read().unwrap().reload_interval {
+                return;
+            }
+            *last_check = Instant::now();
+        }
+        if let Err(e) = self.reload() {
+            warn!(error = %e, "Failed to reload deny lists, keeping previous lists");
+            alerts::notify(Alert::new(
+                Severity::Warning,
+                "screening-reload",
+                "Deny list reload failed",
+                format!("Keeping previously loaded deny lists: {}", e),
+            ));
+        }
+    }
+}
+
+/// 按配置加载名单；未配置名单时返回空的筛查器，之后可通过热加载启用
+pub fn open_screener(config: &Config) -> Result<Screener> {
+    Screener::load(
+        config.screening.lists.clone(),
+        Duration::from_millis(config.screening.reload_interval),
+    )
+}
+
+/// 展开配置中的路径：目录取其中的 `*.csv` / `*.json`（按文件名排序），并记录修改时间
+fn list_files(paths: &[PathBuf]) -> Result<Vec<(PathBuf, Option<SystemTime>)>> {
+    let io_error = |path: &Path, e: std::io::Error| RelayerError::Config(format!("Deny list {}: {}", path.display(), e));
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut entries: Vec<PathBuf> = std::fs::read_dir(path)
+                .map_err(|e| io_error(path, e))?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| list_format(path).is_some())
+                .collect();
+            entries.sort();
+            files.extend(entries);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    files
+        .into_iter()
+        .map(|path| {
+            let modified = std::fs::metadata(&path).map_err(|e| io_error(&path, e))?.modified().ok();
+            Ok((path, modified))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ListFormat {
+    Csv,
+    Json,
+}
+
+fn list_format(path: &Path) -> Option<ListFormat> {
+    match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+        "csv" => Some(ListFormat::Csv),
+        "json" => Some(ListFormat::Json),
+        _ => None,
+    }
+}
+
+fn load_lists(files: &[(PathBuf, Option<SystemTime>)]) -> Result<DenyLists> {
+    let mut entries = HashMap::new();
+    for (path, _) in files {
+        let invalid = |reason: String| RelayerError::Config(format!("Deny list {}: {}", path.display(), reason));
+        let format = list_format(path).ok_or_else(|| invalid("expected a .csv or .json file".to_string()))?;
+        let content = std::fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+        let list = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        let records = match format {
+            ListFormat::Csv => parse_csv(&content),
+            ListFormat::Json => parse_json(&content),
+        }
+        .map_err(invalid)?;
+
+        for (address, reason) in records {
+            let key = address.to_bytes32();
+            // 同一地址出现在多份名单中时保留先加载的记录
+            entries.entry(key).or_insert_with(|| DenyEntry { list: list.clone(), reason });
+        }
+    }
+    Ok(DenyLists { entries, files: files.to_vec() })
+}
+
+fn parse_address(value: &str) -> std::result::Result<ChainAddress, String> {
+    value.trim().parse().map_err(|e: RelayerError| format!("invalid address '{}': {}", value.trim(), e))
+}
+
+/// 解析 CSV 名单（不支持带引号的逗号）
+fn parse_csv(content: &str) -> std::result::Result<Vec<(ChainAddress, Option<String>)>, String> {
+    let mut rows = content
+        .lines()
+        .enumerate()
+        .map(|(index, line)| (index + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line_no, line)| {
+            let cells: Vec<String> = line.split(',').map(|cell| cell.trim().trim_matches('"').to_string()).collect();
+            (line_no, cells)
+        })
+        .peekable();
+
+    let mut columns = (0, Some(1));
+    if let Some((_, header)) = rows.peek() {
+        let find = |name: &str| header.iter().position(|cell| cell.eq_ignore_ascii_case(name));
+        if let Some(address) = find("address") {
+            columns = (address, find("reason"));
+            rows.next();
+        }
+    }
+
+    rows.map(|(line_no, cells)| {
+        let address = cells.get(columns.0).ok_or_else(|| format!("line {}: missing address column", line_no))?;
+        let address = parse_address(address).map_err(|e| format!("line {}: {}", line_no, e))?;
+        let reason = columns.1.and_then(|column| cells.get(column)).filter(|reason| !reason.is_empty()).cloned();
+        Ok((address, reason))
+    })
+    .collect()
+}
+
+/// 解析 JSON 名单
+fn parse_json(content: &str) -> std::result::Result<Vec<(ChainAddress, Option<String>)>, String> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Record {
+        Address(String),
+        Entry { address: String, reason: Option<String> },
+    }
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Document {
+        List(Vec<Record>),
+        Object { addresses: Vec<Record> },
+    }
+
+    let records = match serde_json::from_str(content).map_err(|e| e.to_string())? {
+        Document::List(records) | Document::Object { addresses: records } => records,
+    };
+    records
+        .into_iter()
+        .enumerate()
+        .map(|(index, record)| {
+            let (address, reason) = match record {
+                Record::Address(address) => (address, None),
+                Record::Entry { address, reason } => (address, reason),
+            };
+            let address = parse_address(&address).map_err(|e| format!("entry {}: {}", index, e))?;
+            Ok((address, reason))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{event, TempDir};
+    use crate::types::{SourceRef, StakeEventData};
+
+    const EVM: &str = "0x5FbDB2315678afecb367f032d93F642f64180aa3";
+    const SVM: &str = "7vQa4SSDkbSCgCwzPFjVMc5X9Ms3FdFL1uoYbVMPgHc2";
+
+    fn task(receiver: &str, sender: Option<&str>) -> Task {
+        let mut task = Task::new(StakeEventData { receiver_address: receiver.parse().unwrap(), ..event(1) });
+        task.source = Some(SourceRef {
+            tx: "tx".to_string(),
+            log_index: None,
+            block: 1,
+            sender: sender.map(|sender| sender.parse().unwrap()),
+        });
+        task
+    }
+
+    #[test]
+    fn test_csv_and_json_formats() {
+        let records = parse_csv(&format!("# sanctions\nreason,Address\nfraud,{}\n,{}\n", EVM, SVM)).unwrap();
+        assert_eq!(records[0], (EVM.parse().unwrap(), Some("fraud".to_string())));
+        assert_eq!(records[1], (SVM.parse().unwrap(), None));
+        assert_eq!(parse_csv(&format!("{}, mixer\n", EVM)).unwrap()[0].1.as_deref(), Some("mixer"));
+        assert!(parse_csv("0x1234\n").unwrap_err().starts_with("line 1: invalid address"));
+
+        let plain = parse_json(&format!(r#"["{}", {{"address": "{}", "reason": "ofac"}}]"#, EVM, SVM)).unwrap();
+        assert_eq!(plain[1], (SVM.parse().unwrap(), Some("ofac".to_string())));
+        let wrapped = parse_json(&format!(r#"{{"addresses": ["{}"]}}"#, EVM)).unwrap();
+        assert_eq!(wrapped, vec![(EVM.parse().unwrap(), None)]);
+    }
+
+    #[test]
+    fn test_matches_receiver_and_sender() {
+        let dir = TempDir::new("screening-match");
+        // EVM 地址以 bytes32 形式列出也能命中
+        let padded = format!("0x{}{}", "0".repeat(24), &EVM[2..]);
+        std::fs::write(dir.join("ofac.csv"), format!("address,reason\n{},sanctioned\n", padded)).unwrap();
+        std::fs::write(dir.join("internal.json"), format!(r#"["{}"]"#, SVM)).unwrap();
+        std::fs::write(dir.join("notes.txt"), "ignored").unwrap();
+        let screener = Screener::load(vec![dir.to_path_buf()], Duration::from_secs(60)).unwrap();
+        assert_eq!(screener.len(), 2);
+
+        let hit = screener.check(&task(EVM, None)).unwrap();
+        assert_eq!((hit.party, hit.entry.list.as_str()), (Party::Receiver, "ofac"));
+        assert!(hit.to_string().contains("(sanctioned)"));
+
+        let clean = "0x0000000000000000000000000000000000000001";
+        let hit = screener.check(&task(clean, Some(SVM))).unwrap();
+        assert_eq!((hit.party, hit.entry.list.as_str()), (Party::Sender, "internal"));
+        assert!(screener.check(&task(clean, None)).is_none());
+    }
+
+    #[test]
+    fn test_hot_reload_keeps_previous_lists_on_error() {
+        let dir = TempDir::new("screening-reload");
+        let file = dir.join("deny.json");
+        std::fs::write(&file, format!(r#"["{}"]"#, EVM)).unwrap();
+        let screener = Screener::load(vec![file.clone()], Duration::ZERO).unwrap();
+        assert!(!screener.reload().unwrap());
+
+        // 修改时间精度可能为秒，显式设置为不同的时间
+        let touch = |secs: u64| {
+            let time = SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+            std::fs::File::options().write(true).open(&file).unwrap().set_modified(time).unwrap();
+        };
+        std::fs::write(&file, "not json").unwrap();
+        touch(1_000);
+        assert!(screener.check(&task(EVM, None)).is_some());
+        assert_eq!(screener.len(), 1);
+
+        std::fs::write(&file, format!(r#"["{}"]"#, SVM)).unwrap();
+        touch(2_000);
+        assert!(screener.check(&task(EVM, None)).is_none());
+        assert!(screener.check(&task(SVM, None)).is_some());
+
+        // 更换名单路径：无法加载时保留原有名单
+        assert!(screener.configure(vec![dir.join("missing.csv")], Duration::ZERO).is_err());
+        assert!(screener.check(&task(SVM, None)).is_some());
+        screener.configure(Vec::new(), Duration::ZERO).unwrap();
+        assert!(screener.is_empty());
+    }
+}
@@ -0,0 +1,551 @@
+//! 证明签名器
+//!
+//! `AttestationSigner` 抽象了 relayer 的签名密钥：
+//! - 本地实现（各服务内的 `EcdsaSigner` / `Ed25519Signer`）直接持有私钥
+//! - `RemoteSigner` 通过 HTTP 调用独立的签名服务（web3signer 风格 API），私钥留在加固主机上
+//!
+//! 签名服务 API（`signer_router` 提供同样的服务端实现）：
+//!
+//! | 方法 | 路径 | 说明 |
+//! |------|------|------|
+//! | GET  | `/upcheck` | 健康检查 |
+//! | GET  | `/api/v1/eth1/publicKeys` | secp256k1 签名者地址列表 |
+//! | POST | `/api/v1/eth1/sign/{address}` | secp256k1 签名 |
+//! | GET  | `/api/v1/ed25519/publicKeys` | Ed25519 公钥列表 (base58) |
+//! | POST | `/api/v1/ed25519/sign/{pubkey}` | Ed25519 签名 |
+//!
+//! 签名请求体为 `SignRequest`，始终携带完整的 `StakeEventData`，签名服务据此执行自己的策略；
+//! TRANSACTION 请求的交易先经 [`TransactionPolicy`] 解码校验，只签提交该事件的桥合约调用。
+//! 响应体为十六进制签名（`0x` 前缀，text/plain）。签名服务必须配置 `signer.auth_token`。
+
+use crate::config::SignerConfig;
+use crate::error::{RelayerError, Result};
+use crate::keystore::Secret;
+use crate::types::StakeEventData;
+use async_trait::async_trait;
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 签名算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SignatureScheme {
+    /// EVM：65 字节 r||s||v
+    Secp256k1,
+    /// SVM：64 字节签名
+    Ed25519,
+}
+
+impl SignatureScheme {
+    /// 签名服务 API 中的路径段
+    pub fn api_path(self) -> &'static str {
+        match self {
+            SignatureScheme::Secp256k1 => "eth1",
+            SignatureScheme::Ed25519 => "ed25519",
+        }
+    }
+
+    /// 签名长度（字节）
+    pub fn signature_len(self) -> usize {
+        match self {
+            SignatureScheme::Secp256k1 => 65,
+            SignatureScheme::Ed25519 => 64,
+        }
+    }
+
+    /// 规范化签名者身份（EVM 地址不区分大小写）
+    pub(crate) fn normalize(self, identity: &str) -> String {
+        match self {
+            SignatureScheme::Secp256k1 => identity.to_lowercase(),
+            SignatureScheme::Ed25519 => identity.to_string(),
+        }
+    }
+}
+
+/// relayer 签名器
+#[async_trait]
+pub trait AttestationSigner: Send + Sync {
+    /// 签名算法
+    fn scheme(&self) -> SignatureScheme;
+
+    /// 签名者身份：EVM 地址（0x 开头）或 base58 公钥
+    fn identity(&self) -> String;
+
+    /// 对跨链事件生成证明签名
+    /// - secp256k1：JSON + SHA-256 + EIP-191，返回 r||s||v（v = 27/28）
+    /// - Ed25519：对 Borsh 序列化的事件签名
+    async fn sign_event(&self, event: &StakeEventData) -> Result<Vec<u8>>;
+
+    /// 对提交该事件的目标链交易签名（合约要求交易发送者即证明签名者）
+    /// - secp256k1：对 keccak256(未签名交易 RLP) 签名，返回 r||s||v（v = 27/28）
+    /// - Ed25519：对序列化的交易消息签名
+    async fn sign_transaction(&self, event: &StakeEventData, transaction: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// 签名服务对 TRANSACTION 请求的校验：解码待签名交易，确认目标合约、调用数据和 nonce 与事件一致
+///
+/// 交易格式与目标链有关，由服务 crate 实现（s2e：EVM 交易，e2s-submitter：SVM 交易消息）
+pub trait TransactionPolicy: Send + Sync {
+    fn check(&self, event: &StakeEventData, transaction: &[u8]) -> Result<()>;
+}
+
+/// 签名请求类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum SignType {
+    Attestation,
+    Transaction,
+}
+
+/// 签名请求体
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignRequest {
+    #[serde(rename = "type")]
+    pub sign_type: SignType,
+    /// TRANSACTION 请求的待签名数据（十六进制）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+    pub event: StakeEventData,
+}
+
+/// 远程签名器（web3signer 风格 HTTP API）
+pub struct RemoteSigner {
+    client: reqwest::Client,
+    url: String,
+    scheme: SignatureScheme,
+    identity: String,
+    auth_token: Option<Secret>,
+}
+
+impl RemoteSigner {
+    pub fn new(config: &SignerConfig, scheme: SignatureScheme) -> Result<Self> {
+        let url = config
+            .url
+            .as_deref()
+            .ok_or_else(|| RelayerError::Config("signer.url is required for the remote signer".to_string()))?;
+        let identity = config.identifier.as_deref().ok_or_else(|| {
+            RelayerError::Config("signer.identifier is required for the remote signer".to_string())
+        })?;
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_millis(config.timeout))
+            .build()
+            .map_err(|e| RelayerError::Config(format!("Failed to create signer client: {}", e)))?;
+        Ok(Self {
+            client,
+            url: url.trim_end_matches('/').to_string(),
+            scheme,
+            identity: identity.to_string(),
+            auth_token: config.auth_token.clone(),
+        })
+    }
+
+    /// 检查签名服务可用且持有该身份的密钥
+    pub async fn check(&self) -> Result<()> {
+        let url = format!("{}/api/v1/{}/publicKeys", self.url, self.scheme.api_path());
+        let keys: Vec<String> = self
+            .authorize(self.client.get(&url))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| RelayerError::Network(format!("Remote signer {}: {}", url, e)))?
+            .json()
+            .await
+            .map_err(|e| RelayerError::Signature(format!("Invalid remote signer response: {}", e)))?;
+        let identity = self.scheme.normalize(&self.identity);
+        if keys.iter().any(|key| self.scheme.normalize(key) == identity) {
+            Ok(())
+        } else {
+            Err(RelayerError::Config(format!("Remote signer does not hold key {}", self.identity)))
+        }
+    }
+
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        match &self.auth_token {
+            Some(token) => request.bearer_auth(token.expose()),
+            None => request,
+        }
+    }
+
+    async fn sign(&self, request: &SignRequest) -> Result<Vec<u8>> {
+        let url = format!("{}/api/v1/{}/sign/{}", self.url, self.scheme.api_path(), self.identity);
+        let response = self
+            .authorize(self.client.post(&url))
+            .json(request)
+            .send()
+            .await
+            .map_err(|e| RelayerError::Network(format!("Remote signer {}: {}", url, e)))?;
+        let status = response.status();
+        let body = response
+            .text()
+            .await
+            .map_err(|e| RelayerError::Network(format!("Remote signer {}: {}", url, e)))?;
+        if !status.is_success() {
+            return Err(RelayerError::Signature(format!(
+                "Remote signer refused nonce {} ({}): {}",
+                request.event.nonce,
+                status,
+                body.trim()
+            )));
+        }
+
+        let mut signature = parse_signature(&body)?;
+        if signature.len() != self.scheme.signature_len() {
+            return Err(RelayerError::Signature(format!(
+                "Remote signer returned {} bytes, expected {}",
+                signature.len(),
+                self.scheme.signature_len()
+            )));
+        }
+        if self.scheme == SignatureScheme::Secp256k1 {
+            normalize_recovery_id(&mut signature)?;
+        }
+        Ok(signature)
+    }
+}
+
+#[async_trait]
+impl AttestationSigner for RemoteSigner {
+    fn scheme(&self) -> SignatureScheme {
+        self.scheme
+    }
+
+    fn identity(&self) -> String {
+        self.identity.clone()
+    }
+
+    async fn sign_event(&self, event: &StakeEventData) -> Result<Vec<u8>> {
+        self.sign(&SignRequest {
+            sign_type: SignType::Attestation,
+            data: None,
+            event: event.clone(),
+        })
+        .await
+    }
+
+    async fn sign_transaction(&self, event: &StakeEventData, transaction: &[u8]) -> Result<Vec<u8>> {
+        self.sign(&SignRequest {
+            sign_type: SignType::Transaction,
+            data: Some(format!("0x{}", hex::encode(transaction))),
+            event: event.clone(),
+        })
+        .await
+    }
+}
+
+/// secp256k1 签名 r||s||v 的 v 统一为 27/28（合约 `ecrecover` 的要求），部分兼容服务返回 0/1
+fn normalize_recovery_id(signature: &mut [u8]) -> Result<()> {
+    match signature[64] {
+        0 | 1 => signature[64] += 27,
+        27 | 28 => {}
+        v => return Err(RelayerError::Signature(format!("Remote signer returned invalid recovery id {}", v))),
+    }
+    Ok(())
+}
+
+/// 解析签名响应：`0x…` 文本或 `{"signature": "0x…"}`
+fn parse_signature(body: &str) -> Result<Vec<u8>> {
+    #[derive(Deserialize)]
+    struct SignatureBody {
+        signature: String,
+    }
+
+    let body = body.trim();
+    let text = if body.starts_with('{') {
+        serde_json::from_str::<SignatureBody>(body)
+            .map_err(|e| RelayerError::Signature(format!("Invalid remote signer response: {}", e)))?
+            .signature
+    } else {
+        body.trim_matches('"').to_string()
+    };
+    hex::decode(text.trim_start_matches("0x"))
+        .map_err(|e| RelayerError::Signature(format!("Invalid remote signer signature: {}", e)))
+}
+
+/// 签名服务状态
+#[derive(Clone)]
+struct SignerService {
+    signers: Arc<HashMap<(SignatureScheme, String), Arc<dyn AttestationSigner>>>,
+    policy: Arc<dyn TransactionPolicy>,
+    auth_token: Secret,
+}
+
+/// 签名服务路由：以本地签名器为后端提供与 `RemoteSigner` 对应的 HTTP API
+/// （用于加固签名主机，也可作为本地 mock 签名服务）
+pub fn signer_router(
+    signers: Vec<Arc<dyn AttestationSigner>>,
+    policy: Arc<dyn TransactionPolicy>,
+    auth_token: Secret,
+) -> Router {
+    let signers = signers
+        .into_iter()
+        .map(|signer| ((signer.scheme(), signer.scheme().normalize(&signer.identity())), signer))
+        .collect();
+    let service = SignerService {
+        signers: Arc::new(signers),
+        policy,
+        auth_token,
+    };
+
+    Router::new()
+        .route("/upcheck", get(|| async { "OK" }))
+        .route("/api/v1/eth1/publicKeys", get(eth1_public_keys))
+        .route("/api/v1/eth1/sign/:identifier", post(eth1_sign))
+        .route("/api/v1/ed25519/publicKeys", get(ed25519_public_keys))
+        .route("/api/v1/ed25519/sign/:identifier", post(ed25519_sign))
+        .with_state(service)
+}
+
+type ServiceError = (StatusCode, String);
+
+impl SignerService {
+    fn authorize(&self, headers: &HeaderMap) -> std::result::Result<(), ServiceError> {
+        let provided = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if provided.is_some_and(|provided| self.auth_token.matches(provided)) {
+            Ok(())
+        } else {
+            Err((StatusCode::UNAUTHORIZED, "Unauthorized".to_string()))
+        }
+    }
+
+    fn public_keys(&self, headers: &HeaderMap, scheme: SignatureScheme) -> std::result::Result<Json<Vec<String>>, ServiceError> {
+        self.authorize(headers)?;
+        let mut keys: Vec<String> = self
+            .signers
+            .values()
+            .filter(|signer| signer.scheme() == scheme)
+            .map(|signer| signer.identity())
+            .collect();
+        keys.sort();
+        Ok(Json(keys))
+    }
+
+    async fn sign(
+        &self,
+        headers: &HeaderMap,
+        scheme: SignatureScheme,
+        identifier: &str,
+        request: SignRequest,
+    ) -> std::result::Result<String, ServiceError> {
+        self.authorize(headers)?;
+        let signer = self
+            .signers
+            .get(&(scheme, scheme.normalize(identifier)))
+            .ok_or_else(|| (StatusCode::NOT_FOUND, format!("Key not found: {}", identifier)))?;
+
+        let signature = match request.sign_type {
+            SignType::Attestation => signer.sign_event(&request.event).await,
+            SignType::Transaction => {
+                let data = request
+                    .data
+                    .as_deref()
+                    .ok_or_else(|| (StatusCode::BAD_REQUEST, "Missing data".to_string()))?;
+                let data = hex::decode(data.trim_start_matches("0x"))
+                    .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid data: {}", e)))?;
+                if let Err(e) = self.policy.check(&request.event, &data) {
+                    tracing::warn!(nonce = request.event.nonce, error = %e, "Refusing to sign transaction");
+                    return Err((StatusCode::PRECONDITION_FAILED, e.to_string()));
+                }
+                signer.sign_transaction(&request.event, &data).await
+            }
+        };
+        signature
+            .map(|signature| format!("0x{}", hex::encode(signature)))
+            .map_err(|e| (StatusCode::PRECONDITION_FAILED, e.to_string()))
+    }
+}
+
+async fn eth1_public_keys(State(service): State<SignerService>, headers: HeaderMap) -> impl IntoResponse {
+    service.public_keys(&headers, SignatureScheme::Secp256k1)
+}
+
+async fn ed25519_public_keys(State(service): State<SignerService>, headers: HeaderMap) -> impl IntoResponse {
+    service.public_keys(&headers, SignatureScheme::Ed25519)
+}
+
+async fn eth1_sign(
+    State(service): State<SignerService>,
+    Path(identifier): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<SignRequest>,
+) -> impl IntoResponse {
+    service.sign(&headers, SignatureScheme::Secp256k1, &identifier, request).await
+}
+
+async fn ed25519_sign(
+    State(service): State<SignerService>,
+    Path(identifier): Path<String>,
+    headers: HeaderMap,
+    Json(request): Json<SignRequest>,
+) -> impl IntoResponse {
+    service.sign(&headers, SignatureScheme::Ed25519, &identifier, request).await
+}
+
+/// 启动签名服务；未配置 `signer.auth_token` 时拒绝启动
+pub async fn serve_signer(
+    addr: std::net::SocketAddr,
+    signers: Vec<Arc<dyn AttestationSigner>>,
+    policy: Arc<dyn TransactionPolicy>,
+    auth_token: Option<Secret>,
+) -> Result<()> {
+    let auth_token = auth_token
+        .ok_or_else(|| RelayerError::Config("signer.auth_token is required to serve the signer API".to_string()))?;
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .map_err(|e| RelayerError::Config(format!("Failed to bind signer service on {}: {}", addr, e)))?;
+    tracing::info!(addr = %addr, "Signer service listening");
+    axum::serve(listener, signer_router(signers, policy, auth_token))
+        .await
+        .map_err(|e| RelayerError::Network(format!("Signer service error: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{event, event_with_amount};
+
+    /// mock 签名器：签名 = 重复的 nonce 字节，便于断言；secp256k1 的 v 按 nonce 奇偶返回 0/1
+    struct MockSigner {
+        scheme: SignatureScheme,
+        identity: String,
+    }
+
+    #[async_trait]
+    impl AttestationSigner for MockSigner {
+        fn scheme(&self) -> SignatureScheme {
+            self.scheme
+        }
+
+        fn identity(&self) -> String {
+            self.identity.clone()
+        }
+
+        async fn sign_event(&self, event: &StakeEventData) -> Result<Vec<u8>> {
+            if event.amount > 1_000_000 {
+                return Err(RelayerError::Signature("amount above policy limit".to_string()));
+            }
+            Ok(self.signature(event.nonce as u8))
+        }
+
+        async fn sign_transaction(&self, _event: &StakeEventData, transaction: &[u8]) -> Result<Vec<u8>> {
+            Ok(self.signature(transaction[0]))
+        }
+    }
+
+    impl MockSigner {
+        fn signature(&self, byte: u8) -> Vec<u8> {
+            let mut signature = vec![byte; self.scheme.signature_len()];
+            if self.scheme == SignatureScheme::Secp256k1 {
+                signature[64] = byte % 2;
+            }
+            signature
+        }
+    }
+
+    /// 远程签名器返回的 secp256k1 签名：v 已转换为 27/28
+    fn normalized(byte: u8) -> Vec<u8> {
+        let mut signature = vec![byte; 65];
+        signature[64] = 27 + byte % 2;
+        signature
+    }
+
+    /// mock 交易校验：交易首字节必须等于事件 nonce
+    struct MockPolicy;
+
+    impl TransactionPolicy for MockPolicy {
+        fn check(&self, event: &StakeEventData, transaction: &[u8]) -> Result<()> {
+            if transaction.first() != Some(&(event.nonce as u8)) {
+                return Err(RelayerError::Signature(format!("Transaction does not match nonce {}", event.nonce)));
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_remote_signer_against_mock_service() {
+        let address = "0xAbCdEf0000000000000000000000000000000001";
+        let signers: Vec<Arc<dyn AttestationSigner>> = vec![
+            Arc::new(MockSigner { scheme: SignatureScheme::Secp256k1, identity: address.to_string() }),
+            Arc::new(MockSigner { scheme: SignatureScheme::Ed25519, identity: "MockEd25519Key".to_string() }),
+        ];
+        let token = Secret::new("s3cret");
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let router = signer_router(signers, Arc::new(MockPolicy), token.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let mut config = SignerConfig {
+            backend: "remote".to_string(),
+            url: Some(url),
+            identifier: Some(address.to_lowercase()),
+            auth_token: Some(token),
+            timeout: 5000,
+        };
+        let ecdsa = RemoteSigner::new(&config, SignatureScheme::Secp256k1).unwrap();
+        ecdsa.check().await.unwrap();
+        assert_eq!(ecdsa.sign_event(&event(7)).await.unwrap(), normalized(7));
+        assert_eq!(ecdsa.sign_event(&event(8)).await.unwrap(), normalized(8));
+        assert_eq!(ecdsa.sign_transaction(&event(7), &[7, 1]).await.unwrap(), normalized(7));
+        // 交易与事件不符
+        let err = ecdsa.sign_transaction(&event(7), &[9, 1]).await.unwrap_err();
+        assert!(err.to_string().contains("does not match nonce 7"), "{}", err);
+        // 签名服务策略拒绝
+        let err = ecdsa.sign_event(&event_with_amount(8, 2_000_000)).await.unwrap_err();
+        assert!(err.to_string().contains("policy limit"), "{}", err);
+
+        config.identifier = Some("MockEd25519Key".to_string());
+        let ed25519 = RemoteSigner::new(&config, SignatureScheme::Ed25519).unwrap();
+        assert_eq!(ed25519.sign_event(&event(3)).await.unwrap(), vec![3u8; 64]);
+
+        // 未知密钥 / 错误 token
+        config.identifier = Some("UnknownKey".to_string());
+        let unknown = RemoteSigner::new(&config, SignatureScheme::Ed25519).unwrap();
+        assert!(unknown.check().await.is_err());
+        assert!(unknown.sign_event(&event(3)).await.is_err());
+        config.identifier = Some(address.to_string());
+        config.auth_token = Some(Secret::new("wrong"));
+        let unauthorized = RemoteSigner::new(&config, SignatureScheme::Secp256k1).unwrap();
+        let err = unauthorized.sign_event(&event(3)).await.unwrap_err();
+        assert!(err.to_string().contains("401"), "{}", err);
+        config.auth_token = None;
+        let anonymous = RemoteSigner::new(&config, SignatureScheme::Secp256k1).unwrap();
+        assert!(anonymous.sign_event(&event(3)).await.unwrap_err().to_string().contains("401"));
+    }
+
+    #[tokio::test]
+    async fn test_signer_service_requires_token() {
+        let addr = "127.0.0.1:0".parse().unwrap();
+        let err = serve_signer(addr, Vec::new(), Arc::new(MockPolicy), None).await.unwrap_err();
+        assert!(err.to_string().contains("signer.auth_token"), "{}", err);
+    }
+
+    #[test]
+    fn test_normalize_recovery_id() {
+        for (v, expected) in [(0, 27), (1, 28), (27, 27), (28, 28)] {
+            let mut signature = vec![0u8; 65];
+            signature[64] = v;
+            normalize_recovery_id(&mut signature).unwrap();
+            assert_eq!(signature[64], expected);
+        }
+        let mut signature = vec![0u8; 65];
+        signature[64] = 35;
+        assert!(normalize_recovery_id(&mut signature).is_err());
+    }
+
+    #[test]
+    fn test_parse_signature() {
+        assert_eq!(parse_signature("0x0102\n").unwrap(), vec![1, 2]);
+        assert_eq!(parse_signature("\"0x0102\"").unwrap(), vec![1, 2]);
+        assert_eq!(parse_signature(r#"{"signature":"0x0102"}"#).unwrap(), vec![1, 2]);
+        assert!(parse_signature("not hex").is_err());
+    }
+}
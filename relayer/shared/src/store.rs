@@ -0,0 +1,662 @@
+use crate::config::Config;
+use crate::error::{RelayerError, Result};
+use crate::types::{QueueStatus, StakeEventData, Task, TaskStatus};
+use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+/// 任务持久化存储
+///
+/// 任务以 (源链, 源合约, nonce) 为主键，生命周期为
+/// Pending → Processing → Completed / Failed，风控暂扣时为 Pending → Held → Pending / Failed，
+/// 命中合规拒绝名单或事件入口校验失败时为 Pending → Blocked；源链事件未达到最终性时先登记为 AwaitingFinality，
+/// 被源链回滚时转为 Retracted，重新出现后恢复。
+/// 状态迁移由 [`Task`] 自身校验，存储层只负责原子地落盘。
+#[async_trait]
+pub trait TaskStore: Send + Sync {
+    /// 插入任务；若同 ID 任务已存在则不覆盖，返回 false
+    async fn insert(&self, task: &Task) -> Result<bool>;
+
+    /// 按 ID 读取任务
+    async fn get(&self, id: &str) -> Result<Option<Task>>;
+
+    /// 覆盖保存任务的最新状态，任务不存在时返回错误
+    async fn update(&self, task: &Task) -> Result<()>;
+
+    /// 仅当存储中的任务仍处于 `expected` 状态时保存，返回是否保存成功
+    ///
+    /// 多个实例共享存储时用于认领任务：同一任务只有一个实例能从 Pending 迁移出去
+    async fn update_if(&self, task: &Task, expected: TaskStatus) -> Result<bool>;
+
+    /// 按状态列出任务（按创建时间、nonce 升序）
+    async fn list(&self, status: Option<TaskStatus>, limit: usize) -> Result<Vec<Task>>;
+
+    /// 统计某状态的任务数
+    async fn count(&self, status: TaskStatus) -> Result<u64>;
+
+    /// 按状态统计全部任务数
+    async fn counts(&self) -> Result<Vec<(TaskStatus, u64)>> {
+        let mut counts = Vec::with_capacity(TaskStatus::ALL.len());
+        for status in TaskStatus::ALL {
+            counts.push((status, self.count(status).await?));
+        }
+        Ok(counts)
+    }
+
+    /// 为事件创建任务，重复事件返回 None
+    async fn enqueue(&self, event: StakeEventData) -> Result<Option<Task>> {
+        let task = Task::new(event);
+        if self.insert(&task).await? {
+            Ok(Some(task))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// 崩溃恢复：把超过 `stale_after` 未更新的 Processing 任务放回 Pending
+    ///
+    /// 多个实例共享存储时，只接手崩溃实例遗留的任务，不动其他实例正在处理的任务
+    async fn recover(&self, stale_after: Duration) -> Result<usize> {
+        let now = chrono::Utc::now();
+        let Some(cutoff) = chrono::Duration::from_std(stale_after).ok().and_then(|d| now.checked_sub_signed(d)) else {
+            return Ok(0);
+        };
+        let mut count = 0;
+        for mut task in self.list(Some(TaskStatus::Processing), usize::MAX).await? {
+            if task.updated_at > cutoff {
+                continue;
+            }
+            task.status = TaskStatus::Pending;
+            task.updated_at = now;
+            // 原实例可能恰好在此时完成
+            if self.update_if(&task, TaskStatus::Processing).await? {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+
+    /// 已通过风控的任务中 [`Task::exposed_at`] 晚于 `since` 的，按该时间升序
+    async fn signed_since(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<Task>> {
+        let mut tasks: Vec<Task> = self
+            .list(None, usize::MAX)
+            .await?
+            .into_iter()
+            .filter(|task| task.exposed_at().is_some_and(|at| at > since))
+            .collect();
+        tasks.sort_by_key(|task| task.exposed_at());
+        Ok(tasks)
+    }
+
+    /// 取出已到期、可以处理的 Pending 任务
+    async fn due(&self, retry_delays: &[u64], limit: usize) -> Result<Vec<Task>> {
+        let now = chrono::Utc::now();
+        Ok(self
+            .list(Some(TaskStatus::Pending), usize::MAX)
+            .await?
+            .into_iter()
+            .filter(|task| task.is_due(retry_delays, now))
+            .take(limit)
+            .collect())
+    }
+
+    /// 汇总队列状态（用于 HTTP API）
+    async fn queue_status(&self, limit: usize) -> Result<QueueStatus> {
+        let mut tasks = self.list(None, usize::MAX).await?;
+        tasks.sort_by_key(|task| std::cmp::Reverse(task.updated_at));
+        let counts = self.counts().await?;
+        let count = |status| counts.iter().find(|(s, _)| *s == status).map_or(0, |(_, count)| *count);
+        Ok(QueueStatus {
+            pending: count(TaskStatus::Pending),
+            processing: count(TaskStatus::Processing),
+            completed: count(TaskStatus::Completed),
+            failed: count(TaskStatus::Failed),
+            held: count(TaskStatus::Held),
+            blocked: count(TaskStatus::Blocked),
+            awaiting_finality: count(TaskStatus::AwaitingFinality),
+            retracted: count(TaskStatus::Retracted),
+            tasks: tasks.iter().take(limit).map(Task::summary).collect(),
+        })
+    }
+}
+
+/// 根据配置打开任务存储
+pub async fn open_task_store(config: &Config) -> Result<Arc<dyn TaskStore>> {
+    match config.store.backend.as_str() {
+        "file" => {
+            let path = config.store.file_path(&config.queue);
+            info!(path = %path.display(), "Using file task store");
+            Ok(Arc::new(FileTaskStore::open(path)?))
+        }
+        #[cfg(feature = "database")]
+        "postgres" => {
+            let url = config
+                .store
+                .database_url
+                .as_deref()
+                .ok_or_else(|| RelayerError::Config("STORE__DATABASE_URL is not set".to_string()))?;
+            info!("Using postgres task store");
+            Ok(Arc::new(PgTaskStore::connect(url).await?))
+        }
+        other => Err(RelayerError::Config(format!(
+            "Unsupported store backend: {} (postgres requires the `database` feature)",
+            other
+        ))),
+    }
+}
+
+/// 已结束的任务超过该时长后移入 `archive/`，不再参与列表和统计
+const ARCHIVE_AFTER: Duration = Duration::from_secs(7 * 24 * 3600);
+
+/// 任务修改锁超过该时长视为持有者已崩溃
+const LOCK_STALE_AFTER: Duration = Duration::from_secs(30);
+
+/// 持有任务修改锁（`.<id>.lock`），drop 时释放
+struct TaskLock(PathBuf);
+
+impl Drop for TaskLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// 文件任务存储：每个任务一个 JSON 文件，通过 临时文件 + rename 保证原子写入
+///
+/// 已解析的任务按文件长度和修改时间缓存，文件未变化时不重新解析，但列表和统计仍要遍历目录，
+/// 只适合单机、低流量部署，多实例或高流量时使用 PostgreSQL。
+/// Completed / Failed / Blocked / Retracted 超过 7 天的任务移入 `archive/` 子目录，仍可按 ID 读取和去重，
+/// 再次更新时移回主目录。
+#[derive(Debug, Clone)]
+pub struct FileTaskStore {
+    dir: PathBuf,
+    cache: Arc<Mutex<HashMap<PathBuf, CachedTask>>>,
+}
+
+#[derive(Debug)]
+struct CachedTask {
+    len: u64,
+    modified: SystemTime,
+    task: Task,
+}
+
+impl FileTaskStore {
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(dir.join("archive"))
+            .map_err(|e| RelayerError::Database(format!("Failed to create {}: {}", dir.display(), e)))?;
+        Ok(Self { dir, cache: Arc::default() })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn task_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    fn archive_path(&self, id: &str) -> PathBuf {
+        self.dir.join("archive").join(format!("{}.json", id))
+    }
+
+    /// 获取任务修改锁：create_new 成功即持有，其他进程持有时等待，过期的锁直接接管
+    async fn lock(&self, id: &str) -> Result<TaskLock> {
+        let path = self.dir.join(format!(".{}.lock", id));
+        let deadline = SystemTime::now() + LOCK_STALE_AFTER;
+        loop {
+            match std::fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(TaskLock(path)),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let stale = std::fs::metadata(&path)
+                        .and_then(|metadata| metadata.modified())
+                        .is_ok_and(|modified| modified.elapsed().is_ok_and(|age| age > LOCK_STALE_AFTER));
+                    if stale {
+                        warn!(task = %id, "Taking over stale task lock");
+                        let _ = std::fs::remove_file(&path);
+                    } else if SystemTime::now() > deadline {
+                        return Err(RelayerError::Database(format!("Timed out waiting for task lock {}", id)));
+                    } else {
+                        tokio::time::sleep(Duration::from_millis(10)).await;
+                    }
+                }
+                Err(e) => return Err(RelayerError::Database(format!("Failed to lock task {}: {}", id, e))),
+            }
+        }
+    }
+
+    /// 持有锁时写入：任务文件必须已存在，已归档的任务移回主目录
+    fn replace(&self, task: &Task) -> Result<()> {
+        let path = self.task_path(&task.id);
+        let archived = self.archive_path(&task.id);
+        let restore = !path.exists() && archived.exists();
+        if !path.exists() && !restore {
+            return Err(RelayerError::Database(format!("Task {} does not exist", task.id)));
+        }
+        let tmp = self.write_tmp(task)?;
+        std::fs::rename(&tmp, path)
+            .map_err(|e| RelayerError::Database(format!("Failed to update task {}: {}", task.id, e)))?;
+        if restore {
+            let _ = std::fs::remove_file(archived);
+        }
+        Ok(())
+    }
+
+    /// 现在的主目录文件或归档文件
+    fn current_path(&self, id: &str) -> Option<PathBuf> {
+        [self.task_path(id), self.archive_path(id)].into_iter().find(|path| path.exists())
+    }
+
+    fn write_tmp(&self, task: &Task) -> Result<PathBuf> {
+        let tmp = self.dir.join(format!(".{}.{}.tmp", task.id, std::process::id()));
+        let json = serde_json::to_vec_pretty(task)
+            .map_err(|e| RelayerError::Serialization(e.to_string()))?;
+        let file = std::fs::File::create(&tmp).and_then(|mut f| {
+            use std::io::Write;
+            f.write_all(&json)?;
+            f.sync_all()?;
+            Ok(f)
+        });
+        file.map_err(|e| RelayerError::Database(format!("Failed to write {}: {}", tmp.display(), e)))?;
+        Ok(tmp)
+    }
+
+    fn read_task(path: &Path) -> Result<Task> {
+        let content = std::fs::read(path)
+            .map_err(|e| RelayerError::Database(format!("Failed to read {}: {}", path.display(), e)))?;
+        serde_json::from_slice(&content)
+            .map_err(|e| RelayerError::Serialization(format!("{}: {}", path.display(), e)))
+    }
+
+    fn read_all(&self) -> Result<Vec<Task>> {
+        let entries = std::fs::read_dir(&self.dir)
+            .map_err(|e| RelayerError::Database(format!("Failed to read {}: {}", self.dir.display(), e)))?;
+
+        let archive_before = chrono::Utc::now() - chrono::Duration::from_std(ARCHIVE_AFTER).unwrap();
+        let mut cache = self.cache.lock().unwrap();
+        let mut seen = HashSet::new();
+        let mut tasks = Vec::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let is_task = path.extension().and_then(|s| s.to_str()) == Some("json")
+                && !path
+                    .file_name()
+                    .and_then(|s| s.to_str())
+                    .is_some_and(|s| s.starts_with('.'));
+            if !is_task {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let (len, modified) = (metadata.len(), metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH));
+            let task = match cache.get(&path) {
+                Some(cached) if cached.len == len && cached.modified == modified => cached.task.clone(),
+                _ => match Self::read_task(&path) {
+                    Ok(task) => {
+                        cache.insert(path.clone(), CachedTask { len, modified, task: task.clone() });
+                        task
+                    }
+                    Err(e) => {
+                        warn!(error = %e, "Skipping unreadable task file");
+                        continue;
+                    }
+                },
+            };
+            let finished = matches!(
+                task.status,
+                TaskStatus::Completed | TaskStatus::Failed | TaskStatus::Blocked | TaskStatus::Retracted
+            );
+            if finished && task.updated_at < archive_before {
+                match std::fs::rename(&path, self.archive_path(&task.id)) {
+                    Ok(()) => continue,
+                    Err(e) => warn!(task = %task.id, error = %e, "Failed to archive finished task"),
+                }
+            }
+            seen.insert(path);
+            tasks.push(task);
+        }
+        cache.retain(|path, _| seen.contains(path));
+        tasks.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.nonce.cmp(&b.nonce)));
+        Ok(tasks)
+    }
+}
+
+#[async_trait]
+impl TaskStore for FileTaskStore {
+    async fn insert(&self, task: &Task) -> Result<bool> {
+        if self.archive_path(&task.id).exists() {
+            return Ok(false);
+        }
+        let tmp = self.write_tmp(task)?;
+        // hard_link 在目标已存在时失败，实现原子的"不存在才创建"
+        let result = std::fs::hard_link(&tmp, self.task_path(&task.id));
+        let _ = std::fs::remove_file(&tmp);
+        match result {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+            Err(e) => Err(RelayerError::Database(format!("Failed to insert task {}: {}", task.id, e))),
+        }
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<Task>> {
+        self.current_path(id).map(|path| Self::read_task(&path)).transpose()
+    }
+
+    async fn update(&self, task: &Task) -> Result<()> {
+        let _lock = self.lock(&task.id).await?;
+        self.replace(task)
+    }
+
+    async fn update_if(&self, task: &Task, expected: TaskStatus) -> Result<bool> {
+        let _lock = self.lock(&task.id).await?;
+        match self.current_path(&task.id) {
+            Some(path) if Self::read_task(&path)?.status == expected => {}
+            _ => return Ok(false),
+        }
+        self.replace(task)?;
+        Ok(true)
+    }
+
+    async fn list(&self, status: Option<TaskStatus>, limit: usize) -> Result<Vec<Task>> {
+        Ok(self
+            .read_all()?
+            .into_iter()
+            .filter(|task| status.is_none_or(|s| task.status == s))
+            .take(limit)
+            .collect())
+    }
+
+    async fn count(&self, status: TaskStatus) -> Result<u64> {
+        Ok(self.read_all()?.iter().filter(|task| task.status == status).count() as u64)
+    }
+
+    async fn counts(&self) -> Result<Vec<(TaskStatus, u64)>> {
+        let tasks = self.read_all()?;
+        Ok(TaskStatus::ALL
+            .into_iter()
+            .map(|status| (status, tasks.iter().filter(|task| task.status == status).count() as u64))
+            .collect())
+    }
+}
+
+/// PostgreSQL 任务存储（`database` feature）
+#[cfg(feature = "database")]
+#[derive(Debug, Clone)]
+pub struct PgTaskStore {
+    pool: sqlx::PgPool,
+}
+
+#[cfg(feature = "database")]
+impl PgTaskStore {
+    pub async fn connect(url: &str) -> Result<Self> {
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(5)
+            .connect(url)
+            .await
+            .map_err(|e| RelayerError::Database(format!("Failed to connect: {}", e)))?;
+
+        sqlx::query(
+            r#"CREATE TABLE IF NOT EXISTS relayer_tasks (
+                id TEXT PRIMARY KEY,
+                nonce BIGINT NOT NULL,
+                status TEXT NOT NULL,
+                task JSONB NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL,
+                updated_at TIMESTAMPTZ NOT NULL
+            )"#,
+        )
+        .execute(&pool)
+        .await
+        .map_err(|e| RelayerError::Database(format!("Failed to create table: {}", e)))?;
+
+        // signed_at 列由旧版本表升级而来：最近 24 小时已签名或正在处理的旧任务以 updated_at 补齐
+        for statement in [
+            "ALTER TABLE relayer_tasks ADD COLUMN IF NOT EXISTS signed_at TIMESTAMPTZ",
+            "UPDATE relayer_tasks SET signed_at = updated_at
+             WHERE signed_at IS NULL AND updated_at > now() - interval '24 hours'
+               AND (status = 'processing' OR task->>'signature' IS NOT NULL)",
+            "CREATE INDEX IF NOT EXISTS relayer_tasks_status ON relayer_tasks (status, created_at, nonce)",
+            "CREATE INDEX IF NOT EXISTS relayer_tasks_signed ON relayer_tasks (signed_at)",
+        ] {
+            sqlx::query(statement)
+                .execute(&pool)
+                .await
+                .map_err(|e| RelayerError::Database(format!("Failed to migrate table: {}", e)))?;
+        }
+
+        Ok(Self { pool })
+    }
+
+    fn decode(value: serde_json::Value) -> Result<Task> {
+        serde_json::from_value(value).map_err(|e| RelayerError::Serialization(e.to_string()))
+    }
+}
+
+#[cfg(feature = "database")]
+#[async_trait]
+impl TaskStore for PgTaskStore {
+    async fn insert(&self, task: &Task) -> Result<bool> {
+        let result = sqlx::query(
+            "INSERT INTO relayer_tasks (id, nonce, status, task, created_at, updated_at, signed_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7) ON CONFLICT (id) DO NOTHING",
+        )
+        .bind(&task.id)
+        .bind(task.nonce as i64)
+        .bind(task.status.to_string())
+        .bind(sqlx::types::Json(task))
+        .bind(task.created_at)
+        .bind(task.updated_at)
+        .bind(task.signed_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RelayerError::Database(e.to_string()))?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<Task>> {
+        let row: Option<(serde_json::Value,)> =
+            sqlx::query_as("SELECT task FROM relayer_tasks WHERE id = $1")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| RelayerError::Database(e.to_string()))?;
+        row.map(|(value,)| Self::decode(value)).transpose()
+    }
+
+    async fn update(&self, task: &Task) -> Result<()> {
+        let result = sqlx::query(
+            "UPDATE relayer_tasks SET status = $2, task = $3, updated_at = $4, signed_at = COALESCE($5, signed_at)
+             WHERE id = $1",
+        )
+        .bind(&task.id)
+        .bind(task.status.to_string())
+        .bind(sqlx::types::Json(task))
+        .bind(task.updated_at)
+        .bind(task.signed_at)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RelayerError::Database(e.to_string()))?;
+        if result.rows_affected() == 0 {
+            return Err(RelayerError::Database(format!("Task {} does not exist", task.id)));
+        }
+        Ok(())
+    }
+
+    async fn update_if(&self, task: &Task, expected: TaskStatus) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE relayer_tasks SET status = $2, task = $3, updated_at = $4, signed_at = COALESCE($5, signed_at)
+             WHERE id = $1 AND status = $6",
+        )
+        .bind(&task.id)
+        .bind(task.status.to_string())
+        .bind(sqlx::types::Json(task))
+        .bind(task.updated_at)
+        .bind(task.signed_at)
+        .bind(expected.to_string())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RelayerError::Database(e.to_string()))?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    async fn list(&self, status: Option<TaskStatus>, limit: usize) -> Result<Vec<Task>> {
+        let limit = limit.min(i64::MAX as usize) as i64;
+        let rows: Vec<(serde_json::Value,)> = sqlx::query_as(
+            "SELECT task FROM relayer_tasks WHERE ($1::TEXT IS NULL OR status = $1)
+             ORDER BY created_at, nonce LIMIT $2",
+        )
+        .bind(status.map(|s| s.to_string()))
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RelayerError::Database(e.to_string()))?;
+        rows.into_iter().map(|(value,)| Self::decode(value)).collect()
+    }
+
+    async fn signed_since(&self, since: chrono::DateTime<chrono::Utc>) -> Result<Vec<Task>> {
+        let rows: Vec<(serde_json::Value, chrono::DateTime<chrono::Utc>)> =
+            sqlx::query_as("SELECT task, signed_at FROM relayer_tasks WHERE signed_at > $1 ORDER BY signed_at")
+                .bind(since)
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| RelayerError::Database(e.to_string()))?;
+        rows.into_iter()
+            .map(|(value, signed_at)| {
+                let mut task = Self::decode(value)?;
+                task.signed_at.get_or_insert(signed_at);
+                Ok(task)
+            })
+            .collect()
+    }
+
+    async fn count(&self, status: TaskStatus) -> Result<u64> {
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM relayer_tasks WHERE status = $1")
+            .bind(status.to_string())
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| RelayerError::Database(e.to_string()))?;
+        Ok(count as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{event, TempDir};
+
+    #[tokio::test]
+    async fn test_enqueue_is_idempotent() {
+        let dir = TempDir::new("store-idempotent");
+        let store = FileTaskStore::open(&*dir).unwrap();
+        assert!(store.enqueue(event(1)).await.unwrap().is_some());
+        assert!(store.enqueue(event(1)).await.unwrap().is_none());
+        assert_eq!(store.count(TaskStatus::Pending).await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_lifecycle_persists() {
+        let dir = TempDir::new("store-lifecycle");
+        let store = FileTaskStore::open(&*dir).unwrap();
+        let mut task = store.enqueue(event(2)).await.unwrap().unwrap();
+
+        task.start().unwrap();
+        store.update(&task).await.unwrap();
+        task.retry_later("rpc timeout").unwrap();
+        store.update(&task).await.unwrap();
+        task.start().unwrap();
+        task.complete("0xdeadbeef".to_string()).unwrap();
+        store.update(&task).await.unwrap();
+
+        let mut loaded = store.get(&task.id).await.unwrap().unwrap();
+        assert_eq!(loaded.status, TaskStatus::Completed);
+        assert_eq!(loaded.retries, 1);
+        assert_eq!(loaded.transaction_hash.as_deref(), Some("0xdeadbeef"));
+        assert!(loaded.completed_at.is_some());
+        assert!(loaded.start().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_update_if_claims_once() {
+        let dir = TempDir::new("store-claim");
+        let store = FileTaskStore::open(&*dir).unwrap();
+        let pending = store.enqueue(event(3)).await.unwrap().unwrap();
+
+        // 两个实例读到同一个 Pending 任务，只有一个能认领
+        let (mut first, mut second) = (pending.clone(), pending);
+        first.start().unwrap();
+        second.start().unwrap();
+        assert!(store.update_if(&first, TaskStatus::Pending).await.unwrap());
+        assert!(!store.update_if(&second, TaskStatus::Pending).await.unwrap());
+
+        // 不存在的任务不会被 update 创建
+        let missing = Task::new(event(4));
+        assert!(store.update(&missing).await.is_err());
+        assert!(!store.update_if(&missing, TaskStatus::Pending).await.unwrap());
+        assert!(store.get(&missing.id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_recover_resets_processing() {
+        let dir = TempDir::new("store-recover");
+        let store = FileTaskStore::open(&*dir).unwrap();
+        let mut task = store.enqueue(event(3)).await.unwrap().unwrap();
+        task.start().unwrap();
+        store.update(&task).await.unwrap();
+
+        // 刚开始处理的任务可能属于其他实例，不接手
+        assert_eq!(store.recover(Duration::from_secs(300)).await.unwrap(), 0);
+        assert_eq!(store.count(TaskStatus::Processing).await.unwrap(), 1);
+
+        assert_eq!(store.recover(Duration::ZERO).await.unwrap(), 1);
+        let due = store.due(&[0], 10).await.unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].status, TaskStatus::Pending);
+    }
+
+    #[tokio::test]
+    async fn test_archives_old_completed_tasks() {
+        let dir = TempDir::new("store-archive");
+        let store = FileTaskStore::open(&*dir).unwrap();
+        let mut task = store.enqueue(event(5)).await.unwrap().unwrap();
+        task.start().unwrap();
+        task.complete("0xdeadbeef".to_string()).unwrap();
+        task.updated_at = chrono::Utc::now() - chrono::Duration::days(8);
+        store.update(&task).await.unwrap();
+        store.enqueue(event(6)).await.unwrap().unwrap();
+
+        assert_eq!(store.count(TaskStatus::Completed).await.unwrap(), 0);
+        assert_eq!(store.list(None, usize::MAX).await.unwrap().len(), 1);
+        assert!(dir.join("archive").join(format!("{}.json", task.id)).exists());
+        // 归档后仍可读取，重复事件不会重新建任务
+        assert_eq!(store.get(&task.id).await.unwrap().unwrap().status, TaskStatus::Completed);
+        assert!(store.enqueue(event(5)).await.unwrap().is_none());
+
+        // 撤回的任务同样归档，事件重新出现时移回主目录
+        let mut retracted = Task::new(event(7));
+        retracted.retract("reorg").unwrap();
+        retracted.updated_at = chrono::Utc::now() - chrono::Duration::days(8);
+        store.insert(&retracted).await.unwrap();
+        assert_eq!(store.count(TaskStatus::Retracted).await.unwrap(), 0);
+        let mut reinstated = store.get(&retracted.id).await.unwrap().unwrap();
+        reinstated.reinstate(false).unwrap();
+        assert!(store.update_if(&reinstated, TaskStatus::Retracted).await.unwrap());
+        assert_eq!(store.count(TaskStatus::Pending).await.unwrap(), 2);
+        assert!(!dir.join("archive").join(format!("{}.json", retracted.id)).exists());
+    }
+
+    #[test]
+    fn test_record_failure_respects_retry_limit() {
+        let mut task = Task::new(event(4));
+        task.start().unwrap();
+        task.record_failure("timeout", true, 2).unwrap();
+        assert_eq!(task.status, TaskStatus::Pending);
+        assert!(!task.is_due(&[0, 60_000], chrono::Utc::now()));
+
+        task.start().unwrap();
+        task.record_failure("timeout", true, 2).unwrap();
+        assert_eq!(task.status, TaskStatus::Failed);
+    }
+}
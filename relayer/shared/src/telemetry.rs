@@ -0,0 +1,241 @@
+//! 分布式追踪（OpenTelemetry）
+//!
+//! 一笔跨链转账以全局转账 ID（源链, 源合约, nonce，即任务 ID）为键：
+//! - 监听器为每个事件打开 `capture` span，其 trace ID 由转账 ID 确定性地导出，
+//!   因此即使追踪上下文丢失（旧的队列消息、监听器未启用追踪），同一笔转账的所有 span 仍在同一条 trace 中
+//! - `capture` span 的 W3C `traceparent` 写入任务和队列消息（[`TraceContext`]），
+//!   提交端的 `relay` span 以它为父节点，跨进程串起 捕获 → 签名 → 提交
+//!
+//! span 通过 `logger::init_logger` 安装的 tracing-opentelemetry 层导出到 OTLP 端点或本地 JSON Lines 文件。
+
+use crate::config::TelemetryConfig;
+use crate::error::{RelayerError, Result};
+use crate::types::{StakeEventData, Task};
+use futures_util::future::BoxFuture;
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry::trace::{
+    SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState, TracerProvider as _,
+};
+use opentelemetry::{global, Context, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::export::trace::{ExportResult, SpanData, SpanExporter};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Tracer, TracerProvider};
+use opentelemetry_sdk::{runtime, Resource};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// 跨进程传递的追踪上下文（W3C `traceparent` / `tracestate`）
+pub type TraceContext = HashMap<String, String>;
+
+/// 按配置创建导出 span 的 tracer 并注册为全局 provider；exporter 为 none 时返回 None
+pub(crate) fn init_tracer(config: &TelemetryConfig, service: &str) -> Result<Option<Tracer>> {
+    let builder = TracerProvider::builder().with_config(
+        opentelemetry_sdk::trace::config()
+            .with_resource(Resource::new(vec![KeyValue::new("service.name", service.to_string())])),
+    );
+    let provider = match config.exporter.as_str() {
+        "otlp" => {
+            let endpoint = config
+                .otlp_endpoint
+                .clone()
+                .ok_or_else(|| RelayerError::Config("telemetry.otlp_endpoint is required".to_string()))?;
+            let exporter = opentelemetry_otlp::new_exporter()
+                .http()
+                .with_endpoint(endpoint)
+                .build_span_exporter()
+                .map_err(|e| RelayerError::Config(format!("Failed to create OTLP exporter: {}", e)))?;
+            builder.with_batch_exporter(exporter, runtime::Tokio).build()
+        }
+        "file" => builder
+            .with_batch_exporter(FileSpanExporter::open(&config.file_path)?, runtime::Tokio)
+            .build(),
+        _ => return Ok(None),
+    };
+    let tracer = provider.tracer("relayer");
+    global::set_tracer_provider(provider);
+    Ok(Some(tracer))
+}
+
+/// 进程退出时刷新尚未导出的 span
+#[must_use = "dropping the guard flushes and shuts down the tracer"]
+pub struct TelemetryGuard {
+    enabled: bool,
+}
+
+impl TelemetryGuard {
+    pub(crate) fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if self.enabled {
+            global::shutdown_tracer_provider();
+        }
+    }
+}
+
+/// 全局转账 ID：(源链, 源合约, nonce)，与任务 ID 相同
+pub fn transfer_id(event: &StakeEventData) -> String {
+    Task::id_for(event)
+}
+
+/// 当前 span 的追踪上下文；未启用追踪时为空
+pub fn current_context() -> TraceContext {
+    let mut carrier = TraceContext::new();
+    TraceContextPropagator::new().inject_context(&tracing::Span::current().context(), &mut carrier);
+    carrier
+}
+
+/// 为 `span` 设置父节点：优先使用传入的上下文，否则挂到由转账 ID 导出的 trace 下
+pub fn attach(span: &tracing::Span, transfer_id: &str, context: &TraceContext) {
+    let parent = TraceContextPropagator::new().extract(context);
+    if parent.span().span_context().is_valid() {
+        span.set_parent(parent);
+    } else {
+        span.set_parent(Context::new().with_remote_span_context(transfer_span_context(transfer_id)));
+    }
+}
+
+/// 由转账 ID 导出的远程根 span 上下文
+fn transfer_span_context(transfer_id: &str) -> SpanContext {
+    let digest = Sha256::digest(transfer_id.as_bytes());
+    let trace_id: [u8; 16] = digest[..16].try_into().unwrap();
+    let span_id: [u8; 8] = digest[16..24].try_into().unwrap();
+    SpanContext::new(
+        TraceId::from_bytes(trace_id),
+        SpanId::from_bytes(span_id),
+        TraceFlags::SAMPLED,
+        true,
+        TraceState::default(),
+    )
+}
+
+/// 转账 ID 对应的 trace ID（十六进制），用于在追踪后端中按转账查找
+pub fn transfer_trace_id(transfer_id: &str) -> String {
+    transfer_span_context(transfer_id).trace_id().to_string()
+}
+
+/// 本地文件导出器：每个 span 一行 JSON
+#[derive(Debug)]
+pub struct FileSpanExporter {
+    file: File,
+}
+
+impl FileSpanExporter {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                RelayerError::Config(format!("Failed to create trace directory {}: {}", parent.display(), e))
+            })?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path).map_err(|e| {
+            RelayerError::Config(format!("Failed to open trace file {}: {}", path.display(), e))
+        })?;
+        Ok(Self { file })
+    }
+
+    fn encode(span: &SpanData) -> serde_json::Value {
+        let nanos = |t: std::time::SystemTime| t.duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0);
+        let attributes: serde_json::Map<String, serde_json::Value> = span
+            .attributes
+            .iter()
+            .map(|kv| (kv.key.to_string(), json!(kv.value.to_string())))
+            .collect();
+        let events: Vec<serde_json::Value> = span
+            .events
+            .iter()
+            .map(|event| json!({ "name": event.name, "time_unix_nano": nanos(event.timestamp) }))
+            .collect();
+        let parent = (span.parent_span_id != SpanId::INVALID).then(|| span.parent_span_id.to_string());
+
+        json!({
+            "trace_id": span.span_context.trace_id().to_string(),
+            "span_id": span.span_context.span_id().to_string(),
+            "parent_span_id": parent,
+            "name": span.name,
+            "start_time_unix_nano": nanos(span.start_time),
+            "end_time_unix_nano": nanos(span.end_time),
+            "attributes": attributes,
+            "events": events,
+            "status": format!("{:?}", span.status),
+        })
+    }
+}
+
+impl SpanExporter for FileSpanExporter {
+    fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        let mut lines = String::new();
+        for span in &batch {
+            lines.push_str(&Self::encode(span).to_string());
+            lines.push('\n');
+        }
+        let result = self
+            .file
+            .write_all(lines.as_bytes())
+            .and_then(|_| self.file.flush())
+            .map_err(|e| format!("Failed to write trace file: {}", e).into());
+        Box::pin(std::future::ready(result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_trace_context_links_capture_and_relay() {
+        let path = std::env::temp_dir().join(format!("relayer-traces-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let provider = TracerProvider::builder()
+            .with_simple_exporter(FileSpanExporter::open(&path).unwrap())
+            .build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+
+        let transfer = "421614-abab-7";
+        let carried = tracing::subscriber::with_default(subscriber, || {
+            let capture = tracing::info_span!("capture", transfer_id = transfer);
+            attach(&capture, transfer, &TraceContext::new());
+            let carried = capture.in_scope(current_context);
+
+            // 另一个进程从队列消息恢复上下文
+            let relay = tracing::info_span!("relay", transfer_id = transfer);
+            attach(&relay, transfer, &carried);
+            relay.in_scope(|| tracing::info_span!("submit").in_scope(|| {}));
+            carried
+        });
+        provider.force_flush();
+
+        assert!(carried.contains_key("traceparent"));
+        let spans: Vec<serde_json::Value> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(spans.len(), 3);
+        let trace_id = transfer_trace_id(transfer);
+        assert!(spans.iter().all(|span| span["trace_id"] == trace_id.as_str()));
+
+        let find = |name: &str| spans.iter().find(|span| span["name"] == name).unwrap();
+        assert_eq!(find("relay")["parent_span_id"], find("capture")["span_id"]);
+        assert_eq!(find("submit")["parent_span_id"], find("relay")["span_id"]);
+        assert_eq!(find("capture")["attributes"]["transfer_id"], transfer);
+    }
+
+    #[test]
+    fn test_context_is_empty_without_tracer() {
+        let span = tracing::info_span!("capture");
+        attach(&span, "1-x-1", &TraceContext::new());
+        assert!(span.in_scope(current_context).is_empty());
+    }
+}
@@ -0,0 +1,50 @@
+//! 单元测试共用的事件构造和临时目录
+
+use crate::address::ChainAddress;
+use crate::types::StakeEventData;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// EVM → SVM 方向的测试事件，同一源合约的事件属于同一 nonce 通道
+pub(crate) fn event(nonce: u64) -> StakeEventData {
+    event_with_amount(nonce, 1_000_000)
+}
+
+pub(crate) fn event_with_amount(nonce: u64, amount: u64) -> StakeEventData {
+    StakeEventData {
+        source_contract: ChainAddress::Evm([0x11; 20]),
+        target_contract: ChainAddress::Svm([1u8; 32]),
+        source_chain_id: 421614,
+        target_chain_id: 91024,
+        block_height: 100,
+        amount,
+        receiver_address: ChainAddress::Svm([2u8; 32]),
+        nonce,
+    }
+}
+
+/// 测试用临时目录 `relayer-<name>-<pid>`，创建时清空，drop 时删除
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    pub(crate) fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("relayer-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -1,3 +1,6 @@
+use crate::address::ChainAddress;
+use crate::error::{RelayerError, Result};
+use crate::telemetry::{self, TraceContext};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -5,16 +8,30 @@ use std::fmt;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "borsh", derive(borsh::BorshSerialize, borsh::BorshDeserialize))]
 pub struct StakeEventData {
-    pub source_contract: String,
-    pub target_contract: String,
+    pub source_contract: ChainAddress,
+    pub target_contract: ChainAddress,
     pub source_chain_id: u64,
     pub target_chain_id: u64,
     pub block_height: u64,
     pub amount: u64,
-    pub receiver_address: String,
+    pub receiver_address: ChainAddress,
     pub nonce: u64,
 }
 
+/// 事件在源链上的位置：交易（EVM 交易哈希 / SVM 交易签名）、日志序号和区块
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceRef {
+    pub tx: String,
+    /// EVM 日志在区块中的序号
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log_index: Option<u64>,
+    /// 区块号（EVM）或 slot（SVM）
+    pub block: u64,
+    /// 源链交易发起者（EVM `from` / SVM 手续费支付者），未知时为 None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sender: Option<ChainAddress>,
+}
+
 /// 任务状态
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -23,6 +40,29 @@ pub enum TaskStatus {
     Processing,
     Completed,
     Failed,
+    /// 风控暂扣，等待操作员批准
+    Held,
+    /// 命中合规拒绝名单或事件入口校验失败，不签名
+    Blocked,
+    /// 已在源链观察到、尚未达到配置的最终性，不签名
+    #[serde(rename = "awaiting_finality")]
+    AwaitingFinality,
+    /// 源链回滚后事件已不在规范链上，不签名；事件重新出现时恢复
+    Retracted,
+}
+
+impl TaskStatus {
+    /// 全部状态，用于按状态汇总
+    pub const ALL: [TaskStatus; 8] = [
+        TaskStatus::Pending,
+        TaskStatus::Processing,
+        TaskStatus::Completed,
+        TaskStatus::Failed,
+        TaskStatus::Held,
+        TaskStatus::Blocked,
+        TaskStatus::AwaitingFinality,
+        TaskStatus::Retracted,
+    ];
 }
 
 impl fmt::Display for TaskStatus {
@@ -32,6 +72,10 @@ impl fmt::Display for TaskStatus {
             TaskStatus::Processing => write!(f, "processing"),
             TaskStatus::Completed => write!(f, "completed"),
             TaskStatus::Failed => write!(f, "failed"),
+            TaskStatus::Held => write!(f, "held"),
+            TaskStatus::Blocked => write!(f, "blocked"),
+            TaskStatus::AwaitingFinality => write!(f, "awaiting_finality"),
+            TaskStatus::Retracted => write!(f, "retracted"),
         }
     }
 }
@@ -50,6 +94,218 @@ pub struct Task {
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
     pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// 首次开始处理（已通过风控、即将签名）的时间，风控滚动窗口以此计时
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signed_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// 捕获事件时的追踪上下文，提交端以它为父节点
+    #[serde(default, skip_serializing_if = "TraceContext::is_empty")]
+    pub trace_context: TraceContext,
+    /// 事件在源链上的位置（写入审计日志）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceRef>,
+    /// 操作员已批准，风控放行
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub approved: bool,
+}
+
+/// 跨链转账 ID：`<源链>-<源合约>-<nonce>`，可用作文件名
+pub fn transfer_id(source_chain_id: u64, source_contract: &ChainAddress, nonce: u64) -> String {
+    let contract: String = source_contract
+        .to_string()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{}-{}-{}", source_chain_id, contract, nonce)
+}
+
+impl Task {
+    /// 根据事件创建新任务（初始状态为 Pending）
+    pub fn new(event_data: StakeEventData) -> Self {
+        let now = chrono::Utc::now();
+        Self {
+            id: Self::id_for(&event_data),
+            nonce: event_data.nonce,
+            status: TaskStatus::Pending,
+            event_data,
+            signature: None,
+            transaction_hash: None,
+            retries: 0,
+            error_message: None,
+            created_at: now,
+            updated_at: now,
+            completed_at: None,
+            signed_at: None,
+            trace_context: telemetry::current_context(),
+            source: None,
+            approved: false,
+        }
+    }
+
+    /// 任务 ID：(源链, 源合约, nonce) 唯一确定一笔跨链转账
+    pub fn id_for(event: &StakeEventData) -> String {
+        transfer_id(event.source_chain_id, &event.source_contract, event.nonce)
+    }
+
+    /// Pending → Processing，首次开始时记录 `signed_at`
+    pub fn start(&mut self) -> Result<()> {
+        self.transition(TaskStatus::Processing)?;
+        self.signed_at.get_or_insert(self.updated_at);
+        Ok(())
+    }
+
+    /// 计入风控滚动窗口的时间：`signed_at`；升级前的任务没有记录，已签名或正在处理时退回 `updated_at`
+    pub fn exposed_at(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.signed_at.or_else(|| {
+            (self.signature.is_some() || self.status == TaskStatus::Processing).then_some(self.updated_at)
+        })
+    }
+
+    /// Processing → Completed
+    pub fn complete(&mut self, transaction_hash: String) -> Result<()> {
+        self.transition(TaskStatus::Completed)?;
+        self.transaction_hash = Some(transaction_hash);
+        self.error_message = None;
+        self.completed_at = Some(self.updated_at);
+        Ok(())
+    }
+
+    /// Processing → Pending，记录一次重试
+    pub fn retry_later(&mut self, error: impl ToString) -> Result<()> {
+        self.transition(TaskStatus::Pending)?;
+        self.retries += 1;
+        self.error_message = Some(error.to_string());
+        Ok(())
+    }
+
+    /// Processing → Failed（不再重试）
+    pub fn fail(&mut self, error: impl ToString) -> Result<()> {
+        self.transition(TaskStatus::Failed)?;
+        self.error_message = Some(error.to_string());
+        Ok(())
+    }
+
+    /// Pending → Held（风控暂扣，`reason` 记入 error_message）
+    pub fn hold(&mut self, reason: impl ToString) -> Result<()> {
+        self.transition(TaskStatus::Held)?;
+        self.error_message = Some(reason.to_string());
+        Ok(())
+    }
+
+    /// Held → Pending，操作员批准后跳过风控
+    pub fn approve(&mut self) -> Result<()> {
+        self.transition(TaskStatus::Pending)?;
+        self.approved = true;
+        self.error_message = None;
+        Ok(())
+    }
+
+    /// Held → Failed，操作员拒绝
+    pub fn reject(&mut self, reason: impl ToString) -> Result<()> {
+        self.transition(TaskStatus::Failed)?;
+        self.error_message = Some(reason.to_string());
+        Ok(())
+    }
+
+    /// Pending → Blocked（命中拒绝名单，终态）
+    pub fn block(&mut self, reason: impl ToString) -> Result<()> {
+        self.transition(TaskStatus::Blocked)?;
+        self.error_message = Some(reason.to_string());
+        Ok(())
+    }
+
+    /// Pending → AwaitingFinality（新观察到的事件尚未达到最终性）
+    pub fn await_finality(&mut self) -> Result<()> {
+        self.transition(TaskStatus::AwaitingFinality)
+    }
+
+    /// AwaitingFinality → Pending，事件已达到最终性，可以签名
+    pub fn finalize(&mut self) -> Result<()> {
+        self.transition(TaskStatus::Pending)
+    }
+
+    /// Pending / AwaitingFinality / Held → Retracted（源链回滚，事件已消失）
+    pub fn retract(&mut self, reason: impl ToString) -> Result<()> {
+        self.transition(TaskStatus::Retracted)?;
+        self.error_message = Some(reason.to_string());
+        Ok(())
+    }
+
+    /// Retracted → Pending / AwaitingFinality，事件重新出现在规范链上
+    pub fn reinstate(&mut self, awaiting: bool) -> Result<()> {
+        self.transition(if awaiting { TaskStatus::AwaitingFinality } else { TaskStatus::Pending })?;
+        self.error_message = None;
+        Ok(())
+    }
+
+    /// 处理失败后的统一出口：未达重试上限则回到 Pending，否则标记 Failed
+    pub fn record_failure(&mut self, error: impl ToString, retryable: bool, retry_limit: u32) -> Result<()> {
+        if retryable && self.retries + 1 < retry_limit {
+            self.retry_later(error)
+        } else {
+            self.fail(error)
+        }
+    }
+
+    /// Pending 任务是否已过重试等待时间
+    pub fn is_due(&self, retry_delays: &[u64], now: chrono::DateTime<chrono::Utc>) -> bool {
+        if self.status != TaskStatus::Pending {
+            return false;
+        }
+        if self.retries == 0 {
+            return true;
+        }
+        now >= self.updated_at + chrono::Duration::milliseconds(self.retry_delay(retry_delays) as i64)
+    }
+
+    /// 当前重试次数对应的等待时间（毫秒），超出配置长度时取最后一项
+    pub fn retry_delay(&self, retry_delays: &[u64]) -> u64 {
+        retry_delays
+            .get(self.retries as usize)
+            .or(retry_delays.last())
+            .copied()
+            .unwrap_or(0)
+    }
+
+    pub fn summary(&self) -> TaskSummary {
+        TaskSummary {
+            id: self.id.clone(),
+            nonce: self.nonce,
+            status: self.status,
+            created_at: self.created_at,
+            retries: self.retries,
+        }
+    }
+
+    fn transition(&mut self, to: TaskStatus) -> Result<()> {
+        let allowed = matches!(
+            (self.status, to),
+            (TaskStatus::Pending, TaskStatus::Processing)
+                | (TaskStatus::Processing, TaskStatus::Pending)
+                | (TaskStatus::Processing, TaskStatus::Completed)
+                | (TaskStatus::Processing, TaskStatus::Failed)
+                | (TaskStatus::Pending, TaskStatus::Held)
+                | (TaskStatus::Held, TaskStatus::Pending)
+                | (TaskStatus::Held, TaskStatus::Failed)
+                | (TaskStatus::Pending, TaskStatus::Blocked)
+                | (TaskStatus::Pending, TaskStatus::AwaitingFinality)
+                | (TaskStatus::AwaitingFinality, TaskStatus::Pending)
+                | (TaskStatus::Pending, TaskStatus::Retracted)
+                | (TaskStatus::AwaitingFinality, TaskStatus::Retracted)
+                | (TaskStatus::Held, TaskStatus::Retracted)
+                | (TaskStatus::Retracted, TaskStatus::Pending)
+                | (TaskStatus::Retracted, TaskStatus::AwaitingFinality)
+        );
+        if !allowed {
+            return Err(RelayerError::InvalidTaskTransition {
+                id: self.id.clone(),
+                from: self.status,
+                to,
+            });
+        }
+        self.status = to;
+        self.updated_at = chrono::Utc::now();
+        Ok(())
+    }
 }
 
 /// Relayer 信息
@@ -88,6 +344,10 @@ pub struct QueueStatus {
     pub processing: u64,
     pub completed: u64,
     pub failed: u64,
+    pub held: u64,
+    pub blocked: u64,
+    pub awaiting_finality: u64,
+    pub retracted: u64,
     pub tasks: Vec<TaskSummary>,
 }
 